@@ -0,0 +1,26 @@
+use std::io::{Error, ErrorKind, Result};
+
+mod rotate_encryption_key;
+mod rotate_password_pepper;
+mod rotate_signing_key;
+
+/// Runs a one-off command instead of starting the server.
+///
+/// # Arguments
+///
+/// - `command` - The name of the command, as passed on the command line.
+///
+/// # Errors
+///
+/// Returns an error if the command is unknown or fails.
+pub async fn run(command: &str) -> Result<()> {
+    match command {
+        "rotate-encryption-key" => rotate_encryption_key::run().await,
+        "rotate-password-pepper" => rotate_password_pepper::run(),
+        "rotate-signing-key" => rotate_signing_key::run(),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown command: {command}"),
+        )),
+    }
+}
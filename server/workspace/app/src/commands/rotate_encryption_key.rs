@@ -0,0 +1,53 @@
+use std::io::{Error, Result};
+use std::path::Path;
+
+use lib_crypto::Keyring;
+use lib_environment::{EncryptionKeyPath, EnvironmentVariable};
+
+use crate::database;
+
+/// Adds a new primary key to the keyring and re-encrypts stored data with it.
+///
+/// The new keyring is staged next to the keyring before any data is
+/// re-encrypted, so no data is ever encrypted with a key that is not in a
+/// file, and only replaces the keyring once every value has been re-encrypted.
+/// If the command is interrupted, running it again resumes with the staged
+/// keyring.
+///
+/// # Errors
+///
+/// Returns an error if the keyring could not be loaded or saved, or the data
+/// could not be re-encrypted.
+pub async fn run() -> Result<()> {
+    let path = EncryptionKeyPath::get();
+    let staged = format!("{path}.new");
+
+    let keyring = if Path::new(&staged).exists() {
+        log::info!("Resuming the rotation staged in {}", staged);
+        Keyring::load(&staged).map_err(|e| Error::other(e.to_string()))?
+    } else {
+        let mut keyring =
+            Keyring::load_or_create(&path).map_err(|e| Error::other(e.to_string()))?;
+        keyring.rotate().map_err(|e| Error::other(e.to_string()))?;
+        keyring
+            .save(&staged)
+            .map_err(|e| Error::other(e.to_string()))?;
+        keyring
+    };
+    let id = keyring
+        .primary_id()
+        .ok_or_else(|| Error::other(format!("{staged} has no keys")))?;
+
+    let db_connection = database::open()
+        .await
+        .map_err(|e| Error::other(e.to_string()))?;
+    let count = lib_database::reencrypt(&db_connection, &keyring, lib_database::ENCRYPTED_COLUMNS)
+        .await
+        .map_err(|e| Error::other(e.to_string()))?;
+    log::info!("Re-encrypted {} values with encryption key {}", count, id);
+
+    std::fs::rename(&staged, &path)?;
+    log::info!("Added encryption key {} to {}", id, path);
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use std::io::{Error, ErrorKind, Result};
+
+use lib_crypto::Keyring;
+use lib_environment::{EnvironmentVariable, PasswordPepperPath};
+
+/// Adds a new version to the password pepper.
+///
+/// Existing password hashes are upgraded to the new version as their users log
+/// in, so older versions must stay in the file until every hash is upgraded.
+///
+/// # Errors
+///
+/// Returns an error if no pepper is configured, or the pepper could not be
+/// loaded or saved.
+pub fn run() -> Result<()> {
+    let Some(path) = PasswordPepperPath::get() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is not set", PasswordPepperPath::NAME),
+        ));
+    };
+    let mut pepper = Keyring::load_or_create(&path).map_err(|e| Error::other(e.to_string()))?;
+
+    let id = pepper.rotate().map_err(|e| Error::other(e.to_string()))?;
+    pepper
+        .save(&path)
+        .map_err(|e| Error::other(e.to_string()))?;
+    log::info!("Added password pepper version {} to {}", id, path);
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use std::io::{Error, Result};
+
+use lib_crypto::Keyring;
+use lib_environment::{EnvironmentVariable, SigningKeyPath};
+
+/// Adds a new primary key to the signing keyring.
+///
+/// The previous key is still published until the next rotation, so consumers
+/// can verify signatures made before the running servers are restarted.
+///
+/// # Errors
+///
+/// Returns an error if the keyring could not be loaded or saved.
+pub fn run() -> Result<()> {
+    let path = SigningKeyPath::get();
+    let mut keyring = Keyring::load_or_create(&path).map_err(|e| Error::other(e.to_string()))?;
+
+    let id = keyring.rotate().map_err(|e| Error::other(e.to_string()))?;
+    keyring
+        .save(&path)
+        .map_err(|e| Error::other(e.to_string()))?;
+    log::info!("Added signing key {} to {}", id, path);
+
+    Ok(())
+}
@@ -5,6 +5,8 @@ use lib_base64::Encode;
 use lib_environment::EnvironmentVariable;
 use lib_json_schema::schema::auth::{LoginRequest, LoginResponseSuccess};
 
+use crate::problem::{ErrorCode, Problem};
+
 /// Controls the login process.
 ///
 /// # Arguments
@@ -12,14 +14,14 @@ use lib_json_schema::schema::auth::{LoginRequest, LoginResponseSuccess};
 /// - `provider` - The authentication provider.
 /// - `login_request` - The login request.
 ///
-/// # Returns
+/// # Errors
 ///
-/// - `None` if the login failed.
-/// - `Some` if the login succeeded.
+/// Returns [`ErrorCode::InvalidCredentials`] if the username or password is
+/// wrong, without saying which, or another problem if the login failed.
 pub async fn login(
     provider: &lib_authentication::Provider,
     login_request: &LoginRequest,
-) -> Option<LoginResponseSuccess> {
+) -> Result<LoginResponseSuccess, Problem> {
     let auth_token_ttl = Duration::from_secs(lib_environment::AuthTokenTtl::get());
     let refresh_token_ttl = Duration::from_secs(lib_environment::RefreshTokenTtl::get());
 
@@ -40,7 +42,10 @@ pub async fn login(
         .try_into()
         .unwrap_or(0u32);
 
-    let token = provider
+    let TokenPair {
+        auth_token,
+        refresh_token,
+    } = provider
         .login(
             &LoginCredentials {
                 username: &login_request.username,
@@ -49,42 +54,13 @@ pub async fn login(
             Some(&auth_token_ttl),
             Some(&refresh_token_ttl),
         )
-        .await;
-
-    let TokenPair {
-        auth_token,
-        refresh_token,
-    } = match token {
-        Ok(Some(token)) => token,
-        Ok(None) => {
-            return None;
-        }
-        Err(err) => {
-            log::error!("Error while logging in: {}", err);
-            return None;
-        }
-    };
-
-    let auth_token = match auth_token.encode() {
-        Ok(auth_token) => auth_token,
-        Err(err) => {
-            log::error!("Error while encoding auth token: {}", err);
-            return None;
-        }
-    };
+        .await?
+        .ok_or(ErrorCode::InvalidCredentials)?;
 
-    let refresh_token = match refresh_token.encode() {
-        Ok(refresh_token) => refresh_token,
-        Err(err) => {
-            log::error!("Error while encoding refresh token: {}", err);
-            return None;
-        }
-    };
-
-    Some(LoginResponseSuccess {
-        auth_token,
+    Ok(LoginResponseSuccess {
+        auth_token: auth_token.encode()?,
         auth_token_expires,
-        refresh_token,
+        refresh_token: refresh_token.encode()?,
         refresh_token_expires,
     })
 }
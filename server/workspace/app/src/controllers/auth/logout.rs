@@ -1,4 +1,6 @@
-use lib_authentication::{AuthToken, ProviderInterface, Result};
+use lib_authentication::{AuthToken, ProviderInterface};
+
+use crate::problem::{ErrorCode, Problem};
 
 /// Logs out the user.
 ///
@@ -9,8 +11,13 @@ use lib_authentication::{AuthToken, ProviderInterface, Result};
 ///
 /// # Errors
 ///
-/// Returns an error if the token could not be deleted.
-pub async fn logout(provider: &impl ProviderInterface, auth_token: &AuthToken) -> Result<()> {
+/// Returns [`ErrorCode::InvalidToken`] if no token was given, or another
+/// problem if the token could not be deleted.
+pub async fn logout(
+    provider: &impl ProviderInterface,
+    auth_token: Option<&AuthToken>,
+) -> Result<(), Problem> {
+    let auth_token = auth_token.ok_or(ErrorCode::InvalidToken)?;
     provider.logout(auth_token).await?;
     Ok(())
 }
@@ -1,11 +1,17 @@
 pub use login::login;
 pub use logout::logout;
+pub use passkey_login::{finish_passkey_login, start_passkey_login};
+pub use passkey_register::{finish_passkey_registration, start_passkey_registration};
 pub use refresh::refresh;
 pub use register::register;
-pub use whoami::whoami;
+pub use setup::setup;
+pub use whoami::{authenticated_user, whoami};
 
 mod login;
 mod logout;
+mod passkey_login;
+mod passkey_register;
 mod refresh;
 mod register;
+mod setup;
 mod whoami;
@@ -0,0 +1,108 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use lib_authentication::{ChallengeToken, ProviderInterface, PublicKeyCredential, TokenPair};
+use lib_base64::{decode, Encode};
+use lib_environment::EnvironmentVariable;
+use lib_json_schema::schema::auth::{
+    LoginResponseSuccess, PasskeyChallengeResponse, PasskeyLoginRequest, PasskeyLoginStartRequest,
+};
+
+use crate::problem::{ErrorCode, Problem};
+
+/// Begins logging in a user with a passkey.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `start_request` - The user to log in.
+///
+/// # Returns
+///
+/// The challenge for the user's authenticator. A user who does not exist or
+/// has no passkeys gets a challenge too, which no authenticator can answer.
+///
+/// # Errors
+///
+/// Returns a problem if the login could not be started.
+pub async fn start_passkey_login(
+    provider: &impl ProviderInterface,
+    start_request: &PasskeyLoginStartRequest,
+) -> Result<PasskeyChallengeResponse, Problem> {
+    let challenge_ttl = Duration::from_secs(lib_environment::WebauthnChallengeTtl::get());
+
+    let challenge = provider
+        .start_passkey_login(&start_request.username, Some(&challenge_ttl))
+        .await?;
+
+    let options = serde_json::to_value(challenge.options).map_err(|err| {
+        log::error!("Error while serializing passkey options: {}", err);
+        ErrorCode::Internal
+    })?;
+
+    Ok(PasskeyChallengeResponse {
+        challenge: challenge.token.encode()?,
+        options: Some(options),
+    })
+}
+
+/// Completes logging in a user with a passkey.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `login_request` - The authenticator's answer to the challenge.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidRequest`] if the request is malformed,
+/// [`ErrorCode::InvalidCredentials`] if the challenge is unknown or expired or
+/// the credential could not be verified, or another problem if the login failed.
+pub async fn finish_passkey_login(
+    provider: &impl ProviderInterface,
+    login_request: &PasskeyLoginRequest,
+) -> Result<LoginResponseSuccess, Problem> {
+    let challenge = decode(&login_request.challenge).map_err(|_| ErrorCode::InvalidRequest)?;
+    let challenge = ChallengeToken::from(challenge);
+
+    let credential = login_request
+        .credential
+        .clone()
+        .ok_or(ErrorCode::InvalidRequest)?;
+    let credential = serde_json::from_value::<PublicKeyCredential>(credential)
+        .map_err(|err| Problem::new(ErrorCode::InvalidRequest).with_detail(err.to_string()))?;
+
+    let auth_token_ttl = Duration::from_secs(lib_environment::AuthTokenTtl::get());
+    let refresh_token_ttl = Duration::from_secs(lib_environment::RefreshTokenTtl::get());
+
+    let now = std::time::SystemTime::now();
+    let auth_token_expires = (now + auth_token_ttl)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        .try_into()
+        .unwrap_or(0u32);
+    let refresh_token_expires = (now + refresh_token_ttl)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        .try_into()
+        .unwrap_or(0u32);
+
+    let TokenPair {
+        auth_token,
+        refresh_token,
+    } = provider
+        .finish_passkey_login(
+            &challenge,
+            &credential,
+            Some(&auth_token_ttl),
+            Some(&refresh_token_ttl),
+        )
+        .await?
+        .ok_or(ErrorCode::InvalidCredentials)?;
+
+    Ok(LoginResponseSuccess {
+        auth_token: auth_token.encode()?,
+        auth_token_expires,
+        refresh_token: refresh_token.encode()?,
+        refresh_token_expires,
+    })
+}
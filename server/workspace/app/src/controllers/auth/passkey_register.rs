@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use lib_authentication::{ChallengeToken, ProviderInterface, RegisterPublicKeyCredential, User};
+use lib_base64::{decode, Encode};
+use lib_environment::EnvironmentVariable;
+use lib_json_schema::schema::auth::{PasskeyChallengeResponse, PasskeyRegisterRequest};
+
+use crate::problem::{ErrorCode, Problem};
+
+/// Begins registering a new passkey for the given user.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `user` - The user registering the passkey.
+///
+/// # Returns
+///
+/// The challenge for the user's authenticator.
+///
+/// # Errors
+///
+/// Returns a problem if the registration could not be started.
+pub async fn start_passkey_registration(
+    provider: &impl ProviderInterface,
+    user: &User,
+) -> Result<PasskeyChallengeResponse, Problem> {
+    let challenge_ttl = Duration::from_secs(lib_environment::WebauthnChallengeTtl::get());
+
+    let challenge = provider
+        .start_passkey_registration(user, Some(&challenge_ttl))
+        .await?;
+
+    let options = serde_json::to_value(challenge.options).map_err(|err| {
+        log::error!("Error while serializing passkey options: {}", err);
+        ErrorCode::Internal
+    })?;
+
+    Ok(PasskeyChallengeResponse {
+        challenge: challenge.token.encode()?,
+        options: Some(options),
+    })
+}
+
+/// Completes registering a new passkey for the given user.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `user` - The user registering the passkey.
+/// - `register_request` - The authenticator's answer to the challenge.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidRequest`] if the request is malformed,
+/// [`ErrorCode::PasskeyRejected`] if the challenge is unknown or expired or the
+/// credential could not be verified, or another problem if the passkey could not be stored.
+pub async fn finish_passkey_registration(
+    provider: &impl ProviderInterface,
+    user: &User,
+    register_request: &PasskeyRegisterRequest,
+) -> Result<(), Problem> {
+    let challenge = decode(&register_request.challenge).map_err(|_| ErrorCode::InvalidRequest)?;
+    let challenge = ChallengeToken::from(challenge);
+
+    let credential = register_request
+        .credential
+        .clone()
+        .ok_or(ErrorCode::InvalidRequest)?;
+    let credential = serde_json::from_value::<RegisterPublicKeyCredential>(credential)
+        .map_err(|err| Problem::new(ErrorCode::InvalidRequest).with_detail(err.to_string()))?;
+
+    if provider
+        .finish_passkey_registration(user, &challenge, &credential)
+        .await?
+    {
+        Ok(())
+    } else {
+        Err(ErrorCode::PasskeyRejected.into())
+    }
+}
@@ -5,11 +5,25 @@ use lib_base64::{decode, Encode};
 use lib_environment::EnvironmentVariable;
 use lib_json_schema::schema::auth::{RefreshRequest, RefreshResponseSuccess};
 
+use crate::problem::{ErrorCode, Problem};
+
+/// Exchanges a refresh token for a new token pair.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `refresh_request` - The refresh request.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidToken`] if the refresh token is malformed,
+/// unknown or expired, or another problem if the refresh failed.
 pub async fn refresh(
     provider: &impl ProviderInterface,
     refresh_request: &RefreshRequest,
-) -> Option<RefreshResponseSuccess> {
-    let refresh_token = decode(&refresh_request.refresh_token).ok()?;
+) -> Result<RefreshResponseSuccess, Problem> {
+    let refresh_token =
+        decode(&refresh_request.refresh_token).map_err(|_| ErrorCode::InvalidToken)?;
     let refresh_token = RefreshToken::from(refresh_token);
 
     let auth_token_ttl = Duration::from_secs(lib_environment::AuthTokenTtl::get());
@@ -38,16 +52,13 @@ pub async fn refresh(
             Some(&auth_token_ttl),
             Some(&refresh_token_ttl),
         )
-        .await
-        .ok()??;
-
-    let Ok(auth_token) = auth_token.encode() else { return None };
-    let Ok(refresh_token) = refresh_token.encode() else { return None };
+        .await?
+        .ok_or(ErrorCode::InvalidToken)?;
 
-    Some(RefreshResponseSuccess {
-        auth_token,
+    Ok(RefreshResponseSuccess {
+        auth_token: auth_token.encode()?,
         auth_token_expires,
-        refresh_token,
+        refresh_token: refresh_token.encode()?,
         refresh_token_expires,
     })
 }
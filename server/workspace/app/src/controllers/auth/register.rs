@@ -1,6 +1,8 @@
-use lib_authentication::{ProviderInterface, RegisterCredentials, Result};
+use lib_authentication::{ProviderInterface, RegisterCredentials};
 use lib_json_schema::schema::auth::RegisterRequest;
 
+use crate::problem::Problem;
+
 /// Registers a new user.
 ///
 /// # Arguments
@@ -10,11 +12,12 @@ use lib_json_schema::schema::auth::RegisterRequest;
 ///
 /// # Errors
 ///
-/// Returns an error if the user could not be registered.
+/// Returns [`ErrorCode::UsernameTaken`](crate::problem::ErrorCode::UsernameTaken)
+/// if another user has the username, or another problem if the user could not be registered.
 pub async fn register(
     provider: &impl ProviderInterface,
     register_request: &RegisterRequest,
-) -> Result<()> {
+) -> Result<(), Problem> {
     let credentials = RegisterCredentials {
         username: &register_request.username,
         password: &register_request.password,
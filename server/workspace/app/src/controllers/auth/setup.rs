@@ -0,0 +1,33 @@
+use lib_authentication::{ProviderInterface, SetupToken};
+use lib_base64::decode;
+use lib_json_schema::schema::auth::SetupRequest;
+
+use crate::problem::{ErrorCode, Problem};
+
+/// Sets the password of the first admin with the setup token printed at
+/// startup.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `setup_request` - The setup request.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidToken`] if the setup token is malformed, not
+/// the one printed at startup, expired or already used, or if the admin
+/// already has a password, or another problem if the password could not be
+/// set.
+pub async fn setup(
+    provider: &impl ProviderInterface,
+    setup_request: &SetupRequest,
+) -> Result<(), Problem> {
+    let token = decode(&setup_request.token).map_err(|_| ErrorCode::InvalidToken)?;
+    let token = SetupToken::from(token);
+
+    if provider.setup(&token, &setup_request.password).await? {
+        Ok(())
+    } else {
+        Err(ErrorCode::InvalidToken.into())
+    }
+}
@@ -1,6 +1,8 @@
-use lib_authentication::{AuthToken, ProviderInterface};
+use lib_authentication::{AuthToken, ProviderInterface, User};
 use lib_json_schema::schema::auth::WhoamiResponse;
 
+use crate::problem::{ErrorCode, Problem};
+
 /// Returns the user information for the given token.
 ///
 /// # Arguments
@@ -8,18 +10,41 @@ use lib_json_schema::schema::auth::WhoamiResponse;
 /// - `provider` - The authentication provider.
 /// - `bearer_token` - The bearer token.
 ///
-/// # Returns
+/// # Errors
 ///
-/// - `None` if the token is invalid.
-/// - `Some` if the token is valid.
+/// Returns [`ErrorCode::InvalidToken`] if the token is missing or invalid.
 pub async fn whoami(
     provider: &impl ProviderInterface,
     auth_token: Option<&AuthToken>,
-) -> Option<WhoamiResponse> {
-    let user = provider.whoami(auth_token?).await.ok()??;
+) -> Result<WhoamiResponse, Problem> {
+    let user = authenticated_user(provider, auth_token).await?;
 
-    Some(WhoamiResponse {
+    Ok(WhoamiResponse {
         id: user.id.to_string(),
         username: user.username,
     })
 }
+
+/// Returns the user that the bearer token was issued to.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `bearer_token` - The bearer token.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidToken`] if the token is missing, invalid, or
+/// was issued to a user who no longer exists.
+pub async fn authenticated_user(
+    provider: &impl ProviderInterface,
+    auth_token: Option<&AuthToken>,
+) -> Result<User, Problem> {
+    let auth_token = auth_token.ok_or(ErrorCode::InvalidToken)?;
+    let user = provider
+        .whoami(auth_token)
+        .await?
+        .ok_or(ErrorCode::InvalidToken)?;
+
+    Ok(user)
+}
@@ -0,0 +1,27 @@
+use lib_cache::Cache;
+use lib_json_schema::schema::health::{CacheHealth, HealthResponse};
+
+/// Checks whether the services the server depends on can be reached.
+///
+/// # Arguments
+///
+/// - `cache` - The cache.
+///
+/// # Returns
+///
+/// The health of each service, and whether they are all up.
+pub async fn check(cache: &Cache) -> HealthResponse {
+    let health = cache.health().await;
+    if let Some(err) = &health.error {
+        log::warn!("Health check failed to reach the cache: {}", err);
+    }
+
+    let status = if health.is_up() { "up" } else { "down" };
+    HealthResponse {
+        status: status.to_string(),
+        cache: CacheHealth {
+            status: status.to_string(),
+            circuit: health.state.as_str().to_string(),
+        },
+    }
+}
@@ -0,0 +1,3 @@
+pub use check::check;
+
+mod check;
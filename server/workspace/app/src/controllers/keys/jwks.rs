@@ -0,0 +1,37 @@
+use lib_crypto::SigningKeyring;
+use lib_json_schema::schema::keys::{Jwk, JwksResponse};
+
+use crate::problem::Problem;
+
+/// The number of verification keys published: the current key, and the one
+/// before it so signatures made just before a rotation can still be verified.
+const PUBLISHED_KEYS: usize = 2;
+
+/// Returns the current and previous signature verification keys as a JSON Web
+/// Key Set.
+///
+/// # Arguments
+///
+/// - `signing_keyring` - The keys the server signs with.
+///
+/// # Errors
+///
+/// Returns [`crate::problem::ErrorCode::Internal`] if a key could not be encoded.
+pub fn jwks(signing_keyring: &SigningKeyring) -> Result<JwksResponse, Problem> {
+    let keys = signing_keyring
+        .verifying_keys()
+        .take(PUBLISHED_KEYS)
+        .map(|(id, key)| {
+            Ok(Jwk {
+                kty: "OKP".to_string(),
+                crv: "Ed25519".to_string(),
+                x: lib_base64::encode(key)?,
+                kid: id.to_string(),
+                use_: "sig".to_string(),
+                alg: "EdDSA".to_string(),
+            })
+        })
+        .collect::<Result<_, lib_base64::Error>>()?;
+
+    Ok(JwksResponse { keys })
+}
@@ -0,0 +1,3 @@
+pub use jwks::jwks;
+
+mod jwks;
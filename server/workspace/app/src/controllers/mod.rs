@@ -1 +1,4 @@
 pub mod auth;
+pub mod health;
+pub mod keys;
+pub mod oauth;
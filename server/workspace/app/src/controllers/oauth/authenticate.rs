@@ -0,0 +1,24 @@
+use lib_authentication::Client;
+
+use crate::problem::{ErrorCode, Problem};
+
+/// Requires the service client making a request to have been authenticated.
+///
+/// # Arguments
+///
+/// - `client` - The client authenticated by the [`ClientCredentials`]
+///   middleware, if any.
+///
+/// [`ClientCredentials`]: crate::middleware::ClientCredentials
+///
+/// # Returns
+///
+/// The authenticated client.
+///
+/// # Errors
+///
+/// Returns [`ErrorCode::InvalidClient`] if the client presented no
+/// credentials or the wrong ones.
+pub fn authenticate(client: Option<&Client>) -> Result<&Client, Problem> {
+    client.ok_or_else(|| ErrorCode::InvalidClient.into())
+}
@@ -0,0 +1,67 @@
+use std::time::UNIX_EPOCH;
+
+use lib_authentication::{Introspection, ProviderInterface};
+use lib_base64::decode;
+use lib_json_schema::schema::oauth::{IntrospectRequest, IntrospectResponse};
+
+use super::token_type;
+
+/// Describes a token on behalf of a service client.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `introspect_request` - The introspection request.
+///
+/// # Returns
+///
+/// The token's description. Tokens that are unknown, expired or malformed are reported as
+/// inactive.
+pub async fn introspect(
+    provider: &impl ProviderInterface,
+    introspect_request: &IntrospectRequest,
+) -> IntrospectResponse {
+    let inactive = IntrospectResponse {
+        active: false,
+        sub: None,
+        username: None,
+        scope: None,
+        exp: None,
+        token_type: None,
+    };
+
+    let Ok(token) = decode(&introspect_request.token) else { return inactive };
+    let token_type_hint = token_type::parse(
+        introspect_request
+            .token_type_hint
+            .as_deref()
+            .map(String::as_str),
+    );
+
+    let Introspection {
+        token_type,
+        user,
+        scope,
+        expires,
+    } = match provider.introspect(&token, token_type_hint).await {
+        Ok(Some(introspection)) => introspection,
+        Ok(None) => return inactive,
+        Err(err) => {
+            log::error!("Error while introspecting token: {}", err);
+            return inactive;
+        }
+    };
+
+    let exp = expires
+        .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+        .and_then(|expires| expires.as_secs().try_into().ok());
+
+    IntrospectResponse {
+        active: true,
+        sub: Some(Box::new(user.id.to_string())),
+        username: Some(Box::new(user.username)),
+        scope: scope.map(Box::new),
+        exp: exp.map(Box::new),
+        token_type: Some(Box::new(token_type::format(token_type).to_string())),
+    }
+}
@@ -0,0 +1,8 @@
+pub use authenticate::authenticate;
+pub use introspect::introspect;
+pub use revoke::revoke;
+
+mod authenticate;
+mod introspect;
+mod revoke;
+mod token_type;
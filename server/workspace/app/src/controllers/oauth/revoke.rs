@@ -0,0 +1,31 @@
+use lib_authentication::{ProviderInterface, Result};
+use lib_base64::decode;
+use lib_json_schema::schema::oauth::RevokeRequest;
+
+use super::token_type;
+
+/// Revokes a token on behalf of a service client.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `revoke_request` - The revocation request.
+///
+/// # Errors
+///
+/// Returns an error if the token could not be revoked. Malformed and unknown tokens are not
+/// errors.
+pub async fn revoke(
+    provider: &impl ProviderInterface,
+    revoke_request: &RevokeRequest,
+) -> Result<()> {
+    let Ok(token) = decode(&revoke_request.token) else { return Ok(()) };
+    let token_type_hint = token_type::parse(
+        revoke_request
+            .token_type_hint
+            .as_deref()
+            .map(String::as_str),
+    );
+
+    provider.revoke(&token, token_type_hint).await
+}
@@ -0,0 +1,24 @@
+use lib_authentication::TokenType;
+
+/// The RFC 7009 name of an authentication token.
+const ACCESS_TOKEN: &str = "access_token";
+
+/// The RFC 7009 name of a refresh token.
+const REFRESH_TOKEN: &str = "refresh_token";
+
+/// Parses a `token_type_hint`, ignoring hints that are not recognized.
+pub fn parse(hint: Option<&str>) -> Option<TokenType> {
+    match hint? {
+        ACCESS_TOKEN => Some(TokenType::Auth),
+        REFRESH_TOKEN => Some(TokenType::Refresh),
+        _ => None,
+    }
+}
+
+/// Formats a token type as its RFC 7009 name.
+pub fn format(token_type: TokenType) -> &'static str {
+    match token_type {
+        TokenType::Auth => ACCESS_TOKEN,
+        TokenType::Refresh => REFRESH_TOKEN,
+    }
+}
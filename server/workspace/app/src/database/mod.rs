@@ -1,7 +1,7 @@
 use std::time::Duration;
 
-use lib_database::{Connection, Options, Result};
-use lib_environment::{DbConnectionString, EnvironmentVariable};
+use lib_database::{Connection, Error, Options, Result};
+use lib_environment::{DbConnectionString, DbMigrateOnStartup, EnvironmentVariable};
 
 pub async fn open() -> Result<Connection> {
     log::info!("Opening database connection");
@@ -13,9 +13,20 @@ pub async fn open() -> Result<Connection> {
     let db_connection = Connection::connect(options).await?;
     log::info!("Database connection established");
 
-    log::info!("Applying migrations");
-    db_connection.migrate().await?;
-    log::info!("Migrations complete");
+    if DbMigrateOnStartup::get() {
+        log::info!("Applying migrations");
+        db_connection.migrate().await?;
+        log::info!("Migrations complete");
+    } else {
+        let pending = db_connection.pending_migrations().await?;
+        if !pending.is_empty() {
+            log::error!(
+                "Pending migrations, apply them with `migrate up`: {}",
+                pending.join(", ")
+            );
+            return Err(Error::Migration("Pending migrations".to_string()));
+        }
+    }
 
     Ok(db_connection)
 }
@@ -7,13 +7,25 @@
     clippy::pedantic
 )]
 
+use std::time::Duration;
+
 use actix_web::{middleware as aw_middleware, web, App, HttpServer};
 
-use lib_environment::{EnvironmentVariable, RedisCacheConnectionString};
+use lib_authentication::ProviderInterface;
+use lib_base64::Encode;
+use lib_environment::{
+    AdminPassword, AdminPasswordPath, AdminUsername, AuthTokenExtendInterval, AuthTokenMaxLifetime,
+    AuthTokenTtl, EncryptionKeyPath, EnvironmentVariable, PasswordPepperPath,
+    RedisCacheConnectionString, RedisCommandTimeout, RedisConnectTimeout, RedisFailureThreshold,
+    RedisPoolSize, RedisRetryMaxDelay, RedisRetryMinDelay, RedisWaitTimeout, ServiceCredentials,
+    SigningKeyPath, TrustedProxies, WebauthnRpId, WebauthnRpName, WebauthnRpOrigin,
+};
 
+mod commands;
 mod controllers;
 mod database;
 mod middleware;
+mod problem;
 mod routes;
 
 #[actix_web::main]
@@ -21,6 +33,40 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
     let logger_format = "%a %t \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %D";
 
+    if let Some(command) = std::env::args().nth(1) {
+        return commands::run(&command).await;
+    }
+
+    let keyring = match lib_crypto::Keyring::load_or_create(EncryptionKeyPath::get()) {
+        Ok(keyring) => keyring,
+        Err(e) => {
+            log::error!("Failed to load the encryption keyring: {}", e);
+            return Err(std::io::Error::other("Failed to load the encryption keyring"));
+        }
+    };
+    lib_database::set_keyring(keyring.clone());
+    let keyring = web::Data::new(keyring);
+
+    let signing_keyring = match lib_crypto::SigningKeyring::load_or_create(SigningKeyPath::get()) {
+        Ok(signing_keyring) => signing_keyring,
+        Err(e) => {
+            log::error!("Failed to load the signing keyring: {}", e);
+            return Err(std::io::Error::other("Failed to load the signing keyring"));
+        }
+    };
+    let signing_keyring = web::Data::new(signing_keyring);
+
+    let pepper = match PasswordPepperPath::get()
+        .map(lib_crypto::Keyring::load)
+        .transpose()
+    {
+        Ok(pepper) => pepper,
+        Err(e) => {
+            log::error!("Failed to load the password pepper: {}", e);
+            return Err(std::io::Error::other("Failed to load the password pepper"));
+        }
+    };
+
     let Ok(db_connection) = database::open().await else {
         log::error!("Failed to open database connection");
         return Err(std::io::Error::new(
@@ -30,7 +76,17 @@ async fn main() -> std::io::Result<()> {
     };
 
     let redis_url = RedisCacheConnectionString::get();
-    let Ok(cache_controller) = lib_cache::Controller::open(&redis_url) else {
+    let cache_options = lib_cache::Options::default()
+        .with_pool_size(RedisPoolSize::get())
+        .with_connect_timeout(Duration::from_millis(RedisConnectTimeout::get()))
+        .with_wait_timeout(Duration::from_millis(RedisWaitTimeout::get()))
+        .with_command_timeout(Duration::from_millis(RedisCommandTimeout::get()))
+        .with_retry_delay(
+            Duration::from_millis(RedisRetryMinDelay::get()),
+            Duration::from_millis(RedisRetryMaxDelay::get()),
+        )
+        .with_failure_threshold(RedisFailureThreshold::get());
+    let Ok(cache) = lib_cache::Cache::open_with(&redis_url, &cache_options) else {
         log::error!("Failed to open cache connection");
         return Err(std::io::Error::new(
             std::io::ErrorKind::Other,
@@ -38,22 +94,136 @@ async fn main() -> std::io::Result<()> {
         ));
     };
 
-    let user_repo = lib_authentication::UserRepo::database(db_connection.clone());
-    let auth_token_repo =
-        lib_authentication::TokenRepo::cache(cache_controller.clone(), "a".to_string());
-    let refresh_token_repo =
-        lib_authentication::TokenRepo::cache(cache_controller.clone(), "r".to_string());
+    // Sessions stored by earlier versions are keyed by the raw token.
+    for prefix in ["a", "r", "c"] {
+        match lib_authentication::purge_legacy_tokens(&cache, prefix).await {
+            Ok(0) => {}
+            Ok(count) => log::info!("Invalidated {} tokens stored without hashing", count),
+            Err(e) => log::warn!("Failed to invalidate tokens stored without hashing: {}", e),
+        }
+    }
+
+    // Other server instances subscribe to these to drop what they know about a
+    // user or session that has changed.
+    let events = lib_authentication::events_channel(cache.clone());
+
+    let user_repo = match pepper {
+        Some(pepper) => {
+            lib_authentication::UserRepo::database_with_pepper(db_connection.clone(), pepper)
+        }
+        None => lib_authentication::UserRepo::database(db_connection.clone()),
+    };
+    let user_repo = user_repo.with_events(events.clone());
+    let auth_token_repo = match AuthTokenMaxLifetime::get() {
+        0 => lib_authentication::TokenRepo::cache(cache.clone(), "a".to_string()),
+        max_lifetime => lib_authentication::TokenRepo::cache_with_sliding_expiry(
+            cache.clone(),
+            "a".to_string(),
+            lib_authentication::SlidingExpiry {
+                idle_timeout: Duration::from_secs(AuthTokenTtl::get()),
+                extend_interval: Duration::from_secs(AuthTokenExtendInterval::get()),
+                max_lifetime: Duration::from_secs(max_lifetime),
+            },
+        ),
+    };
+    let refresh_token_repo = lib_authentication::TokenRepo::cache(cache.clone(), "r".to_string());
+    let passkey_repo = lib_authentication::PasskeyRepo::database(db_connection.clone());
+    let challenge_token_repo = lib_authentication::TokenRepo::cache(cache.clone(), "c".to_string());
+
+    let Ok(relying_party) = lib_authentication::RelyingParty::new(
+        &WebauthnRpId::get(),
+        &WebauthnRpOrigin::get(),
+        &WebauthnRpName::get(),
+    ) else {
+        log::error!("Failed to configure the WebAuthn relying party");
+        return Err(std::io::Error::other("Failed to configure the WebAuthn relying party"));
+    };
 
     // let auth_token_repo = lib_authentication::TokenRepo::memory();
     // let refresh_token_repo = lib_authentication::TokenRepo::memory();
     // let user_repo = lib_authentication::UserRepo::memory();
-    let auth_provider =
-        lib_authentication::Provider::new(auth_token_repo, refresh_token_repo, user_repo);
+    // let passkey_repo = lib_authentication::PasskeyRepo::memory();
+    // let challenge_token_repo = lib_authentication::TokenRepo::memory();
+    let auth_provider = lib_authentication::Provider::new(
+        auth_token_repo,
+        refresh_token_repo,
+        user_repo,
+        passkey_repo,
+        challenge_token_repo,
+        relying_party,
+    )
+    .with_events(events);
+
+    let admin_password = match AdminPassword::get() {
+        Some(password) => Some(password),
+        None => match AdminPasswordPath::get()
+            .map(std::fs::read_to_string)
+            .transpose()
+        {
+            Ok(password) => {
+                password.map(|password| password.trim_end_matches(['\r', '\n']).to_string())
+            }
+            Err(e) => {
+                log::error!("Failed to read the admin password: {}", e);
+                return Err(std::io::Error::other("Failed to read the admin password"));
+            }
+        },
+    };
+    let admin_username = AdminUsername::get();
+    match auth_provider
+        .bootstrap(&admin_username, admin_password.as_deref())
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(token)) => match token.encode() {
+            Ok(token) => log::warn!(
+                "{} has no password yet, set one within the hour with POST /auth/setup and the one-time setup token {}",
+                admin_username,
+                token
+            ),
+            Err(e) => log::error!("Failed to encode the setup token: {}", e),
+        },
+        Err(e) => {
+            log::error!("Failed to set up {}: {}", admin_username, e);
+            return Err(std::io::Error::other("Failed to set up the admin"));
+        }
+    }
+
     let auth_provider = web::Data::new(auth_provider);
 
+    let service_credentials = match ServiceCredentials::get() {
+        Ok(service_credentials) => service_credentials,
+        Err(e) => {
+            log::error!("Failed to read the service credentials: {}", e);
+            return Err(std::io::Error::other("Failed to read the service credentials"));
+        }
+    };
+    let Ok(client_repo) = lib_authentication::ClientRepo::memory(service_credentials) else {
+        log::error!("Failed to set up the client repository");
+        return Err(std::io::Error::other("Failed to set up the client repository"));
+    };
+    let client_repo = web::Data::new(client_repo);
+
+    let trusted_proxies = match TrustedProxies::get() {
+        Ok(trusted_proxies) => trusted_proxies,
+        Err(e) => {
+            log::error!("Failed to read the trusted proxies: {}", e);
+            return Err(std::io::Error::other("Failed to read the trusted proxies"));
+        }
+    };
+    let rate_limiter = web::Data::new(
+        middleware::RateLimiter::new(cache.clone()).with_trusted_proxies(trusted_proxies),
+    );
+    let cache = web::Data::new(cache);
+
     HttpServer::new(move || {
         App::new()
             .app_data(auth_provider.clone())
+            .app_data(client_repo.clone())
+            .app_data(keyring.clone())
+            .app_data(signing_keyring.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(cache.clone())
             .wrap(aw_middleware::Logger::new(logger_format))
             .wrap(middleware::BearerToken)
             .configure(routes::register)
@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use actix_web::body::MessageBody;
+use actix_web::web::{self, ReqData};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures::future::LocalBoxFuture;
+
+use lib_authentication::{Client, ClientRepo, ClientRepoInterface};
+use lib_base64::decode_standard;
+
+use crate::problem::Problem;
+
+pub type RequestClient = ReqData<Option<Client>>;
+
+/// The credentials a service client presented with its request.
+#[derive(Clone)]
+pub struct Credentials {
+    /// The ID of the client.
+    pub client_id: String,
+
+    /// The secret of the client.
+    pub client_secret: String,
+}
+
+/// A middleware that authenticates service clients by the credentials in a
+/// `Basic` `Authorization` header, checked against the [`ClientRepo`] in the
+/// application data.
+///
+/// The authenticated client is added to the request, or `None` if there were no
+/// credentials or the wrong ones, so that later middleware only ever sees
+/// clients that proved who they are. Requests fail with HTTP 503 `unavailable`
+/// if the client repository could not be read.
+#[derive(Default)]
+pub struct ClientCredentials;
+
+impl<S, B> Transform<S, ServiceRequest> for ClientCredentials
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = Middleware<S>;
+    type InitError = ();
+    type Future = futures::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        futures::future::ok(Middleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct Middleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for Middleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let credentials = credentials(&req);
+        let client_repo = req.app_data::<web::Data<ClientRepo>>().cloned();
+
+        Box::pin(async move {
+            let client = match (credentials, client_repo) {
+                (Some(credentials), Some(client_repo)) => client_repo
+                    .check_secret(&credentials.client_id, &credentials.client_secret)
+                    .await
+                    .map_err(|e| Problem::from(lib_authentication::Error::from(e)))?,
+                _ => None,
+            };
+
+            req.extensions_mut().insert(client);
+
+            service.call(req).await
+        })
+    }
+}
+
+/// Extract the credentials from a request's `Basic` `Authorization` header.
+///
+/// # Parameters
+///
+/// - `req`: The request.
+fn credentials(req: &ServiceRequest) -> Option<Credentials> {
+    let credentials = req
+        .headers()
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Basic "))
+        .and_then(|credentials| decode_standard(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())?;
+
+    let (client_id, client_secret) = credentials.split_once(':')?;
+    Some(Credentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+    })
+}
@@ -1,3 +1,7 @@
 pub mod bearer_token;
+pub mod client_credentials;
+pub mod rate_limit;
 
 pub use bearer_token::BearerToken;
+pub use client_credentials::ClientCredentials;
+pub use rate_limit::{RateLimit, RateLimiter};
@@ -0,0 +1,504 @@
+use std::net::IpAddr;
+use std::rc::Rc;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER, X_FORWARDED_FOR};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage, ResponseError,
+};
+use futures::future::LocalBoxFuture;
+
+use lib_authentication::{AuthToken, Client as ServiceClient, TokenInterface};
+use lib_base64::encode;
+use lib_cache::{Cache, Decision, Interface, Quota};
+
+use crate::problem::{ErrorCode, Problem};
+
+/// The prefix of the keys quotas are kept under.
+const PREFIX: &str = "ratelimit";
+
+/// The header telling how many more requests the client may make.
+const REMAINING: &str = "ratelimit-remaining";
+
+/// Checks requests against quotas kept in the cache, so that every server
+/// instance shares them.
+///
+/// When the cache cannot be reached, quotas are kept in process instead, so
+/// each instance enforces them separately until it is back. A deployment with
+/// a single instance can use an in-process cache throughout.
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: Cache,
+    fallback: Cache,
+    trusted_proxies: Vec<(IpAddr, u8)>,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter.
+    ///
+    /// # Parameters
+    ///
+    /// - `cache`: The cache to keep quotas in.
+    #[must_use]
+    pub fn new(cache: Cache) -> Self {
+        Self {
+            cache,
+            fallback: Cache::memory(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    /// Trust reverse proxies to report the address a request came from.
+    ///
+    /// # Parameters
+    ///
+    /// - `trusted_proxies`: The networks of the proxies, as an address and prefix length.
+    #[must_use]
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<(IpAddr, u8)>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Admit a request against a quota.
+    ///
+    /// # Parameters
+    ///
+    /// - `key`: The key the quota is kept under.
+    /// - `quota`: The quota to admit the request against.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither the cache nor the fallback could check the quota.
+    pub async fn throttle(&self, key: &str, quota: &Quota) -> lib_cache::Result<Decision> {
+        match self.cache.throttle(key, quota).await {
+            Ok(decision) => Ok(decision),
+            Err(e) => {
+                log::warn!("Falling back to in-process rate limits: {}", e);
+                self.fallback.throttle(key, quota).await
+            }
+        }
+    }
+}
+
+/// What identifies the requests that share a quota.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Client {
+    /// The authenticated service client or the user's bearer token. Anonymous
+    /// requests, and clients that failed to authenticate, are not limited.
+    ///
+    /// Bearer tokens are not checked before the quota is, so a route that can
+    /// be reached without valid credentials should also be limited by IP
+    /// address, in a scope of its own.
+    #[default]
+    Principal,
+
+    /// The IP address the request came from.
+    Ip,
+}
+
+/// A middleware that limits how many requests each client may make to a scope,
+/// responding with HTTP 429 `rate_limited` once the quota is used up.
+///
+/// Every response carries the `RateLimit-Limit`, `RateLimit-Remaining`,
+/// `RateLimit-Reset` and `RateLimit-Policy` headers, and rejected requests a
+/// `Retry-After` header. Quotas are checked with the [`RateLimiter`] in the
+/// application data, and requests pass unchecked if there is none.
+///
+/// The IP address is that of the connection. When it comes from a trusted
+/// proxy, the address the proxy reports in the `X-Forwarded-For` header is used
+/// instead, skipping over any other trusted proxies.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    scope: Rc<str>,
+    quota: Option<Quota>,
+    client: Client,
+}
+
+impl RateLimit {
+    /// Create a rate limit for a scope, keyed by principal.
+    ///
+    /// # Parameters
+    ///
+    /// - `scope`: The name of the scope, which keeps its quotas apart from other scopes'.
+    /// - `quota`: The quota each client is given, or `None` to not limit the scope.
+    #[must_use]
+    pub fn new(scope: &str, quota: Option<Quota>) -> Self {
+        Self {
+            scope: scope.into(),
+            quota,
+            client: Client::default(),
+        }
+    }
+
+    /// Select what identifies the requests that share a quota.
+    ///
+    /// # Parameters
+    ///
+    /// - `client`: What identifies a client.
+    #[must_use]
+    pub fn by(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = Middleware<S>;
+    type InitError = ();
+    type Future = futures::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        futures::future::ok(Middleware {
+            service: Rc::new(service),
+            limit: self.clone(),
+        })
+    }
+}
+
+pub struct Middleware<S> {
+    service: Rc<S>,
+    limit: RateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for Middleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+        let checked = self.limit.quota.zip(limiter);
+        let key = checked
+            .as_ref()
+            .and_then(|(_, limiter)| self.key(&req, limiter));
+
+        Box::pin(async move {
+            let (Some((quota, limiter)), Some(key)) = (checked, key) else {
+                return Ok(service.call(req).await?.map_into_left_body());
+            };
+
+            let decision = match limiter.throttle(&key, &quota).await {
+                Ok(decision) => decision,
+                Err(e) => {
+                    // Failing open keeps the service up when quotas cannot be checked at all.
+                    log::error!("Error while checking rate limit: {}", e);
+                    return Ok(service.call(req).await?.map_into_left_body());
+                }
+            };
+
+            if !decision.allowed {
+                let mut response = Problem::new(ErrorCode::RateLimited).error_response();
+                insert_headers(response.headers_mut(), &quota, &decision);
+                response.headers_mut().insert(
+                    RETRY_AFTER,
+                    HeaderValue::from(seconds(&decision.retry_after)),
+                );
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+
+            let mut response = service.call(req).await?;
+            insert_headers(response.headers_mut(), &quota, &decision);
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+impl<S> Middleware<S> {
+    /// Get the key the quota of the request's client is kept under, or `None`
+    /// if the request has no principal to limit.
+    ///
+    /// Bearer tokens are hashed, so they are never written to the cache.
+    fn key(&self, req: &ServiceRequest, limiter: &RateLimiter) -> Option<String> {
+        let scope = &self.limit.scope;
+        match self.limit.client {
+            Client::Principal => {
+                principal(req).map(|principal| format!("{PREFIX}:{scope}:principal:{principal}"))
+            }
+            Client::Ip => {
+                let ip = client_ip(req, &limiter.trusted_proxies);
+                Some(format!("{PREFIX}:{scope}:ip:{ip}"))
+            }
+        }
+    }
+}
+
+/// Identify the principal making a request by the client authenticated by the
+/// [`ClientCredentials`] middleware, or the bearer token extracted by the
+/// [`BearerToken`] middleware.
+///
+/// [`ClientCredentials`]: super::ClientCredentials
+/// [`BearerToken`]: super::BearerToken
+///
+/// # Parameters
+///
+/// - `req`: The request.
+fn principal(req: &ServiceRequest) -> Option<String> {
+    let extensions = req.extensions();
+    if let Some(Some(client)) = extensions.get::<Option<ServiceClient>>() {
+        return Some(format!("client:{}", client.id));
+    }
+
+    let Some(Some(token)) = extensions.get::<Option<AuthToken>>() else { return None };
+    encode(token.hash())
+        .ok()
+        .map(|hash| format!("token:{hash}"))
+}
+
+/// Identify the IP address a request came from.
+///
+/// Each proxy appends the address it received the request from to the
+/// `X-Forwarded-For` header, so entries are read from the right for as long as
+/// they were appended by a trusted proxy. Entries further left could have been
+/// sent by the client itself.
+///
+/// # Parameters
+///
+/// - `req`: The request.
+/// - `trusted_proxies`: The networks of the trusted proxies.
+fn client_ip(req: &ServiceRequest, trusted_proxies: &[(IpAddr, u8)]) -> String {
+    let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+        return "unknown".to_string();
+    };
+
+    let forwarded = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    let mut ip = peer.to_string();
+    let mut hop = Some(peer);
+    for entry in forwarded.iter().rev() {
+        let trusted = hop.is_some_and(|hop| {
+            trusted_proxies
+                .iter()
+                .any(|&(network, prefix_length)| contains(network, prefix_length, hop))
+        });
+        if !trusted {
+            break;
+        }
+
+        ip = (*entry).to_string();
+        hop = entry.parse().ok();
+    }
+
+    ip
+}
+
+/// Check whether a network contains an IP address.
+///
+/// # Parameters
+///
+/// - `network`: The address of the network.
+/// - `prefix_length`: The number of leading bits that identify the network.
+/// - `ip`: The address to check.
+fn contains(network: IpAddr, prefix_length: u8, ip: IpAddr) -> bool {
+    let (network, ip, bits) = match (network.to_canonical(), ip.to_canonical()) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => (
+            u128::from(u32::from(network)),
+            u128::from(u32::from(ip)),
+            32,
+        ),
+        (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+        _ => return false,
+    };
+
+    (network ^ ip)
+        .checked_shr(bits - u32::from(prefix_length))
+        .unwrap_or(0)
+        == 0
+}
+
+/// Describe the client's quota in the `RateLimit-*` headers of a response,
+/// unless they already describe a quota with fewer requests remaining, so that
+/// the tightest of several nested limits is reported.
+///
+/// # Parameters
+///
+/// - `headers`: The headers of the response.
+/// - `quota`: The quota the request was checked against.
+/// - `decision`: What is left of the quota.
+fn insert_headers(headers: &mut HeaderMap, quota: &Quota, decision: &Decision) {
+    let remaining = headers
+        .get(REMAINING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if matches!(remaining, Some(remaining) if remaining < decision.remaining) {
+        return;
+    }
+
+    let policy = format!("{};w={}", quota.limit(), seconds(&quota.window()));
+    let values = [
+        ("ratelimit-limit", decision.limit.to_string()),
+        (REMAINING, decision.remaining.to_string()),
+        ("ratelimit-reset", seconds(&decision.reset).to_string()),
+        ("ratelimit-policy", policy),
+    ];
+
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::try_from(value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+}
+
+/// Round a duration up to whole seconds, as the headers express it.
+fn seconds(duration: &std::time::Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, test, App, HttpResponse};
+
+    use lib_authentication::ClientRepo;
+
+    use super::*;
+    use crate::middleware::ClientCredentials;
+
+    #[actix_web::test]
+    async fn test_anonymous_limited_by_ip() {
+        let window = Duration::from_mins(1);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RateLimiter::new(Cache::memory())))
+                .wrap(RateLimit::new("client", Quota::new(1, &window).ok()))
+                .wrap(ClientCredentials)
+                .wrap(RateLimit::new("ip", Quota::new(2, &window).ok()).by(Client::Ip))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for status in [
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS,
+        ] {
+            let req = test::TestRequest::get().uri("/").to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_failed_client_authentication_limited_by_ip_only() {
+        let window = Duration::from_mins(1);
+        let secret_hash = lib_crypto::hash_password(b"secret", None).unwrap();
+        let client_repo = ClientRepo::memory([("service".to_string(), secret_hash)]).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RateLimiter::new(Cache::memory())))
+                .app_data(web::Data::new(client_repo))
+                .wrap(RateLimit::new("client", Quota::new(1, &window).ok()))
+                .wrap(ClientCredentials)
+                .wrap(RateLimit::new("ip", Quota::new(4, &window).ok()).by(Client::Ip))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Guessing the secret, as service:wrong, does not use up the quota of
+        // the client authenticating as service:secret.
+        for (status, credentials) in [
+            (StatusCode::OK, "c2VydmljZTp3cm9uZw=="),
+            (StatusCode::OK, "c2VydmljZTp3cm9uZw=="),
+            (StatusCode::OK, "c2VydmljZTpzZWNyZXQ="),
+            (StatusCode::TOO_MANY_REQUESTS, "c2VydmljZTpzZWNyZXQ="),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .insert_header(("Authorization", format!("Basic {credentials}")))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_forwarded_headers_ignored_from_untrusted_peer() {
+        let window = Duration::from_mins(1);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(RateLimiter::new(Cache::memory())))
+                .wrap(RateLimit::new("ip", Quota::new(1, &window).ok()).by(Client::Ip))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // Spoofing the address in each request does not get a new quota.
+        for (status, spoofed) in [
+            (StatusCode::OK, "192.0.2.1"),
+            (StatusCode::TOO_MANY_REQUESTS, "192.0.2.2"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .peer_addr("203.0.113.1:1234".parse().unwrap())
+                .insert_header(("Forwarded", format!("for={spoofed}")))
+                .insert_header((X_FORWARDED_FOR, spoofed))
+                .insert_header(("X-Real-IP", spoofed))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_forwarded_for_read_from_trusted_proxy() {
+        let window = Duration::from_mins(1);
+        let limiter = RateLimiter::new(Cache::memory())
+            .with_trusted_proxies(vec![("10.0.0.0".parse().unwrap(), 8)]);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(limiter))
+                .wrap(RateLimit::new("ip", Quota::new(1, &window).ok()).by(Client::Ip))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // The entry left of the proxies' is set by the client, so does not count.
+        for (status, forwarded_for) in [
+            (StatusCode::OK, "192.0.2.1, 203.0.113.1, 10.0.0.2"),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                "192.0.2.2, 203.0.113.1, 10.0.0.2",
+            ),
+            (StatusCode::OK, "203.0.113.2, 10.0.0.2"),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .peer_addr("10.0.0.1:1234".parse().unwrap())
+                .insert_header((X_FORWARDED_FOR, forwarded_for))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_contains() {
+        let network = "10.1.0.0".parse().unwrap();
+        assert!(contains(network, 16, "10.1.2.3".parse().unwrap()));
+        assert!(!contains(network, 16, "10.2.0.1".parse().unwrap()));
+        assert!(contains(network, 0, "192.0.2.1".parse().unwrap()));
+        assert!(contains(network, 16, "::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!contains(network, 16, "fd00::1".parse().unwrap()));
+        assert!(contains(
+            "fd00::".parse().unwrap(),
+            8,
+            "fd12::1".parse().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE, WWW_AUTHENTICATE};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use lib_authentication::{ClientRepoError, Error, PasskeyRepoError, TokenRepoError, UserRepoError};
+use lib_json_schema::schema::error::ProblemResponse;
+
+/// The media type of an RFC 7807 problem document.
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A stable, machine-readable code identifying why a request failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCode {
+    /// The request body or one of its fields could not be understood.
+    InvalidRequest,
+
+    /// The credentials were wrong. Unknown users are reported the same way,
+    /// so this does not reveal whether an account exists.
+    InvalidCredentials,
+
+    /// The bearer or refresh token was missing, unknown or expired.
+    InvalidToken,
+
+    /// The service client's credentials were missing or wrong.
+    InvalidClient,
+
+    /// The authenticator's answer to a passkey registration challenge could not be verified.
+    PasskeyRejected,
+
+    /// Another user already has the requested username.
+    UsernameTaken,
+
+    /// The client has made too many requests, and should wait before trying again.
+    RateLimited,
+
+    /// A backing store, such as the database or cache, could not be reached.
+    Unavailable,
+
+    /// The request failed for a reason the client cannot fix.
+    Internal,
+}
+
+impl ErrorCode {
+    /// Get the code as sent to clients.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid_request",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::InvalidToken => "invalid_token",
+            Self::InvalidClient => "invalid_client",
+            Self::PasskeyRejected => "passkey_rejected",
+            Self::UsernameTaken => "username_taken",
+            Self::RateLimited => "rate_limited",
+            Self::Unavailable => "unavailable",
+            Self::Internal => "internal_error",
+        }
+    }
+
+    /// Get the HTTP status code to respond with.
+    #[must_use]
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::InvalidRequest | Self::PasskeyRejected => StatusCode::BAD_REQUEST,
+            Self::InvalidCredentials | Self::InvalidToken | Self::InvalidClient => {
+                StatusCode::UNAUTHORIZED
+            }
+            Self::UsernameTaken => StatusCode::CONFLICT,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Get the default human-readable explanation of the code.
+    #[must_use]
+    pub fn detail(self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "The request could not be understood.",
+            Self::InvalidCredentials => "The credentials are invalid.",
+            Self::InvalidToken => "The token is missing, invalid or expired.",
+            Self::InvalidClient => "The client credentials are missing or invalid.",
+            Self::PasskeyRejected => "The passkey could not be verified.",
+            Self::UsernameTaken => "The username is already taken.",
+            Self::RateLimited => "Too many requests were made, try again later.",
+            Self::Unavailable => "The service is temporarily unavailable.",
+            Self::Internal => "An internal error occurred.",
+        }
+    }
+}
+
+/// An error response, sent to the client as an RFC 7807 problem document.
+#[derive(Debug)]
+pub struct Problem {
+    code: ErrorCode,
+    detail: Option<String>,
+}
+
+impl Problem {
+    /// Create a problem with the default explanation of the given code.
+    #[must_use]
+    pub fn new(code: ErrorCode) -> Self {
+        Self { code, detail: None }
+    }
+
+    /// Replace the default explanation of the problem.
+    ///
+    /// # Parameters
+    ///
+    /// - `detail`: The human-readable explanation to send to the client.
+    #[must_use]
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+impl From<ErrorCode> for Problem {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code)
+    }
+}
+
+impl From<Error> for Problem {
+    fn from(err: Error) -> Self {
+        let code = match &err {
+            Error::ClientRepoError(ClientRepoError::NotAvailable)
+            | Error::PasskeyRepoError(PasskeyRepoError::NotAvailable)
+            | Error::TokenRepoError(TokenRepoError::NotAvailable)
+            | Error::UserRepoError(UserRepoError::NotAvailable) => ErrorCode::Unavailable,
+            Error::TokenRepoError(
+                TokenRepoError::TokenNotFound
+                | TokenRepoError::TokenExpired
+                | TokenRepoError::TokenInvalid,
+            ) => ErrorCode::InvalidToken,
+            Error::UserRepoError(UserRepoError::UsernameTaken) => ErrorCode::UsernameTaken,
+            Error::CryptoError(_)
+            | Error::PasskeyRepoError(PasskeyRepoError::CreateFailed)
+            | Error::SerializationError(_)
+            | Error::TokenRepoError(TokenRepoError::TokenRepoError(_))
+            | Error::UserRepoError(
+                UserRepoError::CreateFailed | UserRepoError::DeleteFailed | UserRepoError::NotFound,
+            )
+            | Error::WebauthnError(_) => ErrorCode::Internal,
+        };
+
+        if code.status().is_server_error() {
+            log::error!("{}", err);
+        }
+
+        Self::new(code)
+    }
+}
+
+/// Tokens are only encoded on the way out, so a failure is never the client's fault.
+impl From<lib_base64::Error> for Problem {
+    fn from(err: lib_base64::Error) -> Self {
+        log::error!("Error while encoding token: {}", err);
+        Self::new(ErrorCode::Internal)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code.as_str())
+    }
+}
+
+impl ResponseError for Problem {
+    fn status_code(&self) -> StatusCode {
+        self.code.status()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let mut response = HttpResponse::build(status);
+        response.insert_header((CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON)));
+        match self.code {
+            ErrorCode::InvalidToken => {
+                response.insert_header((WWW_AUTHENTICATE, "Bearer error=\"invalid_token\""));
+            }
+            ErrorCode::InvalidClient => {
+                response.insert_header((WWW_AUTHENTICATE, "Basic realm=\"oauth\""));
+            }
+            _ => {}
+        }
+
+        response.json(ProblemResponse {
+            type_: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: self.code.as_str().to_string(),
+            detail: self
+                .detail
+                .clone()
+                .unwrap_or_else(|| self.code.detail().to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        let codes = [
+            (ErrorCode::InvalidRequest, "invalid_request", 400),
+            (ErrorCode::InvalidCredentials, "invalid_credentials", 401),
+            (ErrorCode::InvalidToken, "invalid_token", 401),
+            (ErrorCode::InvalidClient, "invalid_client", 401),
+            (ErrorCode::PasskeyRejected, "passkey_rejected", 400),
+            (ErrorCode::UsernameTaken, "username_taken", 409),
+            (ErrorCode::RateLimited, "rate_limited", 429),
+            (ErrorCode::Unavailable, "unavailable", 503),
+            (ErrorCode::Internal, "internal_error", 500),
+        ];
+
+        for (code, name, status) in codes {
+            assert_eq!(code.as_str(), name);
+            assert_eq!(code.status().as_u16(), status);
+        }
+    }
+
+    #[test]
+    fn test_from_error() {
+        let errors = [
+            (
+                Error::UserRepoError(UserRepoError::NotAvailable),
+                ErrorCode::Unavailable,
+            ),
+            (
+                Error::ClientRepoError(ClientRepoError::NotAvailable),
+                ErrorCode::Unavailable,
+            ),
+            (
+                Error::TokenRepoError(TokenRepoError::TokenExpired),
+                ErrorCode::InvalidToken,
+            ),
+            (
+                Error::UserRepoError(UserRepoError::UsernameTaken),
+                ErrorCode::UsernameTaken,
+            ),
+            (
+                Error::UserRepoError(UserRepoError::CreateFailed),
+                ErrorCode::Internal,
+            ),
+            (
+                Error::UserRepoError(UserRepoError::NotFound),
+                ErrorCode::Internal,
+            ),
+            (
+                Error::PasskeyRepoError(PasskeyRepoError::CreateFailed),
+                ErrorCode::Internal,
+            ),
+        ];
+
+        for (error, code) in errors {
+            assert_eq!(Problem::from(error).code, code);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_error_response() {
+        let response = Problem::new(ErrorCode::UsernameTaken)
+            .with_detail("Try another one.")
+            .error_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+        assert!(response.headers().get(WWW_AUTHENTICATE).is_none());
+
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Conflict",
+                "status": 409,
+                "code": "username_taken",
+                "detail": "Try another one.",
+            })
+        );
+    }
+
+    #[test]
+    fn test_www_authenticate() {
+        let challenges = [
+            (ErrorCode::InvalidToken, "Bearer error=\"invalid_token\""),
+            (ErrorCode::InvalidClient, "Basic realm=\"oauth\""),
+        ];
+
+        for (code, challenge) in challenges {
+            let response = Problem::new(code).error_response();
+            assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), challenge);
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use actix_web::{get, post, web, HttpResponse, Responder};
+use actix_web::{get, post, web, HttpResponse};
 
 use lib_authentication::Provider;
-use lib_json_schema::schema::auth::{LoginRequest, RefreshRequest, RegisterRequest};
+use lib_json_schema::schema::auth::{
+    LoginRequest, PasskeyLoginRequest, PasskeyLoginStartRequest, PasskeyRegisterRequest,
+    RefreshRequest, RegisterRequest, SetupRequest,
+};
 
-use crate::controllers::auth::{login, logout, refresh, register as register_user, whoami};
+use crate::controllers::auth::{
+    authenticated_user, finish_passkey_login, finish_passkey_registration, login, logout, refresh,
+    register as register_user, setup, start_passkey_login, start_passkey_registration, whoami,
+};
 use crate::middleware::bearer_token::RequestToken;
+use crate::problem::Problem;
 
 /// Registers the routes for the authentication module.
 pub fn register(cfg: &mut web::ServiceConfig) {
@@ -12,7 +19,12 @@ pub fn register(cfg: &mut web::ServiceConfig) {
         .service(get_whoami)
         .service(get_logout)
         .service(post_refresh)
-        .service(post_register);
+        .service(post_register)
+        .service(post_setup)
+        .service(post_passkey_register_start)
+        .service(post_passkey_register_finish)
+        .service(post_passkey_login_start)
+        .service(post_passkey_login_finish);
 }
 
 /// Authenticates a user and returns a token.
@@ -25,16 +37,15 @@ pub fn register(cfg: &mut web::ServiceConfig) {
 /// # Returns
 ///
 /// - HTTP 200 with the token if the login was successful.
-/// - HTTP 401 if the login failed.
+/// - HTTP 401 `invalid_credentials` if the username or password is wrong.
+/// - HTTP 503 `unavailable` if the user or token store could not be reached.
 #[post("/login")]
 async fn post_login(
     provider: web::Data<Provider>,
     login_request: web::Json<LoginRequest>,
-) -> impl Responder {
-    match login(&provider, &login_request).await {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(response) => HttpResponse::Ok().json(response),
-    }
+) -> Result<HttpResponse, Problem> {
+    let response = login(&provider, &login_request).await?;
+    Ok(HttpResponse::Ok().json(response))
 }
 
 /// Refreshes a token.
@@ -47,16 +58,15 @@ async fn post_login(
 /// # Returns
 ///
 /// - HTTP 200 with the token if the refresh was successful.
-/// - HTTP 401 if the refresh failed.
+/// - HTTP 401 `invalid_token` if the refresh token is invalid or expired.
+/// - HTTP 503 `unavailable` if the token store could not be reached.
 #[post("/refresh")]
 async fn post_refresh(
     provider: web::Data<Provider>,
     refresh_request: web::Json<RefreshRequest>,
-) -> impl Responder {
-    match refresh(provider.as_ref(), &refresh_request).await {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(response) => HttpResponse::Ok().json(response),
-    }
+) -> Result<HttpResponse, Problem> {
+    let response = refresh(provider.as_ref(), &refresh_request).await?;
+    Ok(HttpResponse::Ok().json(response))
 }
 
 /// De-authenticates a user with the given token.
@@ -69,14 +79,15 @@ async fn post_refresh(
 /// # Returns
 ///
 /// - HTTP 200 if the logout was successful.
-/// - HTTP 401 if the logout failed.
+/// - HTTP 401 `invalid_token` if the token is missing, invalid or expired.
+/// - HTTP 503 `unavailable` if the token store could not be reached.
 #[get("/logout")]
-async fn get_logout(provider: web::Data<Provider>, bearer_token: RequestToken) -> impl Responder {
-    let Some(token) = bearer_token.as_ref() else { return HttpResponse::Unauthorized().finish() };
-    match logout(provider.as_ref(), token).await {
-        Err(_) => HttpResponse::Unauthorized().finish(),
-        Ok(_) => HttpResponse::Ok().finish(),
-    }
+async fn get_logout(
+    provider: web::Data<Provider>,
+    bearer_token: RequestToken,
+) -> Result<HttpResponse, Problem> {
+    logout(provider.as_ref(), bearer_token.as_ref()).await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
 /// Registers a new user.
@@ -89,16 +100,37 @@ async fn get_logout(provider: web::Data<Provider>, bearer_token: RequestToken) -
 /// # Returns
 ///
 /// - HTTP 201 if the registration was successful.
-/// - HTTP 401 if the registration failed.
+/// - HTTP 409 `username_taken` if another user has the username.
+/// - HTTP 503 `unavailable` if the user store could not be reached.
 #[post("/register")]
 async fn post_register(
     provider: web::Data<Provider>,
     register_credentials: web::Json<RegisterRequest>,
-) -> impl Responder {
-    match register_user(provider.as_ref(), &register_credentials).await {
-        Err(_) => HttpResponse::Unauthorized().finish(),
-        Ok(_) => HttpResponse::Created().finish(),
-    }
+) -> Result<HttpResponse, Problem> {
+    register_user(provider.as_ref(), &register_credentials).await?;
+    Ok(HttpResponse::Created().finish())
+}
+
+/// Sets the password of the first admin with the one-time setup token printed
+/// at startup.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `setup_request` - The setup request.
+///
+/// # Returns
+///
+/// - HTTP 204 if the password was set.
+/// - HTTP 401 `invalid_token` if the setup token is wrong or was already used.
+/// - HTTP 503 `unavailable` if the user store could not be reached.
+#[post("/setup")]
+async fn post_setup(
+    provider: web::Data<Provider>,
+    setup_request: web::Json<SetupRequest>,
+) -> Result<HttpResponse, Problem> {
+    setup(provider.as_ref(), &setup_request).await?;
+    Ok(HttpResponse::NoContent().finish())
 }
 
 /// Returns the user information for the given token.
@@ -111,13 +143,104 @@ async fn post_register(
 /// # Returns
 ///
 /// - HTTP 200 with the user information if the token is valid.
-/// - HTTP 401 if the token is invalid.
+/// - HTTP 401 `invalid_token` if the token is missing, invalid or expired.
+/// - HTTP 503 `unavailable` if the user or token store could not be reached.
 #[get("/whoami")]
-async fn get_whoami(provider: web::Data<Provider>, bearer_token: RequestToken) -> impl Responder {
-    let result = whoami(provider.as_ref(), bearer_token.as_ref()).await;
+async fn get_whoami(
+    provider: web::Data<Provider>,
+    bearer_token: RequestToken,
+) -> Result<HttpResponse, Problem> {
+    let response = whoami(provider.as_ref(), bearer_token.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Begins registering a new passkey for the authenticated user.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `bearer_token` - The bearer token.
+///
+/// # Returns
+///
+/// - HTTP 200 with the challenge for the user's authenticator.
+/// - HTTP 401 `invalid_token` if the token is missing, invalid or expired.
+/// - HTTP 503 `unavailable` if the passkey or challenge store could not be reached.
+#[post("/passkey/register/start")]
+async fn post_passkey_register_start(
+    provider: web::Data<Provider>,
+    bearer_token: RequestToken,
+) -> Result<HttpResponse, Problem> {
+    let user = authenticated_user(provider.as_ref(), bearer_token.as_ref()).await?;
+    let response = start_passkey_registration(provider.as_ref(), &user).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Completes registering a new passkey for the authenticated user.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `bearer_token` - The bearer token.
+/// - `register_request` - The authenticator's answer to the challenge.
+///
+/// # Returns
+///
+/// - HTTP 201 if the passkey was registered.
+/// - HTTP 400 `invalid_request` if the challenge or credential is malformed.
+/// - HTTP 400 `passkey_rejected` if the challenge expired or the credential could not be verified.
+/// - HTTP 401 `invalid_token` if the token is missing, invalid or expired.
+/// - HTTP 503 `unavailable` if the passkey or challenge store could not be reached.
+#[post("/passkey/register/finish")]
+async fn post_passkey_register_finish(
+    provider: web::Data<Provider>,
+    bearer_token: RequestToken,
+    register_request: web::Json<PasskeyRegisterRequest>,
+) -> Result<HttpResponse, Problem> {
+    let user = authenticated_user(provider.as_ref(), bearer_token.as_ref()).await?;
+    finish_passkey_registration(provider.as_ref(), &user, &register_request).await?;
+    Ok(HttpResponse::Created().finish())
+}
 
-    match result {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(data) => HttpResponse::Ok().json(data),
-    }
+/// Begins logging in a user with a passkey.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `start_request` - The user to log in.
+///
+/// # Returns
+///
+/// - HTTP 200 with the challenge for the user's authenticator, or a decoy
+///   challenge if the user does not exist or has no passkeys.
+/// - HTTP 503 `unavailable` if the user, passkey or challenge store could not be reached.
+#[post("/passkey/login/start")]
+async fn post_passkey_login_start(
+    provider: web::Data<Provider>,
+    start_request: web::Json<PasskeyLoginStartRequest>,
+) -> Result<HttpResponse, Problem> {
+    let response = start_passkey_login(provider.as_ref(), &start_request).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Completes logging in a user with a passkey and returns a token.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `login_request` - The authenticator's answer to the challenge.
+///
+/// # Returns
+///
+/// - HTTP 200 with the token if the login was successful.
+/// - HTTP 400 `invalid_request` if the challenge or credential is malformed.
+/// - HTTP 401 `invalid_credentials` if the challenge expired or the credential could not be verified.
+/// - HTTP 503 `unavailable` if the passkey or token store could not be reached.
+#[post("/passkey/login/finish")]
+async fn post_passkey_login_finish(
+    provider: web::Data<Provider>,
+    login_request: web::Json<PasskeyLoginRequest>,
+) -> Result<HttpResponse, Problem> {
+    let response = finish_passkey_login(provider.as_ref(), &login_request).await?;
+    Ok(HttpResponse::Ok().json(response))
 }
@@ -0,0 +1,37 @@
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::{get, web, HttpResponse};
+
+use lib_cache::Cache;
+
+use crate::controllers::health::check;
+
+/// Registers the routes for reporting the server's health.
+pub fn register(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_health);
+}
+
+/// Reports whether the services the server depends on can be reached, for
+/// load balancers and orchestrators to probe.
+///
+/// # Arguments
+///
+/// - `cache` - The cache.
+///
+/// # Returns
+///
+/// - HTTP 200 if every service is up.
+/// - HTTP 503 with the same body if any is down, including while the cache's
+///   circuit breaker is open.
+#[get("")]
+async fn get_health(cache: web::Data<Cache>) -> HttpResponse {
+    let response = check(&cache).await;
+    let mut builder = if response.status == "up" {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+
+    builder
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .json(response)
+}
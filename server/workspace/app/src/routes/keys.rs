@@ -0,0 +1,34 @@
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::{get, web, HttpResponse};
+
+use lib_crypto::SigningKeyring;
+
+use crate::controllers::keys::jwks;
+use crate::problem::Problem;
+
+/// How long clients may cache the published keys, in seconds.
+const JWKS_MAX_AGE: u32 = 300;
+
+/// Registers the routes for publishing keys.
+pub fn register(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_jwks);
+}
+
+/// Publishes the keys that verify the server's signatures, as an RFC 7517
+/// JSON Web Key Set.
+///
+/// # Arguments
+///
+/// - `signing_keyring` - The keys the server signs with.
+///
+/// # Returns
+///
+/// - HTTP 200 with the current verification key, and the previous one if the keys have been rotated.
+/// - HTTP 500 `internal_error` if a key could not be encoded.
+#[get("/jwks.json")]
+async fn get_jwks(signing_keyring: web::Data<SigningKeyring>) -> Result<HttpResponse, Problem> {
+    let response = jwks(&signing_keyring)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, format!("public, max-age={JWKS_MAX_AGE}")))
+        .json(response))
+}
@@ -1,7 +1,75 @@
+use std::time::Duration;
+
 use actix_web::web;
 
+use lib_cache::Quota;
+use lib_environment::{EnvironmentVariable, RateLimitAuth, RateLimitOauth};
+
+use crate::middleware::{self, rate_limit::Client, RateLimit};
+use crate::problem::{ErrorCode, Problem};
+
 mod auth;
+mod health;
+mod keys;
+mod oauth;
 
 pub fn register(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/auth").configure(auth::register));
+    // Services are limited by client ID once they have authenticated, and
+    // every request also by IP address, so that failed attempts are limited
+    // too. The two keep their quotas apart.
+    let oauth_quota = quota(RateLimitOauth::get());
+
+    cfg.service(
+        web::scope("/auth")
+            .app_data(json_config())
+            .wrap(RateLimit::new("auth", quota(RateLimitAuth::get())).by(Client::Ip))
+            .configure(auth::register),
+    )
+    .service(
+        web::scope("/oauth")
+            .app_data(form_config())
+            .wrap(RateLimit::new("oauth-client", oauth_quota))
+            .wrap(middleware::ClientCredentials)
+            .wrap(RateLimit::new("oauth-ip", oauth_quota).by(Client::Ip))
+            .configure(oauth::register),
+    )
+    .service(web::scope("/.well-known").configure(keys::register))
+    .service(web::scope("/health").configure(health::register));
+}
+
+/// Builds the quota of a scope from its configured `requests/seconds` limit.
+fn quota(limit: Option<(u64, u64)>) -> Option<Quota> {
+    let (requests, seconds) = limit?;
+    match Quota::new(requests, &Duration::from_secs(seconds)) {
+        Ok(quota) => Some(quota),
+        Err(e) => {
+            log::error!(
+                "Ignoring rate limit of {} requests per {}s: {}",
+                requests,
+                seconds,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Reports request bodies that are not valid JSON, or do not match the schema,
+/// as HTTP 400 `invalid_request` problems.
+fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _| {
+        Problem::new(ErrorCode::InvalidRequest)
+            .with_detail(err.to_string())
+            .into()
+    })
+}
+
+/// Reports request bodies that are not valid forms, or do not match the
+/// schema, as HTTP 400 `invalid_request` problems.
+fn form_config() -> web::FormConfig {
+    web::FormConfig::default().error_handler(|err, _| {
+        Problem::new(ErrorCode::InvalidRequest)
+            .with_detail(err.to_string())
+            .into()
+    })
 }
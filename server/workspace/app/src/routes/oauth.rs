@@ -0,0 +1,64 @@
+use actix_web::{post, web, HttpResponse};
+
+use lib_authentication::Provider;
+use lib_json_schema::schema::oauth::{IntrospectRequest, RevokeRequest};
+
+use crate::controllers::oauth::{authenticate, introspect, revoke};
+use crate::middleware::client_credentials::RequestClient;
+use crate::problem::Problem;
+
+/// Registers the routes for the OAuth module.
+pub fn register(cfg: &mut web::ServiceConfig) {
+    cfg.service(post_introspect).service(post_revoke);
+}
+
+/// Describes a token, as specified by RFC 7662.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `client` - The authenticated client.
+/// - `introspect_request` - The introspection request.
+///
+/// # Returns
+///
+/// - HTTP 200 with the token's description if the client was authenticated.
+/// - HTTP 400 `invalid_request` if the request body is malformed.
+/// - HTTP 401 `invalid_client` if the client could not be authenticated.
+/// - HTTP 503 `unavailable` if the client store could not be reached.
+#[post("/introspect")]
+async fn post_introspect(
+    provider: web::Data<Provider>,
+    client: RequestClient,
+    introspect_request: web::Form<IntrospectRequest>,
+) -> Result<HttpResponse, Problem> {
+    authenticate(client.as_ref())?;
+
+    let response = introspect(provider.as_ref(), &introspect_request).await;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Revokes a token, as specified by RFC 7009.
+///
+/// # Arguments
+///
+/// - `provider` - The authentication provider.
+/// - `client` - The authenticated client.
+/// - `revoke_request` - The revocation request.
+///
+/// # Returns
+///
+/// - HTTP 200 if the token was revoked or was not recognized.
+/// - HTTP 400 `invalid_request` if the request body is malformed.
+/// - HTTP 401 `invalid_client` if the client could not be authenticated.
+/// - HTTP 503 `unavailable` if the client or token store could not be reached.
+#[post("/revoke")]
+async fn post_revoke(
+    provider: web::Data<Provider>,
+    client: RequestClient,
+    revoke_request: web::Form<RevokeRequest>,
+) -> Result<HttpResponse, Problem> {
+    authenticate(client.as_ref())?;
+    revoke(provider.as_ref(), &revoke_request).await?;
+    Ok(HttpResponse::Ok().finish())
+}
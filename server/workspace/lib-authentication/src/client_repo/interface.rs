@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+
+/// The error type for the client repository.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("The client repository is not available.")]
+    NotAvailable,
+}
+
+/// The result type for the client repository.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ID of a service client.
+pub type ClientId = String;
+
+/// A single service client.
+#[derive(Clone)]
+pub struct Client {
+    /// The ID of the client.
+    pub id: ClientId,
+}
+
+/// The interface for the client repository.
+#[async_trait]
+pub trait Interface: Send + Sync {
+    /// Checks for a client with the given ID and secret.
+    ///
+    /// # Parameters
+    ///
+    /// - `client_id`: The ID of the client to check.
+    /// - `client_secret`: The secret to check.
+    ///
+    /// # Returns
+    ///
+    /// Returns a client if that client exists and the secret is correct.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client repository is not available.
+    async fn check_secret(&self, client_id: &str, client_secret: &str) -> Result<Option<Client>>;
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use lib_crypto::{fill_bytes, hash_password, verify_password};
+
+use super::{Client, ClientId, Error, Interface, Result};
+
+/// A client repository that stores all clients in memory.
+pub struct Repo {
+    secret_hashes: HashMap<ClientId, String>,
+
+    /// The hash of a random secret that unknown clients are checked against,
+    /// so they take as long to turn away as a wrong secret.
+    dummy_hash: String,
+}
+
+impl Repo {
+    /// Creates a new in-memory client repository.
+    ///
+    /// # Parameters
+    ///
+    /// - `clients`: Pairs of client IDs and Argon2 hashes of their secrets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secret unknown clients are checked against
+    /// could not be hashed.
+    pub fn new(clients: impl IntoIterator<Item = (ClientId, String)>) -> Result<Self> {
+        let mut secret = [0; 32];
+        let dummy_hash = fill_bytes(&mut secret)
+            .and_then(|()| hash_password(&secret, None))
+            .map_err(|e| {
+                log::error!("Failed to hash the dummy client secret: {}", e);
+                Error::NotAvailable
+            })?;
+
+        Ok(Self {
+            secret_hashes: clients.into_iter().collect(),
+            dummy_hash,
+        })
+    }
+}
+
+#[async_trait]
+impl Interface for Repo {
+    async fn check_secret(&self, client_id: &str, client_secret: &str) -> Result<Option<Client>> {
+        let (secret_hash, is_known) = match self.secret_hashes.get(client_id) {
+            Some(secret_hash) => (secret_hash, true),
+            None => (&self.dummy_hash, false),
+        };
+
+        let is_valid =
+            verify_password(client_secret.as_bytes(), secret_hash, None).unwrap_or(false);
+
+        if is_known && is_valid {
+            Ok(Some(Client {
+                id: client_id.to_string(),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_crypto::hash_password;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_secret() {
+        let secret_hash = hash_password(b"secret", None).unwrap();
+        let repo = Repo::new([("service".to_string(), secret_hash)]).unwrap();
+
+        let client = repo.check_secret("service", "secret").await.unwrap();
+        assert_eq!(client.map(|c| c.id), Some("service".to_string()));
+
+        assert!(repo
+            .check_secret("service", "wrong")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repo
+            .check_secret("unknown", "secret")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+
+pub use interface::{Client, ClientId, Error, Interface, Result};
+pub use memory::Repo as Memory;
+
+mod interface;
+mod memory;
+
+/// The master client repository.
+#[derive(Clone)]
+pub struct Repo {
+    repo: std::sync::Arc<Box<dyn Interface>>,
+}
+
+impl Repo {
+    /// Creates a new in-memory client repository.
+    ///
+    /// # Parameters
+    ///
+    /// - `clients`: Pairs of client IDs and Argon2 hashes of their secrets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository could not be set up.
+    pub fn memory(clients: impl IntoIterator<Item = (ClientId, String)>) -> Result<Self> {
+        let repo = memory::Repo::new(clients)?;
+        Ok(Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+        })
+    }
+}
+
+#[async_trait]
+impl Interface for Repo {
+    async fn check_secret(&self, client_id: &str, client_secret: &str) -> Result<Option<Client>> {
+        self.repo.check_secret(client_id, client_secret).await
+    }
+}
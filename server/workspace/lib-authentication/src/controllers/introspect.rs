@@ -0,0 +1,286 @@
+use std::time::SystemTime;
+
+use crate::user_repo::User;
+use crate::{
+    AuthToken, RefreshToken, Result, TokenInterface, TokenRepoError, TokenRepoInterface, UserId,
+    UserRepoInterface,
+};
+
+/// The tag holding the space-delimited scopes granted to a token.
+pub(crate) const SCOPE_TAG: &str = "scope";
+
+/// The kind of token being introspected or revoked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenType {
+    /// An authentication (access) token.
+    Auth,
+
+    /// A refresh token.
+    Refresh,
+}
+
+impl TokenType {
+    /// Get the order in which to search the token repositories.
+    ///
+    /// # Parameters
+    ///
+    /// - `hint`: The type the caller expects the token to be, if any.
+    pub(crate) fn search_order(hint: Option<Self>) -> [Self; 2] {
+        match hint {
+            Some(Self::Refresh) => [Self::Refresh, Self::Auth],
+            Some(Self::Auth) | None => [Self::Auth, Self::Refresh],
+        }
+    }
+}
+
+/// The details of an active token.
+pub struct Introspection {
+    /// The type of the token.
+    pub token_type: TokenType,
+
+    /// The user the token was issued to.
+    pub user: User,
+
+    /// The space-delimited scopes granted to the token, if any were recorded.
+    pub scope: Option<String>,
+
+    /// When the token expires, or `None` if it does not expire.
+    pub expires: Option<SystemTime>,
+}
+
+pub struct Request<'a, U, A, R>
+where
+    U: UserRepoInterface,
+    A: TokenRepoInterface<AuthToken>,
+    R: TokenRepoInterface<RefreshToken>,
+{
+    pub user_repo: &'a U,
+    pub auth_token_repo: &'a A,
+    pub refresh_token_repo: &'a R,
+    pub token: &'a [u8],
+    pub token_type_hint: Option<TokenType>,
+}
+
+/// Describes the given token, if it is active.
+///
+/// # Returns
+///
+/// Returns `None` if the token is unknown, expired, or belongs to a user that no longer exists.
+///
+/// # Errors
+///
+/// Returns an error if a repository could not be read.
+pub async fn introspect<U, A, R>(
+    Request {
+        user_repo,
+        auth_token_repo,
+        refresh_token_repo,
+        token,
+        token_type_hint,
+    }: Request<'_, U, A, R>,
+) -> Result<Option<Introspection>>
+where
+    U: UserRepoInterface,
+    A: TokenRepoInterface<AuthToken>,
+    R: TokenRepoInterface<RefreshToken>,
+{
+    for token_type in TokenType::search_order(token_type_hint) {
+        let details = match token_type {
+            TokenType::Auth => lookup(auth_token_repo, &AuthToken::from(token.to_vec())).await?,
+            TokenType::Refresh => {
+                lookup(refresh_token_repo, &RefreshToken::from(token.to_vec())).await?
+            }
+        };
+
+        let Some((user_id, scope, expires)) = details else { continue };
+        let Some(user) = user_repo.get(user_id).await? else { return Ok(None) };
+
+        return Ok(Some(Introspection {
+            token_type,
+            user,
+            scope,
+            expires,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Reads the user, scope and expiry of a token from a single repository.
+async fn lookup<Token: TokenInterface>(
+    token_repo: &impl TokenRepoInterface<Token>,
+    token: &Token,
+) -> Result<Option<(UserId, Option<String>, Option<SystemTime>)>> {
+    let user_id = match token_repo.get(token).await {
+        Ok(user_id) => user_id,
+        Err(
+            TokenRepoError::TokenNotFound
+            | TokenRepoError::TokenExpired
+            | TokenRepoError::TokenInvalid,
+        ) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let expires = match token_repo.expiry(token).await {
+        Ok(expires) => expires,
+        Err(TokenRepoError::TokenNotFound | TokenRepoError::TokenExpired) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let scope = token_repo
+        .get_tag(token, SCOPE_TAG)
+        .await
+        .ok()
+        .and_then(|scope| String::from_utf8(scope).ok());
+
+    Ok(Some((user_id, scope, expires)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::controllers::login::{force, ForceLoginRequest};
+    use crate::user_repo::CreateUser;
+    use crate::{TokenRepo, UserRepo, SESSION_SCOPE};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_introspect_auth_token() {
+        let user_repo = UserRepo::memory();
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+
+        let user_id = user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+        let token = AuthToken::generate(32).unwrap();
+        auth_token_repo
+            .put(
+                &token,
+                &user_id,
+                &[(SCOPE_TAG, b"metrics:read")],
+                Some(&Duration::from_secs(90)),
+            )
+            .await
+            .unwrap();
+
+        let introspection = introspect(Request {
+            user_repo: &user_repo,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            token: token.as_ref(),
+            token_type_hint: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(introspection.token_type, TokenType::Auth);
+        assert_eq!(introspection.user.id, user_id);
+        assert_eq!(introspection.scope.as_deref(), Some("metrics:read"));
+        assert!(introspection.expires.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_introspect_refresh_token() {
+        let user_repo = UserRepo::memory();
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+
+        let user_id = user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+        let token = RefreshToken::generate(32).unwrap();
+        refresh_token_repo
+            .put(&token, &user_id, &[], None)
+            .await
+            .unwrap();
+
+        let introspection = introspect(Request {
+            user_repo: &user_repo,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            token: token.as_ref(),
+            token_type_hint: Some(TokenType::Auth),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(introspection.token_type, TokenType::Refresh);
+        assert!(introspection.scope.is_none());
+        assert!(introspection.expires.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_introspect_session_scope() {
+        let user_repo = UserRepo::memory();
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+
+        let user_id = user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+        let token_pair = force(ForceLoginRequest {
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            user_id: &user_id,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .unwrap();
+
+        for token in [
+            token_pair.auth_token.as_ref(),
+            token_pair.refresh_token.as_ref(),
+        ] {
+            let introspection = introspect(Request {
+                user_repo: &user_repo,
+                auth_token_repo: &auth_token_repo,
+                refresh_token_repo: &refresh_token_repo,
+                token,
+                token_type_hint: None,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(introspection.scope.as_deref(), Some(SESSION_SCOPE));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_introspect_unknown() {
+        let user_repo = UserRepo::memory();
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+
+        let token = AuthToken::generate(32).unwrap();
+        let introspection = introspect(Request {
+            user_repo: &user_repo,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            token: token.as_ref(),
+            token_type_hint: None,
+        })
+        .await
+        .unwrap();
+
+        assert!(introspection.is_none());
+    }
+}
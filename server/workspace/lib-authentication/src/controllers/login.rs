@@ -2,10 +2,15 @@ use std::time::Duration;
 
 use lib_environment::EnvironmentVariable;
 
+use crate::controllers::introspect::SCOPE_TAG;
 use crate::{
     AuthToken, RefreshToken, Result, TokenInterface, TokenRepoInterface, UserId, UserRepoInterface,
 };
 
+/// The scope granted to tokens issued when a user logs in, which act on the
+/// user's behalf wherever the user could.
+pub const SESSION_SCOPE: &str = "session";
+
 /// Authentication credentials.
 pub struct Credentials<'a> {
     /// The username.
@@ -129,31 +134,66 @@ where
     A: TokenRepoInterface<AuthToken>,
     R: TokenRepoInterface<RefreshToken>,
 {
-    let auth_token = AuthToken::generate(lib_environment::AuthTokenSize::get())?;
-    let refresh_token = RefreshToken::generate(lib_environment::RefreshTokenSize::get())?;
+    let token_pair = TokenPair::generate()?;
+    let auth_token_hash = token_pair.auth_token.hash();
+    let refresh_token_hash = token_pair.refresh_token.hash();
+    let (auth_tags, refresh_tags) =
+        token_pair_tags(auth_token_hash.as_ref(), refresh_token_hash.as_ref());
 
     auth_token_repo
-        .put(
-            &auth_token,
-            user_id,
-            &[("refresh-token", refresh_token.as_ref())],
-            auth_token_ttl,
-        )
+        .put(&token_pair.auth_token, user_id, &auth_tags, auth_token_ttl)
         .await?;
 
     refresh_token_repo
         .put(
-            &refresh_token,
+            &token_pair.refresh_token,
             user_id,
-            &[("auth-token", auth_token.as_ref())],
+            &refresh_tags,
             refresh_token_ttl,
         )
         .await?;
 
-    Ok(TokenPair {
-        auth_token,
-        refresh_token,
-    })
+    Ok(token_pair)
+}
+
+impl TokenPair {
+    /// Generates a new pair of tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a token could not be generated.
+    pub(crate) fn generate() -> Result<Self> {
+        Ok(Self {
+            auth_token: AuthToken::generate(lib_environment::AuthTokenSize::get())?,
+            refresh_token: RefreshToken::generate(lib_environment::RefreshTokenSize::get())?,
+        })
+    }
+}
+
+/// The tags of one token of a pair.
+pub(crate) type TokenPairTags<'a> = [(&'static str, &'a [u8]); 2];
+
+/// Returns the tags to put with the authentication and refresh tokens of a new
+/// pair, which link each token to the other.
+///
+/// # Arguments
+///
+/// - `auth_token_hash` - The hash of the authentication token.
+/// - `refresh_token_hash` - The hash of the refresh token.
+pub(crate) fn token_pair_tags<'a>(
+    auth_token_hash: &'a [u8],
+    refresh_token_hash: &'a [u8],
+) -> (TokenPairTags<'a>, TokenPairTags<'a>) {
+    (
+        [
+            ("refresh-token", refresh_token_hash),
+            (SCOPE_TAG, SESSION_SCOPE.as_bytes()),
+        ],
+        [
+            ("auth-token", auth_token_hash),
+            (SCOPE_TAG, SESSION_SCOPE.as_bytes()),
+        ],
+    )
 }
 
 #[cfg(test)]
@@ -201,10 +241,18 @@ mod tests {
         let refresh_token_repo = TokenRepo::memory();
 
         let login_credentials = Credentials {
-            username: "admin",
+            username: "test",
             password: "invalid",
         };
 
+        user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+
         let token = login(Request {
             user_repo: &user_repo,
             auth_token_repo: &auth_token_repo,
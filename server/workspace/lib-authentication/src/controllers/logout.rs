@@ -1,23 +1,25 @@
-use crate::{AuthToken, RefreshToken, Result, TokenRepoInterface};
+use lib_cache::Pipeline;
 
+use crate::controllers::revoke::take_pair;
+use crate::{AuthToken, RefreshToken, Result, TokenRepoInterface, UserId};
+
+/// Deletes the given token along with its refresh token, and returns the user
+/// that was logged in.
 pub async fn logout(
     auth_token_repo: &impl TokenRepoInterface<AuthToken>,
     refresh_token_repo: &impl TokenRepoInterface<RefreshToken>,
     token: &AuthToken,
-) -> Result<()> {
-    let refresh_token = auth_token_repo
-        .get_tag(token, "refresh-token")
-        .await
-        .ok()
-        .map(RefreshToken::from);
-
-    auth_token_repo.delete(token).await?;
-
-    if let Some(refresh_token) = refresh_token {
-        refresh_token_repo.delete(&refresh_token).await?;
-    }
+) -> Result<UserId> {
+    let user_id = take_pair(
+        auth_token_repo,
+        refresh_token_repo,
+        token,
+        "refresh-token",
+        Pipeline::new(),
+    )
+    .await?;
 
-    Ok(())
+    Ok(user_id)
 }
 
 #[cfg(test)]
@@ -30,18 +32,48 @@ mod tests {
     async fn test_logout() {
         let auth_token_repo = crate::TokenRepo::memory();
         let refresh_token_repo = crate::TokenRepo::memory();
+        check_logout(auth_token_repo, refresh_token_repo).await;
+    }
+
+    #[tokio::test]
+    async fn test_logout_shared_cache() {
+        let cache = lib_cache::Memory::default();
+        let auth_token_repo = crate::TokenRepo::cache(cache.clone(), "a".to_string());
+        let refresh_token_repo = crate::TokenRepo::cache(cache, "r".to_string());
+        check_logout(auth_token_repo, refresh_token_repo).await;
+    }
+
+    async fn check_logout(
+        auth_token_repo: crate::TokenRepo<AuthToken>,
+        refresh_token_repo: crate::TokenRepo<RefreshToken>,
+    ) {
         let auth_token = AuthToken::generate(32).unwrap();
+        let refresh_token = RefreshToken::generate(32).unwrap();
         let user_id = uuid::Uuid::new_v4();
 
         auth_token_repo
-            .put(&auth_token, &user_id, &[], None)
+            .put(
+                &auth_token,
+                &user_id,
+                &[("refresh-token", refresh_token.hash().as_ref())],
+                None,
+            )
+            .await
+            .unwrap();
+        refresh_token_repo
+            .put(&refresh_token, &user_id, &[], None)
             .await
             .unwrap();
         assert!(auth_token_repo.get(&auth_token).await.is_ok());
 
-        logout(&auth_token_repo, &refresh_token_repo, &auth_token)
+        let logged_out = logout(&auth_token_repo, &refresh_token_repo, &auth_token)
             .await
             .unwrap();
+        assert_eq!(logged_out, user_id);
         assert!(auth_token_repo.get(&auth_token).await.is_err());
+        assert!(refresh_token_repo.get(&refresh_token).await.is_err());
+        assert!(logout(&auth_token_repo, &refresh_token_repo, &auth_token)
+            .await
+            .is_err());
     }
 }
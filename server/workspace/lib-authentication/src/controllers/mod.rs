@@ -1,5 +1,9 @@
+pub mod introspect;
 pub mod login;
 pub mod logout;
+pub mod passkey;
 pub mod refresh;
 pub mod register;
+pub mod revoke;
+pub mod setup;
 pub mod whoami;
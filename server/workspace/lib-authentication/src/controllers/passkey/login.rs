@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use webauthn_rs::prelude::{PublicKeyCredential, RequestChallengeResponse};
+use webauthn_rs_proto::AllowCredentials;
+
+use crate::controllers::login::{force, ForceLoginRequest, TokenPair};
+use crate::{
+    AuthToken, ChallengeToken, PasskeyRepoInterface, RefreshToken, RelyingParty, Result,
+    TokenRepoInterface, UserId, UserRepoInterface,
+};
+
+use super::Challenge;
+
+/// The tag holding the state of a passkey authentication ceremony.
+const STATE_TAG: &str = "passkey-authentication";
+
+pub struct StartRequest<'a, U, P, C>
+where
+    U: UserRepoInterface,
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    pub user_repo: &'a U,
+    pub passkey_repo: &'a P,
+    pub challenge_token_repo: &'a C,
+    pub relying_party: &'a RelyingParty,
+    pub username: &'a str,
+    pub challenge_ttl: Option<&'a Duration>,
+}
+
+/// Begins logging in the given user with one of their passkeys.
+///
+/// A user who does not exist or has no passkeys is sent a decoy challenge
+/// that no authenticator can answer, offering a credential that does not
+/// exist, so the response does not reveal which usernames have passkeys.
+///
+/// # Returns
+///
+/// Returns the challenge one of the user's authenticators must answer.
+///
+/// # Errors
+///
+/// Returns an error if the challenge could not be generated or stored.
+pub async fn start<U, P, C>(
+    StartRequest {
+        user_repo,
+        passkey_repo,
+        challenge_token_repo,
+        relying_party,
+        username,
+        challenge_ttl,
+    }: StartRequest<'_, U, P, C>,
+) -> Result<Challenge<RequestChallengeResponse>>
+where
+    U: UserRepoInterface,
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    let (user_id, passkeys) = match user_repo.get_by_username(username).await? {
+        Some(user) => (user.id, passkey_repo.list(user.id).await?),
+        None => (UserId::nil(), Vec::new()),
+    };
+
+    let (mut options, state) = relying_party
+        .as_ref()
+        .start_passkey_authentication(&passkeys)?;
+
+    // The state holds no credentials, so the decoy cannot be answered.
+    if passkeys.is_empty() {
+        options.public_key.allow_credentials = vec![AllowCredentials {
+            type_: "public-key".to_string(),
+            id: relying_party.decoy_credential_id(username)?.into(),
+            transports: None,
+        }];
+    }
+
+    let token = super::issue(
+        challenge_token_repo,
+        &user_id,
+        STATE_TAG,
+        &state,
+        challenge_ttl,
+    )
+    .await?;
+
+    Ok(Challenge { token, options })
+}
+
+pub struct FinishRequest<'a, P, C, A, R>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+    A: TokenRepoInterface<AuthToken>,
+    R: TokenRepoInterface<RefreshToken>,
+{
+    pub passkey_repo: &'a P,
+    pub challenge_token_repo: &'a C,
+    pub auth_token_repo: &'a A,
+    pub refresh_token_repo: &'a R,
+    pub relying_party: &'a RelyingParty,
+    pub challenge: &'a ChallengeToken,
+    pub credential: &'a PublicKeyCredential,
+    pub auth_token_ttl: Option<&'a Duration>,
+    pub refresh_token_ttl: Option<&'a Duration>,
+}
+
+/// Verifies the authenticator's answer to a login challenge and issues a new token pair.
+///
+/// # Returns
+///
+/// Returns `None` if the challenge is unknown or expired, or the credential
+/// could not be verified, or else the new token pair.
+///
+/// # Errors
+///
+/// Returns an error if a repository could not be read or the tokens could not be generated.
+pub async fn finish<P, C, A, R>(
+    FinishRequest {
+        passkey_repo,
+        challenge_token_repo,
+        auth_token_repo,
+        refresh_token_repo,
+        relying_party,
+        challenge,
+        credential,
+        auth_token_ttl,
+        refresh_token_ttl,
+    }: FinishRequest<'_, P, C, A, R>,
+) -> Result<Option<TokenPair>>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+    A: TokenRepoInterface<AuthToken>,
+    R: TokenRepoInterface<RefreshToken>,
+{
+    let state = super::redeem(challenge_token_repo, challenge, STATE_TAG).await?;
+    let Some((user_id, state)) = state else { return Ok(None) };
+
+    let result = match relying_party
+        .as_ref()
+        .finish_passkey_authentication(credential, &state)
+    {
+        Ok(result) => result,
+        Err(e) => {
+            log::warn!("Passkey login for user {} failed: {}", user_id, e);
+            return Ok(None);
+        }
+    };
+
+    for mut passkey in passkey_repo.list(user_id).await? {
+        if passkey.update_credential(&result) == Some(true) {
+            passkey_repo.update(&passkey).await?;
+        }
+    }
+
+    let token_pair = force(ForceLoginRequest {
+        auth_token_repo,
+        refresh_token_repo,
+        user_id: &user_id,
+        auth_token_ttl,
+        refresh_token_ttl,
+    })
+    .await?;
+
+    Ok(Some(token_pair))
+}
+
+#[cfg(test)]
+mod tests {
+    use webauthn_authenticator_rs::prelude::Url;
+    use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+    use webauthn_authenticator_rs::WebauthnAuthenticator;
+
+    use crate::controllers::passkey::register;
+    use crate::user_repo::CreateUser;
+    use crate::{PasskeyRepo, TokenRepo, UserRepo};
+
+    use super::*;
+
+    const ORIGIN: &str = "https://localhost";
+
+    #[tokio::test]
+    async fn test_login() {
+        let user_repo = UserRepo::memory();
+        let passkey_repo = PasskeyRepo::memory();
+        let challenge_token_repo = TokenRepo::memory();
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+        let relying_party = RelyingParty::new("localhost", ORIGIN, "test").unwrap();
+        let origin = Url::parse(ORIGIN).unwrap();
+
+        let user_id = user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+        let user = user_repo.get(user_id).await.unwrap().unwrap();
+
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+        let challenge = register::start(register::StartRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &user,
+            challenge_ttl: None,
+        })
+        .await
+        .unwrap();
+        let credential = authenticator
+            .do_registration(origin.clone(), challenge.options)
+            .unwrap();
+        register::finish(register::FinishRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &user,
+            challenge: &challenge.token,
+            credential: &credential,
+        })
+        .await
+        .unwrap();
+
+        let challenge = start(StartRequest {
+            user_repo: &user_repo,
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            username: "test",
+            challenge_ttl: None,
+        })
+        .await
+        .unwrap();
+        let credential = authenticator
+            .do_authentication(origin, challenge.options)
+            .unwrap();
+
+        let token_pair = finish(FinishRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            relying_party: &relying_party,
+            challenge: &challenge.token,
+            credential: &credential,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            auth_token_repo.get(&token_pair.auth_token).await.unwrap(),
+            user_id
+        );
+
+        let replayed = finish(FinishRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            relying_party: &relying_party,
+            challenge: &challenge.token,
+            credential: &credential,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .unwrap();
+
+        assert!(replayed.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_without_passkeys() {
+        let user_repo = UserRepo::memory();
+        let passkey_repo = PasskeyRepo::memory();
+        let challenge_token_repo = TokenRepo::memory();
+        let relying_party = RelyingParty::new("localhost", ORIGIN, "test").unwrap();
+        user_repo
+            .create(&CreateUser {
+                username: "test",
+                password: "test",
+            })
+            .await
+            .unwrap();
+
+        let start = |username| {
+            start(StartRequest {
+                user_repo: &user_repo,
+                passkey_repo: &passkey_repo,
+                challenge_token_repo: &challenge_token_repo,
+                relying_party: &relying_party,
+                username,
+                challenge_ttl: None,
+            })
+        };
+        let credential_ids = |challenge: Challenge<RequestChallengeResponse>| {
+            challenge
+                .options
+                .public_key
+                .allow_credentials
+                .into_iter()
+                .map(|credential| credential.id)
+                .collect::<Vec<_>>()
+        };
+
+        // A user without passkeys and a user who does not exist are both
+        // offered a credential, which is the same each time.
+        for username in ["test", "unknown"] {
+            let first = credential_ids(start(username).await.unwrap());
+            let second = credential_ids(start(username).await.unwrap());
+            assert_eq!(first.len(), 1);
+            assert_eq!(first, second);
+        }
+        assert_ne!(
+            credential_ids(start("test").await.unwrap()),
+            credential_ids(start("unknown").await.unwrap())
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use lib_cache::Pipeline;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ChallengeToken, Result, TokenInterface, TokenRepoError, TokenRepoInterface, UserId};
+
+pub mod login;
+pub mod register;
+
+/// The size of a challenge token in bytes.
+const CHALLENGE_TOKEN_SIZE: usize = 32;
+
+/// A pending passkey ceremony.
+pub struct Challenge<T> {
+    /// Identifies the ceremony when the client returns the authenticator's response.
+    pub token: ChallengeToken,
+
+    /// The options to pass to the browser's `WebAuthn` API.
+    pub options: T,
+}
+
+/// Stores the server-side state of a ceremony and returns the token that identifies it.
+///
+/// # Arguments
+///
+/// - `challenge_token_repo` - The challenge token repository.
+/// - `user_id` - The user performing the ceremony.
+/// - `tag` - The tag to store the state under, which names the kind of ceremony.
+/// - `state` - The ceremony state.
+/// - `ttl` - How long the client has to complete the ceremony.
+///
+/// # Errors
+///
+/// Returns an error if the token could not be generated or stored.
+async fn issue<S: Serialize>(
+    challenge_token_repo: &impl TokenRepoInterface<ChallengeToken>,
+    user_id: &UserId,
+    tag: &str,
+    state: &S,
+    ttl: Option<&Duration>,
+) -> Result<ChallengeToken> {
+    let token = ChallengeToken::generate(CHALLENGE_TOKEN_SIZE)?;
+    let state = serde_json::to_vec(state)?;
+
+    challenge_token_repo
+        .put(&token, user_id, &[(tag, &state)], ttl)
+        .await?;
+
+    Ok(token)
+}
+
+/// Consumes a challenge token and returns the ceremony state stored under it.
+///
+/// The token is deleted whether or not it holds the expected kind of ceremony,
+/// so each challenge can only be answered once.
+///
+/// # Returns
+///
+/// Returns `None` if the token is unknown, expired, or was issued for a different kind of ceremony.
+///
+/// # Errors
+///
+/// Returns an error if the challenge token repository could not be read.
+async fn redeem<S: DeserializeOwned>(
+    challenge_token_repo: &impl TokenRepoInterface<ChallengeToken>,
+    token: &ChallengeToken,
+    tag: &str,
+) -> Result<Option<(UserId, S)>> {
+    let (user_id, state) = match challenge_token_repo
+        .take(token, tag, &Pipeline::new())
+        .await
+    {
+        Ok(redeemed) => redeemed,
+        Err(
+            TokenRepoError::TokenNotFound
+            | TokenRepoError::TokenExpired
+            | TokenRepoError::TokenInvalid,
+        ) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let Some(state) = state else { return Ok(None) };
+    let Ok(state) = serde_json::from_slice(&state) else { return Ok(None) };
+
+    Ok(Some((user_id, state)))
+}
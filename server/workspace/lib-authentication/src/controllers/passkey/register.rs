@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use webauthn_rs::prelude::{CreationChallengeResponse, RegisterPublicKeyCredential};
+
+use crate::user_repo::User;
+use crate::{ChallengeToken, PasskeyRepoInterface, RelyingParty, Result, TokenRepoInterface};
+
+use super::Challenge;
+
+/// The tag holding the state of a passkey registration ceremony.
+const STATE_TAG: &str = "passkey-registration";
+
+pub struct StartRequest<'a, P, C>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    pub passkey_repo: &'a P,
+    pub challenge_token_repo: &'a C,
+    pub relying_party: &'a RelyingParty,
+    pub user: &'a User,
+    pub challenge_ttl: Option<&'a Duration>,
+}
+
+/// Begins registering a new passkey for the given user.
+///
+/// # Returns
+///
+/// Returns the challenge the user's authenticator must answer. Passkeys the
+/// user has already registered are excluded, so the same authenticator cannot
+/// be registered twice.
+///
+/// # Errors
+///
+/// Returns an error if the challenge could not be generated or stored.
+pub async fn start<P, C>(
+    StartRequest {
+        passkey_repo,
+        challenge_token_repo,
+        relying_party,
+        user,
+        challenge_ttl,
+    }: StartRequest<'_, P, C>,
+) -> Result<Challenge<CreationChallengeResponse>>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    let exclude_credentials = passkey_repo
+        .list(user.id)
+        .await?
+        .iter()
+        .map(|passkey| passkey.cred_id().clone())
+        .collect();
+
+    let (options, state) = relying_party.as_ref().start_passkey_registration(
+        user.id,
+        &user.username,
+        &user.username,
+        Some(exclude_credentials),
+    )?;
+
+    let token = super::issue(
+        challenge_token_repo,
+        &user.id,
+        STATE_TAG,
+        &state,
+        challenge_ttl,
+    )
+    .await?;
+
+    Ok(Challenge { token, options })
+}
+
+pub struct FinishRequest<'a, P, C>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    pub passkey_repo: &'a P,
+    pub challenge_token_repo: &'a C,
+    pub relying_party: &'a RelyingParty,
+    pub user: &'a User,
+    pub challenge: &'a ChallengeToken,
+    pub credential: &'a RegisterPublicKeyCredential,
+}
+
+/// Verifies the authenticator's answer to a registration challenge and stores the new passkey.
+///
+/// # Returns
+///
+/// Returns `false` if the challenge is unknown, expired, was issued to a
+/// different user, or the credential could not be verified.
+///
+/// # Errors
+///
+/// Returns an error if a repository could not be read or the passkey could not be stored.
+pub async fn finish<P, C>(
+    FinishRequest {
+        passkey_repo,
+        challenge_token_repo,
+        relying_party,
+        user,
+        challenge,
+        credential,
+    }: FinishRequest<'_, P, C>,
+) -> Result<bool>
+where
+    P: PasskeyRepoInterface,
+    C: TokenRepoInterface<ChallengeToken>,
+{
+    let state = super::redeem(challenge_token_repo, challenge, STATE_TAG).await?;
+    let Some((user_id, state)) = state else { return Ok(false) };
+    if user_id != user.id {
+        return Ok(false);
+    }
+
+    let passkey = match relying_party
+        .as_ref()
+        .finish_passkey_registration(credential, &state)
+    {
+        Ok(passkey) => passkey,
+        Err(e) => {
+            log::warn!("Passkey registration for user {} failed: {}", user.id, e);
+            return Ok(false);
+        }
+    };
+
+    passkey_repo.create(user.id, &passkey).await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use webauthn_authenticator_rs::prelude::Url;
+    use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+    use webauthn_authenticator_rs::WebauthnAuthenticator;
+
+    use crate::{PasskeyRepo, TokenRepo};
+
+    use super::*;
+
+    const ORIGIN: &str = "https://localhost";
+
+    fn test_user() -> User {
+        User {
+            id: uuid::Uuid::new_v4(),
+            username: "test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        let passkey_repo = PasskeyRepo::memory();
+        let challenge_token_repo = TokenRepo::memory();
+        let relying_party = RelyingParty::new("localhost", ORIGIN, "test").unwrap();
+        let user = test_user();
+
+        let challenge = start(StartRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &user,
+            challenge_ttl: None,
+        })
+        .await
+        .unwrap();
+
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+        let credential = authenticator
+            .do_registration(Url::parse(ORIGIN).unwrap(), challenge.options)
+            .unwrap();
+
+        let registered = finish(FinishRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &user,
+            challenge: &challenge.token,
+            credential: &credential,
+        })
+        .await
+        .unwrap();
+
+        assert!(registered);
+        assert_eq!(passkey_repo.list(user.id).await.unwrap().len(), 1);
+        assert!(challenge_token_repo.get(&challenge.token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_register_other_user() {
+        let passkey_repo = PasskeyRepo::memory();
+        let challenge_token_repo = TokenRepo::memory();
+        let relying_party = RelyingParty::new("localhost", ORIGIN, "test").unwrap();
+        let user = test_user();
+
+        let challenge = start(StartRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &user,
+            challenge_ttl: None,
+        })
+        .await
+        .unwrap();
+
+        let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+        let credential = authenticator
+            .do_registration(Url::parse(ORIGIN).unwrap(), challenge.options)
+            .unwrap();
+
+        let registered = finish(FinishRequest {
+            passkey_repo: &passkey_repo,
+            challenge_token_repo: &challenge_token_repo,
+            relying_party: &relying_party,
+            user: &test_user(),
+            challenge: &challenge.token,
+            credential: &credential,
+        })
+        .await
+        .unwrap();
+
+        assert!(!registered);
+        assert!(passkey_repo.list(user.id).await.unwrap().is_empty());
+    }
+}
@@ -1,7 +1,10 @@
 use std::time::Duration;
 
-use crate::controllers::login::{force, ForceLoginRequest, TokenPair};
-use crate::{AuthToken, RefreshToken, Result, TokenRepoInterface};
+use lib_cache::Pipeline;
+
+use crate::controllers::login::{token_pair_tags, TokenPair};
+use crate::controllers::revoke::take_pair;
+use crate::{AuthToken, RefreshToken, Result, TokenInterface, TokenRepoInterface};
 
 pub struct Request<'a, A, R>
 where
@@ -30,29 +33,268 @@ where
 {
     let user_id = refresh_token_repo.get(refresh_token).await?;
 
-    // Remove this refresh token from the repository.
-    let auth_token = refresh_token_repo
-        .get_tag(refresh_token, "auth-token")
-        .await
-        .ok()
-        .map(AuthToken::from);
+    let token_pair = TokenPair::generate()?;
+    let auth_token_hash = token_pair.auth_token.hash();
+    let refresh_token_hash = token_pair.refresh_token.hash();
+    let (auth_tags, refresh_tags) =
+        token_pair_tags(auth_token_hash.as_ref(), refresh_token_hash.as_ref());
 
-    if let Some(auth_token) = auth_token {
-        auth_token_repo.delete(&auth_token).await?;
-    }
+    // When both repositories keep their tokens in the same cache, the new pair
+    // is put in the same atomic operation that takes the old one, so a failure
+    // cannot leave the user with neither.
+    let then = match auth_token_repo.queue_put(
+        Pipeline::new(),
+        &token_pair.auth_token,
+        &user_id,
+        &auth_tags,
+        auth_token_ttl,
+    )? {
+        Some(then) => refresh_token_repo.queue_put(
+            then,
+            &token_pair.refresh_token,
+            &user_id,
+            &refresh_tags,
+            refresh_token_ttl,
+        )?,
+        None => None,
+    };
 
-    // Remove this refresh token from the repository.
-    refresh_token_repo.delete(refresh_token).await?;
+    // Remove this refresh token and the auth token issued with it from the
+    // repositories. Taking it is atomic, so only one of several concurrent
+    // refreshes with the same token succeeds.
+    if let Some(then) = then {
+        take_pair(
+            refresh_token_repo,
+            auth_token_repo,
+            refresh_token,
+            "auth-token",
+            then,
+        )
+        .await?;
 
-    // Generate a new auth token and refresh token.
-    let token_pair = force(ForceLoginRequest {
-        auth_token_repo,
+        return Ok(Some(token_pair));
+    }
+
+    take_pair(
         refresh_token_repo,
-        user_id: &user_id,
-        auth_token_ttl,
-        refresh_token_ttl,
-    })
+        auth_token_repo,
+        refresh_token,
+        "auth-token",
+        Pipeline::new(),
+    )
     .await?;
 
+    auth_token_repo
+        .put(&token_pair.auth_token, &user_id, &auth_tags, auth_token_ttl)
+        .await?;
+
+    refresh_token_repo
+        .put(
+            &token_pair.refresh_token,
+            &user_id,
+            &refresh_tags,
+            refresh_token_ttl,
+        )
+        .await?;
+
     Ok(Some(token_pair))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use async_trait::async_trait;
+    use lib_cache::Interface as _;
+    use uuid::Uuid;
+
+    use crate::{TokenRepo, TokenRepoResult};
+
+    use super::*;
+
+    /// A refresh token repository whose queued puts fail once the rest of the
+    /// rotation has been queued, by writing a field to a key holding a string.
+    struct FailingPut(TokenRepo<RefreshToken>);
+
+    #[async_trait]
+    impl TokenRepoInterface<RefreshToken> for FailingPut {
+        async fn put(
+            &self,
+            token: &RefreshToken,
+            user_id: &Uuid,
+            tags: &[(&str, &[u8])],
+            ttl: Option<&Duration>,
+        ) -> TokenRepoResult<()> {
+            self.0.put(token, user_id, tags, ttl).await
+        }
+
+        fn queue_put(
+            &self,
+            pipeline: Pipeline,
+            token: &RefreshToken,
+            user_id: &Uuid,
+            tags: &[(&str, &[u8])],
+            ttl: Option<&Duration>,
+        ) -> TokenRepoResult<Option<Pipeline>> {
+            let pipeline = self.0.queue_put(pipeline, token, user_id, tags, ttl)?;
+            Ok(pipeline.map(|pipeline| pipeline.hset("string", "field", b"value")))
+        }
+
+        async fn get(&self, token: &RefreshToken) -> TokenRepoResult<Uuid> {
+            self.0.get(token).await
+        }
+
+        async fn expiry(&self, token: &RefreshToken) -> TokenRepoResult<Option<SystemTime>> {
+            self.0.expiry(token).await
+        }
+
+        async fn delete(&self, token: &RefreshToken) -> TokenRepoResult<()> {
+            self.0.delete(token).await
+        }
+
+        async fn delete_hash(&self, hash: &[u8]) -> TokenRepoResult<()> {
+            TokenRepoInterface::<RefreshToken>::delete_hash(&self.0, hash).await
+        }
+
+        fn cache_key(&self, hash: &[u8]) -> Option<String> {
+            TokenRepoInterface::<RefreshToken>::cache_key(&self.0, hash)
+        }
+
+        async fn take(
+            &self,
+            token: &RefreshToken,
+            tag: &str,
+            then: &Pipeline,
+        ) -> TokenRepoResult<(Uuid, Option<Vec<u8>>)> {
+            self.0.take(token, tag, then).await
+        }
+
+        async fn get_tag(&self, token: &RefreshToken, tag: &str) -> TokenRepoResult<Vec<u8>> {
+            self.0.get_tag(token, tag).await
+        }
+
+        async fn put_tag(
+            &self,
+            token: &RefreshToken,
+            tag: &str,
+            value: &[u8],
+        ) -> TokenRepoResult<()> {
+            self.0.put_tag(token, tag, value).await
+        }
+    }
+
+    /// Puts a linked token pair for a new user, as a login would.
+    async fn login(
+        auth_token_repo: &impl TokenRepoInterface<AuthToken>,
+        refresh_token_repo: &impl TokenRepoInterface<RefreshToken>,
+    ) -> (Uuid, TokenPair) {
+        let user_id = Uuid::new_v4();
+        let token_pair =
+            crate::controllers::login::force(crate::controllers::login::ForceLoginRequest {
+                auth_token_repo,
+                refresh_token_repo,
+                user_id: &user_id,
+                auth_token_ttl: None,
+                refresh_token_ttl: None,
+            })
+            .await
+            .unwrap();
+
+        (user_id, token_pair)
+    }
+
+    async fn check_refresh(
+        auth_token_repo: &impl TokenRepoInterface<AuthToken>,
+        refresh_token_repo: &impl TokenRepoInterface<RefreshToken>,
+    ) {
+        let (user_id, old) = login(auth_token_repo, refresh_token_repo).await;
+
+        let new = refresh(Request {
+            refresh_token: &old.refresh_token,
+            auth_token_repo,
+            refresh_token_repo,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert!(auth_token_repo.get(&old.auth_token).await.is_err());
+        assert!(refresh_token_repo.get(&old.refresh_token).await.is_err());
+        assert_eq!(auth_token_repo.get(&new.auth_token).await.unwrap(), user_id);
+        assert_eq!(
+            refresh_token_repo.get(&new.refresh_token).await.unwrap(),
+            user_id
+        );
+        assert_eq!(
+            refresh_token_repo
+                .get_tag(&new.refresh_token, "auth-token")
+                .await
+                .unwrap(),
+            new.auth_token.hash().as_ref()
+        );
+
+        // The old refresh token can only be used once.
+        assert!(refresh(Request {
+            refresh_token: &old.refresh_token,
+            auth_token_repo,
+            refresh_token_repo,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        check_refresh(&TokenRepo::memory(), &TokenRepo::memory()).await;
+    }
+
+    #[tokio::test]
+    async fn test_refresh_shared_cache() {
+        let cache = lib_cache::Memory::default();
+        let auth_token_repo = TokenRepo::cache(cache.clone(), "a".to_string());
+        let refresh_token_repo = TokenRepo::cache(cache, "r".to_string());
+        check_refresh(&auth_token_repo, &refresh_token_repo).await;
+    }
+
+    #[tokio::test]
+    async fn test_refresh_failure_keeps_old_pair() {
+        let cache = lib_cache::Memory::default();
+        cache.set("string", b"value", None).await.unwrap();
+        let auth_token_repo = TokenRepo::cache(cache.clone(), "a".to_string());
+        let refresh_token_repo = TokenRepo::cache(cache, "r".to_string());
+        let (user_id, old) = login(&auth_token_repo, &refresh_token_repo).await;
+
+        // Putting the new pair fails after the old one would have been taken.
+        assert!(refresh(Request {
+            refresh_token: &old.refresh_token,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &FailingPut(refresh_token_repo.clone()),
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .is_err());
+
+        assert_eq!(auth_token_repo.get(&old.auth_token).await.unwrap(), user_id);
+        assert_eq!(
+            refresh_token_repo.get(&old.refresh_token).await.unwrap(),
+            user_id
+        );
+
+        // The old pair can still be rotated.
+        assert!(refresh(Request {
+            refresh_token: &old.refresh_token,
+            auth_token_repo: &auth_token_repo,
+            refresh_token_repo: &refresh_token_repo,
+            auth_token_ttl: None,
+            refresh_token_ttl: None,
+        })
+        .await
+        .unwrap()
+        .is_some());
+    }
+}
@@ -21,3 +21,27 @@ pub async fn register<'a>(
 
     Ok(user_repo.create(&user).await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, UserRepo, UserRepoError};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_username_taken() {
+        let user_repo = UserRepo::memory();
+        let credentials = Credentials {
+            username: "test",
+            password: "test",
+        };
+
+        register(&user_repo, &credentials).await.unwrap();
+        let result = register(&user_repo, &credentials).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::UserRepoError(UserRepoError::UsernameTaken))
+        ));
+    }
+}
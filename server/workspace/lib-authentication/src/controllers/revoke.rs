@@ -0,0 +1,206 @@
+use lib_cache::Pipeline;
+
+use crate::controllers::introspect::TokenType;
+use crate::{
+    AuthToken, RefreshToken, Result, TokenInterface, TokenRepoError, TokenRepoInterface,
+    TokenRepoResult, UserId,
+};
+
+/// Revokes the given token along with its sibling token.
+///
+/// Unknown tokens are ignored, as required by RFC 7009.
+///
+/// # Arguments
+///
+/// - `auth_token_repo` - The authentication token repository.
+/// - `refresh_token_repo` - The refresh token repository.
+/// - `token` - The raw token to revoke.
+/// - `token_type_hint` - The type the caller expects the token to be, if any.
+///
+/// # Returns
+///
+/// The user whose session was revoked, or `None` if the token was unknown.
+///
+/// # Errors
+///
+/// Returns an error if a repository could not be updated.
+pub async fn revoke(
+    auth_token_repo: &impl TokenRepoInterface<AuthToken>,
+    refresh_token_repo: &impl TokenRepoInterface<RefreshToken>,
+    token: &[u8],
+    token_type_hint: Option<TokenType>,
+) -> Result<Option<UserId>> {
+    for token_type in TokenType::search_order(token_type_hint) {
+        let user_id = match token_type {
+            TokenType::Auth => {
+                let auth_token = AuthToken::from(token.to_vec());
+                take_pair(
+                    auth_token_repo,
+                    refresh_token_repo,
+                    &auth_token,
+                    "refresh-token",
+                    Pipeline::new(),
+                )
+                .await
+            }
+            TokenType::Refresh => {
+                let refresh_token = RefreshToken::from(token.to_vec());
+                take_pair(
+                    refresh_token_repo,
+                    auth_token_repo,
+                    &refresh_token,
+                    "auth-token",
+                    Pipeline::new(),
+                )
+                .await
+            }
+        };
+
+        if let Some(user_id) = unknown_as_none(user_id)? {
+            return Ok(Some(user_id));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Treats a token that was not present and unexpired as unknown.
+///
+/// # Returns
+///
+/// Returns `None` if the token was unknown, or `Some` with its user if it was
+/// deleted.
+fn unknown_as_none(result: TokenRepoResult<UserId>) -> Result<Option<UserId>> {
+    match result {
+        Ok(user_id) => Ok(Some(user_id)),
+        Err(
+            TokenRepoError::TokenNotFound
+            | TokenRepoError::TokenExpired
+            | TokenRepoError::TokenInvalid,
+        ) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Deletes a token along with its sibling, the token in `sibling_repo` whose
+/// hash is stored in the token's `tag`, and returns its user.
+///
+/// When both repositories keep their tokens in the same cache, the two are
+/// deleted in one atomic operation, along with the commands of `then`.
+/// Otherwise, as with in-memory repositories, the sibling is deleted once the
+/// token has been taken.
+///
+/// # Arguments
+///
+/// - `token_repo` - The repository holding the token.
+/// - `sibling_repo` - The repository holding the sibling token.
+/// - `token` - The token to delete.
+/// - `tag` - The tag holding the hash of the sibling token.
+/// - `then` - Commands to run on the cache in the same operation, which must be
+///   empty unless `token_repo` uses a cache.
+///
+/// # Errors
+///
+/// Returns an error if the token could not be taken, or the sibling could not
+/// be deleted. A sibling that has already expired is not an error.
+pub(crate) async fn take_pair<Token: TokenInterface, Sibling: TokenInterface>(
+    token_repo: &impl TokenRepoInterface<Token>,
+    sibling_repo: &impl TokenRepoInterface<Sibling>,
+    token: &Token,
+    tag: &str,
+    then: Pipeline,
+) -> TokenRepoResult<UserId> {
+    let sibling_hash = match token_repo.get_tag(token, tag).await {
+        Ok(sibling_hash) => Some(sibling_hash),
+        Err(TokenRepoError::TokenNotFound) => None,
+        Err(e) => return Err(e),
+    };
+    let sibling_key = sibling_hash
+        .as_deref()
+        .and_then(|sibling_hash| sibling_repo.cache_key(sibling_hash));
+    let then = match &sibling_key {
+        Some(sibling_key) => then.delete(sibling_key),
+        None => then,
+    };
+
+    let (user_id, taken_hash) = token_repo.take(token, tag, &then).await?;
+
+    // The sibling is left if it was not deleted along with the token, or if its
+    // tag changed after it was read.
+    if let Some(taken_hash) =
+        taken_hash.filter(|hash| sibling_key.is_none() || sibling_hash.as_ref() != Some(hash))
+    {
+        match sibling_repo.delete_hash(&taken_hash).await {
+            Ok(()) | Err(TokenRepoError::TokenNotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TokenRepo;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_revoke_refresh_token() {
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+        let auth_token = AuthToken::generate(32).unwrap();
+        let refresh_token = RefreshToken::generate(32).unwrap();
+        let user_id = uuid::Uuid::new_v4();
+
+        auth_token_repo
+            .put(
+                &auth_token,
+                &user_id,
+                &[("refresh-token", refresh_token.hash().as_ref())],
+                None,
+            )
+            .await
+            .unwrap();
+        refresh_token_repo
+            .put(
+                &refresh_token,
+                &user_id,
+                &[("auth-token", auth_token.hash().as_ref())],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let revoked = revoke(
+            &auth_token_repo,
+            &refresh_token_repo,
+            refresh_token.as_ref(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(revoked, Some(user_id));
+        assert!(refresh_token_repo.get(&refresh_token).await.is_err());
+        assert!(auth_token_repo.get(&auth_token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_unknown() {
+        let auth_token_repo = TokenRepo::memory();
+        let refresh_token_repo = TokenRepo::memory();
+        let token = AuthToken::generate(32).unwrap();
+
+        let revoked = revoke(
+            &auth_token_repo,
+            &refresh_token_repo,
+            token.as_ref(),
+            Some(TokenType::Refresh),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(revoked, None);
+    }
+}
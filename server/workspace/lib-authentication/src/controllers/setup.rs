@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use crate::user_repo::CreateUser;
+use crate::{Result, UserId, UserRepoError, UserRepoInterface};
+
+/// The password the first admin was seeded with before deployments had to
+/// choose their own.
+const DEFAULT_PASSWORD: &str = "admin";
+
+/// The size of a setup token in bytes.
+pub const SETUP_TOKEN_SIZE: usize = 32;
+
+/// How long a setup token can be used for.
+pub const SETUP_TOKEN_TTL: Duration = Duration::from_hours(1);
+
+/// What starting up did to the first admin.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Bootstrap {
+    /// The admin already has a password of its own.
+    Ready,
+
+    /// The admin was given the configured password.
+    Configured,
+
+    /// The admin is locked until a password is set with a setup token.
+    Pending,
+}
+
+/// Makes sure the first admin exists with a password nobody could guess.
+///
+/// Without a configured password, a missing admin is created locked, so that
+/// nobody can register its username before a password is set with a setup
+/// token. An admin still using the password it was once seeded with is
+/// locked as well, so the seeded password stops working. A deleted admin is
+/// restored, but not trusted with its old password.
+///
+/// # Arguments
+///
+/// - `user_repo` - The user repository.
+/// - `username` - The username of the admin.
+/// - `password` - The configured password of the admin, if any.
+///
+/// # Returns
+///
+/// Returns whether the admin was given the configured password, or still
+/// needs one to be set with a setup token.
+///
+/// # Errors
+///
+/// Returns an error if the admin could not be read, created or updated.
+pub async fn bootstrap(
+    user_repo: &impl UserRepoInterface,
+    username: &str,
+    password: Option<&str>,
+) -> Result<Bootstrap> {
+    let id = match user_repo.get_by_username(username).await? {
+        Some(user) => {
+            let has_default_password = user_repo
+                .check_password(username, DEFAULT_PASSWORD)
+                .await?
+                .is_some();
+            if !has_default_password && !user_repo.is_locked(user.id).await? {
+                return Ok(Bootstrap::Ready);
+            }
+            user.id
+        }
+        None => match user_repo.restore(username).await? {
+            Some(id) => id,
+            None => return create(user_repo, username, password).await,
+        },
+    };
+
+    if let Some(password) = password {
+        user_repo.update_password(id, password).await?;
+        Ok(Bootstrap::Configured)
+    } else {
+        user_repo.lock(id).await?;
+        Ok(Bootstrap::Pending)
+    }
+}
+
+/// Creates the admin, locked unless there is a configured password.
+///
+/// # Arguments
+///
+/// - `user_repo` - The user repository.
+/// - `username` - The username of the admin.
+/// - `password` - The configured password of the admin, if any.
+///
+/// # Errors
+///
+/// Returns an error if the admin could not be created, or was created by
+/// another server and could not be read.
+async fn create(
+    user_repo: &impl UserRepoInterface,
+    username: &str,
+    password: Option<&str>,
+) -> Result<Bootstrap> {
+    let created = match password {
+        Some(password) => user_repo.create(&CreateUser { username, password }).await,
+        None => user_repo.create_locked(username).await,
+    };
+
+    match created {
+        Ok(_) if password.is_some() => Ok(Bootstrap::Configured),
+        Ok(_) => Ok(Bootstrap::Pending),
+        // Another server created the admin first.
+        Err(UserRepoError::UsernameTaken) => {
+            let user = user_repo
+                .get_by_username(username)
+                .await?
+                .ok_or(UserRepoError::CreateFailed)?;
+            if !user_repo.is_locked(user.id).await? {
+                return Ok(Bootstrap::Ready);
+            }
+            match password {
+                Some(password) if user_repo.unlock(user.id, password).await? => {
+                    Ok(Bootstrap::Configured)
+                }
+                Some(_) => Ok(Bootstrap::Ready),
+                None => Ok(Bootstrap::Pending),
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Gives the admin a password, if it is still locked. The admin is read
+/// from the user repository rather than remembered, so that once any server
+/// has set the password, the setup tokens of every server stop working.
+///
+/// # Arguments
+///
+/// - `user_repo` - The user repository.
+/// - `username` - The username of the admin.
+/// - `password` - The new password of the admin.
+///
+/// # Returns
+///
+/// Returns the ID of the admin, or `None` if the admin does not exist or
+/// already has a password.
+///
+/// # Errors
+///
+/// Returns an error if the admin could not be read or updated.
+pub async fn set_password(
+    user_repo: &impl UserRepoInterface,
+    username: &str,
+    password: &str,
+) -> Result<Option<UserId>> {
+    let Some(user) = user_repo.get_by_username(username).await? else {
+        return Ok(None);
+    };
+    let is_unlocked = user_repo.unlock(user.id, password).await?;
+    Ok(is_unlocked.then_some(user.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::UserRepo;
+
+    use super::*;
+
+    async fn seeded() -> UserRepo {
+        let user_repo = UserRepo::memory();
+        user_repo
+            .create(&CreateUser {
+                username: "admin",
+                password: DEFAULT_PASSWORD,
+            })
+            .await
+            .unwrap();
+        user_repo
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_configured() {
+        let user_repo = UserRepo::memory();
+
+        let result = bootstrap(&user_repo, "root", Some("secret")).await.unwrap();
+        assert_eq!(result, Bootstrap::Configured);
+        assert!(user_repo
+            .check_password("root", "secret")
+            .await
+            .unwrap()
+            .is_some());
+
+        // A password of its own is left alone.
+        let result = bootstrap(&user_repo, "root", Some("other")).await.unwrap();
+        assert_eq!(result, Bootstrap::Ready);
+        assert!(user_repo
+            .check_password("root", "secret")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_pending() {
+        let user_repo = UserRepo::memory();
+
+        let result = bootstrap(&user_repo, "admin", None).await.unwrap();
+        assert_eq!(result, Bootstrap::Pending);
+
+        // The username is taken before a password is set.
+        let user = user_repo.get_by_username("admin").await.unwrap().unwrap();
+        assert!(user_repo.is_locked(user.id).await.unwrap());
+        assert!(matches!(
+            user_repo
+                .create(&CreateUser {
+                    username: "admin",
+                    password: "attacker",
+                })
+                .await,
+            Err(UserRepoError::UsernameTaken)
+        ));
+
+        // Starting up again still leaves the admin to a setup token.
+        let result = bootstrap(&user_repo, "admin", None).await.unwrap();
+        assert_eq!(result, Bootstrap::Pending);
+
+        let result = set_password(&user_repo, "admin", "secret").await.unwrap();
+        assert_eq!(result, Some(user.id));
+        let result = bootstrap(&user_repo, "admin", None).await.unwrap();
+        assert_eq!(result, Bootstrap::Ready);
+    }
+
+    #[tokio::test]
+    async fn test_set_password_once() {
+        let user_repo = UserRepo::memory();
+        bootstrap(&user_repo, "admin", None).await.unwrap();
+
+        let result = set_password(&user_repo, "admin", "secret").await.unwrap();
+        assert!(result.is_some());
+
+        // Another server's setup token cannot replace the password.
+        let result = set_password(&user_repo, "admin", "other").await.unwrap();
+        assert!(result.is_none());
+        assert!(user_repo
+            .check_password("admin", "secret")
+            .await
+            .unwrap()
+            .is_some());
+
+        let result = set_password(&user_repo, "root", "secret").await.unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_configured_pending() {
+        let user_repo = UserRepo::memory();
+        bootstrap(&user_repo, "admin", None).await.unwrap();
+
+        let result = bootstrap(&user_repo, "admin", Some("secret"))
+            .await
+            .unwrap();
+        assert_eq!(result, Bootstrap::Configured);
+        assert!(user_repo
+            .check_password("admin", "secret")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_default_password() {
+        let user_repo = seeded().await;
+        let result = bootstrap(&user_repo, "admin", Some("secret"))
+            .await
+            .unwrap();
+        assert_eq!(result, Bootstrap::Configured);
+        assert!(user_repo
+            .check_password("admin", "secret")
+            .await
+            .unwrap()
+            .is_some());
+
+        let user_repo = seeded().await;
+        let result = bootstrap(&user_repo, "admin", None).await.unwrap();
+        assert_eq!(result, Bootstrap::Pending);
+        assert!(user_repo
+            .check_password("admin", DEFAULT_PASSWORD)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
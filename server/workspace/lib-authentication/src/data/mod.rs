@@ -1,7 +1,11 @@
 pub use auth::Auth as AuthToken;
+pub use challenge::Challenge as ChallengeToken;
 pub use refresh::Refresh as RefreshToken;
+pub use setup::Setup as SetupToken;
 pub use token::Interface;
 
 mod auth;
+mod challenge;
 mod refresh;
+mod setup;
 mod token;
@@ -0,0 +1,68 @@
+use bytes::Bytes;
+
+use crate::data::token::Interface;
+use crate::Result;
+
+use super::token::Token;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Setup(Token);
+
+impl Interface for Setup {
+    /// Generate a new setup token of the given size, filled with random bytes.
+    ///
+    /// # Parameters
+    ///
+    /// - `size`: The size of the setup token in bytes.
+    ///
+    /// # Returns
+    ///
+    /// The generated setup token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the setup token could not be generated.
+    fn generate(size: usize) -> Result<Self> {
+        Token::generate(size).map(Self)
+    }
+
+    /// Get the size of the setup token in bytes.
+    ///
+    /// # Returns
+    ///
+    /// The size of the setup token in bytes.
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if the setup token is empty.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the setup token is empty, `false` otherwise.
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Convert the setup token into a string.
+    ///
+    /// # Returns
+    ///
+    /// The setup token as a string, or `None` if the setup token is not valid UTF-8.
+    fn to_string(&self) -> Option<String> {
+        self.0.to_string()
+    }
+}
+
+impl AsRef<[u8]> for Setup {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl<T: Into<Bytes>> From<T> for Setup {
+    fn from(token: T) -> Self {
+        let token = token.into().into();
+        Self(token)
+    }
+}
@@ -1,6 +1,6 @@
 use bytes::{Bytes, BytesMut};
 
-use lib_crypto::fill_bytes;
+use lib_crypto::{fill_bytes, Sha256Hash};
 
 use crate::Result;
 
@@ -42,6 +42,16 @@ pub trait Interface: From<Vec<u8>> + AsRef<[u8]> + Send + Sync {
     ///
     /// The token as a string, or else None if the token could not be converted.
     fn to_string(&self) -> Option<String>;
+
+    /// Hash the token. Token repositories store tokens only by their hash, so
+    /// reading a repository does not reveal the tokens themselves.
+    ///
+    /// # Returns
+    ///
+    /// The SHA-256 hash of the token.
+    fn hash(&self) -> Sha256Hash {
+        Sha256Hash::new(self.as_ref())
+    }
 }
 
 /// A token is a random string of bytes.
@@ -141,4 +151,11 @@ mod tests {
         let token2 = Token::generate(32).unwrap();
         assert_ne!(token1, token2);
     }
+
+    #[test]
+    fn test_hash() {
+        let token = Token::from(b"token".to_vec());
+        assert_eq!(token.hash(), Sha256Hash::new(b"token"));
+        assert_ne!(token.hash().as_ref(), token.as_ref());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{TokenRepoError, UserRepoError};
+use crate::{ClientRepoError, PasskeyRepoError, TokenRepoError, UserRepoError};
 
 /// The result type for the authentication library.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -6,12 +6,24 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// The error type for the authentication library.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("unspecified authentication error")]
+    #[error("client repository error: {0}")]
+    ClientRepoError(#[from] ClientRepoError),
+
+    #[error("cryptography error: {0}")]
     CryptoError(#[from] lib_crypto::Error),
 
-    #[error("unspecified authentication error")]
+    #[error("passkey repository error: {0}")]
+    PasskeyRepoError(#[from] PasskeyRepoError),
+
+    #[error("serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("token repository error: {0}")]
     TokenRepoError(#[from] TokenRepoError),
 
-    #[error("unspecified authentication error")]
+    #[error("user repository error: {0}")]
     UserRepoError(#[from] UserRepoError),
+
+    #[error("webauthn error: {0}")]
+    WebauthnError(#[from] webauthn_rs::prelude::WebauthnError),
 }
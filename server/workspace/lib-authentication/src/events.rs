@@ -0,0 +1,50 @@
+use lib_cache::{Cache, Channel};
+use serde::{Deserialize, Serialize};
+
+use crate::UserId;
+
+/// The channel authentication events are published on.
+const CHANNEL: &str = "authentication:events";
+
+/// A change to a user or their sessions, published so that every server
+/// instance can drop any state it holds about them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+    /// A user was created.
+    UserCreated { user_id: UserId },
+
+    /// The username or password of a user was changed.
+    UserUpdated { user_id: UserId },
+
+    /// A user was deleted.
+    UserDeleted { user_id: UserId },
+
+    /// A session was ended by logging out or revoking one of its tokens.
+    SessionEnded { user_id: UserId },
+}
+
+/// The channel of authentication events.
+pub type Events = Channel<Event>;
+
+/// Opens the channel of authentication events.
+///
+/// # Arguments
+///
+/// - `cache` - The cache shared by every server instance.
+#[must_use]
+pub fn events(cache: Cache) -> Events {
+    Channel::new(cache, CHANNEL)
+}
+
+/// Publishes an event, if there is a channel to publish it on.
+///
+/// The change the event describes has already been made, so a failure to
+/// publish is logged rather than returned.
+pub(crate) async fn publish(events: Option<&Events>, event: Event) {
+    let Some(events) = events else { return };
+
+    if let Err(e) = events.publish(&event).await {
+        log::warn!("Failed to publish {:?}: {}", event, e);
+    }
+}
@@ -8,26 +8,47 @@
 )]
 
 pub use self::{
+    client_repo::{
+        Client, ClientId, Error as ClientRepoError, Interface as ClientRepoInterface,
+        Memory as MemoryClientRepo, Repo as ClientRepo, Result as ClientRepoResult,
+    },
     controllers::{
-        login::{Credentials as LoginCredentials, TokenPair},
+        introspect::{Introspection, TokenType},
+        login::{Credentials as LoginCredentials, TokenPair, SESSION_SCOPE},
+        passkey::Challenge as PasskeyChallenge,
         register::Credentials as RegisterCredentials,
     },
-    data::{AuthToken, Interface as TokenInterface, RefreshToken},
+    data::{AuthToken, ChallengeToken, Interface as TokenInterface, RefreshToken, SetupToken},
     error::{Error, Result},
+    events::{events as events_channel, Event, Events},
+    passkey_repo::{
+        Error as PasskeyRepoError, Interface as PasskeyRepoInterface, Memory as MemoryPasskeyRepo,
+        Repo as PasskeyRepo, Result as PasskeyRepoResult,
+    },
     provider::{Interface as ProviderInterface, Provider},
+    relying_party::RelyingParty,
     token_repo::{
-        Error as TokenRepoError, Interface as TokenRepoInterface, Memory as MemoryTokenRepo,
-        Result as TokenRepoResult, TokenRepo,
+        purge_legacy as purge_legacy_tokens, Error as TokenRepoError,
+        Interface as TokenRepoInterface, Memory as MemoryTokenRepo, Result as TokenRepoResult,
+        SlidingExpiry, TokenRepo,
     },
     user_repo::{
         Error as UserRepoError, Interface as UserRepoInterface, Memory as MemoryUserRepo,
         Repo as UserRepo, Result as UserRepoResult, User, UserId,
     },
 };
+pub use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
 
+mod client_repo;
 mod controllers;
 mod data;
 mod error;
+mod events;
+mod passkey_repo;
 mod provider;
+mod relying_party;
 mod token_repo;
 mod user_repo;
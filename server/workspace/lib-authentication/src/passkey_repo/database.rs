@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use webauthn_rs::prelude::{Credential, Passkey};
+
+use lib_database::{
+    Connection, WebauthnCredentials, WebauthnCredentialsController, WebauthnCredentialsFilter,
+    WebauthnCredentialsWrite,
+};
+
+use super::{Error, Interface, Result};
+use crate::UserId;
+
+pub struct Repo {
+    controller: WebauthnCredentialsController,
+}
+
+impl Repo {
+    pub fn new(connection: Connection) -> Self {
+        let controller = WebauthnCredentialsController::new(connection);
+        Self { controller }
+    }
+}
+
+#[async_trait]
+impl Interface for Repo {
+    async fn list(&self, user_id: UserId) -> Result<Vec<Passkey>> {
+        let rows = self
+            .controller
+            .read_many(WebauthnCredentialsFilter::default().user_id(user_id))
+            .await
+            .map_err(|_| Error::NotAvailable)?;
+
+        rows.into_iter().map(read).collect()
+    }
+
+    async fn create(&self, user_id: UserId, passkey: &Passkey) -> Result<()> {
+        let credential = Credential::from(passkey.clone());
+        let write = write(&credential)?
+            .user_id(user_id)
+            .credential_id(credential.cred_id.to_string());
+        self.controller.create(write).await.map_err(|e| {
+            log::error!("Failed to store passkey: {}", e);
+            Error::CreateFailed
+        })?;
+        Ok(())
+    }
+
+    async fn update(&self, passkey: &Passkey) -> Result<()> {
+        let credential = Credential::from(passkey.clone());
+        let filter =
+            WebauthnCredentialsFilter::default().credential_id(credential.cred_id.to_string());
+        self.controller
+            .update_many(filter, write(&credential)?)
+            .await
+            .map_err(|_| Error::NotAvailable)?;
+        Ok(())
+    }
+}
+
+/// Rebuilds a passkey from a database row.
+///
+/// The public key, signature counter and transports are read from their own
+/// columns, and everything else from the serialized credential record.
+fn read(row: WebauthnCredentials) -> Result<Passkey> {
+    let mut credential: Credential =
+        serde_json::from_str(&row.credential).map_err(|_| Error::NotAvailable)?;
+    credential.cred = serde_json::from_str(&row.public_key).map_err(|_| Error::NotAvailable)?;
+    credential.counter = u32::try_from(row.sign_count).map_err(|_| Error::NotAvailable)?;
+    credential.transports = row
+        .transports
+        .map(|transports| serde_json::from_str(&transports))
+        .transpose()
+        .map_err(|_| Error::NotAvailable)?;
+    Ok(credential.into())
+}
+
+/// Describes the mutable columns of a credential.
+fn write(credential: &Credential) -> Result<WebauthnCredentialsWrite> {
+    let public_key = serde_json::to_string(&credential.cred).map_err(|_| Error::NotAvailable)?;
+    let transports = credential
+        .transports
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|_| Error::NotAvailable)?;
+    let record = serde_json::to_string(credential).map_err(|_| Error::NotAvailable)?;
+
+    Ok(WebauthnCredentialsWrite::default()
+        .public_key(public_key)
+        .sign_count(i64::from(credential.counter))
+        .transports(transports)
+        .credential(record))
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use webauthn_rs::prelude::Passkey;
+
+use crate::UserId;
+
+/// The error type for the passkey repository.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("The passkey repository is not available.")]
+    NotAvailable,
+    #[error("The passkey could not be stored.")]
+    CreateFailed,
+}
+
+/// The result type for the passkey repository.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The interface for the passkey repository.
+#[async_trait]
+pub trait Interface: Send + Sync {
+    /// Lists the passkeys registered to a user.
+    ///
+    /// # Parameters
+    ///
+    /// - `user_id`: The ID of the user whose passkeys to list.
+    ///
+    /// # Returns
+    ///
+    /// Returns every passkey the user has registered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passkey repository is not available.
+    async fn list(&self, user_id: UserId) -> Result<Vec<Passkey>>;
+
+    /// Stores a newly registered passkey.
+    ///
+    /// # Parameters
+    ///
+    /// - `user_id`: The ID of the user who registered the passkey.
+    /// - `passkey`: The passkey to store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passkey could not be stored, such as when the
+    /// credential is already registered.
+    async fn create(&self, user_id: UserId, passkey: &Passkey) -> Result<()>;
+
+    /// Replaces a stored passkey with an updated copy, such as after its
+    /// signature counter has advanced.
+    ///
+    /// # Parameters
+    ///
+    /// - `passkey`: The passkey to store, identified by its credential ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passkey repository is not available.
+    async fn update(&self, passkey: &Passkey) -> Result<()>;
+}
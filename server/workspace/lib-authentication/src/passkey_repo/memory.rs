@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use webauthn_rs::prelude::Passkey;
+
+use super::{Error, Interface, Result};
+use crate::UserId;
+
+/// A passkey and the user who registered it.
+struct Record {
+    user_id: UserId,
+    passkey: Passkey,
+}
+
+/// A passkey repository that stores all passkeys in memory, keyed by credential ID.
+#[derive(Default)]
+pub struct Repo {
+    passkeys: Arc<RwLock<HashMap<Vec<u8>, Record>>>,
+}
+
+#[async_trait]
+impl Interface for Repo {
+    async fn list(&self, user_id: UserId) -> Result<Vec<Passkey>> {
+        let passkeys = self.passkeys.read().map_err(|_| Error::NotAvailable)?;
+        Ok(passkeys
+            .values()
+            .filter(|record| record.user_id == user_id)
+            .map(|record| record.passkey.clone())
+            .collect())
+    }
+
+    async fn create(&self, user_id: UserId, passkey: &Passkey) -> Result<()> {
+        let mut passkeys = self.passkeys.write().map_err(|_| Error::NotAvailable)?;
+        let credential_id = passkey.cred_id().as_ref().to_vec();
+        if passkeys.contains_key(&credential_id) {
+            return Err(Error::CreateFailed);
+        }
+        passkeys.insert(
+            credential_id,
+            Record {
+                user_id,
+                passkey: passkey.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn update(&self, passkey: &Passkey) -> Result<()> {
+        let mut passkeys = self.passkeys.write().map_err(|_| Error::NotAvailable)?;
+        if let Some(record) = passkeys.get_mut(passkey.cred_id().as_ref()) {
+            record.passkey = passkey.clone();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use webauthn_rs::prelude::Passkey;
+
+pub use interface::{Error, Interface, Result};
+use lib_database::Connection;
+pub use memory::Repo as Memory;
+
+use crate::UserId;
+
+mod database;
+mod interface;
+mod memory;
+
+/// The master passkey repository.
+#[derive(Clone)]
+pub struct Repo {
+    repo: std::sync::Arc<Box<dyn Interface>>,
+}
+
+impl Repo {
+    /// Creates a new in-memory passkey repository.
+    #[must_use]
+    pub fn memory() -> Self {
+        let repo = memory::Repo::default();
+        Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+        }
+    }
+
+    /// Creates a new database passkey repository.
+    #[must_use]
+    pub fn database(connection: Connection) -> Self {
+        let repo = database::Repo::new(connection);
+        Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+        }
+    }
+}
+
+#[async_trait]
+impl Interface for Repo {
+    async fn list(&self, user_id: UserId) -> Result<Vec<Passkey>> {
+        self.repo.list(user_id).await
+    }
+
+    async fn create(&self, user_id: UserId, passkey: &Passkey) -> Result<()> {
+        self.repo.create(user_id, passkey).await
+    }
+
+    async fn update(&self, passkey: &Passkey) -> Result<()> {
+        self.repo.update(passkey).await
+    }
+}
@@ -1,17 +1,27 @@
-use std::time::Duration;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use lib_crypto::Sha256Hash;
 
+use crate::controllers::introspect::{
+    introspect as introspect_controller, Introspection, Request as IntrospectRequest, TokenType,
+};
 use crate::controllers::login::{Request as LoginRequest, TokenPair};
+use crate::controllers::passkey::{login as passkey_login, register as passkey_register};
 use crate::controllers::refresh::{refresh, Request as RefreshRequest};
+use crate::controllers::setup::{self, Bootstrap, SETUP_TOKEN_SIZE, SETUP_TOKEN_TTL};
 use crate::controllers::{
     login::login as login_controller, logout::logout, register::register as register_controller,
-    whoami::whoami,
+    revoke::revoke as revoke_controller, whoami::whoami,
 };
+use crate::events::{publish, Event, Events};
 use crate::user_repo::User;
 use crate::{
-    AuthToken, LoginCredentials, ProviderInterface, RefreshToken, RegisterCredentials, Result,
-    TokenRepo, UserRepo,
+    AuthToken, ChallengeToken, CreationChallengeResponse, LoginCredentials, PasskeyChallenge,
+    PasskeyRepo, ProviderInterface, PublicKeyCredential, RefreshToken, RegisterCredentials,
+    RegisterPublicKeyCredential, RelyingParty, RequestChallengeResponse, Result, SetupToken,
+    TokenInterface, TokenRepo, UserRepo,
 };
 
 /// The core authentication provider.
@@ -20,6 +30,23 @@ pub struct Core {
     auth_token_repo: TokenRepo<AuthToken>,
     refresh_token_repo: TokenRepo<RefreshToken>,
     user_repo: UserRepo,
+    passkey_repo: PasskeyRepo,
+    challenge_token_repo: TokenRepo<ChallengeToken>,
+    relying_party: RelyingParty,
+    events: Option<Events>,
+    setup: Arc<Mutex<Option<PendingSetup>>>,
+}
+
+/// A setup token issued at startup, waiting to be used.
+struct PendingSetup {
+    /// The username of the admin whose password the token sets.
+    username: String,
+
+    /// The hash of the token.
+    token: Sha256Hash,
+
+    /// When the token stops working.
+    expires_at: Instant,
 }
 
 impl Core {
@@ -29,6 +56,9 @@ impl Core {
     ///
     /// - `token_repo` - The token repository.
     /// - `user_repo` - The user repository.
+    /// - `passkey_repo` - The passkey repository.
+    /// - `challenge_token_repo` - The repository holding pending passkey ceremonies.
+    /// - `relying_party` - The relying party that passkeys are registered with.
     ///
     /// # Returns
     ///
@@ -38,13 +68,32 @@ impl Core {
         auth_token_repo: TokenRepo<AuthToken>,
         refresh_token_repo: TokenRepo<RefreshToken>,
         user_repo: UserRepo,
+        passkey_repo: PasskeyRepo,
+        challenge_token_repo: TokenRepo<ChallengeToken>,
+        relying_party: RelyingParty,
     ) -> Self {
         Self {
             auth_token_repo,
             refresh_token_repo,
             user_repo,
+            passkey_repo,
+            challenge_token_repo,
+            relying_party,
+            events: None,
+            setup: Arc::default(),
         }
     }
+
+    /// Publishes an event whenever a session is ended.
+    ///
+    /// # Arguments
+    ///
+    /// - `events` - The channel to publish on.
+    #[must_use]
+    pub fn with_events(mut self, events: Events) -> Self {
+        self.events = Some(events);
+        self
+    }
 }
 
 #[async_trait]
@@ -104,10 +153,173 @@ impl ProviderInterface for Core {
 
     async fn logout(&self, auth_token: &AuthToken) -> Result<()> {
         let user = self.whoami(auth_token).await?;
-        logout(&self.auth_token_repo, &self.refresh_token_repo, auth_token).await?;
+        let user_id = logout(&self.auth_token_repo, &self.refresh_token_repo, auth_token).await?;
         if let Some(user) = user {
             log::info!("User {} ({}) logged out", user.username, user.id);
         }
+        publish(self.events.as_ref(), Event::SessionEnded { user_id }).await;
+        Ok(())
+    }
+
+    async fn introspect(
+        &self,
+        token: &[u8],
+        token_type_hint: Option<TokenType>,
+    ) -> Result<Option<Introspection>> {
+        introspect_controller(IntrospectRequest {
+            user_repo: &self.user_repo,
+            auth_token_repo: &self.auth_token_repo,
+            refresh_token_repo: &self.refresh_token_repo,
+            token,
+            token_type_hint,
+        })
+        .await
+    }
+
+    async fn revoke(&self, token: &[u8], token_type_hint: Option<TokenType>) -> Result<()> {
+        let revoked = revoke_controller(
+            &self.auth_token_repo,
+            &self.refresh_token_repo,
+            token,
+            token_type_hint,
+        )
+        .await?;
+        if let Some(user_id) = revoked {
+            publish(self.events.as_ref(), Event::SessionEnded { user_id }).await;
+        }
         Ok(())
     }
+
+    async fn start_passkey_registration(
+        &self,
+        user: &User,
+        challenge_ttl: Option<&Duration>,
+    ) -> Result<PasskeyChallenge<CreationChallengeResponse>> {
+        passkey_register::start(passkey_register::StartRequest {
+            passkey_repo: &self.passkey_repo,
+            challenge_token_repo: &self.challenge_token_repo,
+            relying_party: &self.relying_party,
+            user,
+            challenge_ttl,
+        })
+        .await
+    }
+
+    async fn finish_passkey_registration(
+        &self,
+        user: &User,
+        challenge: &ChallengeToken,
+        credential: &RegisterPublicKeyCredential,
+    ) -> Result<bool> {
+        let result = passkey_register::finish(passkey_register::FinishRequest {
+            passkey_repo: &self.passkey_repo,
+            challenge_token_repo: &self.challenge_token_repo,
+            relying_party: &self.relying_party,
+            user,
+            challenge,
+            credential,
+        })
+        .await?;
+        if result {
+            log::info!("User {} ({}) registered a passkey", user.username, user.id);
+        }
+        Ok(result)
+    }
+
+    async fn start_passkey_login(
+        &self,
+        username: &str,
+        challenge_ttl: Option<&Duration>,
+    ) -> Result<PasskeyChallenge<RequestChallengeResponse>> {
+        passkey_login::start(passkey_login::StartRequest {
+            user_repo: &self.user_repo,
+            passkey_repo: &self.passkey_repo,
+            challenge_token_repo: &self.challenge_token_repo,
+            relying_party: &self.relying_party,
+            username,
+            challenge_ttl,
+        })
+        .await
+    }
+
+    async fn finish_passkey_login(
+        &self,
+        challenge: &ChallengeToken,
+        credential: &PublicKeyCredential,
+        auth_token_ttl: Option<&Duration>,
+        refresh_token_ttl: Option<&Duration>,
+    ) -> Result<Option<TokenPair>> {
+        passkey_login::finish(passkey_login::FinishRequest {
+            passkey_repo: &self.passkey_repo,
+            challenge_token_repo: &self.challenge_token_repo,
+            auth_token_repo: &self.auth_token_repo,
+            refresh_token_repo: &self.refresh_token_repo,
+            relying_party: &self.relying_party,
+            challenge,
+            credential,
+            auth_token_ttl,
+            refresh_token_ttl,
+        })
+        .await
+    }
+
+    async fn bootstrap(
+        &self,
+        username: &str,
+        password: Option<&str>,
+    ) -> Result<Option<SetupToken>> {
+        match setup::bootstrap(&self.user_repo, username, password).await? {
+            Bootstrap::Ready => Ok(None),
+            Bootstrap::Configured => {
+                log::info!("Set the password of {} from the configuration", username);
+                Ok(None)
+            }
+            Bootstrap::Pending => {
+                let token = SetupToken::generate(SETUP_TOKEN_SIZE)?;
+                *self.setup.lock().unwrap_or_else(PoisonError::into_inner) = Some(PendingSetup {
+                    username: username.to_string(),
+                    token: token.hash(),
+                    expires_at: Instant::now() + SETUP_TOKEN_TTL,
+                });
+                Ok(Some(token))
+            }
+        }
+    }
+
+    async fn setup(&self, token: &SetupToken, password: &str) -> Result<bool> {
+        let pending = {
+            let mut pending = self.setup.lock().unwrap_or_else(PoisonError::into_inner);
+            match pending.take() {
+                Some(setup) if setup.expires_at <= Instant::now() => return Ok(false),
+                Some(setup) if setup.token == token.hash() => setup,
+                other => {
+                    *pending = other;
+                    return Ok(false);
+                }
+            }
+        };
+
+        match setup::set_password(&self.user_repo, &pending.username, password).await {
+            Ok(Some(user_id)) => {
+                log::info!(
+                    "Set the password of {} ({}) with the setup token",
+                    pending.username,
+                    user_id
+                );
+                Ok(true)
+            }
+            Ok(None) => {
+                log::warn!(
+                    "Refused the setup token, as {} already has a password",
+                    pending.username
+                );
+                Ok(false)
+            }
+            Err(e) => {
+                // Let the token be used again once the user store is back.
+                *self.setup.lock().unwrap_or_else(PoisonError::into_inner) = Some(pending);
+                Err(e)
+            }
+        }
+    }
 }
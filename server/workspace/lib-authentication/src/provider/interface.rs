@@ -2,9 +2,14 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 
+use crate::controllers::introspect::{Introspection, TokenType};
 use crate::controllers::login::TokenPair;
 use crate::user_repo::User;
-use crate::{AuthToken, LoginCredentials, RefreshToken, RegisterCredentials, Result};
+use crate::{
+    AuthToken, ChallengeToken, CreationChallengeResponse, LoginCredentials, PasskeyChallenge,
+    PublicKeyCredential, RefreshToken, RegisterCredentials, RegisterPublicKeyCredential,
+    RequestChallengeResponse, Result, SetupToken,
+};
 
 #[async_trait]
 pub trait Interface {
@@ -52,4 +57,92 @@ pub trait Interface {
     ///
     /// Returns an error if the token could not be deleted.
     async fn logout(&self, auth_token: &AuthToken) -> Result<()>;
+
+    /// Describes the given token, if it is active.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be checked.
+    async fn introspect(
+        &self,
+        token: &[u8],
+        token_type_hint: Option<TokenType>,
+    ) -> Result<Option<Introspection>>;
+
+    /// Revokes the given token along with its sibling token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be deleted.
+    async fn revoke(&self, token: &[u8], token_type_hint: Option<TokenType>) -> Result<()>;
+
+    /// Begins registering a new passkey for the given user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the challenge could not be generated.
+    async fn start_passkey_registration(
+        &self,
+        user: &User,
+        challenge_ttl: Option<&Duration>,
+    ) -> Result<PasskeyChallenge<CreationChallengeResponse>>;
+
+    /// Returns `true` if the passkey was verified and stored for the given user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passkey could not be stored.
+    async fn finish_passkey_registration(
+        &self,
+        user: &User,
+        challenge: &ChallengeToken,
+        credential: &RegisterPublicKeyCredential,
+    ) -> Result<bool>;
+
+    /// Begins logging in the given user with a passkey. A user who does not
+    /// exist or has no passkeys is sent a decoy challenge that cannot be
+    /// answered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the challenge could not be generated.
+    async fn start_passkey_login(
+        &self,
+        username: &str,
+        challenge_ttl: Option<&Duration>,
+    ) -> Result<PasskeyChallenge<RequestChallengeResponse>>;
+
+    /// Returns `None` if the passkey login failed, `Some` if it succeeded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be generated.
+    async fn finish_passkey_login(
+        &self,
+        challenge: &ChallengeToken,
+        credential: &PublicKeyCredential,
+        auth_token_ttl: Option<&Duration>,
+        refresh_token_ttl: Option<&Duration>,
+    ) -> Result<Option<TokenPair>>;
+
+    /// Makes sure the first admin has a password nobody could guess, giving
+    /// it the configured password if there is one. Otherwise, if the admin
+    /// does not exist, still has its seeded password or was never given one,
+    /// locks it and returns a one-time setup token with which to set its
+    /// password. The token expires after an hour.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the admin could not be read, created or updated.
+    async fn bootstrap(&self, username: &str, password: Option<&str>)
+        -> Result<Option<SetupToken>>;
+
+    /// Sets the password of the first admin, using up the setup token.
+    /// Returns `false` if the token is not the one issued at startup, has
+    /// expired or was already used, or if the admin already has a password.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the admin could not be created or updated.
+    async fn setup(&self, token: &SetupToken, password: &str) -> Result<bool>;
 }
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use lib_crypto::{fill_bytes, HmacSha256};
+use webauthn_rs::prelude::{Url, Webauthn, WebauthnBuilder, WebauthnError};
+
+use crate::Result;
+
+/// The `WebAuthn` relying party that passkey ceremonies are performed for.
+#[derive(Clone)]
+pub struct RelyingParty {
+    webauthn: Arc<Webauthn>,
+
+    /// The key the IDs of decoy credentials are derived with.
+    decoy_key: [u8; 32],
+}
+
+impl RelyingParty {
+    /// Creates a new relying party.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` - The relying party ID, which is the effective domain of the origin.
+    /// - `origin` - The origin that browsers will report during ceremonies.
+    /// - `name` - The human-readable name that authenticators show the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the origin is not a valid URL or is not within the
+    /// relying party ID, or the decoy key could not be generated.
+    pub fn new(id: &str, origin: &str, name: &str) -> Result<Self> {
+        let origin = Url::parse(origin).map_err(|_| WebauthnError::Configuration)?;
+        let webauthn = WebauthnBuilder::new(id, &origin)?.rp_name(name).build()?;
+
+        let mut decoy_key = [0; 32];
+        fill_bytes(&mut decoy_key)?;
+
+        Ok(Self {
+            webauthn: Arc::new(webauthn),
+            decoy_key,
+        })
+    }
+
+    /// Get the ID of a credential that does not exist, to offer in place of
+    /// the passkeys of a user who has none. The ID is the same every time for
+    /// the same username, so it cannot be told apart from a real one by asking
+    /// twice.
+    ///
+    /// # Arguments
+    ///
+    /// - `username` - The username the credential is offered for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ID could not be derived.
+    pub(crate) fn decoy_credential_id(&self, username: &str) -> Result<Vec<u8>> {
+        let id = HmacSha256::sign(&self.decoy_key, username.as_bytes())?;
+        Ok(id.as_ref().to_vec())
+    }
+}
+
+impl AsRef<Webauthn> for RelyingParty {
+    fn as_ref(&self) -> &Webauthn {
+        &self.webauthn
+    }
+}
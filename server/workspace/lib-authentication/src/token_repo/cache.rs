@@ -1,57 +1,172 @@
-use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use lib_base64::Encode;
+use lib_cache::{Interface as CacheInterface, Pipeline, Typed, Versioned};
 
+use crate::token_repo::sliding::SESSION_EXPIRES_TAG;
+use crate::token_repo::SlidingExpiry;
 use crate::{TokenInterface, TokenRepoError, TokenRepoInterface, TokenRepoResult};
 
 /// A token repository that uses a cache.
-pub struct TokenRepo {
+pub struct TokenRepo<Cache: CacheInterface> {
     prefix: String,
-    controller: lib_cache::Controller,
+    cache: Typed<Cache>,
+    sliding_expiry: Option<SlidingExpiry>,
 }
 
-impl TokenRepo {
+impl<Cache: CacheInterface> TokenRepo<Cache> {
     /// Create a new token repository.
     ///
     /// # Arguments
     ///
-    /// * `controller` - The cache controller.
+    /// * `cache` - The cache.
     /// * `prefix` - The prefix to use for keys.
-    pub fn new(controller: lib_cache::Controller, prefix: String) -> Self {
-        Self { prefix, controller }
+    pub fn new(cache: Cache, prefix: String) -> Self {
+        Self {
+            prefix,
+            cache: Typed::new(cache),
+            sliding_expiry: None,
+        }
     }
 
-    /// Get formatted key for retrieving a value from the cache.
+    /// Extend the expiry of tokens each time they are read.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to format.
-    fn get_key(&self, key: &str) -> String {
-        format!("{}:{}", self.prefix, key)
+    /// * `sliding_expiry` - How far and how often to extend tokens.
+    #[must_use]
+    pub fn with_sliding_expiry(mut self, sliding_expiry: SlidingExpiry) -> Self {
+        self.sliding_expiry = Some(sliding_expiry);
+        self
     }
 
-    /// Get a connection to the cache.
+    /// Get formatted key for retrieving a token's values from the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash of the token.
     ///
     /// # Errors
     ///
-    /// Returns an error if the connection could not be established.
-    async fn get_connection(&self) -> TokenRepoResult<lib_cache::Connection> {
-        self.controller
-            .connect()
+    /// Returns an error if the hash could not be encoded.
+    fn get_key(&self, hash: &[u8]) -> TokenRepoResult<String> {
+        let hash = hash.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
+        Ok(format!("{}:{HASHED}:{}", self.prefix, hash))
+    }
+
+    /// Extend the expiry of a token that has just been read, if it is due.
+    ///
+    /// Only the remaining TTL is read on every call; the key is written to at
+    /// most once per `extend_interval`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the token that was read.
+    async fn slide(&self, key: &str) -> TokenRepoResult<()> {
+        let Some(sliding_expiry) = self.sliding_expiry else { return Ok(()) };
+
+        let ttl = self.cache.ttl(key).await.map_err(TokenRepoError::from)?;
+        let Some(ttl) = ttl else { return Ok(()) };
+
+        let session_expires = self
+            .cache
+            .hget(key, SESSION_EXPIRES_TAG)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))
+            .map_err(TokenRepoError::from)?;
+
+        let now = SystemTime::now();
+        let expiry = now + ttl;
+        let Some(expiry) = sliding_expiry.extend(now, expiry, session_expires.as_deref()) else {
+            return Ok(());
+        };
+        let ttl = expiry.duration_since(now).unwrap_or_default();
+
+        self.cache
+            .expire(key, &ttl)
+            .await
+            .map_err(TokenRepoError::from)?;
+
+        Ok(())
     }
+
+    /// Get the user of a token from its session record.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The token's session record.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenRepoError::TokenInvalid`] if the record could not be
+    /// decoded.
+    fn user_id(&self, session: &[u8]) -> TokenRepoResult<Uuid> {
+        let session: Session = self
+            .cache
+            .decode(session)
+            .map_err(|_| TokenRepoError::TokenInvalid)?;
+        Ok(session.user_id)
+    }
+}
+
+/// The record stored with each token, describing its session.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    /// The ID of the user the token was issued to.
+    user_id: Uuid,
 }
 
-/// The key used to store the user ID.
-const USER_ID: &str = "uid";
+impl Versioned for Session {
+    const VERSION: u32 = 1;
+}
+
+/// The key used to store the session record.
+const SESSION: &str = "session";
+
+/// The segment marking keys that hold a token's hash rather than the token.
+const HASHED: &str = "sha256";
+
+/// Delete every token stored under the token itself rather than its hash, as
+/// earlier versions did.
+///
+/// Such tokens can no longer be used, since tokens are looked up by their hash,
+/// but anyone able to read the cache could still replay them against an older
+/// deployment until they expire.
+///
+/// # Arguments
+///
+/// * `cache` - The cache.
+/// * `prefix` - The prefix of the token repository's keys.
+///
+/// # Returns
+///
+/// The number of tokens deleted.
+///
+/// # Errors
+///
+/// Returns an error if the keys could not be listed or deleted.
+pub async fn purge_legacy(cache: &impl CacheInterface, prefix: &str) -> TokenRepoResult<u64> {
+    let hashed = format!("{prefix}:{HASHED}:");
+    let legacy = cache
+        .scan(&format!("{prefix}:*"))
+        .await
+        .map_err(TokenRepoError::from)?
+        .into_iter()
+        .filter(|key| !key.starts_with(&hashed))
+        .collect::<Vec<_>>();
+
+    for key in &legacy {
+        cache.delete(key).await.map_err(TokenRepoError::from)?;
+    }
+
+    Ok(legacy.len() as u64)
+}
 
 #[async_trait]
-impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
+impl<Token: TokenInterface, Cache: CacheInterface> TokenRepoInterface<Token> for TokenRepo<Cache> {
     async fn put(
         &self,
         token: &Token,
@@ -59,94 +174,358 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
         tags: &[(&str, &[u8])],
         ttl: Option<&Duration>,
     ) -> TokenRepoResult<()> {
-        let user_id = user_id.to_string();
-        let token = token.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
-        let key = self.get_key(&token);
+        // The token and its expiry are written in one transaction, so a failure
+        // cannot leave behind a token that never expires.
+        let pipeline = self
+            .queue_put(Pipeline::new(), token, user_id, tags, ttl)?
+            .unwrap_or_default();
 
-        let mut c = self.get_connection().await?;
-
-        c.hset(&key, USER_ID, user_id)
+        self.cache
+            .transaction(&pipeline)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(format!("{e}: {key}")))?;
+            .map_err(TokenRepoError::from)?;
+
+        Ok(())
+    }
+
+    fn queue_put(
+        &self,
+        pipeline: Pipeline,
+        token: &Token,
+        user_id: &Uuid,
+        tags: &[(&str, &[u8])],
+        ttl: Option<&Duration>,
+    ) -> TokenRepoResult<Option<Pipeline>> {
+        let key = self.get_key(token.hash().as_ref())?;
+        let session = self
+            .cache
+            .encode(&Session { user_id: *user_id })
+            .map_err(TokenRepoError::from)?;
+
+        let mut pipeline = pipeline.hset(&key, SESSION, &session);
 
         for &(tag, value) in tags {
-            c.hset(&key, tag, value)
-                .await
-                .map_err(|e| TokenRepoError::TokenRepoError(format!("{e}: {key}")))?;
+            pipeline = pipeline.hset(&key, tag, value);
+        }
+
+        let ttl = match (ttl, self.sliding_expiry) {
+            (Some(ttl), Some(sliding_expiry)) => Some(sliding_expiry.issue_ttl(ttl)),
+            (ttl, _) => ttl.copied(),
+        };
+
+        if let Some(sliding_expiry) = self.sliding_expiry {
+            pipeline = pipeline.hset(
+                &key,
+                SESSION_EXPIRES_TAG,
+                &sliding_expiry.session_expires(SystemTime::now()),
+            );
         }
 
         if let Some(expiry) = ttl {
-            let _ = c
-                .expire(&key, expiry)
-                .await
-                .map_err(|e| TokenRepoError::TokenRepoError(format!("{e}: {key}")))?;
+            pipeline = pipeline.expire(&key, expiry);
         }
 
-        Ok(())
+        Ok(Some(pipeline))
     }
 
     async fn get(&self, token: &Token) -> crate::token_repo::Result<Uuid> {
-        let token = token.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
-        let key = self.get_key(&token);
+        let key = self.get_key(token.hash().as_ref())?;
 
-        let mut c = self.get_connection().await?;
-
-        let uuid: String = c
-            .hget(&key, USER_ID)
+        let session = self
+            .cache
+            .hget(&key, SESSION)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))?
+            .map_err(TokenRepoError::from)?
             .ok_or(TokenRepoError::TokenNotFound)?;
+        let uuid = self.user_id(&session)?;
+
+        self.slide(&key).await?;
 
-        let uuid = Uuid::from_str(&uuid).map_err(|_| TokenRepoError::TokenInvalid)?;
         Ok(uuid)
     }
 
+    async fn expiry(&self, token: &Token) -> TokenRepoResult<Option<SystemTime>> {
+        let key = self.get_key(token.hash().as_ref())?;
+
+        if !self
+            .cache
+            .exists(&key)
+            .await
+            .map_err(TokenRepoError::from)?
+        {
+            return Err(TokenRepoError::TokenNotFound);
+        }
+
+        let ttl = self.cache.ttl(&key).await.map_err(TokenRepoError::from)?;
+
+        Ok(ttl.map(|ttl| SystemTime::now() + ttl))
+    }
+
     async fn delete(&self, token: &Token) -> crate::token_repo::Result<()> {
-        let token = token.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
-        let key = self.get_key(&token);
+        TokenRepoInterface::<Token>::delete_hash(self, token.hash().as_ref()).await
+    }
 
-        let mut c = self.get_connection().await?;
+    async fn delete_hash(&self, hash: &[u8]) -> crate::token_repo::Result<()> {
+        let key = self.get_key(hash)?;
 
-        if !c
+        if !self
+            .cache
             .exists(&key)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))?
+            .map_err(TokenRepoError::from)?
         {
             return Err(TokenRepoError::TokenNotFound);
         }
 
-        c.delete(&key)
+        self.cache
+            .delete(&key)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))?;
+            .map_err(TokenRepoError::from)?;
 
         Ok(())
     }
 
-    async fn get_tag(&self, token: &Token, tag: &str) -> crate::token_repo::Result<Vec<u8>> {
-        let token = token.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
-        let key = self.get_key(&token);
+    fn cache_key(&self, hash: &[u8]) -> Option<String> {
+        self.get_key(hash).ok()
+    }
 
-        let mut c = self.get_connection().await?;
+    async fn take(
+        &self,
+        token: &Token,
+        tag: &str,
+        then: &Pipeline,
+    ) -> crate::token_repo::Result<(Uuid, Option<Vec<u8>>)> {
+        let key = self.get_key(token.hash().as_ref())?;
 
-        let value: Vec<u8> = c
+        let mut fields = self
+            .cache
+            .take(&key, then)
+            .await
+            .map_err(TokenRepoError::from)?
+            .ok_or(TokenRepoError::TokenNotFound)?;
+
+        let session = fields.remove(SESSION).ok_or(TokenRepoError::TokenInvalid)?;
+        let uuid = self.user_id(&session)?;
+
+        Ok((uuid, fields.remove(tag)))
+    }
+
+    async fn get_tag(&self, token: &Token, tag: &str) -> crate::token_repo::Result<Vec<u8>> {
+        let key = self.get_key(token.hash().as_ref())?;
+
+        let value = self
+            .cache
             .hget(&key, tag)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))?
+            .map_err(TokenRepoError::from)?
             .ok_or(TokenRepoError::TokenNotFound)?;
 
         Ok(value)
     }
 
     async fn put_tag(&self, token: &Token, tag: &str, value: &[u8]) -> TokenRepoResult<()> {
-        let token = token.encode().map_err(|_| TokenRepoError::TokenInvalid)?;
-        let key = self.get_key(&token);
-
-        let mut c = self.get_connection().await?;
+        let key = self.get_key(token.hash().as_ref())?;
 
-        c.hset(&key, tag, value)
+        self.cache
+            .hset(&key, tag, value)
             .await
-            .map_err(|e| TokenRepoError::TokenRepoError(e.to_string()))?;
+            .map_err(TokenRepoError::from)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lib_cache::Memory;
+
+    use crate::{AuthToken, TokenInterface};
+
+    use super::*;
+
+    const SLIDING: SlidingExpiry = SlidingExpiry {
+        idle_timeout: Duration::from_mins(10),
+        extend_interval: Duration::from_mins(1),
+        max_lifetime: Duration::from_hours(1),
+    };
+
+    /// Pretend the token was last used long enough ago that it now expires in `ttl`.
+    async fn set_ttl(repo: &TokenRepo<Memory>, token: &AuthToken, ttl: Duration) {
+        let key = repo.get_key(token.hash().as_ref()).unwrap();
+        repo.cache.expire(&key, &ttl).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_put_get_delete() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        let user_id = Uuid::new_v4();
+        repo.put(&token, &user_id, &[("scope", b"metrics:read")], None)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.get(&token).await.unwrap(), user_id);
+        assert_eq!(repo.expiry(&token).await.unwrap(), None);
+        assert_eq!(
+            repo.get_tag(&token, "scope").await.unwrap(),
+            b"metrics:read"
+        );
+        assert!(matches!(
+            repo.get_tag(&token, "other").await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+
+        repo.put_tag(&token, "other", b"value").await.unwrap();
+        assert_eq!(repo.get_tag(&token, "other").await.unwrap(), b"value");
+
+        repo.delete(&token).await.unwrap();
+        assert!(matches!(
+            repo.get(&token).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+        assert!(matches!(
+            repo.delete(&token).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(
+            &token,
+            &Uuid::new_v4(),
+            &[],
+            Some(&Duration::from_millis(50)),
+        )
+        .await
+        .unwrap();
+
+        let expiry = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(expiry <= SystemTime::now() + Duration::from_millis(50));
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(matches!(
+            repo.get(&token).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+        assert!(matches!(
+            repo.expiry(&token).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_extends() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string()).with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        let user_id = Uuid::new_v4();
+        repo.put(&token, &user_id, &[], Some(&SLIDING.idle_timeout))
+            .await
+            .unwrap();
+
+        set_ttl(&repo, &token, Duration::from_mins(5)).await;
+        let before = SystemTime::now() + Duration::from_mins(5);
+
+        assert_eq!(repo.get(&token).await.unwrap(), user_id);
+        let after = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(after >= before + Duration::from_secs(299));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_throttled() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string()).with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], Some(&SLIDING.idle_timeout))
+            .await
+            .unwrap();
+
+        set_ttl(&repo, &token, Duration::from_secs(590)).await;
+        let before = SystemTime::now() + Duration::from_secs(590);
+
+        repo.get(&token).await.unwrap();
+        let after = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(after <= before + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_capped() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string()).with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], Some(&Duration::from_hours(2)))
+            .await
+            .unwrap();
+
+        let expiry = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(expiry <= SystemTime::now() + SLIDING.max_lifetime);
+
+        let session_expires = repo.get_tag(&token, SESSION_EXPIRES_TAG).await.unwrap();
+        assert!(!session_expires.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stores_only_hash() {
+        let cache = Memory::default();
+        let repo = TokenRepo::new(cache.clone(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], None).await.unwrap();
+
+        let encoded = token.as_ref().encode().unwrap();
+        let keys = cache.scan("*").await.unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].starts_with("a:sha256:"));
+        assert!(!keys[0].contains(&encoded));
+    }
+
+    #[tokio::test]
+    async fn test_purge_legacy() {
+        let cache = Memory::default();
+        let repo = TokenRepo::new(cache.clone(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], None).await.unwrap();
+        cache.hset("a:legacy", SESSION, b"user").await.unwrap();
+        cache.hset("r:legacy", SESSION, b"user").await.unwrap();
+
+        assert_eq!(purge_legacy(&cache, "a").await.unwrap(), 1);
+        assert!(!cache.exists("a:legacy").await.unwrap());
+        assert!(cache.exists("r:legacy").await.unwrap());
+        assert!(repo.get(&token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_session() {
+        let cache = Memory::default();
+        let repo = TokenRepo::new(cache.clone(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        let key = repo.get_key(token.hash().as_ref()).unwrap();
+
+        cache.hset(&key, SESSION, b"not a session").await.unwrap();
+        assert!(matches!(
+            repo.get(&token).await,
+            Err(TokenRepoError::TokenInvalid)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_take() {
+        let repo = TokenRepo::new(Memory::default(), "a".to_string());
+        let token = AuthToken::generate(32).unwrap();
+        let user_id = Uuid::new_v4();
+        repo.put(&token, &user_id, &[("scope", b"metrics:read")], None)
+            .await
+            .unwrap();
+
+        let (taken, scope) = repo.take(&token, "scope", &Pipeline::new()).await.unwrap();
+        assert_eq!(taken, user_id);
+        assert_eq!(scope.as_deref(), Some(&b"metrics:read"[..]));
+        assert!(matches!(
+            repo.get(&token).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+        assert!(matches!(
+            repo.take(&token, "scope", &Pipeline::new()).await,
+            Err(TokenRepoError::TokenNotFound)
+        ));
+    }
+}
@@ -1,6 +1,7 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
+use lib_cache::Pipeline;
 use uuid::Uuid;
 
 use crate::TokenInterface;
@@ -19,10 +20,25 @@ pub enum Error {
     #[error("token invalid")]
     TokenInvalid,
 
+    #[error("token repo not available")]
+    NotAvailable,
+
     #[error("token repo error: {0}")]
     TokenRepoError(String),
 }
 
+/// Errors reaching the cache are reported as [`Error::NotAvailable`], so they
+/// can be told apart from the cache rejecting an operation.
+impl From<lib_cache::Error> for Error {
+    fn from(err: lib_cache::Error) -> Self {
+        if err.is_unavailable() {
+            return Self::NotAvailable;
+        }
+
+        Self::TokenRepoError(err.to_string())
+    }
+}
+
 /// Provides the interface for a token repository.
 #[async_trait]
 pub trait Interface<Token: TokenInterface>: Send + Sync {
@@ -46,8 +62,39 @@ pub trait Interface<Token: TokenInterface>: Send + Sync {
         ttl: Option<&Duration>,
     ) -> Result<()>;
 
+    /// Add the commands that put a user ID into the token repository to a
+    /// pipeline, if the repository keeps its tokens in a cache, so they can be
+    /// run along with taking a token in [`Interface::take`].
+    ///
+    /// # Parameters
+    ///
+    /// - `pipeline`: The pipeline to add the commands to.
+    /// - `token`: The token to put into the token repository.
+    /// - `user_id`: The user ID to put into the token repository.
+    /// - `tags`: The tags to put into the token repository.
+    /// - `ttl`: The time to live of the token.
+    ///
+    /// # Returns
+    ///
+    /// The pipeline, or `None` if the repository does not use a cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the commands could not be encoded.
+    fn queue_put(
+        &self,
+        pipeline: Pipeline,
+        token: &Token,
+        user_id: &Uuid,
+        tags: &[(&str, &[u8])],
+        ttl: Option<&Duration>,
+    ) -> Result<Option<Pipeline>>;
+
     /// Get the user ID from the token repository.
     ///
+    /// If the repository uses sliding expiry, this also extends the token's
+    /// expiry when it is due.
+    ///
     /// # Parameters
     ///
     /// - `token`: The token to get from the token repository.
@@ -61,6 +108,21 @@ pub trait Interface<Token: TokenInterface>: Send + Sync {
     /// Returns an error if the token could not be found.
     async fn get(&self, token: &Token) -> Result<Uuid>;
 
+    /// Get the time at which the token expires.
+    ///
+    /// # Parameters
+    ///
+    /// - `token`: The token to get the expiry of.
+    ///
+    /// # Returns
+    ///
+    /// The expiry time, or `None` if the token does not expire.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be found.
+    async fn expiry(&self, token: &Token) -> Result<Option<SystemTime>>;
+
     /// Delete the token from the token repository.
     ///
     /// # Parameters
@@ -72,6 +134,59 @@ pub trait Interface<Token: TokenInterface>: Send + Sync {
     /// Returns an error if the token could not be deleted.
     async fn delete(&self, token: &Token) -> Result<()>;
 
+    /// Delete a token from the token repository by its hash, as stored in the
+    /// tags of a sibling token.
+    ///
+    /// # Parameters
+    ///
+    /// - `hash`: The hash of the token to delete, from [`TokenInterface::hash`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be deleted.
+    async fn delete_hash(&self, hash: &[u8]) -> Result<()>;
+
+    /// Get the cache key a token is stored under, if the repository keeps its
+    /// tokens in a cache that other repositories can share, so they can delete
+    /// it in the pipeline of [`Interface::take`].
+    ///
+    /// # Parameters
+    ///
+    /// - `hash`: The hash of the token, from [`TokenInterface::hash`].
+    ///
+    /// # Returns
+    ///
+    /// The key, or `None` if the repository does not use a cache.
+    fn cache_key(&self, hash: &[u8]) -> Option<String>;
+
+    /// Delete the token from the token repository, returning its user ID and
+    /// the value of one of its tags, as a single atomic operation. When the
+    /// same token is taken several times at once only one call succeeds, so a
+    /// token can be used once by taking it.
+    ///
+    /// # Parameters
+    ///
+    /// - `token`: The token to take from the token repository.
+    /// - `tag`: The tag to get the value of.
+    /// - `then`: Commands to run on the cache in the same operation, such as
+    ///   from [`Interface::queue_put`]. They are only run if the token exists,
+    ///   and the token is kept if they fail.
+    ///
+    /// # Returns
+    ///
+    /// The user ID, and the value of the tag if the token has it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token could not be found, or could not be taken,
+    /// including when commands are given to a repository without a cache.
+    async fn take(
+        &self,
+        token: &Token,
+        tag: &str,
+        then: &Pipeline,
+    ) -> Result<(Uuid, Option<Vec<u8>>)>;
+
     /// Get the value of a tag.
     ///
     /// # Parameters
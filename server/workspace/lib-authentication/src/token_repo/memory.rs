@@ -3,12 +3,14 @@ use std::sync::RwLock;
 use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
+use lib_cache::Pipeline;
 use uuid::Uuid;
 
-use crate::token_repo::{Error, Result, TokenInterface};
+use crate::token_repo::sliding::SESSION_EXPIRES_TAG;
+use crate::token_repo::{Error, Result, SlidingExpiry, TokenInterface};
 use crate::TokenRepoInterface;
 
-/// A record within the token repository.
+/// A record within the token repository, keyed by the hash of its token.
 struct Record {
     user_id: Uuid,
     expiry: Option<SystemTime>,
@@ -17,7 +19,7 @@ struct Record {
 struct TagRecord {
     tag: String,
     value: Vec<u8>,
-    token: Vec<u8>,
+    token_hash: Vec<u8>,
 }
 
 /// An in-memory token repository.
@@ -25,6 +27,58 @@ struct TagRecord {
 pub struct TokenRepo {
     token_repo: RwLock<HashMap<Vec<u8>, Record>>,
     tags_repo: RwLock<Vec<TagRecord>>,
+    sliding_expiry: Option<SlidingExpiry>,
+}
+
+impl TokenRepo {
+    /// Extend the expiry of tokens each time they are read.
+    ///
+    /// # Arguments
+    ///
+    /// * `sliding_expiry` - How far and how often to extend tokens.
+    #[must_use]
+    pub fn with_sliding_expiry(mut self, sliding_expiry: SlidingExpiry) -> Self {
+        self.sliding_expiry = Some(sliding_expiry);
+        self
+    }
+
+    /// Extend the expiry of a token that has just been read, if it is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `token_hash` - The hash of the token that was read.
+    /// * `expiry` - The current expiry of the token.
+    fn slide(&self, token_hash: &[u8], expiry: SystemTime) -> Result<()> {
+        let Some(sliding_expiry) = self.sliding_expiry else { return Ok(()) };
+
+        let session_expires = {
+            let tags_repo = self
+                .tags_repo
+                .read()
+                .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+
+            tags_repo
+                .iter()
+                .find(|t| t.token_hash == token_hash && t.tag == SESSION_EXPIRES_TAG)
+                .map(|t| t.value.clone())
+        };
+
+        let now = SystemTime::now();
+        let Some(expiry) = sliding_expiry.extend(now, expiry, session_expires.as_deref()) else {
+            return Ok(());
+        };
+
+        let mut token_repo = self
+            .token_repo
+            .write()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+
+        if let Some(record) = token_repo.get_mut(token_hash) {
+            record.expiry = Some(expiry);
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -36,45 +90,68 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
         tags: &[(&str, &[u8])],
         ttl: Option<&Duration>,
     ) -> Result<()> {
-        let expiry = ttl.map(|ttl| SystemTime::now() + *ttl);
+        let now = SystemTime::now();
+        let expiry = match (ttl, self.sliding_expiry) {
+            (Some(ttl), Some(sliding_expiry)) => Some(now + sliding_expiry.issue_ttl(ttl)),
+            (Some(ttl), None) => Some(now + *ttl),
+            (None, _) => None,
+        };
         let user_id = *user_id;
-        let token = token.as_ref().to_owned();
+        let token_hash = token.hash().as_ref().to_vec();
 
-        {
-            let mut token_repo = self
-                .token_repo
-                .write()
-                .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+        // Both locks are held throughout, so the token is never seen without its tags.
+        let mut token_repo = self
+            .token_repo
+            .write()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+        let mut tags_repo = self
+            .tags_repo
+            .write()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
 
-            token_repo.insert(token.clone(), Record { user_id, expiry });
+        for &(tag, value) in tags {
+            tags_repo.push(TagRecord {
+                tag: tag.to_owned(),
+                value: value.to_vec(),
+                token_hash: token_hash.clone(),
+            });
         }
 
-        {
-            let mut tags_repo = self
-                .tags_repo
-                .write()
-                .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
-
-            for &(tag, value) in tags {
-                tags_repo.push(TagRecord {
-                    tag: tag.to_owned(),
-                    value: value.to_vec(),
-                    token: token.clone(),
-                });
-            }
+        if let Some(sliding_expiry) = self.sliding_expiry {
+            tags_repo.push(TagRecord {
+                tag: SESSION_EXPIRES_TAG.to_owned(),
+                value: sliding_expiry.session_expires(now),
+                token_hash: token_hash.clone(),
+            });
         }
 
+        token_repo.insert(token_hash, Record { user_id, expiry });
+
         Ok(())
     }
 
+    fn queue_put(
+        &self,
+        _pipeline: Pipeline,
+        _token: &Token,
+        _user_id: &Uuid,
+        _tags: &[(&str, &[u8])],
+        _ttl: Option<&Duration>,
+    ) -> Result<Option<Pipeline>> {
+        Ok(None)
+    }
+
     async fn get(&self, token: &Token) -> Result<Uuid> {
+        let token_hash = token.hash();
         let (user_id, expiry) = {
             let token_repo = self
                 .token_repo
                 .read()
                 .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
 
-            let record = token_repo.get(token.as_ref()).ok_or(Error::TokenNotFound)?;
+            let record = token_repo
+                .get(token_hash.as_ref())
+                .ok_or(Error::TokenNotFound)?;
 
             let expiry = record.expiry;
             let user_id = record.user_id;
@@ -88,28 +165,90 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
                 self.delete(token).await?;
                 return Err(Error::TokenExpired);
             }
+            self.slide(token_hash.as_ref(), expiry)?;
         }
         Ok(user_id)
     }
 
+    async fn expiry(&self, token: &Token) -> Result<Option<SystemTime>> {
+        // Checking the token first purges it if it has already expired.
+        self.get(token).await?;
+
+        let token_repo = self
+            .token_repo
+            .read()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+
+        let record = token_repo
+            .get(token.hash().as_ref())
+            .ok_or(Error::TokenNotFound)?;
+        Ok(record.expiry)
+    }
+
     async fn delete(&self, token: &Token) -> Result<()> {
+        TokenRepoInterface::<Token>::delete_hash(self, token.hash().as_ref()).await
+    }
+
+    async fn delete_hash(&self, hash: &[u8]) -> Result<()> {
         let mut token_repo = self
             .token_repo
             .write()
             .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
 
-        token_repo.remove(token.as_ref());
+        token_repo.remove(hash);
 
         let mut tags_repo = self
             .tags_repo
             .write()
             .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
 
-        tags_repo.retain(|t| t.token != token.as_ref());
+        tags_repo.retain(|t| t.token_hash != hash);
 
         Ok(())
     }
 
+    fn cache_key(&self, _hash: &[u8]) -> Option<String> {
+        None
+    }
+
+    async fn take(
+        &self,
+        token: &Token,
+        tag: &str,
+        then: &Pipeline,
+    ) -> Result<(Uuid, Option<Vec<u8>>)> {
+        if !then.is_empty() {
+            return Err(Error::TokenRepoError(
+                "an in-memory repository cannot run cache commands".to_string(),
+            ));
+        }
+
+        let token_hash = token.hash();
+        let token_hash = token_hash.as_ref();
+
+        let mut token_repo = self
+            .token_repo
+            .write()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+        let mut tags_repo = self
+            .tags_repo
+            .write()
+            .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
+
+        let record = token_repo.remove(token_hash).ok_or(Error::TokenNotFound)?;
+        let value = tags_repo
+            .iter()
+            .find(|t| t.token_hash == token_hash && t.tag == tag)
+            .map(|t| t.value.clone());
+        tags_repo.retain(|t| t.token_hash != token_hash);
+
+        if matches!(record.expiry, Some(expiry) if SystemTime::now() > expiry) {
+            return Err(Error::TokenExpired);
+        }
+
+        Ok((record.user_id, value))
+    }
+
     async fn put_tag(&self, token: &Token, tag: &str, value: &[u8]) -> Result<()> {
         let mut tags_repo = self
             .tags_repo
@@ -118,7 +257,7 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
         tags_repo.push(TagRecord {
             tag: tag.to_owned(),
             value: value.to_vec(),
-            token: token.as_ref().to_owned(),
+            token_hash: token.hash().as_ref().to_vec(),
         });
         Ok(())
     }
@@ -130,8 +269,125 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo {
             .map_err(|e| Error::TokenRepoError(format!("{e}")))?;
         let tag_record = tags_repo
             .iter()
-            .find(|t| t.token == token.as_ref() && t.tag == tag)
+            .find(|t| t.token_hash == token.hash().as_ref() && t.tag == tag)
             .ok_or(Error::TokenNotFound)?;
         Ok(tag_record.value.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{AuthToken, TokenInterface};
+
+    use super::*;
+
+    const SLIDING: SlidingExpiry = SlidingExpiry {
+        idle_timeout: Duration::from_mins(10),
+        extend_interval: Duration::from_mins(1),
+        max_lifetime: Duration::from_hours(1),
+    };
+
+    /// Pretend the token was last used long enough ago that it now expires at `expiry`.
+    fn set_expiry(repo: &TokenRepo, token: &AuthToken, expiry: SystemTime) {
+        let mut token_repo = repo.token_repo.write().unwrap();
+        token_repo.get_mut(token.hash().as_ref()).unwrap().expiry = Some(expiry);
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_extends() {
+        let repo = TokenRepo::default().with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        let user_id = Uuid::new_v4();
+        repo.put(&token, &user_id, &[], Some(&SLIDING.idle_timeout))
+            .await
+            .unwrap();
+
+        let before = SystemTime::now() + Duration::from_mins(5);
+        set_expiry(&repo, &token, before);
+
+        assert_eq!(repo.get(&token).await.unwrap(), user_id);
+        let after = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(after >= before + Duration::from_secs(299));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_throttled() {
+        let repo = TokenRepo::default().with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], Some(&SLIDING.idle_timeout))
+            .await
+            .unwrap();
+
+        let before = SystemTime::now() + Duration::from_secs(590);
+        set_expiry(&repo, &token, before);
+
+        repo.get(&token).await.unwrap();
+        assert_eq!(repo.expiry(&token).await.unwrap(), Some(before));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_expiry_capped() {
+        let repo = TokenRepo::default().with_sliding_expiry(SLIDING);
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[], Some(&Duration::from_hours(2)))
+            .await
+            .unwrap();
+
+        let expiry = repo.expiry(&token).await.unwrap().unwrap();
+        assert!(expiry <= SystemTime::now() + SLIDING.max_lifetime);
+
+        let session_expires = repo.get_tag(&token, SESSION_EXPIRES_TAG).await.unwrap();
+        assert!(!session_expires.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stores_only_hash() {
+        let repo = TokenRepo::default();
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[("scope", b"metrics:read")], None)
+            .await
+            .unwrap();
+
+        let token_repo = repo.token_repo.read().unwrap();
+        assert!(!token_repo.contains_key(token.as_ref()));
+        assert!(token_repo.contains_key(token.hash().as_ref()));
+        drop(token_repo);
+
+        let tags_repo = repo.tags_repo.read().unwrap();
+        assert!(tags_repo.iter().all(|t| t.token_hash != token.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_delete_hash() {
+        let repo = TokenRepo::default();
+        let token = AuthToken::generate(32).unwrap();
+        repo.put(&token, &Uuid::new_v4(), &[("scope", b"metrics:read")], None)
+            .await
+            .unwrap();
+
+        TokenRepoInterface::<AuthToken>::delete_hash(&repo, token.hash().as_ref())
+            .await
+            .unwrap();
+        assert!(matches!(repo.get(&token).await, Err(Error::TokenNotFound)));
+        assert!(repo.get_tag(&token, "scope").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_take() {
+        let repo = TokenRepo::default();
+        let token = AuthToken::generate(32).unwrap();
+        let user_id = Uuid::new_v4();
+        repo.put(&token, &user_id, &[("scope", b"metrics:read")], None)
+            .await
+            .unwrap();
+
+        let (taken, scope) = repo.take(&token, "scope", &Pipeline::new()).await.unwrap();
+        assert_eq!(taken, user_id);
+        assert_eq!(scope.as_deref(), Some(&b"metrics:read"[..]));
+        assert!(matches!(repo.get(&token).await, Err(Error::TokenNotFound)));
+        assert!(matches!(
+            repo.take(&token, "scope", &Pipeline::new()).await,
+            Err(Error::TokenNotFound)
+        ));
+    }
+}
@@ -1,17 +1,20 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
+use lib_cache::Pipeline;
 use uuid::Uuid;
 
-pub use cache::TokenRepo as Cache;
+pub use cache::{purge_legacy, TokenRepo as Cache};
 pub use interface::{Error, Interface, Result};
 pub use memory::TokenRepo as Memory;
+pub use sliding::SlidingExpiry;
 
 use crate::{TokenInterface, TokenRepoInterface};
 
 mod cache;
 mod interface;
 mod memory;
+mod sliding;
 
 /// The master token repository.
 ///
@@ -34,8 +37,30 @@ impl<Token: TokenInterface> TokenRepo<Token> {
     }
 
     #[must_use]
-    pub fn cache(controller: lib_cache::Controller, prefix: String) -> Self {
-        let repo = Cache::new(controller, prefix);
+    pub fn cache<C: lib_cache::Interface + 'static>(cache: C, prefix: String) -> Self {
+        let repo = Cache::new(cache, prefix);
+        Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+        }
+    }
+
+    /// Creates an in-memory token repository whose tokens expire after a period of inactivity.
+    #[must_use]
+    pub fn memory_with_sliding_expiry(sliding_expiry: SlidingExpiry) -> Self {
+        let repo = Memory::default().with_sliding_expiry(sliding_expiry);
+        Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+        }
+    }
+
+    /// Creates a cache token repository whose tokens expire after a period of inactivity.
+    #[must_use]
+    pub fn cache_with_sliding_expiry<C: lib_cache::Interface + 'static>(
+        cache: C,
+        prefix: String,
+        sliding_expiry: SlidingExpiry,
+    ) -> Self {
+        let repo = Cache::new(cache, prefix).with_sliding_expiry(sliding_expiry);
         Self {
             repo: std::sync::Arc::new(Box::new(repo)),
         }
@@ -54,14 +79,46 @@ impl<Token: TokenInterface> TokenRepoInterface<Token> for TokenRepo<Token> {
         self.repo.put(token, user_id, tags, ttl).await
     }
 
+    fn queue_put(
+        &self,
+        pipeline: Pipeline,
+        token: &Token,
+        user_id: &Uuid,
+        tags: &[(&str, &[u8])],
+        ttl: Option<&Duration>,
+    ) -> Result<Option<Pipeline>> {
+        self.repo.queue_put(pipeline, token, user_id, tags, ttl)
+    }
+
     async fn get(&self, token: &Token) -> Result<Uuid> {
         self.repo.get(token).await
     }
 
+    async fn expiry(&self, token: &Token) -> Result<Option<SystemTime>> {
+        self.repo.expiry(token).await
+    }
+
     async fn delete(&self, token: &Token) -> Result<()> {
         self.repo.delete(token).await
     }
 
+    async fn delete_hash(&self, hash: &[u8]) -> Result<()> {
+        self.repo.delete_hash(hash).await
+    }
+
+    fn cache_key(&self, hash: &[u8]) -> Option<String> {
+        self.repo.cache_key(hash)
+    }
+
+    async fn take(
+        &self,
+        token: &Token,
+        tag: &str,
+        then: &Pipeline,
+    ) -> Result<(Uuid, Option<Vec<u8>>)> {
+        self.repo.take(token, tag, then).await
+    }
+
     async fn put_tag(&self, token: &Token, tag: &str, value: &[u8]) -> Result<()> {
         self.repo.put_tag(token, tag, value).await
     }
@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The tag holding the time, in seconds after unix-epoch, past which a token may not be extended.
+pub(crate) const SESSION_EXPIRES_TAG: &str = "session-expires";
+
+/// Configures tokens to expire after a period of inactivity rather than a fixed time after issue.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SlidingExpiry {
+    /// How long a token may go unused before it expires.
+    pub idle_timeout: Duration,
+
+    /// The minimum time between extensions of a token, which bounds how often the repository is written to.
+    pub extend_interval: Duration,
+
+    /// The longest a token may live after it is issued, no matter how often it is used.
+    pub max_lifetime: Duration,
+}
+
+impl SlidingExpiry {
+    /// Get the TTL to issue a new token with.
+    ///
+    /// # Parameters
+    ///
+    /// - `ttl`: The TTL requested for the token.
+    ///
+    /// # Returns
+    ///
+    /// The requested TTL, shortened to the maximum lifetime if necessary.
+    pub(crate) fn issue_ttl(&self, ttl: &Duration) -> Duration {
+        (*ttl).min(self.max_lifetime)
+    }
+
+    /// Get the value of the session-expiry tag for a token issued at the given time.
+    pub(crate) fn session_expires(&self, now: SystemTime) -> Vec<u8> {
+        (now + self.max_lifetime)
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .to_string()
+            .into_bytes()
+    }
+
+    /// Get the new expiry of a token that has just been used, if it should be extended.
+    ///
+    /// # Parameters
+    ///
+    /// - `now`: The time the token was used.
+    /// - `expiry`: The current expiry of the token.
+    /// - `session_expires`: The value of the token's session-expiry tag, if it has one.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the token was extended less than `extend_interval` ago,
+    /// or has already been extended up to its maximum lifetime.
+    pub(crate) fn extend(
+        &self,
+        now: SystemTime,
+        expiry: SystemTime,
+        session_expires: Option<&[u8]>,
+    ) -> Option<SystemTime> {
+        let target = now + self.idle_timeout;
+        let target = match session_expires.and_then(parse_timestamp) {
+            Some(session_expires) => target.min(session_expires),
+            None => target,
+        };
+
+        // Each extension moves the expiry to `idle_timeout` after the last use,
+        // so the distance it would move now is the time since the last extension.
+        let elapsed = target.duration_since(expiry).ok()?;
+        if elapsed < self.extend_interval {
+            return None;
+        }
+
+        Some(target)
+    }
+}
+
+/// Parse a timestamp written by [`SlidingExpiry::session_expires`].
+fn parse_timestamp(value: &[u8]) -> Option<SystemTime> {
+    let seconds = std::str::from_utf8(value).ok()?.parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIDING: SlidingExpiry = SlidingExpiry {
+        idle_timeout: Duration::from_mins(10),
+        extend_interval: Duration::from_mins(1),
+        max_lifetime: Duration::from_hours(1),
+    };
+
+    #[test]
+    fn test_extend() {
+        let now = SystemTime::now();
+        let expiry = now + Duration::from_secs(500);
+
+        assert_eq!(
+            SLIDING.extend(now, expiry, None),
+            Some(now + Duration::from_mins(10))
+        );
+    }
+
+    #[test]
+    fn test_extend_throttled() {
+        let now = SystemTime::now();
+        let expiry = now + Duration::from_secs(590);
+
+        assert_eq!(SLIDING.extend(now, expiry, None), None);
+    }
+
+    #[test]
+    fn test_extend_capped() {
+        let now = UNIX_EPOCH + Duration::from_secs(10_000);
+        let expiry = now + Duration::from_secs(100);
+
+        assert_eq!(
+            SLIDING.extend(now, expiry, Some(b"10300")),
+            Some(now + Duration::from_mins(5))
+        );
+        assert_eq!(SLIDING.extend(now, expiry, Some(b"10100")), None);
+    }
+}
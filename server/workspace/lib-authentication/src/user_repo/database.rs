@@ -1,8 +1,8 @@
 use async_trait::async_trait;
 
-use lib_crypto::{hash_password, verify_password};
+use lib_crypto::{hash_password, password_needs_rehash, verify_password, Keyring};
 use lib_database::{
-    Connection, UserCredentialsController, UserCredentialsFilter, UserCredentialsWrite,
+    Connection, Deleted, UserCredentialsController, UserCredentialsFilter, UserCredentialsWrite,
 };
 
 use crate::{
@@ -10,14 +10,66 @@ use crate::{
     User, UserId,
 };
 
+/// The password hash of a locked user. It is not a hash any password could
+/// have, so no password matches it.
+const LOCKED: &str = "!";
+
+/// A user repository that stores users in the database.
+///
+/// Deleted users are only marked deleted, and keep their username. Usernames
+/// only have to be unique among the users that have not been deleted, so the
+/// username of a deleted user may be given to another user, after which the
+/// deleted user can no longer be restored.
 pub struct Repo {
     controller: UserCredentialsController,
+    pepper: Option<Keyring>,
 }
 
 impl Repo {
     pub fn new(connection: Connection) -> Self {
         let controller = UserCredentialsController::new(connection);
-        Self { controller }
+        Self {
+            controller,
+            pepper: None,
+        }
+    }
+
+    /// Pepper password hashes with the given keyring. Existing hashes are
+    /// upgraded to the pepper's primary key the next time their password is
+    /// checked successfully.
+    ///
+    /// # Arguments
+    ///
+    /// * `pepper` - The keyring holding every version of the pepper.
+    #[must_use]
+    pub fn with_pepper(mut self, pepper: Keyring) -> Self {
+        self.pepper = Some(pepper);
+        self
+    }
+
+    /// Hash a password with the current pepper, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the password could not be hashed.
+    fn hash(&self, password: &str) -> crate::user_repo::Result<String> {
+        hash_password(password.as_bytes(), self.pepper.as_ref()).map_err(|e| {
+            log::error!("Failed to hash password: {}", e);
+            super::Error::NotAvailable
+        })
+    }
+}
+
+/// Map an error from updating a user's record to the repository's error.
+///
+/// # Arguments
+///
+/// * `error` - The error the database reported.
+fn update_error(error: &lib_database::Error) -> super::Error {
+    match error {
+        lib_database::Error::NotFound => super::Error::NotFound,
+        lib_database::Error::Conflict(_) => super::Error::UsernameTaken,
+        _ => super::Error::NotAvailable,
     }
 }
 
@@ -39,42 +91,55 @@ impl super::Interface for Repo {
             .map(|u| u.password_hash.to_string())
             .unwrap_or_default();
 
-        let is_valid = verify_password(password.as_bytes(), &password_hash).unwrap_or(false);
+        let is_valid =
+            match verify_password(password.as_bytes(), &password_hash, self.pepper.as_ref()) {
+                Ok(is_valid) => is_valid,
+                Err(e) => {
+                    if user.is_some() && password_hash != LOCKED {
+                        log::error!("Failed to verify the password of {}: {}", username, e);
+                    }
+                    false
+                }
+            };
 
-        if is_valid {
-            Ok(user.map(|u| User {
-                id: u.id,
-                username: u.username,
-            }))
-        } else {
-            Ok(None)
+        let Some(user) = user.filter(|_| is_valid) else { return Ok(None) };
+
+        if password_needs_rehash(&password_hash, self.pepper.as_ref()) {
+            if let Err(e) = self.update_password(user.id, password).await {
+                log::warn!("Failed to upgrade password hash: {}", e);
+            }
         }
+
+        Ok(Some(User {
+            id: user.id,
+            username: user.username,
+        }))
     }
 
     async fn update_password(&self, id: UserId, password: &str) -> crate::user_repo::Result<()> {
-        let write = UserCredentialsWrite::default().password_hash(password.to_string());
+        let write = UserCredentialsWrite::default().password_hash(self.hash(password)?);
         self.controller
             .update(id, write)
             .await
-            .map_err(|_| super::Error::NotAvailable)?;
+            .map_err(|e| update_error(&e))?;
         Ok(())
     }
 
     async fn create(&self, user: &CreateUser) -> crate::user_repo::Result<UserId> {
-        let password_hash = hash_password(user.password.as_bytes()).map_err(|e| {
-            log::error!("Failed to hash password: {}", e);
-            super::Error::NotAvailable
-        })?;
+        if self.get_by_username(user.username).await?.is_some() {
+            return Err(super::Error::UsernameTaken);
+        }
+
+        let password_hash = self.hash(user.password)?;
         let write = UserCredentialsWrite::default()
             .username(user.username.to_string())
             .password_hash(password_hash)
             .enabled(true);
-        let id = self
-            .controller
-            .create(write)
-            .await
-            .map_err(|_| super::Error::NotAvailable)?;
-        Ok(id)
+        let user = self.controller.create(write).await.map_err(|e| match e {
+            lib_database::Error::Conflict(_) => super::Error::UsernameTaken,
+            _ => super::Error::NotAvailable,
+        })?;
+        Ok(user.id)
     }
 
     async fn get(&self, id: UserId) -> crate::user_repo::Result<Option<User>> {
@@ -127,7 +192,84 @@ impl super::Interface for Repo {
         self.controller
             .update(id, write)
             .await
-            .map_err(|_| super::Error::NotAvailable)?;
+            .map_err(|e| update_error(&e))?;
+        Ok(())
+    }
+
+    async fn create_locked(&self, username: &str) -> crate::user_repo::Result<UserId> {
+        if self.get_by_username(username).await?.is_some() {
+            return Err(super::Error::UsernameTaken);
+        }
+
+        let write = UserCredentialsWrite::default()
+            .username(username.to_string())
+            .password_hash(LOCKED.to_string())
+            .enabled(true);
+        let user = self.controller.create(write).await.map_err(|e| match e {
+            lib_database::Error::Conflict(_) => super::Error::UsernameTaken,
+            _ => super::Error::NotAvailable,
+        })?;
+        Ok(user.id)
+    }
+
+    async fn lock(&self, id: UserId) -> crate::user_repo::Result<()> {
+        let write = UserCredentialsWrite::default().password_hash(LOCKED.to_string());
+        self.controller
+            .update(id, write)
+            .await
+            .map_err(|e| update_error(&e))?;
         Ok(())
     }
+
+    async fn is_locked(&self, id: UserId) -> crate::user_repo::Result<bool> {
+        let user = self
+            .controller
+            .read(UserCredentialsFilter::default().id(id))
+            .await
+            .map_err(|_| super::Error::NotAvailable)?
+            .ok_or(super::Error::NotFound)?;
+        Ok(user.password_hash == LOCKED)
+    }
+
+    async fn unlock(&self, id: UserId, password: &str) -> crate::user_repo::Result<bool> {
+        // Only updating the row while it is still locked keeps two concurrent
+        // calls from both succeeding.
+        let filter = UserCredentialsFilter::default()
+            .id(id)
+            .password_hash(LOCKED.to_string());
+        let write = UserCredentialsWrite::default().password_hash(self.hash(password)?);
+        let updated = self
+            .controller
+            .update_many(filter, write)
+            .await
+            .map_err(|_| super::Error::NotAvailable)?;
+        Ok(updated > 0)
+    }
+
+    async fn restore(&self, username: &str) -> crate::user_repo::Result<Option<UserId>> {
+        if self.get_by_username(username).await?.is_some() {
+            return Err(super::Error::UsernameTaken);
+        }
+
+        // A username may have been held by several users deleted in turn, in
+        // which case the last of them is restored.
+        let filter = UserCredentialsFilter::default().username(username.to_string());
+        let user = self
+            .controller
+            .clone()
+            .with_deleted(Deleted::Only)
+            .read_many(filter)
+            .await
+            .map_err(|_| super::Error::NotAvailable)?
+            .into_iter()
+            .max_by_key(|user| user.deleted_at);
+        let Some(user) = user else { return Ok(None) };
+
+        let restored = self
+            .controller
+            .restore(user.id)
+            .await
+            .map_err(|e| update_error(&e))?;
+        Ok((restored > 0).then_some(user.id))
+    }
 }
@@ -9,6 +9,10 @@ pub enum Error {
     CreateFailed,
     #[error("The user could not be deleted.")]
     DeleteFailed,
+    #[error("The username is already taken.")]
+    UsernameTaken,
+    #[error("The user does not exist.")]
+    NotFound,
 }
 
 /// The result type for the user repository.
@@ -69,7 +73,8 @@ pub trait Interface: Send + Sync {
     ///
     /// # Errors
     ///
-    /// Returns an error if the password could not be updated.
+    /// Returns an error if the password could not be updated, or
+    /// [`Error::NotFound`] if there is no such user.
     async fn update_password(&self, id: UserId, password: &str) -> Result<()>;
 
     /// Creates a new user.
@@ -84,7 +89,8 @@ pub trait Interface: Send + Sync {
     ///
     /// # Errors
     ///
-    /// Returns an error if the user could not be created.
+    /// Returns an error if the user could not be created, or
+    /// [`Error::UsernameTaken`] if another user already has the username.
     async fn create(&self, user: &CreateUser) -> Result<UserId>;
 
     /// Retrieves a user by ID.
@@ -137,6 +143,84 @@ pub trait Interface: Send + Sync {
     ///
     /// # Errors
     ///
-    /// Returns an error if the user could not be updated.
+    /// Returns an error if the user could not be updated, [`Error::NotFound`]
+    /// if there is no such user, or [`Error::UsernameTaken`] if another user
+    /// already has the new username.
     async fn update(&self, id: UserId, user: &UpdateUser) -> Result<()>;
+
+    /// Creates a new user that is locked, so that no password logs it in
+    /// until it is given one with [`Interface::unlock`].
+    ///
+    /// # Parameters
+    ///
+    /// - `username`: The username of the user.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the new user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user could not be created, or
+    /// [`Error::UsernameTaken`] if another user already has the username.
+    async fn create_locked(&self, username: &str) -> Result<UserId>;
+
+    /// Locks a user, taking away its password until it is given a new one
+    /// with [`Interface::unlock`].
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The ID of the user to lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user could not be updated, or
+    /// [`Error::NotFound`] if there is no such user.
+    async fn lock(&self, id: UserId) -> Result<()>;
+
+    /// Checks whether a user is locked.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The ID of the user to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user repository is not available, or
+    /// [`Error::NotFound`] if there is no such user.
+    async fn is_locked(&self, id: UserId) -> Result<bool>;
+
+    /// Gives a locked user a password. Of several concurrent calls for the
+    /// same user, only one succeeds.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The ID of the user to unlock.
+    /// - `password`: The new password of the user.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if there is no such user, or it is not locked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the password could not be updated.
+    async fn unlock(&self, id: UserId, password: &str) -> Result<bool>;
+
+    /// Restores a deleted user.
+    ///
+    /// # Parameters
+    ///
+    /// - `username`: The username of the deleted user.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the user if a deleted user with the username was
+    /// restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UsernameTaken`] if another user has been given the
+    /// username since, or an error if the user could not be restored.
+    async fn restore(&self, username: &str) -> Result<Option<UserId>>;
 }
@@ -6,7 +6,9 @@ use std::sync::{Arc, RwLock};
 /// A user repository that stores all users in memory.
 struct Record {
     username: String,
-    password: String,
+
+    /// The password of the user, or `None` while it is locked.
+    password: Option<String>,
 }
 
 /// A user repository that stores all users in memory.
@@ -15,18 +17,10 @@ pub struct Repo {
 }
 
 impl Default for Repo {
-    /// Creates a new in-memory user repository.
+    /// Creates a new in-memory user repository, without any users.
     fn default() -> Self {
-        let mut users = HashMap::new();
-        users.insert(
-            uuid::Uuid::new_v4(),
-            Record {
-                username: "admin".to_string(),
-                password: "admin".to_string(),
-            },
-        );
         Self {
-            users: Arc::new(RwLock::new(users)),
+            users: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -36,7 +30,7 @@ impl Interface for Repo {
     async fn check_password(&self, username: &str, password: &str) -> Result<Option<User>> {
         let users = self.users.read().map_err(|_| Error::NotAvailable)?;
         for (id, user) in users.iter() {
-            if user.username == username && user.password == password {
+            if user.username == username && user.password.as_deref() == Some(password) {
                 return Ok(Some(User {
                     id: *id,
                     username: user.username.clone(),
@@ -48,20 +42,22 @@ impl Interface for Repo {
 
     async fn update_password(&self, id: UserId, password: &str) -> Result<()> {
         let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
-        if let Some(user) = users.get_mut(&id) {
-            user.password = password.to_string();
-        }
+        let user = users.get_mut(&id).ok_or(Error::NotFound)?;
+        user.password = Some(password.to_string());
         Ok(())
     }
 
     async fn create(&self, user: &CreateUser) -> Result<UserId> {
         let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
+        if users.values().any(|u| u.username == user.username) {
+            return Err(Error::UsernameTaken);
+        }
         let id = uuid::Uuid::new_v4();
         users.insert(
             id,
             Record {
                 username: user.username.to_string(),
-                password: user.password.to_string(),
+                password: Some(user.password.to_string()),
             },
         );
         Ok(id)
@@ -100,11 +96,64 @@ impl Interface for Repo {
 
     async fn update(&self, id: UserId, updates: &UpdateUser) -> Result<()> {
         let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
-        if let Some(user) = users.get_mut(&id) {
-            if let Some(ref username) = updates.username {
-                user.username = username.to_string();
+        if let Some(ref username) = updates.username {
+            if users
+                .iter()
+                .any(|(other, user)| *other != id && user.username == *username)
+            {
+                return Err(Error::UsernameTaken);
             }
         }
+
+        let user = users.get_mut(&id).ok_or(Error::NotFound)?;
+        if let Some(ref username) = updates.username {
+            user.username.clone_from(username);
+        }
         Ok(())
     }
+
+    async fn create_locked(&self, username: &str) -> Result<UserId> {
+        let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
+        if users.values().any(|u| u.username == username) {
+            return Err(Error::UsernameTaken);
+        }
+        let id = uuid::Uuid::new_v4();
+        users.insert(
+            id,
+            Record {
+                username: username.to_string(),
+                password: None,
+            },
+        );
+        Ok(id)
+    }
+
+    async fn lock(&self, id: UserId) -> Result<()> {
+        let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
+        let user = users.get_mut(&id).ok_or(Error::NotFound)?;
+        user.password = None;
+        Ok(())
+    }
+
+    async fn is_locked(&self, id: UserId) -> Result<bool> {
+        let users = self.users.read().map_err(|_| Error::NotAvailable)?;
+        let user = users.get(&id).ok_or(Error::NotFound)?;
+        Ok(user.password.is_none())
+    }
+
+    async fn unlock(&self, id: UserId, password: &str) -> Result<bool> {
+        let mut users = self.users.write().map_err(|_| Error::NotAvailable)?;
+        match users.get_mut(&id) {
+            Some(user) if user.password.is_none() => {
+                user.password = Some(password.to_string());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn restore(&self, _username: &str) -> Result<Option<UserId>> {
+        // Deleted users are removed for good.
+        Ok(None)
+    }
 }
@@ -1,9 +1,12 @@
 use async_trait::async_trait;
 
 pub use interface::{CreateUser, Error, Interface, Result, UpdateUser, User, UserId};
+use lib_crypto::Keyring;
 use lib_database::Connection;
 pub use memory::Repo as Memory;
 
+use crate::events::{publish, Event, Events};
+
 mod database;
 mod interface;
 mod memory;
@@ -12,6 +15,7 @@ mod memory;
 #[derive(Clone)]
 pub struct Repo {
     repo: std::sync::Arc<Box<dyn Interface>>,
+    events: Option<Events>,
 }
 
 impl Repo {
@@ -21,6 +25,7 @@ impl Repo {
         let repo = memory::Repo::default();
         Self {
             repo: std::sync::Arc::new(Box::new(repo)),
+            events: None,
         }
     }
 
@@ -30,8 +35,26 @@ impl Repo {
         let repo = database::Repo::new(connection);
         Self {
             repo: std::sync::Arc::new(Box::new(repo)),
+            events: None,
+        }
+    }
+
+    /// Creates a new database user repository whose password hashes are peppered.
+    #[must_use]
+    pub fn database_with_pepper(connection: Connection, pepper: Keyring) -> Self {
+        let repo = database::Repo::new(connection).with_pepper(pepper);
+        Self {
+            repo: std::sync::Arc::new(Box::new(repo)),
+            events: None,
         }
     }
+
+    /// Publishes an event whenever a user is created, updated or deleted.
+    #[must_use]
+    pub fn with_events(mut self, events: Events) -> Self {
+        self.events = Some(events);
+        self
+    }
 }
 
 #[async_trait]
@@ -41,11 +64,15 @@ impl Interface for Repo {
     }
 
     async fn update_password(&self, id: UserId, password: &str) -> Result<()> {
-        self.repo.update_password(id, password).await
+        self.repo.update_password(id, password).await?;
+        publish(self.events.as_ref(), Event::UserUpdated { user_id: id }).await;
+        Ok(())
     }
 
     async fn create(&self, user: &CreateUser) -> Result<UserId> {
-        self.repo.create(user).await
+        let user_id = self.repo.create(user).await?;
+        publish(self.events.as_ref(), Event::UserCreated { user_id }).await;
+        Ok(user_id)
     }
 
     async fn get(&self, id: UserId) -> Result<Option<User>> {
@@ -57,10 +84,79 @@ impl Interface for Repo {
     }
 
     async fn delete(&self, id: UserId) -> Result<()> {
-        self.repo.delete(id).await
+        self.repo.delete(id).await?;
+        publish(self.events.as_ref(), Event::UserDeleted { user_id: id }).await;
+        Ok(())
     }
 
     async fn update(&self, id: UserId, user: &UpdateUser) -> Result<()> {
-        self.repo.update(id, user).await
+        self.repo.update(id, user).await?;
+        publish(self.events.as_ref(), Event::UserUpdated { user_id: id }).await;
+        Ok(())
+    }
+
+    async fn create_locked(&self, username: &str) -> Result<UserId> {
+        let user_id = self.repo.create_locked(username).await?;
+        publish(self.events.as_ref(), Event::UserCreated { user_id }).await;
+        Ok(user_id)
+    }
+
+    async fn lock(&self, id: UserId) -> Result<()> {
+        self.repo.lock(id).await?;
+        publish(self.events.as_ref(), Event::UserUpdated { user_id: id }).await;
+        Ok(())
+    }
+
+    async fn is_locked(&self, id: UserId) -> Result<bool> {
+        self.repo.is_locked(id).await
+    }
+
+    async fn unlock(&self, id: UserId, password: &str) -> Result<bool> {
+        let is_unlocked = self.repo.unlock(id, password).await?;
+        if is_unlocked {
+            publish(self.events.as_ref(), Event::UserUpdated { user_id: id }).await;
+        }
+        Ok(is_unlocked)
+    }
+
+    async fn restore(&self, username: &str) -> Result<Option<UserId>> {
+        let user_id = self.repo.restore(username).await?;
+        if let Some(user_id) = user_id {
+            publish(self.events.as_ref(), Event::UserCreated { user_id }).await;
+        }
+        Ok(user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_cache::{Cache, Message};
+
+    use super::*;
+    use crate::events::events;
+
+    #[tokio::test]
+    async fn test_events() {
+        let events = events(Cache::memory());
+        let mut subscriber = events.subscribe().await.unwrap();
+        let repo = Repo::memory().with_events(events);
+
+        let user_id = repo
+            .create(&CreateUser {
+                username: "user",
+                password: "password",
+            })
+            .await
+            .unwrap();
+        repo.update_password(user_id, "new password").await.unwrap();
+        repo.delete(user_id).await.unwrap();
+
+        for event in [
+            Event::UserCreated { user_id },
+            Event::UserUpdated { user_id },
+            Event::UserDeleted { user_id },
+        ] {
+            assert_eq!(subscriber.recv().await, Some(Message::Payload(event)));
+        }
     }
 }
@@ -1,5 +1,5 @@
 use crate::Result;
-use base64ct::{Base64UrlUnpadded, Encoding};
+use base64ct::{Base64, Base64UrlUnpadded, Encoding};
 
 /// Decodes the provided bytes into the provided target buffer.
 ///
@@ -65,6 +65,33 @@ pub fn decode(bytes: impl AsRef<[u8]>) -> Result<Vec<u8>> {
     Ok(target)
 }
 
+/// Decodes the provided bytes, encoded with the standard padded alphabet, into a new `Vec<u8>`.
+///
+/// This is the encoding used by HTTP Basic authentication.
+///
+/// # Errors
+///
+/// Returns an error if the string could not be decoded.
+///
+/// # Examples
+///
+/// ```
+/// use lib_base64::decode_standard;
+///
+/// let bytes = b"c2VydmljZTpzZWNyZXQ=";
+/// let decoded = decode_standard(bytes).unwrap();
+///
+/// assert_eq!(decoded, b"service:secret");
+/// ```
+pub fn standard(bytes: impl AsRef<[u8]>) -> Result<Vec<u8>> {
+    let bytes = bytes.as_ref();
+    let len = (3 * bytes.len()) / 4;
+    let mut target = vec![0u8; len];
+    let len = Base64::decode(bytes, &mut target)?.len();
+    target.truncate(len);
+    Ok(target)
+}
+
 pub trait Decode {
     /// Decodes the provided bytes into the provided target buffer.
     ///
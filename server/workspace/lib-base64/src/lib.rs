@@ -7,7 +7,7 @@
     clippy::pedantic
 )]
 
-pub use decode::{decode, into as decode_into, Decode};
+pub use decode::{decode, into as decode_into, standard as decode_standard, Decode};
 pub use encode::{encode, into as encode_into, Encode};
 pub use error::{Error, Result};
 
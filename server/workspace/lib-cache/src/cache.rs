@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::circuit::{Breaker, Circuit, State};
+use crate::url::NodesUrl;
+use crate::{
+    cluster, Cluster, Controller, Decision, Error, Interface, Memory, Options, Pipeline, Quota,
+    Result, Subscription,
+};
+
+/// The URL that opens an in-process cache rather than connecting to Redis.
+const MEMORY_URL: &str = "memory://";
+
+/// The master cache, backed by Redis, a Redis Cluster or an in-process store.
+///
+/// Operations on Redis time out, and fail fast with [`Error::Unavailable`]
+/// while it appears to be down; see [`Circuit`].
+#[derive(Clone)]
+pub struct Cache {
+    cache: Arc<Box<dyn Interface>>,
+    circuit: Option<Arc<Circuit>>,
+}
+
+impl std::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache").finish()
+    }
+}
+
+/// Whether a cache can be reached.
+#[derive(Debug)]
+pub struct Health {
+    /// The state of the cache's circuit breaker, which is always closed for an
+    /// in-process cache.
+    pub state: State,
+
+    /// Why the cache could not be reached, if it could not.
+    pub error: Option<Error>,
+}
+
+impl Health {
+    /// Check whether the cache could be reached.
+    #[must_use]
+    pub fn is_up(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl Cache {
+    /// Open a cache with the default options, selecting the backend by the
+    /// URL's scheme as [`Cache::open_with`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to connect to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid, or the Redis client could not
+    /// be created.
+    pub fn open(url: &str) -> Result<Self> {
+        Self::open_with(url, &Options::default())
+    }
+
+    /// Open a cache, selecting the backend by the URL's scheme:
+    ///
+    /// - `memory://` for an in-process store.
+    /// - `redis+cluster://host1:6379,host2:6379` for a Redis Cluster.
+    /// - `redis+sentinel://host1:26379,host2:26379/mymaster` for the master of
+    ///   a deployment managed by Sentinel.
+    /// - Any other URL, such as `redis://cache`, for a single Redis node.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to connect to.
+    /// * `options` - How to connect to Redis, and behave while it is down.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid, or the Redis client could not
+    /// be created.
+    pub fn open_with(url: &str, options: &Options) -> Result<Self> {
+        if url == MEMORY_URL {
+            return Ok(Self::memory());
+        }
+
+        if NodesUrl::is(url, cluster::KIND) {
+            return Ok(Self::cluster(Cluster::open_with(url, options)?));
+        }
+
+        Ok(Self::redis(Controller::open_with(url, options)?))
+    }
+
+    /// Create a cache backed by a Redis Cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The cluster client.
+    #[must_use]
+    pub fn cluster(cluster: Cluster) -> Self {
+        let options = cluster.options().clone();
+        Self::guarded(Box::new(cluster), &options)
+    }
+
+    /// Create a cache backed by Redis.
+    ///
+    /// # Arguments
+    ///
+    /// * `controller` - The Redis controller.
+    #[must_use]
+    pub fn redis(controller: Controller) -> Self {
+        let options = controller.options().clone();
+        Self::guarded(Box::new(controller), &options)
+    }
+
+    /// Create a cache guarded by a circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to guard.
+    /// * `options` - The command timeout, failure threshold and retry delays.
+    fn guarded(cache: Box<dyn Interface>, options: &Options) -> Self {
+        let circuit = Arc::new(Circuit::new(options));
+        Self {
+            cache: Arc::new(Box::new(Breaker::new(cache, circuit.clone(), options))),
+            circuit: Some(circuit),
+        }
+    }
+
+    /// Create a cache backed by an in-process store, which is lost when the
+    /// process exits and is not shared with other processes.
+    #[must_use]
+    pub fn memory() -> Self {
+        Self {
+            cache: Arc::new(Box::<Memory>::default()),
+            circuit: None,
+        }
+    }
+
+    /// Check whether the cache can be reached. While the circuit breaker is
+    /// open this fails fast, and once it is time to try again the check is
+    /// what finds out whether Redis is back.
+    pub async fn health(&self) -> Health {
+        let error = self.ping().await.err();
+        let state = self
+            .circuit
+            .as_ref()
+            .map_or(State::Closed, |circuit| circuit.state());
+
+        Health { state, error }
+    }
+}
+
+#[async_trait]
+impl Interface for Cache {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        self.cache.hset(key, field, value).await
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        self.cache.hget(key, field).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        self.cache.set(key, value, expiry).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.cache.get(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        self.cache.exists(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.cache.delete(key).await
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        self.cache.expire(key, expiry).await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        self.cache.ttl(key).await
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        self.cache.scan(pattern).await
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        self.cache.transaction(pipeline).await
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        self.cache.take(key, then).await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.cache.set_if_absent(key, value, expiry).await
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.cache.expire_if_equal(key, value, expiry).await
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        self.cache.delete_if_equal(key, value).await
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        self.cache.advance(key, floor).await
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        self.cache.throttle(key, quota).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.cache.ping().await
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        self.cache.publish(channel, message).await
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        self.cache.subscribe(channel).await
+    }
+}
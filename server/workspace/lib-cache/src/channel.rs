@@ -0,0 +1,169 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Cache, Interface, Message, Result, Subscription};
+
+/// A channel of typed events, published to every subscriber of the cache as
+/// JSON.
+///
+/// Delivery is at most once: subscribers only receive events published while
+/// they are subscribed, and may miss some, which they are told about with
+/// [`Message::Missed`].
+///
+/// # Examples
+///
+/// ```
+/// # async fn example() -> lib_cache::Result<()> {
+/// use lib_cache::{Cache, Channel, Message};
+///
+/// let channel = Channel::<String>::new(Cache::memory(), "greetings");
+/// let mut subscriber = channel.subscribe().await?;
+///
+/// channel.publish(&"hello".to_string()).await?;
+/// assert_eq!(
+///     subscriber.recv().await,
+///     Some(Message::Payload("hello".to_string()))
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct Channel<T> {
+    cache: Cache,
+    name: String,
+    event: PhantomData<fn(T) -> T>,
+}
+
+impl<T> Clone for Channel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+            name: self.name.clone(),
+            event: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Channel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Channel")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Channel<T> {
+    /// Create a new channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to publish through.
+    /// * `name` - The name of the channel.
+    #[must_use]
+    pub fn new(cache: Cache, name: &str) -> Self {
+        Self {
+            cache,
+            name: name.to_string(),
+            event: PhantomData,
+        }
+    }
+
+    /// Publish an event to every subscriber.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event to publish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event could not be serialized or published.
+    pub async fn publish(&self, event: &T) -> Result<()> {
+        let message = serde_json::to_vec(event)?;
+        self.cache.publish(&self.name, &message).await
+    }
+
+    /// Subscribe to the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscription could not be made.
+    pub async fn subscribe(&self) -> Result<Subscriber<T>> {
+        Ok(Subscriber {
+            name: self.name.clone(),
+            subscription: self.cache.subscribe(&self.name).await?,
+            event: PhantomData,
+        })
+    }
+}
+
+/// A subscription to a [`Channel`], which ends when it is dropped.
+pub struct Subscriber<T> {
+    name: String,
+    subscription: Subscription,
+    event: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for Subscriber<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscriber")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: DeserializeOwned> Subscriber<T> {
+    /// Wait for the next event.
+    ///
+    /// An event that cannot be deserialized, such as one published by a newer
+    /// version, is received as [`Message::Missed`].
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` if the channel was closed.
+    pub async fn recv(&mut self) -> Option<Message<T>> {
+        match self.subscription.recv().await? {
+            Message::Payload(payload) => match serde_json::from_slice(&payload) {
+                Ok(event) => Some(Message::Payload(event)),
+                Err(e) => {
+                    log::warn!("Ignoring an invalid event on {}: {}", self.name, e);
+                    Some(Message::Missed)
+                }
+            },
+            Message::Missed => Some(Message::Missed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_subscribe() {
+        let channel = Channel::<Vec<u32>>::new(Cache::memory(), "numbers");
+
+        // Events published before subscribing are not received.
+        channel.publish(&vec![1]).await.unwrap();
+
+        let mut first = channel.subscribe().await.unwrap();
+        let mut second = channel.clone().subscribe().await.unwrap();
+        channel.publish(&vec![2, 3]).await.unwrap();
+
+        assert_eq!(first.recv().await, Some(Message::Payload(vec![2, 3])));
+        assert_eq!(second.recv().await, Some(Message::Payload(vec![2, 3])));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_event() {
+        let cache = Cache::memory();
+        let channel = Channel::<u32>::new(cache.clone(), "numbers");
+        let mut subscriber = channel.subscribe().await.unwrap();
+
+        cache.publish("numbers", b"not a number").await.unwrap();
+        channel.publish(&1).await.unwrap();
+
+        assert_eq!(subscriber.recv().await, Some(Message::Missed));
+        assert_eq!(subscriber.recv().await, Some(Message::Payload(1)));
+    }
+}
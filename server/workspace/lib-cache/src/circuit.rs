@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::{Decision, Error, Interface, Options, Pipeline, Quota, Result, Subscription};
+
+/// The state of a [`Circuit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Redis is reachable, and operations run as usual.
+    Closed,
+
+    /// Redis could not be reached, and operations fail fast until it is time
+    /// to try again.
+    Open,
+
+    /// One operation is being let through to find out whether Redis is back,
+    /// while others still fail fast.
+    HalfOpen,
+}
+
+impl State {
+    /// Get the state as reported to operators.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half-open",
+        }
+    }
+}
+
+/// What a circuit knows about whether Redis can be reached.
+struct Status {
+    state: State,
+    failures: u32,
+    delay: Duration,
+    retry_at: Instant,
+}
+
+/// A circuit breaker, which stops operations from waiting on Redis while it is
+/// down.
+///
+/// Once [`Options::failure_threshold`] operations in a row have failed to
+/// reach Redis, the circuit opens and operations fail fast with
+/// [`Error::Unavailable`]. After the retry delay one operation is let through,
+/// and the circuit closes again if it succeeds, or stays open for twice as
+/// long if it fails.
+pub struct Circuit {
+    options: Options,
+    status: Mutex<Status>,
+}
+
+impl std::fmt::Debug for Circuit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Circuit")
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Circuit {
+    /// Create a new, closed circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The failure threshold and retry delays.
+    #[must_use]
+    pub fn new(options: &Options) -> Self {
+        Self {
+            options: options.clone(),
+            status: Mutex::new(Status {
+                state: State::Closed,
+                failures: 0,
+                delay: options.min_retry_delay(),
+                retry_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Get the state of the circuit.
+    #[must_use]
+    pub fn state(&self) -> State {
+        self.lock().state
+    }
+
+    /// Lock the status of the circuit.
+    ///
+    /// Every update leaves the status consistent, so a poisoned lock is
+    /// recovered.
+    fn lock(&self) -> MutexGuard<'_, Status> {
+        self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Ask to run an operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unavailable`] if the circuit is open, or half-open
+    /// with another operation already let through.
+    fn permit(&self) -> Result<Permit<'_>> {
+        let mut status = self.lock();
+
+        match status.state {
+            State::Closed => {}
+            State::Open if Instant::now() >= status.retry_at => status.state = State::HalfOpen,
+            State::Open | State::HalfOpen => return Err(Error::Unavailable),
+        }
+
+        Ok(Permit {
+            circuit: self,
+            probe: status.state == State::HalfOpen,
+            recorded: false,
+        })
+    }
+
+    /// Record that an operation reached Redis, closing the circuit.
+    fn succeed(&self) {
+        let mut status = self.lock();
+        if status.state != State::Closed {
+            log::info!("Redis can be reached again");
+        }
+        status.state = State::Closed;
+        status.failures = 0;
+        status.delay = self.options.min_retry_delay();
+    }
+
+    /// Record that an operation failed to reach Redis, opening the circuit if
+    /// too many have in a row.
+    ///
+    /// # Arguments
+    ///
+    /// * `probe` - Whether the operation was let through a half-open circuit.
+    fn fail(&self, probe: bool) {
+        let threshold = self.options.failure_threshold();
+        if threshold == 0 {
+            return;
+        }
+
+        let mut status = self.lock();
+        status.failures = status.failures.saturating_add(1);
+
+        if probe {
+            status.delay = self.options.next_retry_delay(status.delay);
+        } else if status.state != State::Closed || status.failures < threshold {
+            return;
+        }
+
+        if status.state == State::Closed {
+            log::warn!(
+                "Redis could not be reached, failing fast for {:?}",
+                status.delay
+            );
+        }
+        status.state = State::Open;
+        status.retry_at = Instant::now() + status.delay;
+    }
+}
+
+/// Permission to run an operation. A probe that is dropped before its outcome
+/// is recorded, such as when the request is cancelled, counts as a failure so
+/// the circuit does not stay half-open.
+struct Permit<'a> {
+    circuit: &'a Circuit,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    /// Record the outcome of the operation. Errors that do not suggest Redis
+    /// is unreachable, such as a key holding the wrong type, count as success.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of the operation.
+    fn record<T>(mut self, result: &Result<T>) {
+        self.recorded = true;
+        match result {
+            Err(e) if e.is_unavailable() => self.circuit.fail(self.probe),
+            _ => self.circuit.succeed(),
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.circuit.fail(true);
+        }
+    }
+}
+
+/// A cache whose operations time out, and fail fast while the circuit is open.
+pub(crate) struct Breaker {
+    cache: Box<dyn Interface>,
+    circuit: Arc<Circuit>,
+    timeout: Duration,
+}
+
+impl Breaker {
+    /// Create a new breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to guard.
+    /// * `circuit` - The circuit, shared with whoever reports its state.
+    /// * `options` - The command timeout.
+    pub(crate) fn new(cache: Box<dyn Interface>, circuit: Arc<Circuit>, options: &Options) -> Self {
+        Self {
+            cache,
+            circuit,
+            timeout: options.command_timeout(),
+        }
+    }
+
+    /// Run an operation if the circuit allows it, and record its outcome.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unavailable`] if the circuit is open,
+    /// [`Error::Timeout`] if the operation took too long, or the error of the
+    /// operation.
+    async fn call<T>(&self, operation: impl Future<Output = Result<T>> + Send) -> Result<T> {
+        let permit = self.circuit.permit()?;
+        let result = match tokio::time::timeout(self.timeout, operation).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout),
+        };
+
+        permit.record(&result);
+        result
+    }
+}
+
+#[async_trait]
+impl Interface for Breaker {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        self.call(self.cache.hset(key, field, value)).await
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        self.call(self.cache.hget(key, field)).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        self.call(self.cache.set(key, value, expiry)).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.call(self.cache.get(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        self.call(self.cache.exists(key)).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.call(self.cache.delete(key)).await
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        self.call(self.cache.expire(key, expiry)).await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        self.call(self.cache.ttl(key)).await
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        self.call(self.cache.scan(pattern)).await
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        self.call(self.cache.transaction(pipeline)).await
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        self.call(self.cache.take(key, then)).await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.call(self.cache.set_if_absent(key, value, expiry))
+            .await
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.call(self.cache.expire_if_equal(key, value, expiry))
+            .await
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        self.call(self.cache.delete_if_equal(key, value)).await
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        self.call(self.cache.advance(key, floor)).await
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        self.call(self.cache.throttle(key, quota)).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.call(self.cache.ping()).await
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        self.call(self.cache.publish(channel, message)).await
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        self.call(self.cache.subscribe(channel)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::Memory;
+
+    /// A cache that cannot be reached while it is down.
+    #[derive(Default)]
+    struct Flaky {
+        memory: Memory,
+        down: Arc<AtomicBool>,
+    }
+
+    impl Flaky {
+        fn check(&self) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(Error::IO(std::io::ErrorKind::ConnectionRefused.into()));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Interface for Flaky {
+        async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+            self.check()?;
+            self.memory.hset(key, field, value).await
+        }
+
+        async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+            self.check()?;
+            self.memory.hget(key, field).await
+        }
+
+        async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+            self.check()?;
+            self.memory.set(key, value, expiry).await
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.check()?;
+            self.memory.get(key).await
+        }
+
+        async fn exists(&self, key: &str) -> Result<bool> {
+            self.memory.exists(key).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.memory.delete(key).await
+        }
+
+        async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+            self.memory.expire(key, expiry).await
+        }
+
+        async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+            self.memory.ttl(key).await
+        }
+
+        async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+            self.memory.scan(pattern).await
+        }
+
+        async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+            self.memory.transaction(pipeline).await
+        }
+
+        async fn take(
+            &self,
+            key: &str,
+            then: &Pipeline,
+        ) -> Result<Option<HashMap<String, Vec<u8>>>> {
+            self.memory.take(key, then).await
+        }
+
+        async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+            self.memory.set_if_absent(key, value, expiry).await
+        }
+
+        async fn expire_if_equal(
+            &self,
+            key: &str,
+            value: &[u8],
+            expiry: &Duration,
+        ) -> Result<bool> {
+            self.memory.expire_if_equal(key, value, expiry).await
+        }
+
+        async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+            self.memory.delete_if_equal(key, value).await
+        }
+
+        async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+            self.memory.advance(key, floor).await
+        }
+
+        async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+            self.memory.throttle(key, quota).await
+        }
+
+        async fn ping(&self) -> Result<()> {
+            self.check()
+        }
+
+        async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+            self.memory.publish(channel, message).await
+        }
+
+        async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+            self.memory.subscribe(channel).await
+        }
+    }
+
+    fn breaker(options: &Options) -> (Breaker, Arc<Circuit>, Arc<AtomicBool>) {
+        let flaky = Flaky::default();
+        let down = flaky.down.clone();
+        let circuit = Arc::new(Circuit::new(options));
+        let breaker = Breaker::new(Box::new(flaky), circuit.clone(), options);
+        (breaker, circuit, down)
+    }
+
+    #[tokio::test]
+    async fn test_open_and_close() {
+        let options = Options::default()
+            .with_failure_threshold(2)
+            .with_retry_delay(Duration::from_millis(50), Duration::from_millis(200));
+        let (breaker, circuit, down) = breaker(&options);
+
+        down.store(true, Ordering::SeqCst);
+        assert!(matches!(breaker.ping().await, Err(Error::IO(_))));
+        assert_eq!(circuit.state(), State::Closed);
+        assert!(matches!(breaker.ping().await, Err(Error::IO(_))));
+        assert_eq!(circuit.state(), State::Open);
+
+        // Operations fail fast without reaching the cache.
+        down.store(false, Ordering::SeqCst);
+        assert!(matches!(breaker.ping().await, Err(Error::Unavailable)));
+
+        // After the retry delay, one operation is let through and closes the
+        // circuit.
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        breaker.ping().await.unwrap();
+        assert_eq!(circuit.state(), State::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe() {
+        let options = Options::default()
+            .with_failure_threshold(1)
+            .with_retry_delay(Duration::from_millis(50), Duration::from_secs(5));
+        let (breaker, circuit, down) = breaker(&options);
+
+        down.store(true, Ordering::SeqCst);
+        assert!(breaker.ping().await.is_err());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(matches!(breaker.ping().await, Err(Error::IO(_))));
+
+        // The retry delay doubled, so the circuit is still open.
+        down.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(matches!(breaker.ping().await, Err(Error::Unavailable)));
+        assert_eq!(circuit.state(), State::Open);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors() {
+        let options = Options::default().with_failure_threshold(1);
+        let (breaker, circuit, _) = breaker(&options);
+
+        breaker.hset("hash", "field", b"value").await.unwrap();
+        assert!(matches!(
+            breaker.get("hash").await,
+            Err(Error::WrongType(_))
+        ));
+        assert_eq!(circuit.state(), State::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let options = Options::default().with_failure_threshold(0);
+        let (breaker, circuit, down) = breaker(&options);
+
+        down.store(true, Ordering::SeqCst);
+        for _ in 0..10 {
+            assert!(matches!(breaker.ping().await, Err(Error::IO(_))));
+        }
+        assert_eq!(circuit.state(), State::Closed);
+    }
+}
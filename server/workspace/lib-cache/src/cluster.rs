@@ -0,0 +1,530 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use redis::cluster::{ClusterClient, ClusterConnection};
+use redis::{Commands, RedisResult, Script, Value};
+
+use crate::duration::{ceil_millis, millis};
+use crate::lock::{ADVANCE_SCRIPT, DELETE_IF_EQUAL_SCRIPT, EXPIRE_IF_EQUAL_SCRIPT};
+use crate::pipeline::{PIPELINE_SCRIPT, TAKE_SCRIPT};
+use crate::rate_limit::THROTTLE_SCRIPT;
+use crate::subscription::subscribe;
+use crate::url::{node_address, NodesUrl};
+use crate::{Decision, Error, Interface, Options, Pipeline, Quota, Result, Subscription};
+
+/// The kind of connection string that selects a cluster.
+pub(crate) const KIND: &str = "cluster";
+
+/// The number of hash slots keys are spread across.
+const SLOTS: u16 = 16384;
+
+/// The port of seed nodes that do not name one.
+const DEFAULT_PORT: u16 = 6379;
+
+/// Get the hash slot of a key, which decides the cluster node that holds it.
+///
+/// If the key contains a hash tag, a non-empty section between the first `{`
+/// and the next `}`, only the tag is hashed, so keys sharing a tag are always
+/// held by the same node.
+///
+/// # Arguments
+///
+/// * `key` - The key to get the slot of.
+#[must_use]
+pub fn slot(key: &str) -> u16 {
+    let key = key.as_bytes();
+    let tag = key.iter().position(|&b| b == b'{').and_then(|open| {
+        let tag = &key[open + 1..];
+        tag.iter()
+            .position(|&b| b == b'}')
+            .filter(|&close| close > 0)
+            .map(|close| &tag[..close])
+    });
+
+    crc16(tag.unwrap_or(key)) % SLOTS
+}
+
+/// Name a key so it shares a hash slot with every other key of the same tag,
+/// allowing them to be written in one transaction on a cluster.
+///
+/// # Arguments
+///
+/// * `tag` - The tag shared by related keys, which must not contain `}`.
+/// * `key` - The rest of the key.
+#[must_use]
+pub fn hash_tagged(tag: &str, key: &str) -> String {
+    format!("{{{tag}}}:{key}")
+}
+
+/// The CRC16-CCITT (XMODEM) checksum Redis Cluster uses to assign hash slots.
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
+
+/// A cache backed by a Redis Cluster, selected by a connection string such as
+/// `redis+cluster://:password@host1:6379,host2:6379,host3:6379`.
+///
+/// The seed nodes are only used to discover the cluster, so they need not list
+/// every node. Transactions run as Lua scripts, and so may only write keys in
+/// a single hash slot; see [`hash_tagged`].
+#[derive(Clone)]
+pub struct Cluster {
+    url: Arc<NodesUrl>,
+    client: Arc<ClusterClient>,
+    idle: Arc<Mutex<Vec<ClusterConnection>>>,
+    options: Arc<Options>,
+}
+
+impl std::fmt::Debug for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cluster")
+            .field("nodes", &self.url.nodes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cluster {
+    /// Create a new cluster client with the default options. No connection is
+    /// made until the cache is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The `redis+cluster://` URL listing the seed nodes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or uses TLS, which is not
+    /// supported in cluster mode.
+    pub fn open(url: &str) -> Result<Self> {
+        Self::open_with(url, &Options::default())
+    }
+
+    /// Create a new cluster client. No connection is made until the cache is
+    /// used.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The `redis+cluster://` URL listing the seed nodes.
+    /// * `options` - The most connections kept open, the timeout of each
+    ///   command, and the delays between attempts to subscribe again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or uses TLS, which is not
+    /// supported in cluster mode.
+    pub fn open_with(url: &str, options: &Options) -> Result<Self> {
+        let url = NodesUrl::parse(url, KIND, DEFAULT_PORT)?;
+        if url.tls() {
+            return Err(Error::InvalidUrl(
+                "TLS is not supported in cluster mode".to_string(),
+            ));
+        }
+
+        let nodes = url
+            .nodes
+            .iter()
+            .map(|node| url.node_url(node, None))
+            .collect::<Vec<_>>();
+        let client = ClusterClient::new(nodes)?;
+
+        Ok(Self {
+            url: Arc::new(url),
+            client: Arc::new(client),
+            idle: Arc::new(Mutex::new(Vec::new())),
+            options: Arc::new(options.clone()),
+        })
+    }
+
+    /// Get the options the cluster client was created with.
+    pub(crate) fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Run a blocking operation on a connection to the cluster, off the async
+    /// runtime's worker threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The operation to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no connection could be made, or the operation failed.
+    async fn run<T, F>(&self, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut ClusterConnection) -> RedisResult<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+        let idle = self.idle.clone();
+        let (max_idle, timeout) = (self.options.pool_size(), self.options.command_timeout());
+
+        tokio::task::spawn_blocking(move || {
+            let connection = idle.lock().unwrap_or_else(PoisonError::into_inner).pop();
+            let mut connection = if let Some(connection) = connection {
+                connection
+            } else {
+                // Blocking threads cannot be cancelled, so they must not wait
+                // on an unresponsive node forever.
+                let connection = client.get_connection()?;
+                connection.set_read_timeout(Some(timeout))?;
+                connection.set_write_timeout(Some(timeout))?;
+                connection
+            };
+
+            let result = operation(&mut connection);
+
+            // A connection that failed may be broken, so only reuse healthy ones.
+            if result.is_ok() {
+                let mut idle = idle.lock().unwrap_or_else(PoisonError::into_inner);
+                if idle.len() < max_idle {
+                    idle.push(connection);
+                }
+            }
+
+            Ok(result?)
+        })
+        .await
+        .map_err(|e| Error::IO(std::io::Error::other(e)))?
+    }
+}
+
+/// Get the addresses of the master nodes from a `CLUSTER SLOTS` reply.
+///
+/// # Arguments
+///
+/// * `slots` - The reply, listing each range of slots with its master first.
+fn masters(slots: &[Value]) -> BTreeSet<String> {
+    slots
+        .iter()
+        .filter_map(|range| match range {
+            Value::Bulk(range) => match range.get(2) {
+                Some(Value::Bulk(master)) => match (master.first(), master.get(1)) {
+                    (Some(Value::Data(host)), Some(Value::Int(port))) => Some((
+                        String::from_utf8_lossy(host).into_owned(),
+                        u16::try_from(*port).ok()?,
+                    )),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|(host, port)| node_address(&host, port))
+        .collect()
+}
+
+#[async_trait]
+impl Interface for Cluster {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        let (key, field, value) = (key.to_string(), field.to_string(), value.to_vec());
+        self.run(move |c| c.hset::<_, _, _, ()>(key, field, value))
+            .await
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        let (key, field) = (key.to_string(), field.to_string());
+        self.run(move |c| c.hget(key, field)).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        if matches!(expiry, Some(expiry) if expiry.is_zero()) {
+            return Err(Error::InvalidExpiry);
+        }
+
+        let (key, value) = (key.to_string(), value.to_vec());
+        let millis = expiry.map(ceil_millis);
+        self.run(move |c| {
+            let mut set = redis::cmd("SET");
+            set.arg(key).arg(value);
+            if let Some(millis) = millis {
+                set.arg("PX").arg(millis);
+            }
+            set.query::<()>(c)
+        })
+        .await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let key = key.to_string();
+        self.run(move |c| c.get(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let key = key.to_string();
+        self.run(move |c| c.exists(key)).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let key = key.to_string();
+        self.run(move |c| c.del::<_, ()>(key)).await
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        let (key, millis) = (key.to_string(), ceil_millis(expiry));
+        self.run(move |c| redis::cmd("PEXPIRE").arg(key).arg(millis).query::<()>(c))
+            .await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        let key = key.to_string();
+        let millis: i64 = self.run(move |c| c.pttl(key)).await?;
+        Ok(u64::try_from(millis).ok().map(Duration::from_millis))
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        // Each master only scans its own keys, so every master is scanned.
+        let slots: Vec<Value> = self
+            .run(|c| redis::cmd("CLUSTER").arg("SLOTS").query(c))
+            .await?;
+
+        let mut keys = Vec::new();
+        for master in masters(&slots) {
+            let (url, pattern) = (self.url.node_url(&master, None), pattern.to_string());
+            let master_keys = tokio::task::spawn_blocking(move || -> RedisResult<Vec<String>> {
+                let mut connection = redis::Client::open(url)?.get_connection()?;
+                let keys = connection.scan_match::<_, String>(pattern)?.collect();
+                Ok(keys)
+            })
+            .await
+            .map_err(|e| Error::IO(std::io::Error::other(e)))??;
+
+            keys.extend(master_keys);
+        }
+
+        Ok(keys)
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        pipeline.validate()?;
+
+        let (keys, args) = pipeline.script_args(Vec::new());
+        let Some(first) = keys.first() else { return Ok(()) };
+        if keys.iter().any(|key| slot(key) != slot(first)) {
+            return Err(Error::CrossSlot(keys));
+        }
+
+        self.run(move |c| {
+            let script = Script::new(PIPELINE_SCRIPT);
+            let mut invocation = script.prepare_invoke();
+            for key in &keys {
+                invocation.key(key);
+            }
+            for arg in &args {
+                invocation.arg(arg);
+            }
+            invocation.invoke::<()>(c)
+        })
+        .await
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        then.validate()?;
+
+        // A script can only touch keys in one slot, so commands on keys
+        // elsewhere are run once the hash has been taken.
+        let mut pipelines = then.group_by(slot);
+        let same_slot = pipelines.remove(&slot(key)).unwrap_or_default();
+        let (keys, args) = same_slot.script_args(vec![key.to_string()]);
+        let fields: HashMap<String, Vec<u8>> = self
+            .run(move |c| {
+                let script = Script::new(TAKE_SCRIPT);
+                let mut invocation = script.prepare_invoke();
+                for key in &keys {
+                    invocation.key(key);
+                }
+                for arg in &args {
+                    invocation.arg(arg);
+                }
+                invocation.invoke(c)
+            })
+            .await?;
+
+        // A hash is deleted once its last field is, so it is never empty.
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        for pipeline in pipelines.values() {
+            self.transaction(pipeline).await?;
+        }
+
+        Ok(Some(fields))
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        let expiry = millis(expiry)?;
+        let (key, value) = (key.to_string(), value.to_vec());
+        let set: Option<String> = self
+            .run(move |c| {
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(expiry)
+                    .query(c)
+            })
+            .await?;
+        Ok(set.is_some())
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        let expiry = millis(expiry)?;
+        let (key, value) = (key.to_string(), value.to_vec());
+        self.run(move |c| {
+            Script::new(EXPIRE_IF_EQUAL_SCRIPT)
+                .key(key)
+                .arg(value)
+                .arg(expiry)
+                .invoke(c)
+        })
+        .await
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        let (key, value) = (key.to_string(), value.to_vec());
+        self.run(move |c| {
+            Script::new(DELETE_IF_EQUAL_SCRIPT)
+                .key(key)
+                .arg(value)
+                .invoke(c)
+        })
+        .await
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        let key = key.to_string();
+        self.run(move |c| Script::new(ADVANCE_SCRIPT).key(key).arg(floor).invoke(c))
+            .await
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        let key = key.to_string();
+        let (window, interval) = (quota.window_millis(), quota.interval_millis());
+        let (allowed, remaining, reset, retry_after) = self
+            .run(move |c| {
+                Script::new(THROTTLE_SCRIPT)
+                    .key(key)
+                    .arg(window)
+                    .arg(interval)
+                    .invoke::<(bool, u64, u64, u64)>(c)
+            })
+            .await?;
+        Ok(Decision::from_millis(
+            quota,
+            allowed,
+            remaining,
+            reset,
+            retry_after,
+        ))
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.run(|c| redis::cmd("PING").query::<String>(c).map(|_| ()))
+            .await
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        let (channel, message) = (channel.to_string(), message.to_vec());
+        self.run(move |c| c.publish::<_, _, ()>(channel, message))
+            .await
+    }
+
+    /// Messages are broadcast to every node, so subscriptions are made on one
+    /// seed node, moving on to the next whenever the connection is lost.
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        let url = self.url.clone();
+        let attempts = AtomicUsize::new(0);
+        subscribe(channel, &self.options, move || {
+            let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+            let node = &url.nodes[attempt % url.nodes.len()];
+            let node_url = url.node_url(node, None);
+            async move { Ok(node_url) }.boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{exercise, Server};
+
+    use super::*;
+
+    #[test]
+    fn test_slot() {
+        // Examples from the Redis Cluster specification.
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(slot("foo"), 12182);
+        assert_eq!(slot("{user1000}.following"), slot("{user1000}.followers"));
+        assert_ne!(slot("foo{}{bar}"), slot("bar"));
+        assert_eq!(slot("foo{{bar}}zap"), slot("{bar"));
+        assert_eq!(slot("foo{bar}{zap}"), slot("bar"));
+
+        assert_eq!(slot(&hash_tagged("session", "a")), slot("session"));
+        assert_eq!(
+            slot(&hash_tagged("session", "a")),
+            slot(&hash_tagged("session", "b"))
+        );
+    }
+
+    #[test]
+    fn test_masters() {
+        let slots = [
+            Value::Bulk(vec![
+                Value::Int(0),
+                Value::Int(5460),
+                Value::Bulk(vec![Value::Data(b"10.0.0.1".to_vec()), Value::Int(6379)]),
+                Value::Bulk(vec![Value::Data(b"10.0.0.4".to_vec()), Value::Int(6379)]),
+            ]),
+            Value::Bulk(vec![
+                Value::Int(5461),
+                Value::Int(10922),
+                Value::Bulk(vec![Value::Data(b"::1".to_vec()), Value::Int(6380)]),
+            ]),
+            Value::Bulk(vec![
+                Value::Int(10923),
+                Value::Int(16383),
+                Value::Bulk(vec![Value::Data(b"10.0.0.1".to_vec()), Value::Int(6379)]),
+            ]),
+        ];
+
+        assert_eq!(
+            masters(&slots).into_iter().collect::<Vec<_>>(),
+            ["10.0.0.1:6379", "[::1]:6380"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cross_slot() {
+        let cluster = Cluster::open("redis+cluster://127.0.0.1:1").unwrap();
+
+        let result = cluster
+            .transaction(&Pipeline::new().hset("a", "f", b"1").hset("b", "f", b"1"))
+            .await;
+        assert!(matches!(result, Err(Error::CrossSlot(_))));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires redis-server and redis-cli"]
+    async fn test_cluster() {
+        let nodes = Server::cluster();
+        let cluster =
+            Cluster::open(&format!("redis+cluster://127.0.0.1:{}", nodes[0].port)).unwrap();
+
+        exercise(&cluster).await;
+    }
+}
@@ -0,0 +1,89 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Result;
+
+/// A format for encoding values stored in the cache.
+pub trait Codec: Send + Sync {
+    /// Encode a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to encode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be encoded.
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>>;
+
+    /// Decode a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The encoded value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid encoding of the value.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// Encodes values as JSON, which is readable when inspecting the cache.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Encodes values as `MessagePack`, which is more compact than JSON.
+///
+/// Struct fields are encoded with their names, so fields can be added with
+/// `#[serde(default)]` as they can with JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+impl Codec for MessagePack {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn round_trip(codec: &impl Codec) {
+        let value = HashMap::from([("a".to_string(), vec![1u32, 2]), ("b".to_string(), vec![])]);
+        let encoded = codec.encode(&value).unwrap();
+        assert_eq!(
+            codec.decode::<HashMap<String, Vec<u32>>>(&encoded).unwrap(),
+            value
+        );
+        assert!(codec.decode::<String>(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_json() {
+        round_trip(&Json);
+        assert_eq!(Json.encode(&[1, 2]).unwrap(), b"[1,2]");
+    }
+
+    #[test]
+    fn test_message_pack() {
+        round_trip(&MessagePack);
+        assert_eq!(MessagePack.encode(&[1, 2]).unwrap(), [0x92, 0x01, 0x02]);
+    }
+}
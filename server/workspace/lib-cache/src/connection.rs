@@ -1,9 +1,10 @@
 use std::time::Duration;
 
 use deadpool_redis::Connection as RedisConnection;
-use redis::{AsyncCommands, FromRedisValue, ToRedisArgs};
+use redis::{AsyncCommands, FromRedisValue, Script, ToRedisArgs};
 
-use crate::Result;
+use crate::duration::ceil_millis;
+use crate::{Pipeline, Result};
 
 /// A wrapper around a Redis connection.
 pub struct Connection {
@@ -68,8 +69,7 @@ impl Connection {
         set.arg(&key).arg(&value);
 
         if let Some(expiry) = expiry {
-            let seconds = expiry.as_secs();
-            set.arg("EX").arg(seconds);
+            set.arg("PX").arg(ceil_millis(expiry));
         }
 
         set.query_async(&mut self.connection).await?;
@@ -132,6 +132,31 @@ impl Connection {
         Ok(value)
     }
 
+    /// Get every key in the cache matching a glob-style pattern.
+    ///
+    /// The keys are listed with `SCAN`, so the cache is not blocked, but keys
+    /// added or removed meanwhile may or may not be returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to match, such as `prefix:*`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys could not be listed.
+    pub async fn scan<'a, P: ToRedisArgs + Send + Sync + 'a>(
+        &mut self,
+        pattern: P,
+    ) -> Result<Vec<String>> {
+        let mut iter = self.connection.scan_match(pattern).await?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+
     /// Delete a key from the cache.
     ///
     /// # Arguments
@@ -161,13 +186,178 @@ impl Connection {
         key: K,
         expiry: &Duration,
     ) -> Result<()> {
-        let seconds = expiry.as_secs();
-        redis::cmd("EXPIRE")
+        redis::cmd("PEXPIRE")
             .arg(&key)
-            .arg(seconds)
+            .arg(ceil_millis(expiry))
             .query_async(&mut self.connection)
             .await?;
 
         Ok(())
     }
+
+    /// Get the remaining time-to-live of a key in the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to check.
+    ///
+    /// # Returns
+    ///
+    /// The remaining time-to-live, or `None` if the key does not exist or has no expiry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the time-to-live could not be retrieved.
+    pub async fn ttl<'a, K: ToRedisArgs + Send + Sync + 'a>(
+        &mut self,
+        key: K,
+    ) -> Result<Option<Duration>> {
+        let millis: i64 = self.connection.pttl(key).await?;
+        Ok(u64::try_from(millis).ok().map(Duration::from_millis))
+    }
+
+    /// Send the commands of a pipeline in a single round trip. The commands
+    /// are not atomic, so other clients may see some of them applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - The commands to send.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the commands failed.
+    pub async fn pipeline(&mut self, pipeline: &Pipeline) -> Result<()> {
+        pipeline.validate()?;
+        to_redis(pipeline)
+            .query_async::<_, ()>(&mut self.connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Run the commands of a pipeline as a single `MULTI`/`EXEC` transaction,
+    /// so other clients see either all or none of them applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - The commands to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction failed.
+    pub async fn transaction(&mut self, pipeline: &Pipeline) -> Result<()> {
+        pipeline.validate()?;
+        to_redis(pipeline)
+            .atomic()
+            .query_async::<_, ()>(&mut self.connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Run a Lua script atomically, loading it into the cache if it has not
+    /// been already.
+    ///
+    /// # Arguments
+    ///
+    /// * `script` - The script to run.
+    /// * `keys` - The keys the script accesses, available as `KEYS`.
+    /// * `args` - The arguments to the script, available as `ARGV`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script failed, or its result could not be converted.
+    pub async fn invoke<RV: FromRedisValue>(
+        &mut self,
+        script: &Script,
+        keys: &[&str],
+        args: &[&[u8]],
+    ) -> Result<RV> {
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(*key);
+        }
+        for arg in args {
+            invocation.arg(*arg);
+        }
+
+        let value = invocation.invoke_async(&mut self.connection).await?;
+        Ok(value)
+    }
+
+    /// Set a value only if the key does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set a value under.
+    /// * `value` - The value to set.
+    /// * `expiry` - The expiry time for the value, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// Whether the value was set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be set.
+    pub async fn set_if_absent(&mut self, key: &str, value: &[u8], expiry: u64) -> Result<bool> {
+        let set = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("PX")
+            .arg(expiry)
+            .query_async::<_, Option<String>>(&mut self.connection)
+            .await?;
+        Ok(set.is_some())
+    }
+
+    /// Publish a message to a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to publish to.
+    /// * `message` - The message to publish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message could not be published.
+    pub async fn publish(&mut self, channel: &str, message: &[u8]) -> Result<()> {
+        self.connection
+            .publish::<_, _, ()>(channel, message)
+            .await?;
+        Ok(())
+    }
+
+    /// Check that the node responds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node did not respond.
+    pub async fn ping(&mut self) -> Result<()> {
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut self.connection)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Convert a pipeline into the Redis commands that run it.
+///
+/// # Arguments
+///
+/// * `pipeline` - The pipeline to convert.
+fn to_redis(pipeline: &Pipeline) -> redis::Pipeline {
+    let mut pipe = redis::pipe();
+
+    for command in pipeline.commands() {
+        let (name, args) = command.redis_args();
+        pipe.cmd(name).arg(command.key());
+        for arg in args {
+            pipe.arg(arg);
+        }
+        pipe.ignore();
+    }
+
+    pipe
 }
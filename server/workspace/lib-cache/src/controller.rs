@@ -1,12 +1,32 @@
-use deadpool_redis::{Config, Runtime};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use deadpool_redis::{Config, Pool, PoolConfig, Runtime, Timeouts};
+use futures_util::FutureExt;
 
 use crate::connection::Connection;
-use crate::Result;
+use crate::duration::millis;
+use crate::lock::{ADVANCE_SCRIPT, DELETE_IF_EQUAL_SCRIPT, EXPIRE_IF_EQUAL_SCRIPT};
+use crate::pipeline::TAKE_SCRIPT;
+use crate::rate_limit::THROTTLE_SCRIPT;
+use crate::sentinel::Sentinel;
+use crate::subscription::subscribe;
+use crate::{Decision, Error, Interface, Options, Pipeline, Quota, Result, Subscription};
 
 /// A controller for a Redis cache.
+///
+/// The controller connects either to a single node, given a `redis://` or
+/// `rediss://` URL, or to the master of a deployment managed by Sentinel,
+/// given a `redis+sentinel://` URL. In the latter case the master is looked up
+/// when first connecting, and again whenever it appears to have failed over.
 #[derive(Clone)]
 pub struct Controller {
-    pool: deadpool_redis::Pool,
+    url: Arc<str>,
+    pool: Arc<RwLock<Option<Pool>>>,
+    sentinel: Option<Arc<Sentinel>>,
+    options: Arc<Options>,
 }
 
 impl std::fmt::Debug for Controller {
@@ -15,8 +35,31 @@ impl std::fmt::Debug for Controller {
     }
 }
 
+/// Create a connection pool for a single node.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the node.
+/// * `options` - The size and timeouts of the pool.
+///
+/// # Errors
+///
+/// Returns an error if the pool could not be created.
+fn create_pool(url: &str, options: &Options) -> Result<Pool> {
+    let mut cfg = Config::from_url(url);
+    cfg.pool = Some(PoolConfig {
+        max_size: options.pool_size(),
+        timeouts: Timeouts {
+            wait: Some(options.wait_timeout()),
+            create: Some(options.connect_timeout()),
+            recycle: Some(options.connect_timeout()),
+        },
+    });
+    Ok(cfg.create_pool(Some(Runtime::Tokio1))?)
+}
+
 impl Controller {
-    /// Create a new controller.
+    /// Create a new controller with the default options.
     ///
     /// # Arguments
     ///
@@ -26,9 +69,41 @@ impl Controller {
     ///
     /// Returns an error if the controller could not be created.
     pub fn open(url: &str) -> Result<Self> {
-        let cfg = Config::from_url(url);
-        let pool = cfg.create_pool(Some(Runtime::Tokio1))?;
-        Ok(Self { pool })
+        Self::open_with(url, &Options::default())
+    }
+
+    /// Create a new controller.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to connect to.
+    /// * `options` - The size and timeouts of the connection pool, and the
+    ///   delays between attempts to subscribe again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller could not be created.
+    pub fn open_with(url: &str, options: &Options) -> Result<Self> {
+        if Sentinel::is(url) {
+            return Ok(Self {
+                url: url.into(),
+                pool: Arc::new(RwLock::new(None)),
+                sentinel: Some(Arc::new(Sentinel::parse(url)?)),
+                options: Arc::new(options.clone()),
+            });
+        }
+
+        Ok(Self {
+            url: url.into(),
+            pool: Arc::new(RwLock::new(Some(create_pool(url, options)?))),
+            sentinel: None,
+            options: Arc::new(options.clone()),
+        })
+    }
+
+    /// Get the options the controller was created with.
+    pub(crate) fn options(&self) -> &Options {
+        &self.options
     }
 
     /// Get a connection to the cache.
@@ -37,7 +112,282 @@ impl Controller {
     ///
     /// Returns an error if the connection could not be established.
     pub async fn connect(&self) -> Result<Connection> {
-        let client = self.pool.get().await?;
-        Ok(Connection::new(client))
+        let pool = self
+            .pool
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let pool = match pool {
+            Some(pool) => pool,
+            None => self.discover().await?,
+        };
+
+        match pool.get().await {
+            Ok(client) => Ok(Connection::new(client)),
+            // The master may have failed over, so ask the sentinels again.
+            Err(_) if self.sentinel.is_some() => {
+                let client = self.discover().await?.get().await?;
+                Ok(Connection::new(client))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Look up the master with the sentinels, and connect to it from now on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the master could not be found.
+    async fn discover(&self) -> Result<Pool> {
+        let Some(sentinel) = &self.sentinel else {
+            return Err(Error::Sentinel("not using Sentinel".to_string()));
+        };
+
+        let pool = create_pool(&sentinel.discover().await?, &self.options)?;
+        *self.pool.write().unwrap_or_else(PoisonError::into_inner) = Some(pool.clone());
+
+        Ok(pool)
+    }
+
+    /// Get the URL of the node to connect to, asking the sentinels for the
+    /// current master when using Sentinel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the master could not be found.
+    async fn node_url(&self) -> Result<String> {
+        match &self.sentinel {
+            Some(sentinel) => sentinel.discover().await,
+            None => Ok(self.url.to_string()),
+        }
+    }
+
+    /// Forget the master after an error suggesting it has failed over, so the
+    /// next connection asks the sentinels again.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of an operation.
+    fn observe<T>(&self, result: Result<T>) -> Result<T> {
+        if let (Some(_), Err(Error::Redis(e))) = (&self.sentinel, &result) {
+            if e.kind() == redis::ErrorKind::ReadOnly
+                || e.is_io_error()
+                || e.is_connection_dropped()
+                || e.is_connection_refusal()
+            {
+                *self.pool.write().unwrap_or_else(PoisonError::into_inner) = None;
+            }
+        }
+
+        result
+    }
+}
+
+/// Each operation takes a connection from the pool. Expiries are sent to
+/// Redis in milliseconds, rounded up so that a key never expires early.
+#[async_trait]
+impl Interface for Controller {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        let result = self.connect().await?.hset(key, field, value).await;
+        self.observe(result)
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        let result = self.connect().await?.hget(key, field).await;
+        self.observe(result)
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        if matches!(expiry, Some(expiry) if expiry.is_zero()) {
+            return Err(Error::InvalidExpiry);
+        }
+
+        let result = self.connect().await?.set(key, value, expiry).await;
+        self.observe(result)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let result = self.connect().await?.get(key).await;
+        self.observe(result)
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let result = self.connect().await?.exists(key).await;
+        self.observe(result)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let result = self.connect().await?.delete(key).await;
+        self.observe(result)
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        let result = self.connect().await?.expire(key, expiry).await;
+        self.observe(result)
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        let result = self.connect().await?.ttl(key).await;
+        self.observe(result)
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        let result = self.connect().await?.scan(pattern).await;
+        self.observe(result)
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        if pipeline.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.connect().await?.transaction(pipeline).await;
+        self.observe(result)
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        then.validate()?;
+
+        let (keys, args) = then.script_args(vec![key.to_string()]);
+        let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+        let args = args.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let result = self
+            .connect()
+            .await?
+            .invoke(&redis::Script::new(TAKE_SCRIPT), &keys, &args)
+            .await;
+        let fields: HashMap<String, Vec<u8>> = self.observe(result)?;
+
+        // A hash is deleted once its last field is, so it is never empty.
+        Ok(Some(fields).filter(|fields| !fields.is_empty()))
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        let expiry = millis(expiry)?;
+        let result = self
+            .connect()
+            .await?
+            .set_if_absent(key, value, expiry)
+            .await;
+        self.observe(result)
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        let expiry = millis(expiry)?.to_string();
+        let result = self
+            .connect()
+            .await?
+            .invoke(
+                &redis::Script::new(EXPIRE_IF_EQUAL_SCRIPT),
+                &[key],
+                &[value, expiry.as_bytes()],
+            )
+            .await;
+        self.observe(result)
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        let result = self
+            .connect()
+            .await?
+            .invoke(
+                &redis::Script::new(DELETE_IF_EQUAL_SCRIPT),
+                &[key],
+                &[value],
+            )
+            .await;
+        self.observe(result)
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        let floor = floor.to_string();
+        let result = self
+            .connect()
+            .await?
+            .invoke(
+                &redis::Script::new(ADVANCE_SCRIPT),
+                &[key],
+                &[floor.as_bytes()],
+            )
+            .await;
+        self.observe(result)
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        let window = quota.window_millis().to_string();
+        let interval = quota.interval_millis().to_string();
+        let result = self
+            .connect()
+            .await?
+            .invoke::<(bool, u64, u64, u64)>(
+                &redis::Script::new(THROTTLE_SCRIPT),
+                &[key],
+                &[window.as_bytes(), interval.as_bytes()],
+            )
+            .await;
+        let (allowed, remaining, reset, retry_after) = self.observe(result)?;
+        Ok(Decision::from_millis(
+            quota,
+            allowed,
+            remaining,
+            reset,
+            retry_after,
+        ))
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let result = self.connect().await?.ping().await;
+        self.observe(result)
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        let result = self.connect().await?.publish(channel, message).await;
+        self.observe(result)
+    }
+
+    /// Subscriptions use a dedicated connection rather than one from the pool.
+    /// With Sentinel, they follow the master across failovers.
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        let controller = self.clone();
+        subscribe(channel, &self.options, move || {
+            let controller = controller.clone();
+            async move { controller.node_url().await }.boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{exercise, Server};
+
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires redis-server"]
+    async fn test_standalone() {
+        let server = Server::redis(&[]);
+        let controller = Controller::open(&format!("redis://127.0.0.1:{}", server.port)).unwrap();
+
+        exercise(&controller).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires redis-server"]
+    async fn test_sentinel() {
+        let master = Server::redis(&[]);
+        let sentinel = Server::sentinel(master.port, "mymaster");
+        let controller = Controller::open(&format!(
+            "redis+sentinel://127.0.0.1:{}/mymaster",
+            sentinel.port
+        ))
+        .unwrap();
+
+        exercise(&controller).await;
+
+        // Losing the master makes the controller ask the sentinels again.
+        drop(master);
+        assert!(controller.get("key").await.is_err());
+        assert!(controller.pool.read().unwrap().is_none());
     }
 }
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+/// Convert an expiry to whole milliseconds, rounding up so a key never
+/// expires earlier than asked. A zero expiry stays zero.
+///
+/// # Arguments
+///
+/// * `expiry` - The expiry.
+pub(crate) fn ceil_millis(expiry: &Duration) -> u64 {
+    let millis = expiry.as_nanos().saturating_add(999_999) / 1_000_000;
+    u64::try_from(millis).unwrap_or(u64::MAX)
+}
+
+/// Convert an expiry to whole milliseconds.
+///
+/// # Arguments
+///
+/// * `expiry` - The expiry.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidExpiry`] if the expiry is less than a millisecond.
+pub(crate) fn millis(expiry: &Duration) -> Result<u64> {
+    match u64::try_from(expiry.as_millis()) {
+        Ok(0) => Err(Error::InvalidExpiry),
+        Ok(millis) => Ok(millis),
+        Err(_) => Ok(u64::MAX),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceil_millis() {
+        assert_eq!(ceil_millis(&Duration::ZERO), 0);
+        assert_eq!(ceil_millis(&Duration::from_micros(1)), 1);
+        assert_eq!(ceil_millis(&Duration::from_millis(1500)), 1500);
+        assert_eq!(ceil_millis(&Duration::from_micros(1_500_001)), 1501);
+        assert_eq!(ceil_millis(&Duration::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,161 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+
+use crate::{Guard, Lock};
+
+/// Elects one server replica at a time as the leader, to run work that must
+/// not run on more than one, such as scheduled jobs.
+///
+/// # Examples
+///
+/// ```
+/// # async fn example() {
+/// use std::time::Duration;
+///
+/// use lib_cache::{Cache, Election, Lock};
+///
+/// let lock = Lock::new(Cache::memory(), "token-cleanup", Duration::from_secs(30));
+/// let election = Election::new(lock);
+///
+/// election
+///     .lead(|fencing_token| async move {
+///         println!("cleaning up tokens with fencing token {fencing_token}");
+///     })
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Election {
+    lock: Lock,
+    retry_interval: Duration,
+}
+
+impl Election {
+    /// Create an election, which tries to acquire the lock every half lease.
+    ///
+    /// # Arguments
+    ///
+    /// * `lock` - The lock held by the leader.
+    #[must_use]
+    pub fn new(lock: Lock) -> Self {
+        Self {
+            retry_interval: lock.lease() / 2,
+            lock,
+        }
+    }
+
+    /// Set how often to try to acquire the lock while another replica leads.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_interval` - The time between attempts.
+    #[must_use]
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Wait until this replica is elected. Errors reaching the cache are
+    /// logged and retried, so an outage only delays the election.
+    ///
+    /// # Returns
+    ///
+    /// The guard holding the leader's lock, which this replica leads until
+    /// the guard is released or the lock is lost.
+    pub async fn campaign(&self) -> Guard {
+        loop {
+            match self.lock.try_acquire().await {
+                Ok(Some(guard)) => return guard,
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to campaign for leadership: {}", e),
+            }
+
+            tokio::time::sleep(self.retry_interval).await;
+        }
+    }
+
+    /// Wait until this replica is elected, then run a job for as long as it
+    /// leads, and step down.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The job to run, given the fencing token of the leadership.
+    ///
+    /// # Returns
+    ///
+    /// The output of the job, or `None` if leadership was lost first, in
+    /// which case the job is cancelled.
+    pub async fn lead<F, Fut>(&self, job: F) -> Option<Fut::Output>
+    where
+        F: FnOnce(u64) -> Fut,
+        Fut: Future,
+    {
+        let mut guard = self.campaign().await;
+
+        let output = {
+            let job = job(guard.fencing_token());
+            let lost = guard.lost();
+            pin_mut!(job, lost);
+
+            match select(job, lost).await {
+                Either::Left((output, _)) => Some(output),
+                Either::Right(_) => None,
+            }
+        };
+
+        if let Err(e) = guard.release().await {
+            log::warn!("Failed to step down from leadership: {}", e);
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, Interface};
+
+    use super::*;
+
+    // Long enough that a loaded machine cannot stall a test past a lease.
+    const LEASE: Duration = Duration::from_secs(1);
+
+    #[tokio::test]
+    async fn test_campaign() {
+        let election = Election::new(Lock::new(Cache::memory(), "job", LEASE));
+
+        let leader = election.campaign().await;
+        assert!(tokio::time::timeout(LEASE * 2, election.campaign())
+            .await
+            .is_err());
+
+        let first = leader.fencing_token();
+        leader.release().await.unwrap();
+        assert_eq!(
+            election
+                .lead(|fencing_token| async move { fencing_token })
+                .await,
+            Some(first + 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lead_lost() {
+        let cache = Cache::memory();
+        let lock = Lock::new(cache.clone(), "job", LEASE);
+        let election = Election::new(lock.clone());
+
+        let output = election
+            .lead(|_| async {
+                cache.delete("lock:job").await.unwrap();
+                let _thief = lock.try_acquire().await.unwrap().unwrap();
+                futures_util::future::pending::<()>().await;
+            })
+            .await;
+
+        assert_eq!(output, None);
+    }
+}
@@ -0,0 +1,190 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Codec, Error, Result};
+
+/// The first byte of an envelope, identifying the envelope format.
+const FORMAT: u8 = 1;
+
+/// The length of the header before the encoded value: the format, then the
+/// version as a big-endian 32-bit integer.
+const HEADER_LEN: usize = 5;
+
+/// A type stored in the cache under a version number, so its shape can change
+/// while values in an older shape are still cached.
+///
+/// Fields added with `#[serde(default)]` do not need a new version. Any other
+/// change should increase [`Versioned::VERSION`], and implement
+/// [`Versioned::upgrade`] to read values written in older versions.
+///
+/// # Examples
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Session {
+///     user_id: String,
+/// }
+///
+/// impl lib_cache::Versioned for Session {
+///     const VERSION: u32 = 1;
+/// }
+/// ```
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The version that values are written in.
+    const VERSION: u32;
+
+    /// Read a value written in another version.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The version the value was written in.
+    /// * `payload` - The encoded value.
+    /// * `codec` - The codec the value was encoded with.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedVersion`] by default, since no other
+    /// versions can be read unless this is implemented.
+    fn upgrade(version: u32, payload: &[u8], codec: &impl Codec) -> Result<Self> {
+        let _ = (payload, codec);
+        Err(Error::UnsupportedVersion(version))
+    }
+}
+
+/// Encode a value in an envelope recording its version.
+///
+/// # Arguments
+///
+/// * `codec` - The codec to encode the value with.
+/// * `value` - The value to encode.
+///
+/// # Errors
+///
+/// Returns an error if the value could not be encoded.
+pub(crate) fn seal<T: Versioned>(codec: &impl Codec, value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.push(FORMAT);
+    bytes.extend(T::VERSION.to_be_bytes());
+    bytes.extend(codec.encode(value)?);
+    Ok(bytes)
+}
+
+/// Decode a value from an envelope, upgrading it if it was written in another
+/// version.
+///
+/// # Arguments
+///
+/// * `codec` - The codec the value was encoded with.
+/// * `bytes` - The envelope.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidEnvelope`] if the bytes are not an envelope, or an
+/// error if the value could not be decoded or upgraded.
+pub(crate) fn open<T: Versioned>(codec: &impl Codec, bytes: &[u8]) -> Result<T> {
+    let (header, payload) = match bytes {
+        [FORMAT, ..] if bytes.len() >= HEADER_LEN => bytes.split_at(HEADER_LEN),
+        _ => return Err(Error::InvalidEnvelope),
+    };
+
+    let mut version = [0; 4];
+    version.copy_from_slice(&header[1..]);
+    let version = u32::from_be_bytes(version);
+
+    if version == T::VERSION {
+        codec.decode(payload)
+    } else {
+        T::upgrade(version, payload, codec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{Json, MessagePack};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V1 {
+        name: String,
+    }
+
+    impl Versioned for V1 {
+        const VERSION: u32 = 1;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V2 {
+        names: Vec<String>,
+    }
+
+    impl Versioned for V2 {
+        const VERSION: u32 = 2;
+
+        fn upgrade(version: u32, payload: &[u8], codec: &impl Codec) -> Result<Self> {
+            match version {
+                1 => {
+                    let v1: V1 = codec.decode(payload)?;
+                    Ok(Self {
+                        names: vec![v1.name],
+                    })
+                }
+                _ => Err(Error::UnsupportedVersion(version)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let value = V1 {
+            name: "name".to_string(),
+        };
+
+        let sealed = seal(&Json, &value).unwrap();
+        assert_eq!(&sealed[..HEADER_LEN], [1, 0, 0, 0, 1]);
+        assert_eq!(open::<V1>(&Json, &sealed).unwrap(), value);
+
+        let sealed = seal(&MessagePack, &value).unwrap();
+        assert_eq!(open::<V1>(&MessagePack, &sealed).unwrap(), value);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let sealed = seal(
+            &Json,
+            &V1 {
+                name: "name".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            open::<V2>(&Json, &sealed).unwrap(),
+            V2 {
+                names: vec!["name".to_string()]
+            }
+        );
+
+        // Values written by a newer version cannot be read by an older one.
+        let sealed = seal(&Json, &V2 { names: vec![] }).unwrap();
+        assert!(matches!(
+            open::<V1>(&Json, &sealed),
+            Err(Error::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            open::<V1>(&Json, b"{\"name\":\"name\"}"),
+            Err(Error::InvalidEnvelope)
+        ));
+        assert!(matches!(
+            open::<V1>(&Json, &[1, 0, 0]),
+            Err(Error::InvalidEnvelope)
+        ));
+    }
+}
@@ -17,4 +17,69 @@ pub enum Error {
 
     #[error("Invalid Expiry")]
     InvalidExpiry,
+
+    #[error("Invalid rate limit quota")]
+    InvalidQuota,
+
+    #[error("Key holds the wrong kind of value: {0}")]
+    WrongType(String),
+
+    #[error("Invalid connection string: {0}")]
+    InvalidUrl(String),
+
+    #[error("Sentinel error: {0}")]
+    Sentinel(String),
+
+    #[error("Keys are in different cluster hash slots: {0:?}")]
+    CrossSlot(Vec<String>),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("MessagePack encode error: {0}")]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+
+    #[error("MessagePack decode error: {0}")]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+
+    #[error("Value is not in a versioned envelope")]
+    InvalidEnvelope,
+
+    #[error("Unsupported value version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Redis is unavailable")]
+    Unavailable,
+
+    #[error("Redis did not respond in time")]
+    Timeout,
+}
+
+impl Error {
+    /// Check whether the error suggests Redis cannot be reached, rather than
+    /// that the operation itself was at fault.
+    #[must_use]
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            Self::Redis(e) => {
+                e.is_io_error()
+                    || e.is_timeout()
+                    || e.is_connection_dropped()
+                    || e.is_connection_refusal()
+                    || matches!(
+                        e.kind(),
+                        redis::ErrorKind::BusyLoadingError
+                            | redis::ErrorKind::ClusterDown
+                            | redis::ErrorKind::MasterDown
+                            | redis::ErrorKind::TryAgain
+                    )
+            }
+            Self::IO(_)
+            | Self::PoolError(_)
+            | Self::Sentinel(_)
+            | Self::Unavailable
+            | Self::Timeout => true,
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{Decision, Pipeline, Quota, Result, Subscription};
+
+/// The interface of a cache backend.
+///
+/// Values are stored as bytes, either directly under a key or in a hash of
+/// fields under a key. Keys may expire, after which they behave as if they had
+/// been deleted.
+#[async_trait]
+pub trait Interface: Send + Sync {
+    /// Set a field of a hash, keeping the key's expiry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `field` - The field to set.
+    /// * `value` - The value to set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be set, including when the key
+    /// holds a value that is not a hash.
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()>;
+
+    /// Get a field of a hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `field` - The field to get.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the key or field does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be retrieved, including when
+    /// the key holds a value that is not a hash.
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Set a value, replacing the key's previous value and expiry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set a value under.
+    /// * `value` - The value to set.
+    /// * `expiry` - The expiry time for the value, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidExpiry`] if the expiry is zero, or an
+    /// error if the value could not be set.
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()>;
+
+    /// Get a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to get a value from.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the key does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be retrieved, including when
+    /// the key holds a hash.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Check if a key exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key could not be checked.
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Delete a key. Deleting a key that does not exist is not an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to delete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key could not be deleted.
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Set the expiry of a key. Has no effect if the key does not exist, and
+    /// deletes the key if the expiry is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set an expiry on.
+    /// * `expiry` - The expiry time for the key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expiry could not be set.
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()>;
+
+    /// Get the remaining time-to-live of a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to check.
+    ///
+    /// # Returns
+    ///
+    /// The remaining time-to-live, or `None` if the key does not exist or has no expiry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the time-to-live could not be retrieved.
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>>;
+
+    /// Get every key matching a glob-style pattern, where `*` matches any
+    /// sequence of characters and `?` matches any single character.
+    ///
+    /// Keys added or removed while the keys are listed may or may not be
+    /// returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to match, such as `prefix:*`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys could not be listed.
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>>;
+
+    /// Run the commands of a pipeline atomically, so other clients never see
+    /// some of them applied and others not yet.
+    ///
+    /// The commands are not rolled back if one of them fails, for instance
+    /// because its key holds the wrong type: Redis still applies the others,
+    /// while the in-process store applies none of them. Pipelines should only
+    /// hold commands that cannot fail that way.
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - The commands to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidExpiry`] if a value is set with a zero
+    /// expiry, or an error if the transaction failed.
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()>;
+
+    /// Get every field of a hash and delete it, running the commands of a
+    /// pipeline in the same atomic operation. When several clients take the
+    /// same key at once, only one of them gets the fields. The commands are
+    /// only run if the hash exists, and fail as they would in
+    /// [`Interface::transaction`], in which case the hash is kept. They should
+    /// not write to `key`.
+    ///
+    /// On a Redis Cluster, commands on keys in a different hash slot than `key`
+    /// are run right after the hash is taken rather than atomically with it;
+    /// see [`crate::hash_tagged`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `then` - The commands to run once the hash is taken.
+    ///
+    /// # Returns
+    ///
+    /// The fields of the hash, or `None` if the key does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidExpiry`] if a value is set with a zero
+    /// expiry, or an error if the hash could not be taken, including when the
+    /// key holds a value that is not a hash.
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>>;
+
+    /// Set a value only if the key does not exist, as a single atomic
+    /// operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set a value under.
+    /// * `value` - The value to set.
+    /// * `expiry` - The expiry time for the value.
+    ///
+    /// # Returns
+    ///
+    /// Whether the value was set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidExpiry`] if the expiry is less than a
+    /// millisecond, or an error if the value could not be set.
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool>;
+
+    /// Set the expiry of a key only if it holds the given value, as a single
+    /// atomic operation. The expiry is kept to the millisecond.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set an expiry on.
+    /// * `value` - The value the key must hold.
+    /// * `expiry` - The expiry time for the key.
+    ///
+    /// # Returns
+    ///
+    /// Whether the expiry was set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidExpiry`] if the expiry is less than a
+    /// millisecond, or an error if the expiry could not be set.
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool>;
+
+    /// Delete a key only if it holds the given value, as a single atomic
+    /// operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to delete.
+    /// * `value` - The value the key must hold.
+    ///
+    /// # Returns
+    ///
+    /// Whether the key was deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key could not be deleted.
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool>;
+
+    /// Increment a counter, raising it to at least `floor`, as a single atomic
+    /// operation. A counter that does not exist starts at zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the counter.
+    /// * `floor` - The least value to leave the counter at.
+    ///
+    /// # Returns
+    ///
+    /// The new value of the counter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the counter could not be incremented, including
+    /// when the key holds a value that is not an integer.
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64>;
+
+    /// Admit a request against a quota kept under a key, as a single atomic
+    /// operation. The key expires once the full quota is available again.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the quota is kept under.
+    /// * `quota` - The quota to admit the request against.
+    ///
+    /// # Returns
+    ///
+    /// Whether the request was admitted, and what is left of the quota.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the quota could not be checked, including when the
+    /// key holds a value that is not a time.
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision>;
+
+    /// Check that the cache can be reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache could not be reached.
+    async fn ping(&self) -> Result<()>;
+
+    /// Publish a message to every current subscriber of a channel. Channels
+    /// are separate from keys, and a message with no subscribers is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to publish to.
+    /// * `message` - The message to publish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message could not be published.
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()>;
+
+    /// Subscribe to a channel. If the connection is lost, the subscription
+    /// reconnects by itself and reports that messages may have been missed.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to subscribe to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscription could not be made.
+    async fn subscribe(&self, channel: &str) -> Result<Subscription>;
+}
@@ -7,10 +7,46 @@
     clippy::pedantic
 )]
 
+pub use cache::{Cache, Health};
+pub use channel::{Channel, Subscriber};
+pub use circuit::{Circuit, State as CircuitState};
+pub use cluster::{hash_tagged, slot, Cluster};
+pub use codec::{Codec, Json, MessagePack};
 pub use connection::Connection;
 pub use controller::Controller;
+pub use election::Election;
+pub use envelope::Versioned;
 pub use error::{Error, Result};
+pub use interface::Interface;
+pub use lock::{Guard, Lock};
+pub use memory::Memory;
+pub use options::Options;
+pub use pipeline::Pipeline;
+pub use rate_limit::{Decision, Quota};
+pub use redis::Script;
+pub use subscription::{Message, Subscription};
+pub use typed::Typed;
 
+mod cache;
+mod channel;
+mod circuit;
+mod cluster;
+mod codec;
 mod connection;
 mod controller;
+mod duration;
+mod election;
+mod envelope;
 mod error;
+mod interface;
+mod lock;
+mod memory;
+mod options;
+mod pipeline;
+mod rate_limit;
+mod sentinel;
+mod subscription;
+#[cfg(test)]
+mod test_server;
+mod typed;
+mod url;
@@ -0,0 +1,469 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::{Cache, Error, Interface, Result};
+
+/// Set the expiry of `KEYS[1]` to `ARGV[2]` milliseconds if it holds `ARGV[1]`.
+pub(crate) const EXPIRE_IF_EQUAL_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+";
+
+/// Delete `KEYS[1]` if it holds `ARGV[1]`.
+pub(crate) const DELETE_IF_EQUAL_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Increment the counter at `KEYS[1]`, raising it to at least `ARGV[1]`.
+pub(crate) const ADVANCE_SCRIPT: &str = r"
+local value = redis.call('INCR', KEYS[1])
+if value < tonumber(ARGV[1]) then
+    redis.call('SET', KEYS[1], ARGV[1], 'KEEPTTL')
+    return tonumber(ARGV[1])
+end
+return value
+";
+
+/// How many times a lease is renewed within its length, so that renewal can
+/// fail once without losing the lock.
+const RENEWALS_PER_LEASE: u32 = 3;
+
+/// The allowance for clocks running at different rates, as a fraction of the
+/// lease, as suggested by Redlock.
+const CLOCK_DRIFT_DIVISOR: u32 = 100;
+
+/// The allowance for clock drift regardless of the lease.
+const MIN_CLOCK_DRIFT: Duration = Duration::from_millis(2);
+
+/// A lock held by at most one process at a time, for work that must only run
+/// on one server replica.
+///
+/// The lock is held for a lease, which is renewed in the background for as
+/// long as the [`Guard`] is kept, so a process that dies releases it once the
+/// lease runs out. Each acquisition is given a fencing token greater than any
+/// given before, which the work can pass to the systems it writes to so they
+/// can reject writes from a process that has lost the lock without noticing.
+///
+/// Following Redlock, the lock can be held across several independent caches
+/// rather than one, and is acquired once it is held in a majority of them, so
+/// it survives the loss of a minority.
+///
+/// # Examples
+///
+/// ```
+/// # async fn example() -> lib_cache::Result<()> {
+/// use std::time::Duration;
+///
+/// use lib_cache::{Cache, Lock};
+///
+/// let lock = Lock::new(Cache::memory(), "rollup", Duration::from_secs(30));
+/// if let Some(guard) = lock.try_acquire().await? {
+///     println!("rolling up with fencing token {}", guard.fencing_token());
+///     guard.release().await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Lock {
+    caches: Arc<[Cache]>,
+    key: String,
+    fence_key: String,
+    lease: Duration,
+}
+
+impl std::fmt::Debug for Lock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lock")
+            .field("key", &self.key)
+            .field("lease", &self.lease)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Lock {
+    /// Create a lock held in a single cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to hold the lock in.
+    /// * `name` - The name of the lock.
+    /// * `lease` - How long the lock is held for without being renewed.
+    #[must_use]
+    pub fn new(cache: Cache, name: &str, lease: Duration) -> Self {
+        Self::redlock(vec![cache], name, lease)
+    }
+
+    /// Create a lock held across independent caches, which is acquired once
+    /// it is held in a majority of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `caches` - The caches to hold the lock in, which must not be replicas
+    ///   of one another.
+    /// * `name` - The name of the lock.
+    /// * `lease` - How long the lock is held for without being renewed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no caches are given.
+    #[must_use]
+    pub fn redlock(caches: Vec<Cache>, name: &str, lease: Duration) -> Self {
+        assert!(!caches.is_empty(), "a lock needs at least one cache");
+
+        Self {
+            caches: caches.into(),
+            key: format!("lock:{name}"),
+            fence_key: format!("lock:{name}:fence"),
+            lease,
+        }
+    }
+
+    /// Get how long the lock is held for without being renewed.
+    #[must_use]
+    pub fn lease(&self) -> Duration {
+        self.lease
+    }
+
+    /// Get the number of caches the lock must be held in.
+    fn quorum(&self) -> usize {
+        self.caches.len() / 2 + 1
+    }
+
+    /// Get the time until which a lease taken at `started` is certainly held.
+    fn valid_until(&self, started: Instant) -> Instant {
+        let drift = self.lease / CLOCK_DRIFT_DIVISOR + MIN_CLOCK_DRIFT;
+        started + self.lease.saturating_sub(drift)
+    }
+
+    /// Try to acquire the lock, without waiting if it is held elsewhere.
+    ///
+    /// # Returns
+    ///
+    /// A guard that holds the lock until it is dropped or released, or `None`
+    /// if the lock is held elsewhere.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidExpiry`] if the lease is less than a
+    /// millisecond, or an error if too few caches could be reached.
+    pub async fn try_acquire(&self) -> Result<Option<Guard>> {
+        let owner = format!("{:032x}", rand::random::<u128>()).into_bytes();
+        let started = Instant::now();
+
+        let mut acquired = Vec::new();
+        let mut error = None;
+        let mut failures = 0;
+        for cache in self.caches.iter() {
+            match cache.set_if_absent(&self.key, &owner, &self.lease).await {
+                Ok(true) => acquired.push(cache),
+                Ok(false) => {}
+                Err(e) => {
+                    failures += 1;
+                    error = Some(e);
+                }
+            }
+        }
+
+        if acquired.len() < self.quorum() || Instant::now() >= self.valid_until(started) {
+            self.release_all(&owner).await;
+
+            // Only report errors that may have been why the lock was not acquired.
+            return match error {
+                Some(e) if acquired.len() + failures >= self.quorum() => Err(e),
+                _ => Ok(None),
+            };
+        }
+
+        let fencing_token = match self.fence(&acquired).await {
+            Ok(fencing_token) => fencing_token,
+            Err(e) => {
+                self.release_all(&owner).await;
+                return Err(e);
+            }
+        };
+
+        let (held, receiver) = watch::channel(true);
+        let renewal = tokio::spawn(renew(
+            self.clone(),
+            owner.clone(),
+            self.valid_until(started),
+            held,
+        ));
+
+        Ok(Some(Guard {
+            lock: self.clone(),
+            owner,
+            fencing_token,
+            held: receiver,
+            renewal,
+        }))
+    }
+
+    /// Get a fencing token greater than any given before.
+    ///
+    /// The counter is advanced in every cache the lock was acquired in, and
+    /// those that fall behind are raised to the greatest, so that every later
+    /// acquisition, which shares at least one of those caches, gets a greater
+    /// token.
+    ///
+    /// # Arguments
+    ///
+    /// * `acquired` - The caches the lock was acquired in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a counter could not be advanced.
+    async fn fence(&self, acquired: &[&Cache]) -> Result<u64> {
+        let mut tokens = Vec::with_capacity(acquired.len());
+        for cache in acquired {
+            tokens.push(cache.advance(&self.fence_key, 0).await?);
+        }
+
+        let fencing_token = tokens.iter().copied().max().unwrap_or_default();
+        for (cache, token) in acquired.iter().zip(tokens) {
+            if token < fencing_token {
+                cache.advance(&self.fence_key, fencing_token).await?;
+            }
+        }
+
+        Ok(fencing_token)
+    }
+
+    /// Release the lock in every cache it is held in by the given owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The value identifying the owner of the lock.
+    ///
+    /// # Returns
+    ///
+    /// The last error, if the lock could not be released in a cache.
+    async fn release_all(&self, owner: &[u8]) -> Option<Error> {
+        let mut error = None;
+        for cache in self.caches.iter() {
+            if let Err(e) = cache.delete_if_equal(&self.key, owner).await {
+                error = Some(e);
+            }
+        }
+        error
+    }
+}
+
+/// Renew a lease until it is lost, or the guard is dropped.
+///
+/// # Arguments
+///
+/// * `lock` - The lock to renew.
+/// * `owner` - The value identifying the owner of the lock.
+/// * `valid_until` - The time until which the lease is certainly held.
+/// * `held` - Told when the lock is lost.
+async fn renew(lock: Lock, owner: Vec<u8>, mut valid_until: Instant, held: watch::Sender<bool>) {
+    let interval = lock.lease / RENEWALS_PER_LEASE;
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let started = Instant::now();
+        let mut renewed = 0;
+        let mut lost = 0;
+        for cache in lock.caches.iter() {
+            match cache.expire_if_equal(&lock.key, &owner, &lock.lease).await {
+                Ok(true) => renewed += 1,
+                Ok(false) => lost += 1,
+                Err(e) => log::warn!("Failed to renew {}: {}", lock.key, e),
+            }
+        }
+
+        if renewed >= lock.quorum() {
+            valid_until = lock.valid_until(started);
+        }
+
+        // The lock is lost once it is gone from too many caches to get a
+        // majority, or once the lease may have run out.
+        if lost > lock.caches.len() - lock.quorum() || Instant::now() >= valid_until {
+            log::warn!("Lost {}", lock.key);
+            let _ = held.send(false);
+            return;
+        }
+    }
+}
+
+/// Holds a [`Lock`] until it is dropped or released, renewing its lease in
+/// the background.
+///
+/// Dropping the guard stops renewing the lease, so the lock is released once
+/// it runs out. Releasing it frees the lock straight away.
+pub struct Guard {
+    lock: Lock,
+    owner: Vec<u8>,
+    fencing_token: u64,
+    held: watch::Receiver<bool>,
+    renewal: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for Guard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Guard")
+            .field("lock", &self.lock)
+            .field("fencing_token", &self.fencing_token)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Guard {
+    /// Get the fencing token of this acquisition, which is greater than that
+    /// of any earlier acquisition.
+    #[must_use]
+    pub fn fencing_token(&self) -> u64 {
+        self.fencing_token
+    }
+
+    /// Check whether the lock is still held, as far as is known.
+    #[must_use]
+    pub fn is_held(&self) -> bool {
+        *self.held.borrow()
+    }
+
+    /// Wait until the lock is lost, such as because the lease could not be
+    /// renewed in time.
+    pub async fn lost(&mut self) {
+        while *self.held.borrow() {
+            if self.held.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Release the lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock could not be released in every cache, in
+    /// which case it is released there once its lease runs out.
+    pub async fn release(self) -> Result<()> {
+        self.renewal.abort();
+
+        match self.lock.release_all(&self.owner).await {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Long enough that a loaded machine cannot stall a test past a lease.
+    const LEASE: Duration = Duration::from_secs(1);
+
+    #[tokio::test]
+    async fn test_exclusive() {
+        let lock = Lock::new(Cache::memory(), "job", LEASE);
+
+        let guard = lock.try_acquire().await.unwrap().unwrap();
+        assert!(lock.try_acquire().await.unwrap().is_none());
+
+        let first = guard.fencing_token();
+        guard.release().await.unwrap();
+
+        let guard = lock.try_acquire().await.unwrap().unwrap();
+        assert!(guard.fencing_token() > first);
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let lock = Lock::new(Cache::memory(), "job", LEASE);
+
+        drop(lock.try_acquire().await.unwrap().unwrap());
+        assert!(lock.try_acquire().await.unwrap().is_none());
+
+        tokio::time::sleep(LEASE + LEASE / 2).await;
+        assert!(lock.try_acquire().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_renewal() {
+        let lock = Lock::new(Cache::memory(), "job", LEASE);
+
+        let guard = lock.try_acquire().await.unwrap().unwrap();
+        tokio::time::sleep(LEASE * 2).await;
+
+        assert!(guard.is_held());
+        assert!(lock.try_acquire().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lost() {
+        let cache = Cache::memory();
+        let lock = Lock::new(cache.clone(), "job", LEASE);
+
+        let mut guard = lock.try_acquire().await.unwrap().unwrap();
+        cache.delete("lock:job").await.unwrap();
+        let thief = lock.try_acquire().await.unwrap().unwrap();
+
+        tokio::time::timeout(LEASE * 2, guard.lost()).await.unwrap();
+        assert!(!guard.is_held());
+        assert!(thief.fencing_token() > guard.fencing_token());
+
+        // Releasing a lost lock leaves the new holder's alone.
+        guard.release().await.unwrap();
+        assert!(lock.try_acquire().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_redlock() {
+        let caches = vec![Cache::memory(), Cache::memory(), Cache::memory()];
+        let lock = Lock::redlock(caches.clone(), "job", LEASE);
+
+        // Held elsewhere in a majority of the caches.
+        for cache in &caches[..2] {
+            cache
+                .set_if_absent("lock:job", b"other", &LEASE)
+                .await
+                .unwrap();
+        }
+        assert!(lock.try_acquire().await.unwrap().is_none());
+        assert!(!caches[2].exists("lock:job").await.unwrap());
+
+        // Held elsewhere in a minority of the caches.
+        caches[0].delete("lock:job").await.unwrap();
+        let guard = lock.try_acquire().await.unwrap().unwrap();
+        assert!(caches[2].exists("lock:job").await.unwrap());
+
+        // Fencing tokens keep increasing whichever majority is acquired.
+        let first = guard.fencing_token();
+        guard.release().await.unwrap();
+        caches[1].delete("lock:job").await.unwrap();
+        caches[2]
+            .set_if_absent("lock:job", b"other", &LEASE)
+            .await
+            .unwrap();
+        let guard = lock.try_acquire().await.unwrap().unwrap();
+        assert!(guard.fencing_token() > first);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_lease() {
+        let lock = Lock::new(Cache::memory(), "job", Duration::from_micros(10));
+        assert!(matches!(
+            lock.try_acquire().await,
+            Err(Error::InvalidExpiry)
+        ));
+    }
+}
@@ -0,0 +1,737 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::duration::millis;
+use crate::pipeline::Command;
+use crate::subscription::CAPACITY;
+use crate::{Decision, Error, Interface, Message, Pipeline, Quota, Result, Subscription};
+
+/// How often expired keys that are never read again are removed.
+const PURGE_INTERVAL: Duration = Duration::from_mins(1);
+
+/// A value stored in the cache.
+#[derive(Clone)]
+enum Value {
+    String(Vec<u8>),
+    Hash(HashMap<String, Vec<u8>>),
+}
+
+/// A value stored in the cache, with its expiry.
+#[derive(Clone)]
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+/// The entries of the cache.
+struct Entries {
+    entries: HashMap<String, Entry>,
+    purged_at: Instant,
+}
+
+impl Entries {
+    /// Get a live entry, removing it if it has expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the entry.
+    /// * `now` - The current time.
+    fn get_mut(&mut self, key: &str, now: Instant) -> Option<&mut Entry> {
+        if self.entries.get(key)?.is_expired(now) {
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.entries.get_mut(key)
+    }
+
+    /// Remove every expired entry, if it has not been done recently.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time.
+    fn purge(&mut self, now: Instant) {
+        if now.duration_since(self.purged_at) < PURGE_INTERVAL {
+            return;
+        }
+
+        self.entries.retain(|_, entry| !entry.is_expired(now));
+        self.purged_at = now;
+    }
+
+    /// Set a field of a hash, keeping the key's expiry.
+    fn hset(&mut self, key: &str, field: &str, value: &[u8], now: Instant) -> Result<()> {
+        let Some(entry) = self.get_mut(key, now) else {
+            let hash = HashMap::from([(field.to_string(), value.to_vec())]);
+            self.entries.insert(
+                key.to_string(),
+                Entry {
+                    value: Value::Hash(hash),
+                    expires_at: None,
+                },
+            );
+            return Ok(());
+        };
+
+        match &mut entry.value {
+            Value::Hash(hash) => {
+                hash.insert(field.to_string(), value.to_vec());
+                Ok(())
+            }
+            Value::String(_) => Err(Error::WrongType(key.to_string())),
+        }
+    }
+
+    /// Set a value, replacing the key's previous value and expiry.
+    fn set(
+        &mut self,
+        key: &str,
+        value: &[u8],
+        expiry: Option<&Duration>,
+        now: Instant,
+    ) -> Result<()> {
+        let expires_at = match expiry {
+            Some(expiry) if expiry.is_zero() => return Err(Error::InvalidExpiry),
+            Some(expiry) => Some(now.checked_add(*expiry).ok_or(Error::InvalidExpiry)?),
+            None => None,
+        };
+
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                value: Value::String(value.to_vec()),
+                expires_at,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Set the expiry of a key, deleting it if the expiry is zero.
+    fn expire(&mut self, key: &str, expiry: &Duration, now: Instant) -> Result<()> {
+        let expires_at = now.checked_add(*expiry).ok_or(Error::InvalidExpiry)?;
+
+        if expiry.is_zero() {
+            self.entries.remove(key);
+        } else if let Some(entry) = self.get_mut(key, now) {
+            entry.expires_at = Some(expires_at);
+        }
+
+        Ok(())
+    }
+
+    /// Run a command of a pipeline.
+    fn apply(&mut self, command: &Command, now: Instant) -> Result<()> {
+        match command {
+            Command::HSet { key, field, value } => self.hset(key, field, value, now),
+            Command::Set { key, value, expiry } => self.set(key, value, expiry.as_ref(), now),
+            Command::Delete { key } => {
+                self.entries.remove(key);
+                Ok(())
+            }
+            Command::Expire { key, expiry } => self.expire(key, expiry, now),
+        }
+    }
+
+    /// Run the commands of a pipeline, undoing every change if one of them
+    /// fails, including changes made just before.
+    ///
+    /// # Arguments
+    ///
+    /// * `pipeline` - The commands to run.
+    /// * `snapshot` - The entries of keys changed just before, as they were.
+    /// * `now` - The current time.
+    fn run(
+        &mut self,
+        pipeline: &Pipeline,
+        mut snapshot: Vec<(String, Option<Entry>)>,
+        now: Instant,
+    ) -> Result<()> {
+        // Keep the entries the commands write to, to restore them if one fails.
+        snapshot.extend(pipeline.commands().iter().map(|command| {
+            let key = command.key();
+            (key.to_string(), self.entries.get(key).cloned())
+        }));
+
+        for command in pipeline.commands() {
+            if let Err(e) = self.apply(command, now) {
+                // Restoring in reverse leaves each key as it was first seen.
+                for (key, entry) in snapshot.into_iter().rev() {
+                    match entry {
+                        Some(entry) => self.entries.insert(key, entry),
+                        None => self.entries.remove(&key),
+                    };
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An in-process cache, for running without Redis and for tests.
+///
+/// Keys expire like in Redis: an expired key is never returned, and is removed
+/// when next accessed or during a periodic purge. Clones share the same entries
+/// and channels.
+#[derive(Clone)]
+pub struct Memory {
+    entries: Arc<Mutex<Entries>>,
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<Message>>>>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                entries: HashMap::new(),
+                purged_at: Instant::now(),
+            })),
+            channels: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory").finish()
+    }
+}
+
+impl Memory {
+    /// Lock the entries of the cache.
+    ///
+    /// A panic while the lock was held cannot leave an entry half-written, so
+    /// a poisoned lock is recovered.
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Check whether a key matches a glob-style pattern.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern, where `*` matches any sequence of characters and `?` matches any single character.
+/// * `key` - The key to match.
+fn matches(pattern: &[char], key: &[char]) -> bool {
+    match pattern.split_first() {
+        None => key.is_empty(),
+        Some(('*', rest)) => (0..=key.len()).any(|i| matches(rest, &key[i..])),
+        Some(('?', rest)) => !key.is_empty() && matches(rest, &key[1..]),
+        Some((c, rest)) => key.first() == Some(c) && matches(rest, &key[1..]),
+    }
+}
+
+#[async_trait]
+impl Interface for Memory {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+        entries.hset(key, field, value, now)
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        let mut entries = self.lock();
+
+        match entries
+            .get_mut(key, Instant::now())
+            .map(|entry| &entry.value)
+        {
+            None => Ok(None),
+            Some(Value::Hash(hash)) => Ok(hash.get(field).cloned()),
+            Some(Value::String(_)) => Err(Error::WrongType(key.to_string())),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+        entries.set(key, value, expiry, now)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut entries = self.lock();
+
+        match entries
+            .get_mut(key, Instant::now())
+            .map(|entry| &entry.value)
+        {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(Value::Hash(_)) => Err(Error::WrongType(key.to_string())),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.lock().get_mut(key, Instant::now()).is_some())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.lock().entries.remove(key);
+        Ok(())
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        let now = Instant::now();
+        self.lock().expire(key, expiry, now)
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+
+        Ok(entries
+            .get_mut(key, now)
+            .and_then(|entry| entry.expires_at)
+            .map(|expires_at| expires_at.duration_since(now)))
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        let now = Instant::now();
+        let pattern = pattern.chars().collect::<Vec<_>>();
+
+        Ok(self
+            .lock()
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key)
+            .filter(|key| matches(&pattern, &key.chars().collect::<Vec<_>>()))
+            .cloned()
+            .collect())
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        pipeline.validate()?;
+
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+
+        entries.run(pipeline, Vec::new(), now)
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        then.validate()?;
+
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+
+        match entries.get_mut(key, now).map(|entry| &entry.value) {
+            None => return Ok(None),
+            Some(Value::String(_)) => return Err(Error::WrongType(key.to_string())),
+            Some(Value::Hash(_)) => {}
+        }
+
+        let Some(entry) = entries.entries.remove(key) else {
+            return Ok(None);
+        };
+        let Value::Hash(hash) = entry.value.clone() else {
+            return Ok(None);
+        };
+        entries.run(then, vec![(key.to_string(), Some(entry))], now)?;
+
+        Ok(Some(hash))
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        millis(expiry)?;
+
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+
+        if entries.get_mut(key, now).is_some() {
+            return Ok(false);
+        }
+
+        entries.set(key, value, Some(expiry), now)?;
+        Ok(true)
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        millis(expiry)?;
+
+        let now = Instant::now();
+        let expires_at = now.checked_add(*expiry).ok_or(Error::InvalidExpiry)?;
+        let mut entries = self.lock();
+
+        match entries.get_mut(key, now) {
+            Some(entry) if matches!(&entry.value, Value::String(current) if current == value) => {
+                entry.expires_at = Some(expires_at);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        let mut entries = self.lock();
+
+        let equal = matches!(
+            entries.get_mut(key, Instant::now()).map(|entry| &entry.value),
+            Some(Value::String(current)) if current == value
+        );
+        if equal {
+            entries.entries.remove(key);
+        }
+
+        Ok(equal)
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.purge(now);
+
+        let Some(entry) = entries.get_mut(key, now) else {
+            let value = floor.max(1);
+            entries.set(key, value.to_string().as_bytes(), None, now)?;
+            return Ok(value);
+        };
+
+        // Like Redis, keep the counter's expiry.
+        let Value::String(current) = &mut entry.value else {
+            return Err(Error::WrongType(key.to_string()));
+        };
+        let value = std::str::from_utf8(current)
+            .ok()
+            .and_then(|current| current.parse::<u64>().ok())
+            .ok_or_else(|| Error::WrongType(key.to_string()))?
+            .saturating_add(1)
+            .max(floor);
+        *current = value.to_string().into_bytes();
+
+        Ok(value)
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        let now = Instant::now();
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| {
+                u64::try_from(since.as_millis()).unwrap_or(u64::MAX)
+            });
+        let mut entries = self.lock();
+        entries.purge(now);
+
+        let tat = match entries.get_mut(key, now).map(|entry| &entry.value) {
+            Some(Value::String(tat)) => Some(
+                std::str::from_utf8(tat)
+                    .ok()
+                    .and_then(|tat| tat.parse::<u64>().ok())
+                    .ok_or_else(|| Error::WrongType(key.to_string()))?,
+            ),
+            Some(Value::Hash(_)) => return Err(Error::WrongType(key.to_string())),
+            None => None,
+        };
+
+        let (decision, tat) = quota.admit(millis, tat);
+        if let Some(tat) = tat {
+            let expiry = Duration::from_millis(tat - millis);
+            entries.set(key, tat.to_string().as_bytes(), Some(&expiry), now)?;
+        }
+
+        Ok(decision)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        let mut channels = self.channels.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Sending only fails once every subscriber has gone.
+        let sent = channels
+            .get(channel)
+            .map(|sender| sender.send(Message::Payload(message.to_vec())).is_ok());
+        if sent == Some(false) {
+            channels.remove(channel);
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        let mut channels = self.channels.lock().unwrap_or_else(PoisonError::into_inner);
+        let sender = channels
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(CAPACITY).0);
+
+        Ok(Subscription::new(sender.subscribe()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_exercise() {
+        crate::test_server::exercise(&Memory::default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_set() {
+        let cache = Memory::default();
+
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(!cache.exists("key").await.unwrap());
+
+        cache.set("key", b"value", None).await.unwrap();
+        assert_eq!(cache.get("key").await.unwrap(), Some(b"value".to_vec()));
+        assert!(cache.exists("key").await.unwrap());
+        assert_eq!(cache.ttl("key").await.unwrap(), None);
+
+        cache.delete("key").await.unwrap();
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        cache.delete("key").await.unwrap();
+
+        assert!(matches!(
+            cache.set("key", b"value", Some(&Duration::ZERO)).await,
+            Err(Error::InvalidExpiry)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hget_hset() {
+        let cache = Memory::default();
+
+        assert_eq!(cache.hget("key", "a").await.unwrap(), None);
+
+        cache.hset("key", "a", b"1").await.unwrap();
+        cache.hset("key", "b", b"2").await.unwrap();
+        cache.hset("key", "a", b"3").await.unwrap();
+        assert_eq!(cache.hget("key", "a").await.unwrap(), Some(b"3".to_vec()));
+        assert_eq!(cache.hget("key", "b").await.unwrap(), Some(b"2".to_vec()));
+        assert_eq!(cache.hget("key", "c").await.unwrap(), None);
+
+        assert!(matches!(cache.get("key").await, Err(Error::WrongType(_))));
+        cache.set("other", b"value", None).await.unwrap();
+        assert!(matches!(
+            cache.hget("other", "a").await,
+            Err(Error::WrongType(_))
+        ));
+        assert!(matches!(
+            cache.hset("other", "a", b"1").await,
+            Err(Error::WrongType(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let cache = Memory::default();
+
+        cache.set("key", b"value", Some(&SHORT)).await.unwrap();
+        let ttl = cache.ttl("key").await.unwrap().unwrap();
+        assert!(ttl > Duration::ZERO && ttl <= SHORT);
+
+        std::thread::sleep(SHORT * 2);
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(!cache.exists("key").await.unwrap());
+        assert_eq!(cache.ttl("key").await.unwrap(), None);
+
+        // Setting a value without an expiry clears the previous one.
+        cache.set("key", b"value", Some(&SHORT)).await.unwrap();
+        cache.set("key", b"value", None).await.unwrap();
+        std::thread::sleep(SHORT * 2);
+        assert!(cache.exists("key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expire() {
+        let cache = Memory::default();
+
+        // Expiring a missing key has no effect.
+        cache.expire("key", &SHORT).await.unwrap();
+        assert!(!cache.exists("key").await.unwrap());
+
+        cache.hset("key", "a", b"1").await.unwrap();
+        cache.expire("key", &Duration::from_mins(1)).await.unwrap();
+
+        // Setting a field keeps the expiry.
+        cache.hset("key", "b", b"2").await.unwrap();
+        assert!(cache.ttl("key").await.unwrap().unwrap() > SHORT);
+
+        cache.expire("key", &SHORT).await.unwrap();
+        assert!(cache.ttl("key").await.unwrap().unwrap() <= SHORT);
+        std::thread::sleep(SHORT * 2);
+        assert_eq!(cache.hget("key", "a").await.unwrap(), None);
+
+        // A new hash does not inherit the expired key's expiry.
+        cache.hset("key", "a", b"1").await.unwrap();
+        assert_eq!(cache.ttl("key").await.unwrap(), None);
+
+        cache.expire("key", &Duration::ZERO).await.unwrap();
+        assert!(!cache.exists("key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scan() {
+        let cache = Memory::default();
+
+        cache.set("a:1", b"", None).await.unwrap();
+        cache.set("a:22", b"", None).await.unwrap();
+        cache.set("b:1", b"", None).await.unwrap();
+        cache.set("a:3", b"", Some(&SHORT)).await.unwrap();
+        std::thread::sleep(SHORT * 2);
+
+        let mut keys = cache.scan("a:*").await.unwrap();
+        keys.sort();
+        assert_eq!(keys, ["a:1", "a:22"]);
+        assert_eq!(cache.scan("?:1").await.unwrap().len(), 2);
+        assert_eq!(cache.scan("a:?").await.unwrap(), ["a:1"]);
+        assert_eq!(cache.scan("*").await.unwrap().len(), 3);
+        assert!(cache.scan("c*").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_entries() {
+        let cache = Memory::default();
+        let clone = cache.clone();
+
+        cache.set("key", b"value", None).await.unwrap();
+        assert_eq!(clone.get("key").await.unwrap(), Some(b"value".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let cache = Memory::default();
+
+        cache
+            .transaction(
+                &Pipeline::new()
+                    .hset("key", "a", b"1")
+                    .hset("key", "b", b"2")
+                    .expire("key", Duration::from_mins(1))
+                    .set("other", b"value", None),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cache.hget("key", "b").await.unwrap(), Some(b"2".to_vec()));
+        assert!(cache.ttl("key").await.unwrap().is_some());
+        assert_eq!(cache.get("other").await.unwrap(), Some(b"value".to_vec()));
+
+        // A failing command undoes the ones before it.
+        let result = cache
+            .transaction(
+                &Pipeline::new()
+                    .delete("key")
+                    .set("new", b"value", None)
+                    .hset("other", "a", b"1"),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::WrongType(_))));
+        assert_eq!(cache.hget("key", "a").await.unwrap(), Some(b"1".to_vec()));
+        assert!(cache.ttl("key").await.unwrap().is_some());
+        assert!(!cache.exists("new").await.unwrap());
+
+        assert!(matches!(
+            cache
+                .transaction(&Pipeline::new().set("new", b"value", Some(Duration::ZERO)))
+                .await,
+            Err(Error::InvalidExpiry)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_take() {
+        let cache = Memory::default();
+
+        cache.hset("key", "a", b"1").await.unwrap();
+        cache.hset("key", "b", b"2").await.unwrap();
+
+        cache.set("linked", b"value", None).await.unwrap();
+
+        let then = Pipeline::new().delete("linked").hset("new", "a", b"3");
+        let fields = cache.take("key", &then).await.unwrap().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["a"], b"1");
+        assert!(!cache.exists("key").await.unwrap());
+        assert!(!cache.exists("linked").await.unwrap());
+        assert_eq!(cache.hget("new", "a").await.unwrap().unwrap(), b"3");
+        assert!(cache.take("key", &Pipeline::new()).await.unwrap().is_none());
+
+        cache.set("other", b"value", None).await.unwrap();
+        cache.set("linked", b"value", None).await.unwrap();
+        let then = Pipeline::new().delete("linked");
+        assert!(cache.take("key", &then).await.unwrap().is_none());
+        assert!(cache.exists("linked").await.unwrap());
+        assert!(matches!(
+            cache.take("other", &then).await,
+            Err(Error::WrongType(_))
+        ));
+        assert!(cache.exists("other").await.unwrap());
+        assert!(cache.exists("linked").await.unwrap());
+
+        // A failing command leaves the hash and every other key untouched.
+        cache.hset("key", "a", b"1").await.unwrap();
+        let then = Pipeline::new().delete("linked").hset("other", "a", b"1");
+        assert!(matches!(
+            cache.take("key", &then).await,
+            Err(Error::WrongType(_))
+        ));
+        assert_eq!(cache.hget("key", "a").await.unwrap().unwrap(), b"1");
+        assert!(cache.exists("linked").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_publish_subscribe() {
+        let cache = Memory::default();
+        cache.publish("channel", b"unheard").await.unwrap();
+
+        let mut subscription = cache.clone().subscribe("channel").await.unwrap();
+        let mut other = cache.subscribe("other").await.unwrap();
+        cache.publish("channel", b"message").await.unwrap();
+        cache.publish("other", b"other message").await.unwrap();
+
+        assert_eq!(
+            subscription.recv().await,
+            Some(Message::Payload(b"message".to_vec()))
+        );
+        assert_eq!(
+            other.recv().await,
+            Some(Message::Payload(b"other message".to_vec()))
+        );
+
+        drop(subscription);
+        drop(other);
+        cache.publish("channel", b"unheard").await.unwrap();
+        assert!(!cache.channels.lock().unwrap().contains_key("channel"));
+    }
+
+    #[tokio::test]
+    async fn test_missed() {
+        let cache = Memory::default();
+        let mut subscription = cache.subscribe("channel").await.unwrap();
+
+        for _ in 0..=CAPACITY {
+            cache.publish("channel", b"message").await.unwrap();
+        }
+
+        assert_eq!(subscription.recv().await, Some(Message::Missed));
+        assert_eq!(
+            subscription.recv().await,
+            Some(Message::Payload(b"message".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+/// How a cache connects to Redis, and how it behaves while Redis is down.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use lib_cache::Options;
+///
+/// let options = Options::default()
+///     .with_pool_size(32)
+///     .with_command_timeout(Duration::from_secs(1));
+/// assert_eq!(options.pool_size(), 32);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pool_size: usize,
+    connect_timeout: Duration,
+    wait_timeout: Duration,
+    command_timeout: Duration,
+    min_retry_delay: Duration,
+    max_retry_delay: Duration,
+    failure_threshold: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            pool_size: 16,
+            connect_timeout: Duration::from_secs(5),
+            wait_timeout: Duration::from_secs(5),
+            command_timeout: Duration::from_secs(5),
+            min_retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(5),
+            failure_threshold: 5,
+        }
+    }
+}
+
+impl Options {
+    /// Set the most connections kept to each Redis node.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - The most connections, which is raised to at least one.
+    #[must_use]
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size.max(1);
+        self
+    }
+
+    /// Set how long to wait for a new connection to be made.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout.
+    #[must_use]
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for a connection to be free when every connection
+    /// in the pool is in use.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout.
+    #[must_use]
+    pub fn with_wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for an operation to complete, including getting a
+    /// connection, before giving up on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout.
+    #[must_use]
+    pub fn with_command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Set the delays between attempts to reach Redis again after losing it,
+    /// which double from the least to the most.
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - The delay before the first attempt.
+    /// * `max` - The longest delay between attempts, which is raised to at
+    ///   least `min`.
+    #[must_use]
+    pub fn with_retry_delay(mut self, min: Duration, max: Duration) -> Self {
+        self.min_retry_delay = min;
+        self.max_retry_delay = max.max(min);
+        self
+    }
+
+    /// Set how many operations in a row must fail to reach Redis before
+    /// further operations fail fast.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The number of failures, or zero to never fail fast.
+    #[must_use]
+    pub fn with_failure_threshold(mut self, threshold: u32) -> Self {
+        self.failure_threshold = threshold;
+        self
+    }
+
+    /// Get the most connections kept to each Redis node.
+    #[must_use]
+    pub fn pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    /// Get how long to wait for a new connection to be made.
+    #[must_use]
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Get how long to wait for a connection to be free.
+    #[must_use]
+    pub fn wait_timeout(&self) -> Duration {
+        self.wait_timeout
+    }
+
+    /// Get how long to wait for an operation to complete.
+    #[must_use]
+    pub fn command_timeout(&self) -> Duration {
+        self.command_timeout
+    }
+
+    /// Get the delay before first trying to reach Redis again.
+    #[must_use]
+    pub fn min_retry_delay(&self) -> Duration {
+        self.min_retry_delay
+    }
+
+    /// Get the longest delay between attempts to reach Redis again.
+    #[must_use]
+    pub fn max_retry_delay(&self) -> Duration {
+        self.max_retry_delay
+    }
+
+    /// Get how many operations in a row must fail before failing fast.
+    #[must_use]
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    /// Get the delay after the given one, doubling up to the longest delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The previous delay.
+    pub(crate) fn next_retry_delay(&self, delay: Duration) -> Duration {
+        (delay * 2).clamp(self.min_retry_delay, self.max_retry_delay)
+    }
+}
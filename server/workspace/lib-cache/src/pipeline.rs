@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::duration::ceil_millis;
+use crate::{Error, Result};
+
+/// A command within a [`Pipeline`].
+#[derive(Clone, Debug)]
+pub(crate) enum Command {
+    HSet {
+        key: String,
+        field: String,
+        value: Vec<u8>,
+    },
+    Set {
+        key: String,
+        value: Vec<u8>,
+        expiry: Option<Duration>,
+    },
+    Delete {
+        key: String,
+    },
+    Expire {
+        key: String,
+        expiry: Duration,
+    },
+}
+
+impl Command {
+    /// Get the name and arguments of the Redis command, after the key.
+    pub(crate) fn redis_args(&self) -> (&'static str, Vec<Vec<u8>>) {
+        match self {
+            Self::HSet { field, value, .. } => {
+                ("HSET", vec![field.as_bytes().to_vec(), value.clone()])
+            }
+            Self::Set {
+                value,
+                expiry: Some(expiry),
+                ..
+            } => (
+                "SET",
+                vec![
+                    value.clone(),
+                    b"PX".to_vec(),
+                    ceil_millis(expiry).to_string().into_bytes(),
+                ],
+            ),
+            Self::Set {
+                value,
+                expiry: None,
+                ..
+            } => ("SET", vec![value.clone()]),
+            Self::Delete { .. } => ("DEL", Vec::new()),
+            Self::Expire { expiry, .. } => (
+                "PEXPIRE",
+                vec![ceil_millis(expiry).to_string().into_bytes()],
+            ),
+        }
+    }
+
+    /// Get the key the command writes to.
+    pub(crate) fn key(&self) -> &str {
+        match self {
+            Self::HSet { key, .. }
+            | Self::Set { key, .. }
+            | Self::Delete { key }
+            | Self::Expire { key, .. } => key,
+        }
+    }
+}
+
+/// Run the commands encoded by [`Pipeline::script_args`]. Each command is
+/// its number of arguments, its name, the index of its key within `KEYS`,
+/// and then its remaining arguments.
+pub(crate) const PIPELINE_SCRIPT: &str = r"
+local i = 1
+while i <= #ARGV do
+    local n = tonumber(ARGV[i])
+    redis.call(ARGV[i + 1], KEYS[tonumber(ARGV[i + 2])], unpack(ARGV, i + 3, i + n))
+    i = i + n + 1
+end
+";
+
+/// Get every field of the hash at `KEYS[1]`, and if it exists run the commands
+/// encoded as for [`PIPELINE_SCRIPT`] and delete it. The hash is deleted last,
+/// so that it is kept if a command fails.
+pub(crate) const TAKE_SCRIPT: &str = r"
+local fields = redis.call('HGETALL', KEYS[1])
+if #fields > 0 then
+    local i = 1
+    while i <= #ARGV do
+        local n = tonumber(ARGV[i])
+        redis.call(ARGV[i + 1], KEYS[tonumber(ARGV[i + 2])], unpack(ARGV, i + 3, i + n))
+        i = i + n + 1
+    end
+    redis.call('DEL', KEYS[1])
+end
+return fields
+";
+
+/// A sequence of write commands sent to the cache at once.
+///
+/// The commands are queued by the builder methods, which behave like the
+/// methods of [`crate::Interface`] with the same names, and are then run either
+/// as a single atomic transaction or simply pipelined.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let pipeline = lib_cache::Pipeline::new()
+///     .hset("session", "user", b"1")
+///     .expire("session", Duration::from_secs(60));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    commands: Vec<Command>,
+}
+
+impl Pipeline {
+    /// Create an empty pipeline.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue setting a field of a hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `field` - The field to set.
+    /// * `value` - The value to set.
+    #[must_use]
+    pub fn hset(mut self, key: &str, field: &str, value: &[u8]) -> Self {
+        self.commands.push(Command::HSet {
+            key: key.to_string(),
+            field: field.to_string(),
+            value: value.to_vec(),
+        });
+        self
+    }
+
+    /// Queue setting a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set a value under.
+    /// * `value` - The value to set.
+    /// * `expiry` - The expiry time for the value, if any.
+    #[must_use]
+    pub fn set(mut self, key: &str, value: &[u8], expiry: Option<Duration>) -> Self {
+        self.commands.push(Command::Set {
+            key: key.to_string(),
+            value: value.to_vec(),
+            expiry,
+        });
+        self
+    }
+
+    /// Queue deleting a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to delete.
+    #[must_use]
+    pub fn delete(mut self, key: &str) -> Self {
+        self.commands.push(Command::Delete {
+            key: key.to_string(),
+        });
+        self
+    }
+
+    /// Queue setting the expiry of a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set an expiry on.
+    /// * `expiry` - The expiry time for the key.
+    #[must_use]
+    pub fn expire(mut self, key: &str, expiry: Duration) -> Self {
+        self.commands.push(Command::Expire {
+            key: key.to_string(),
+            expiry,
+        });
+        self
+    }
+
+    /// Split the commands into pipelines by a property of their keys, keeping
+    /// their order within each.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Get the property of a key.
+    pub(crate) fn group_by<K: Ord>(&self, group: impl Fn(&str) -> K) -> BTreeMap<K, Pipeline> {
+        let mut pipelines = BTreeMap::<K, Pipeline>::new();
+        for command in &self.commands {
+            pipelines
+                .entry(group(command.key()))
+                .or_default()
+                .commands
+                .push(command.clone());
+        }
+        pipelines
+    }
+
+    /// Check whether no commands have been queued.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Get the queued commands.
+    pub(crate) fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Encode the commands as the keys and arguments of [`PIPELINE_SCRIPT`],
+    /// for running them atomically where `MULTI` is not available.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - Keys the script uses besides those of the commands, which
+    ///   come first in `KEYS`.
+    pub(crate) fn script_args(&self, mut keys: Vec<String>) -> (Vec<String>, Vec<Vec<u8>>) {
+        let mut args = Vec::new();
+
+        for command in &self.commands {
+            let index = keys
+                .iter()
+                .position(|key| key == command.key())
+                .unwrap_or_else(|| {
+                    keys.push(command.key().to_string());
+                    keys.len() - 1
+                });
+
+            let (name, command_args) = command.redis_args();
+            args.push((command_args.len() + 2).to_string().into_bytes());
+            args.push(name.as_bytes().to_vec());
+            args.push((index + 1).to_string().into_bytes());
+            args.extend(command_args);
+        }
+
+        (keys, args)
+    }
+
+    /// Check the commands can be run, so that a transaction is not rejected
+    /// partway through.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidExpiry`] if a value is set with a zero expiry.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let invalid = self.commands.iter().any(|command| {
+            matches!(command, Command::Set { expiry: Some(expiry), .. } if expiry.is_zero())
+        });
+
+        if invalid {
+            return Err(Error::InvalidExpiry);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use crate::duration::millis;
+use crate::{Error, Result};
+
+/// Admit a request against the quota at `KEYS[1]`, whose window and emission
+/// interval are `ARGV[1]` and `ARGV[2]` milliseconds, using the server's clock
+/// so that every instance agrees on the time.
+///
+/// Returns whether the request was admitted, how many more would be, and the
+/// milliseconds until the quota is full again and until a request would be
+/// admitted.
+pub(crate) const THROTTLE_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local tat = math.max(tonumber(redis.call('GET', KEYS[1]) or now), now)
+local next_tat = tat + interval
+if next_tat > now + window then
+    return {0, 0, tat - now, next_tat - window - now}
+end
+redis.call('SET', KEYS[1], string.format('%d', next_tat), 'PX', next_tat - now)
+return {1, math.floor((now + window - next_tat) / interval), next_tat - now, 0}
+";
+
+/// A limit on how many requests are admitted within a sliding window.
+///
+/// Requests are admitted by the generic cell rate algorithm, which behaves
+/// like a token bucket holding `limit` tokens and refilling one every
+/// `window / limit`: a burst of up to `limit` requests is admitted at once,
+/// after which requests are admitted at the steady rate. Only the time at
+/// which the bucket will be full again is stored, so checking a quota is a
+/// single atomic read and write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    limit: u64,
+    window: u64,
+}
+
+impl Quota {
+    /// Create a new quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The most requests admitted within the window.
+    /// * `window` - The length of the window.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidQuota`] if the limit is zero, or the window
+    /// is shorter than a millisecond per request.
+    pub fn new(limit: u64, window: &Duration) -> Result<Self> {
+        let window = millis(window).map_err(|_| Error::InvalidQuota)?;
+        if limit == 0 || limit > window {
+            return Err(Error::InvalidQuota);
+        }
+
+        Ok(Self { limit, window })
+    }
+
+    /// Get the most requests admitted within the window.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Get the length of the window.
+    #[must_use]
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window)
+    }
+
+    /// Get the length of the window, in milliseconds.
+    pub(crate) fn window_millis(&self) -> u64 {
+        self.window
+    }
+
+    /// Get the time it takes for one more request to be admitted, in
+    /// milliseconds.
+    pub(crate) fn interval_millis(&self) -> u64 {
+        self.window / self.limit
+    }
+
+    /// Admit a request, as [`THROTTLE_SCRIPT`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, in milliseconds.
+    /// * `tat` - The stored time at which the quota will be full again, in
+    ///   milliseconds, if any.
+    ///
+    /// # Returns
+    ///
+    /// The decision, and the time to store if the request was admitted.
+    pub(crate) fn admit(&self, now: u64, tat: Option<u64>) -> (Decision, Option<u64>) {
+        let interval = self.interval_millis();
+        let tat = tat.unwrap_or(now).max(now);
+        let next_tat = tat.saturating_add(interval);
+
+        if next_tat > now.saturating_add(self.window) {
+            let decision =
+                Decision::from_millis(self, false, 0, tat - now, next_tat - self.window - now);
+            return (decision, None);
+        }
+
+        let remaining = (now + self.window - next_tat) / interval;
+        let decision = Decision::from_millis(self, true, remaining, next_tat - now, 0);
+        (decision, Some(next_tat))
+    }
+}
+
+/// Whether a request was admitted by a [`Quota`], and what is left of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether the request was admitted.
+    pub allowed: bool,
+
+    /// The most requests admitted within the window.
+    pub limit: u64,
+
+    /// How many more requests would be admitted right now.
+    pub remaining: u64,
+
+    /// How long until the full quota is available again.
+    pub reset: Duration,
+
+    /// How long until a request would be admitted, which is zero if this one
+    /// was.
+    pub retry_after: Duration,
+}
+
+impl Decision {
+    /// Create a decision from the results of [`THROTTLE_SCRIPT`].
+    ///
+    /// # Arguments
+    ///
+    /// * `quota` - The quota the request was checked against.
+    /// * `allowed` - Whether the request was admitted.
+    /// * `remaining` - How many more requests would be admitted.
+    /// * `reset` - The milliseconds until the full quota is available again.
+    /// * `retry_after` - The milliseconds until a request would be admitted.
+    pub(crate) fn from_millis(
+        quota: &Quota,
+        allowed: bool,
+        remaining: u64,
+        reset: u64,
+        retry_after: u64,
+    ) -> Self {
+        Self {
+            allowed,
+            limit: quota.limit,
+            remaining,
+            reset: Duration::from_millis(reset),
+            retry_after: Duration::from_millis(retry_after),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cache, Interface};
+
+    #[test]
+    fn test_invalid_quota() {
+        assert!(matches!(
+            Quota::new(0, &Duration::from_secs(1)),
+            Err(Error::InvalidQuota)
+        ));
+        assert!(matches!(
+            Quota::new(1, &Duration::ZERO),
+            Err(Error::InvalidQuota)
+        ));
+        assert!(matches!(
+            Quota::new(2000, &Duration::from_secs(1)),
+            Err(Error::InvalidQuota)
+        ));
+    }
+
+    #[test]
+    fn test_admit() {
+        let quota = Quota::new(3, &Duration::from_secs(3)).unwrap();
+
+        // A burst of up to the limit is admitted at once.
+        let mut tat = None;
+        for remaining in [2, 1, 0] {
+            let (decision, next_tat) = quota.admit(0, tat);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+            tat = next_tat;
+        }
+        assert_eq!(tat, Some(3000));
+
+        let (decision, next_tat) = quota.admit(0, tat);
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset, Duration::from_secs(3));
+        assert_eq!(decision.retry_after, Duration::from_secs(1));
+        assert_eq!(next_tat, None);
+
+        // One more request is admitted for each interval that passes.
+        let (decision, next_tat) = quota.admit(1000, tat);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(next_tat, Some(4000));
+
+        // The full quota is available again once the window has passed.
+        let (decision, _) = quota.admit(10_000, next_tat);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 2);
+        assert_eq!(decision.reset, Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let cache = Cache::memory();
+        let quota = Quota::new(2, &Duration::from_mins(1)).unwrap();
+
+        assert!(cache.throttle("a", &quota).await.unwrap().allowed);
+        assert!(cache.throttle("a", &quota).await.unwrap().allowed);
+
+        let decision = cache.throttle("a", &quota).await.unwrap();
+        assert!(!decision.allowed);
+        assert!(decision.retry_after > Duration::from_secs(29));
+
+        // Quotas are kept per key.
+        assert!(cache.throttle("b", &quota).await.unwrap().allowed);
+
+        // The key expires once the quota is full again.
+        let ttl = cache.ttl("a").await.unwrap().unwrap();
+        assert!(ttl <= Duration::from_mins(1));
+    }
+}
@@ -0,0 +1,147 @@
+use redis::Value;
+
+use crate::url::{node_address, NodesUrl};
+use crate::{Error, Result};
+
+/// The kind of connection string that selects Sentinel discovery.
+pub(crate) const KIND: &str = "sentinel";
+
+/// The port of sentinels that do not name one.
+const DEFAULT_PORT: u16 = 26379;
+
+/// Discovers the current master of a Redis deployment managed by Sentinel,
+/// from a connection string such as
+/// `redis+sentinel://:password@host1:26379,host2:26379/mymaster/0`.
+///
+/// The credentials and database apply to the master; the sentinels are
+/// connected to without credentials.
+pub(crate) struct Sentinel {
+    url: NodesUrl,
+    master: String,
+    db: Option<String>,
+}
+
+impl Sentinel {
+    /// Check whether a connection string selects Sentinel discovery.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The connection string.
+    pub(crate) fn is(url: &str) -> bool {
+        NodesUrl::is(url, KIND)
+    }
+
+    /// Parse a `redis+sentinel://` connection string.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The connection string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrl`] if the connection string is invalid or
+    /// does not name the master.
+    pub(crate) fn parse(url: &str) -> Result<Self> {
+        let mut url = NodesUrl::parse(url, KIND, DEFAULT_PORT)?;
+        let mut path = std::mem::take(&mut url.path).into_iter();
+        let master = path.next().ok_or_else(|| {
+            Error::InvalidUrl("a redis+sentinel:// URL must name the master".to_string())
+        })?;
+
+        Ok(Self {
+            url,
+            master,
+            db: path.next(),
+        })
+    }
+
+    /// Ask each sentinel in turn for the address of the master.
+    ///
+    /// # Returns
+    ///
+    /// The URL of the master.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Sentinel`] if no sentinel could name a reachable master.
+    pub(crate) async fn discover(&self) -> Result<String> {
+        let mut errors = Vec::new();
+
+        for sentinel in &self.url.nodes {
+            match self.ask(sentinel).await {
+                Ok(url) => return Ok(url),
+                Err(e) => errors.push(format!("{sentinel}: {e}")),
+            }
+        }
+
+        Err(Error::Sentinel(errors.join("; ")))
+    }
+
+    /// Ask a sentinel for the address of the master, and check the node it
+    /// names is a master.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentinel` - The address of the sentinel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sentinel could not be reached, does not know the
+    /// master, or names a node that is not a master.
+    async fn ask(&self, sentinel: &str) -> Result<String> {
+        let client = redis::Client::open(self.url.anonymous_node_url(sentinel, None))?;
+        let mut connection = client.get_async_connection().await?;
+        let address = redis::cmd("SENTINEL")
+            .arg("get-master-addr-by-name")
+            .arg(&self.master)
+            .query_async::<_, Option<(String, u16)>>(&mut connection)
+            .await?;
+
+        let Some((host, port)) = address else {
+            return Err(Error::Sentinel(format!("unknown master {}", self.master)));
+        };
+        let master = node_address(&host, port);
+        let url = self.url.node_url(&master, self.db.as_deref());
+
+        // A sentinel that has not yet noticed a failover may name a replica.
+        let mut connection = redis::Client::open(url.as_str())?
+            .get_async_connection()
+            .await?;
+        let role = redis::cmd("ROLE")
+            .query_async::<_, Value>(&mut connection)
+            .await?;
+
+        match role {
+            Value::Bulk(role) if matches!(role.first(), Some(Value::Data(role)) if role == b"master") => {
+                Ok(url)
+            }
+            _ => Err(Error::Sentinel(format!("{master} is not a master"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let sentinel = Sentinel::parse("redis+sentinel://:secret@a,b:26380/mymaster/1").unwrap();
+        assert_eq!(sentinel.master, "mymaster");
+        assert_eq!(sentinel.db.as_deref(), Some("1"));
+        assert_eq!(sentinel.url.nodes, ["a:26379", "b:26380"]);
+
+        let sentinel = Sentinel::parse("redis+sentinel://a/mymaster").unwrap();
+        assert_eq!(sentinel.db, None);
+
+        assert!(Sentinel::parse("redis+sentinel://a").is_err());
+        assert!(Sentinel::is("rediss+sentinel://a/mymaster"));
+        assert!(!Sentinel::is("redis://a"));
+    }
+
+    #[tokio::test]
+    async fn test_discover_unreachable() {
+        let sentinel = Sentinel::parse("redis+sentinel://127.0.0.1:1/mymaster").unwrap();
+        assert!(matches!(sentinel.discover().await, Err(Error::Sentinel(_))));
+    }
+}
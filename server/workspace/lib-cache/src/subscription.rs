@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use redis::aio::PubSub;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{Options, Result};
+
+/// The most messages held for a slow subscriber before it misses some.
+pub(crate) const CAPACITY: usize = 1024;
+
+/// How often an idle subscription checks whether it is still wanted.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A message received on a channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message<T = Vec<u8>> {
+    /// A message published to the channel.
+    Payload(T),
+
+    /// Some messages were not received, such as while reconnecting or because
+    /// the subscriber fell behind, so any state kept up to date by the channel
+    /// should be discarded.
+    Missed,
+}
+
+/// A subscription to a channel, which ends when it is dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: broadcast::Receiver<Message>,
+}
+
+impl Subscription {
+    /// Create a new subscription.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - The receiver of the channel's messages.
+    pub(crate) fn new(receiver: broadcast::Receiver<Message>) -> Self {
+        Self { receiver }
+    }
+
+    /// Wait for the next message.
+    ///
+    /// # Returns
+    ///
+    /// The message, or `None` if the channel was closed.
+    pub async fn recv(&mut self) -> Option<Message> {
+        match self.receiver.recv().await {
+            Ok(message) => Some(message),
+            Err(RecvError::Lagged(_)) => Some(Message::Missed),
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
+/// Subscribe to a channel on a Redis node, and subscribe again whenever the
+/// connection is lost until the subscription is dropped.
+///
+/// # Arguments
+///
+/// * `channel` - The channel to subscribe to.
+/// * `options` - The delays between attempts to subscribe again.
+/// * `locate` - Get the URL of the node to subscribe on, which is asked again
+///   before each attempt to subscribe.
+///
+/// # Errors
+///
+/// Returns an error if the first attempt to subscribe failed.
+pub(crate) async fn subscribe<F>(
+    channel: &str,
+    options: &Options,
+    locate: F,
+) -> Result<Subscription>
+where
+    F: Fn() -> BoxFuture<'static, Result<String>> + Send + Sync + 'static,
+{
+    let pubsub = connect(&locate().await?, channel).await?;
+    let (sender, receiver) = broadcast::channel(CAPACITY);
+
+    tokio::spawn(forward(
+        channel.to_string(),
+        options.clone(),
+        pubsub,
+        sender,
+        locate,
+    ));
+
+    Ok(Subscription::new(receiver))
+}
+
+/// Connect to a node and subscribe to a channel.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the node.
+/// * `channel` - The channel to subscribe to.
+///
+/// # Errors
+///
+/// Returns an error if the node could not be reached.
+async fn connect(url: &str, channel: &str) -> Result<PubSub> {
+    let client = redis::Client::open(url)?;
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(channel).await?;
+    Ok(pubsub)
+}
+
+/// Forward messages to the subscribers, subscribing again after the connection
+/// is lost, until every subscriber has gone.
+async fn forward<F>(
+    channel: String,
+    options: Options,
+    mut pubsub: PubSub,
+    sender: broadcast::Sender<Message>,
+    locate: F,
+) where
+    F: Fn() -> BoxFuture<'static, Result<String>> + Send + Sync + 'static,
+{
+    loop {
+        receive(&mut pubsub, &sender).await;
+
+        let mut delay = options.min_retry_delay();
+        pubsub = loop {
+            if sender.receiver_count() == 0 {
+                return;
+            }
+
+            tokio::time::sleep(delay).await;
+
+            let url = match locate().await {
+                Ok(url) => url,
+                Err(e) => {
+                    log::warn!("Failed to locate a node to subscribe to {channel} on: {e}");
+                    delay = options.next_retry_delay(delay);
+                    continue;
+                }
+            };
+
+            match connect(&url, &channel).await {
+                Ok(pubsub) => break pubsub,
+                Err(e) => {
+                    log::warn!("Failed to subscribe to {channel} again: {e}");
+                    delay = options.next_retry_delay(delay);
+                }
+            }
+        };
+
+        // Anything published while disconnected was lost.
+        let _ = sender.send(Message::Missed);
+    }
+}
+
+/// Forward messages to the subscribers until the connection is lost or every
+/// subscriber has gone.
+async fn receive(pubsub: &mut PubSub, sender: &broadcast::Sender<Message>) {
+    let mut messages = pubsub.on_message();
+
+    loop {
+        match tokio::time::timeout(IDLE_CHECK_INTERVAL, messages.next()).await {
+            Ok(Some(message)) => {
+                let payload = message.get_payload_bytes().to_vec();
+                if sender.send(Message::Payload(payload)).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(_) if sender.receiver_count() == 0 => return,
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::{hash_tagged, Interface, Message, Pipeline, Quota};
+
+/// How long to wait for a spawned server to accept connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A `redis-server` process spawned for a test, killed when dropped.
+pub(crate) struct Server {
+    pub(crate) port: u16,
+    child: Child,
+}
+
+impl Server {
+    /// Spawn a Redis node that keeps nothing on disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Extra arguments for `redis-server`.
+    pub(crate) fn redis(args: &[&str]) -> Self {
+        let port = free_port();
+        let dir = scratch_dir(port);
+        let mut command = Command::new("redis-server");
+        command
+            .args([
+                "--port",
+                &port.to_string(),
+                "--save",
+                "",
+                "--appendonly",
+                "no",
+            ])
+            .arg("--dir")
+            .arg(&dir)
+            .args(args);
+
+        Self::spawn(command, port)
+    }
+
+    /// Spawn a sentinel monitoring a master.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_port` - The port of the master.
+    /// * `master` - The name to monitor the master under.
+    pub(crate) fn sentinel(master_port: u16, master: &str) -> Self {
+        let port = free_port();
+        let config = scratch_dir(port).join("sentinel.conf");
+        std::fs::write(
+            &config,
+            format!("port {port}\nsentinel monitor {master} 127.0.0.1 {master_port} 1\n"),
+        )
+        .unwrap();
+
+        let mut command = Command::new("redis-server");
+        command.arg(&config).arg("--sentinel");
+
+        Self::spawn(command, port)
+    }
+
+    /// Spawn three nodes and join them into a cluster.
+    pub(crate) fn cluster() -> Vec<Self> {
+        let nodes = (0..3)
+            .map(|_| Self::redis(&["--cluster-enabled", "yes"]))
+            .collect::<Vec<_>>();
+
+        let status = Command::new("redis-cli")
+            .args(["--cluster", "create"])
+            .args(nodes.iter().map(|node| format!("127.0.0.1:{}", node.port)))
+            .arg("--cluster-yes")
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let started = Instant::now();
+        while started.elapsed() < STARTUP_TIMEOUT {
+            let info = Command::new("redis-cli")
+                .args(["-p", &nodes[0].port.to_string(), "cluster", "info"])
+                .output()
+                .unwrap();
+            if String::from_utf8_lossy(&info.stdout).contains("cluster_state:ok") {
+                return nodes;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        panic!("the cluster did not become ready");
+    }
+
+    /// Spawn a server, and wait until it accepts connections.
+    fn spawn(mut command: Command, port: u16) -> Self {
+        let child = command.stdout(Stdio::null()).spawn().unwrap();
+        let server = Self { port, child };
+
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                started.elapsed() < STARTUP_TIMEOUT,
+                "the server did not start"
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        server
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(scratch_dir(self.port));
+    }
+}
+
+/// Find a port that is not in use.
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// Create a directory for a server's files.
+fn scratch_dir(port: u16) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lib-cache-test-{port}"));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Exercise every operation of a cache backend.
+pub(crate) async fn exercise(cache: &impl Interface) {
+    let string = hash_tagged("test", "string");
+    let hash = hash_tagged("test", "hash");
+
+    cache
+        .set(&string, b"value", Some(&Duration::from_mins(1)))
+        .await
+        .unwrap();
+    assert_eq!(cache.get(&string).await.unwrap(), Some(b"value".to_vec()));
+    assert!(cache.ttl(&string).await.unwrap().is_some());
+
+    cache
+        .transaction(
+            &Pipeline::new()
+                .hset(&hash, "a", b"1")
+                .hset(&hash, "b", b"2")
+                .expire(&hash, Duration::from_mins(1))
+                .delete(&string),
+        )
+        .await
+        .unwrap();
+    assert_eq!(cache.hget(&hash, "b").await.unwrap(), Some(b"2".to_vec()));
+    assert!(!cache.exists(&string).await.unwrap());
+
+    cache.hset(&hash, "c", b"3").await.unwrap();
+    cache.expire(&hash, &Duration::from_mins(2)).await.unwrap();
+    assert!(cache.ttl(&hash).await.unwrap().unwrap() > Duration::from_mins(1));
+    assert_eq!(cache.scan("{test}:*").await.unwrap(), vec![hash.clone()]);
+
+    // On a cluster, the untagged key is in another slot than the hash.
+    let other = "test-take";
+    cache.set(&string, b"value", None).await.unwrap();
+    let then = Pipeline::new()
+        .delete(&string)
+        .set(other, b"value", Some(Duration::from_mins(1)));
+    let fields = cache.take(&hash, &then).await.unwrap().unwrap();
+    assert_eq!(fields.len(), 3);
+    assert!(!cache.exists(&string).await.unwrap());
+    assert_eq!(cache.get(other).await.unwrap(), Some(b"value".to_vec()));
+    cache.delete(other).await.unwrap();
+    assert!(cache.take(&hash, &then).await.unwrap().is_none());
+    assert!(!cache.exists(other).await.unwrap());
+
+    cache.set(&string, b"value", None).await.unwrap();
+    cache.delete(&string).await.unwrap();
+    assert_eq!(cache.get(&string).await.unwrap(), None);
+
+    let lease = Duration::from_millis(1500);
+    assert!(cache.set_if_absent(&string, b"a", &lease).await.unwrap());
+    assert!(!cache.set_if_absent(&string, b"b", &lease).await.unwrap());
+    assert!(!cache.expire_if_equal(&string, b"b", &lease).await.unwrap());
+    assert!(cache.expire_if_equal(&string, b"a", &lease).await.unwrap());
+    assert!(!cache.delete_if_equal(&string, b"b").await.unwrap());
+    assert!(cache.delete_if_equal(&string, b"a").await.unwrap());
+
+    let short = Duration::from_millis(300);
+    cache.set(&string, b"value", Some(&short)).await.unwrap();
+    let ttl = cache.ttl(&string).await.unwrap().unwrap();
+    assert!(ttl > Duration::ZERO && ttl <= short);
+    cache
+        .transaction(&Pipeline::new().hset(&hash, "a", b"1").expire(&hash, short))
+        .await
+        .unwrap();
+    assert!(cache.ttl(&hash).await.unwrap().unwrap() <= short);
+    tokio::time::sleep(short * 2).await;
+    assert!(!cache.exists(&string).await.unwrap());
+    assert!(!cache.exists(&hash).await.unwrap());
+
+    let counter = hash_tagged("test", "counter");
+    assert_eq!(cache.advance(&counter, 0).await.unwrap(), 1);
+    assert_eq!(cache.advance(&counter, 5).await.unwrap(), 5);
+    assert_eq!(cache.advance(&counter, 0).await.unwrap(), 6);
+    cache.delete(&counter).await.unwrap();
+
+    cache.ping().await.unwrap();
+
+    let quota = Quota::new(2, &Duration::from_mins(1)).unwrap();
+    let throttled = hash_tagged("test", "throttled");
+    assert_eq!(
+        cache.throttle(&throttled, &quota).await.unwrap().remaining,
+        1
+    );
+    assert_eq!(
+        cache.throttle(&throttled, &quota).await.unwrap().remaining,
+        0
+    );
+    let decision = cache.throttle(&throttled, &quota).await.unwrap();
+    assert!(!decision.allowed);
+    assert!(decision.retry_after > Duration::from_secs(29));
+    assert!(cache.ttl(&throttled).await.unwrap().is_some());
+    cache.delete(&throttled).await.unwrap();
+
+    let mut subscription = cache.subscribe("test:channel").await.unwrap();
+    cache.publish("test:channel", b"message").await.unwrap();
+    let message = tokio::time::timeout(STARTUP_TIMEOUT, subscription.recv()).await;
+    assert_eq!(
+        message.unwrap(),
+        Some(Message::Payload(b"message".to_vec()))
+    );
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::envelope::{open, seal};
+use crate::{Codec, Decision, Interface, Json, Pipeline, Quota, Result, Subscription, Versioned};
+
+/// A cache that also stores typed values, encoded with a codec inside
+/// versioned envelopes.
+///
+/// The typed methods are the counterparts of the [`Interface`] methods of the
+/// same names without the `_typed` suffix. Values written through the
+/// [`Interface`] directly, such as with a [`Pipeline`], can be encoded with
+/// [`Typed::encode`].
+///
+/// # Examples
+///
+/// ```
+/// # async fn example() -> lib_cache::Result<()> {
+/// use lib_cache::{Cache, Typed, Versioned};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Answer(u32);
+///
+/// impl Versioned for Answer {
+///     const VERSION: u32 = 1;
+/// }
+///
+/// let cache = Typed::new(Cache::memory());
+/// cache.set_typed("answer", &Answer(42), None).await?;
+/// assert_eq!(cache.get_typed("answer").await?, Some(Answer(42)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Typed<C, K = Json> {
+    cache: C,
+    codec: K,
+}
+
+impl<C: Interface> Typed<C> {
+    /// Create a typed cache that encodes values as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to store values in.
+    #[must_use]
+    pub fn new(cache: C) -> Self {
+        Self { cache, codec: Json }
+    }
+}
+
+impl<C: Interface, K: Codec> Typed<C, K> {
+    /// Create a typed cache that encodes values with the given codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The cache to store values in.
+    /// * `codec` - The codec to encode values with.
+    #[must_use]
+    pub fn with_codec(cache: C, codec: K) -> Self {
+        Self { cache, codec }
+    }
+
+    /// Encode a value in a versioned envelope.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to encode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be encoded.
+    pub fn encode<T: Versioned>(&self, value: &T) -> Result<Vec<u8>> {
+        seal(&self.codec, value)
+    }
+
+    /// Decode a value from a versioned envelope.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The envelope.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not an envelope, or the value could
+    /// not be decoded or upgraded from the version it was written in.
+    pub fn decode<T: Versioned>(&self, bytes: &[u8]) -> Result<T> {
+        open(&self.codec, bytes)
+    }
+
+    /// Set a field of a hash to a typed value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `field` - The field to set.
+    /// * `value` - The value to set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be encoded or set.
+    pub async fn hset_typed<T: Versioned + Sync>(
+        &self,
+        key: &str,
+        field: &str,
+        value: &T,
+    ) -> Result<()> {
+        let value = self.encode(value)?;
+        self.cache.hset(key, field, &value).await
+    }
+
+    /// Get a typed value from a field of a hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the hash.
+    /// * `field` - The field to get.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the key or field does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be retrieved or decoded.
+    pub async fn hget_typed<T: Versioned>(&self, key: &str, field: &str) -> Result<Option<T>> {
+        self.cache
+            .hget(key, field)
+            .await?
+            .map(|value| self.decode(&value))
+            .transpose()
+    }
+
+    /// Set a typed value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to set a value under.
+    /// * `value` - The value to set.
+    /// * `expiry` - The expiry time for the value, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be encoded or set.
+    pub async fn set_typed<T: Versioned + Sync>(
+        &self,
+        key: &str,
+        value: &T,
+        expiry: Option<&Duration>,
+    ) -> Result<()> {
+        let value = self.encode(value)?;
+        self.cache.set(key, &value, expiry).await
+    }
+
+    /// Get a typed value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to get a value from.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if the key does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be retrieved or decoded.
+    pub async fn get_typed<T: Versioned>(&self, key: &str) -> Result<Option<T>> {
+        self.cache
+            .get(key)
+            .await?
+            .map(|value| self.decode(&value))
+            .transpose()
+    }
+}
+
+#[async_trait]
+impl<C: Interface, K: Codec> Interface for Typed<C, K> {
+    async fn hset(&self, key: &str, field: &str, value: &[u8]) -> Result<()> {
+        self.cache.hset(key, field, value).await
+    }
+
+    async fn hget(&self, key: &str, field: &str) -> Result<Option<Vec<u8>>> {
+        self.cache.hget(key, field).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8], expiry: Option<&Duration>) -> Result<()> {
+        self.cache.set(key, value, expiry).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.cache.get(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        self.cache.exists(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.cache.delete(key).await
+    }
+
+    async fn expire(&self, key: &str, expiry: &Duration) -> Result<()> {
+        self.cache.expire(key, expiry).await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        self.cache.ttl(key).await
+    }
+
+    async fn scan(&self, pattern: &str) -> Result<Vec<String>> {
+        self.cache.scan(pattern).await
+    }
+
+    async fn transaction(&self, pipeline: &Pipeline) -> Result<()> {
+        self.cache.transaction(pipeline).await
+    }
+
+    async fn take(&self, key: &str, then: &Pipeline) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        self.cache.take(key, then).await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.cache.set_if_absent(key, value, expiry).await
+    }
+
+    async fn expire_if_equal(&self, key: &str, value: &[u8], expiry: &Duration) -> Result<bool> {
+        self.cache.expire_if_equal(key, value, expiry).await
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &[u8]) -> Result<bool> {
+        self.cache.delete_if_equal(key, value).await
+    }
+
+    async fn advance(&self, key: &str, floor: u64) -> Result<u64> {
+        self.cache.advance(key, floor).await
+    }
+
+    async fn throttle(&self, key: &str, quota: &Quota) -> Result<Decision> {
+        self.cache.throttle(key, quota).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.cache.ping().await
+    }
+
+    async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        self.cache.publish(channel, message).await
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        self.cache.subscribe(channel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Error, Memory, MessagePack};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u64,
+        tags: Vec<String>,
+    }
+
+    impl Versioned for Record {
+        const VERSION: u32 = 1;
+    }
+
+    fn record() -> Record {
+        Record {
+            id: 7,
+            tags: vec!["a".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_set_typed() {
+        let cache = Typed::new(Memory::default());
+
+        assert_eq!(cache.get_typed::<Record>("key").await.unwrap(), None);
+        cache.set_typed("key", &record(), None).await.unwrap();
+        assert_eq!(cache.get_typed("key").await.unwrap(), Some(record()));
+
+        // Values are stored in an envelope, not bare.
+        cache
+            .set("bare", b"{\"id\":7,\"tags\":[]}", None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            cache.get_typed::<Record>("bare").await,
+            Err(Error::InvalidEnvelope)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hget_hset_typed() {
+        let cache = Typed::with_codec(Memory::default(), MessagePack);
+
+        cache.hset_typed("key", "field", &record()).await.unwrap();
+        assert_eq!(
+            cache.hget_typed("key", "field").await.unwrap(),
+            Some(record())
+        );
+        assert_eq!(
+            cache.hget_typed::<Record>("key", "other").await.unwrap(),
+            None
+        );
+
+        // The codec is not recorded, so values must be read with the same one.
+        let json = Typed::new(cache.cache.clone());
+        assert!(json.hget_typed::<Record>("key", "field").await.is_err());
+    }
+}
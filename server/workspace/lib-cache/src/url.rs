@@ -0,0 +1,185 @@
+use crate::{Error, Result};
+
+/// A connection string naming several nodes, such as
+/// `redis+sentinel://:password@host1:26379,host2:26379/mymaster/0`.
+///
+/// The scheme is `redis+<kind>`, or `rediss+<kind>` for TLS. Nodes without a
+/// port use the default port of their kind.
+pub(crate) struct NodesUrl {
+    tls: bool,
+    userinfo: Option<String>,
+    pub(crate) nodes: Vec<String>,
+    pub(crate) path: Vec<String>,
+}
+
+impl NodesUrl {
+    /// Check whether a connection string is of the given kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The connection string.
+    /// * `kind` - The kind of connection string, such as `sentinel`.
+    pub(crate) fn is(url: &str, kind: &str) -> bool {
+        matches!(
+            url.split_once("://")
+                .and_then(|(scheme, _)| scheme.split_once('+')),
+            Some(("redis" | "rediss", scheme_kind)) if scheme_kind == kind
+        )
+    }
+
+    /// Parse a connection string of the given kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The connection string.
+    /// * `kind` - The kind of connection string, such as `sentinel`.
+    /// * `default_port` - The port of nodes that do not name one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrl`] if the connection string is not of the
+    /// given kind, or names no nodes.
+    pub(crate) fn parse(url: &str, kind: &str, default_port: u16) -> Result<Self> {
+        let invalid = || Error::InvalidUrl(format!("expected a redis+{kind}:// URL"));
+
+        let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+        let tls = match scheme.split_once('+') {
+            Some(("redis", scheme_kind)) if scheme_kind == kind => false,
+            Some(("rediss", scheme_kind)) if scheme_kind == kind => true,
+            _ => return Err(invalid()),
+        };
+
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (userinfo, hosts) = match authority.rsplit_once('@') {
+            Some((userinfo, hosts)) => (Some(userinfo.to_string()), hosts),
+            None => (None, authority),
+        };
+
+        let nodes = hosts
+            .split(',')
+            .filter(|host| !host.is_empty())
+            .map(|host| match host.rsplit_once(':') {
+                Some((_, port)) if !port.ends_with(']') => host.to_string(),
+                _ => format!("{host}:{default_port}"),
+            })
+            .collect::<Vec<_>>();
+
+        if nodes.is_empty() {
+            return Err(Error::InvalidUrl(format!(
+                "a redis+{kind}:// URL must name at least one node"
+            )));
+        }
+
+        let path = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(Self {
+            tls,
+            userinfo,
+            nodes,
+            path,
+        })
+    }
+
+    /// Check whether the nodes are connected to over TLS.
+    pub(crate) fn tls(&self) -> bool {
+        self.tls
+    }
+
+    /// Get the URL of a single node, with the connection string's credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The address of the node, as `host:port`.
+    /// * `db` - The database to select, if any.
+    pub(crate) fn node_url(&self, node: &str, db: Option<&str>) -> String {
+        let userinfo = self
+            .userinfo
+            .as_ref()
+            .map(|userinfo| format!("{userinfo}@"))
+            .unwrap_or_default();
+
+        self.anonymous_node_url(&format!("{userinfo}{node}"), db)
+    }
+
+    /// Get the URL of a single node, without the connection string's credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The address of the node, as `host:port`.
+    /// * `db` - The database to select, if any.
+    pub(crate) fn anonymous_node_url(&self, node: &str, db: Option<&str>) -> String {
+        let scheme = if self.tls { "rediss" } else { "redis" };
+        let db = db.map(|db| format!("/{db}")).unwrap_or_default();
+
+        format!("{scheme}://{node}{db}")
+    }
+}
+
+/// Format a host and port as a node address, bracketing IPv6 hosts.
+///
+/// # Arguments
+///
+/// * `host` - The host.
+/// * `port` - The port.
+pub(crate) fn node_address(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let url = NodesUrl::parse(
+            "redis+sentinel://:secret@one,two:26380,[::1]:26381/mymaster/2",
+            "sentinel",
+            26379,
+        )
+        .unwrap();
+
+        assert!(!url.tls());
+        assert_eq!(url.nodes, ["one:26379", "two:26380", "[::1]:26381"]);
+        assert_eq!(url.path, ["mymaster", "2"]);
+        assert_eq!(
+            url.node_url("three:6379", Some("2")),
+            "redis://:secret@three:6379/2"
+        );
+        assert_eq!(
+            url.anonymous_node_url("one:26379", None),
+            "redis://one:26379"
+        );
+
+        let url = NodesUrl::parse("rediss+cluster://a,b", "cluster", 6379).unwrap();
+        assert!(url.tls());
+        assert_eq!(url.nodes, ["a:6379", "b:6379"]);
+        assert!(url.path.is_empty());
+        assert_eq!(url.node_url("a:6379", None), "rediss://a:6379");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(NodesUrl::parse("redis://cache", "cluster", 6379).is_err());
+        assert!(NodesUrl::parse("redis+sentinel://a/m", "cluster", 6379).is_err());
+        assert!(NodesUrl::parse("redis+cluster://", "cluster", 6379).is_err());
+        assert!(NodesUrl::parse("redis+cluster://:pw@/", "cluster", 6379).is_err());
+
+        assert!(NodesUrl::is("rediss+cluster://a", "cluster"));
+        assert!(!NodesUrl::is("redis://a", "cluster"));
+        assert!(!NodesUrl::is("memory://", "cluster"));
+    }
+
+    #[test]
+    fn test_node_address() {
+        assert_eq!(node_address("10.0.0.1", 6379), "10.0.0.1:6379");
+        assert_eq!(node_address("::1", 6379), "[::1]:6379");
+    }
+}
@@ -1,6 +1,7 @@
-use aead::{Aead, AeadCore, Nonce};
+use aead::{Aead, AeadCore, Nonce, Payload};
 use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
 
+use crate::kdf::hkdf_sha256;
 use crate::rand::crypto_rng;
 use crate::{Error, Result, Sha256Hash};
 
@@ -40,6 +41,23 @@ impl Key {
         Ok(Self(key))
     }
 
+    /// Derive a subkey for a single purpose, such as signing share links, using
+    /// HKDF-SHA256. Subkeys for different purposes are unrelated, so one cannot
+    /// be used to recover this key or any other subkey.
+    ///
+    /// # Arguments
+    ///
+    /// * `purpose` - name of the purpose, unique within the application
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if key derivation fails.
+    pub fn derive(&self, purpose: &str) -> Result<Self> {
+        let mut key = [0u8; KEY_LEN];
+        hkdf_sha256(&self.0, None, purpose.as_bytes(), &mut key)?;
+        Ok(Self(key))
+    }
+
     /// Encrypt plaintext using `XChaCha20Poly1305`. The nonce is generated
     /// automatically and prepended to the ciphertext.
     ///
@@ -52,11 +70,33 @@ impl Key {
     ///
     /// Returns [`Error::Unspecified`] if encryption fails.
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_with_aad(plaintext, &[])
+    }
+
+    /// Encrypt plaintext using `XChaCha20Poly1305`, authenticating the given
+    /// associated data along with it. The same associated data must be passed
+    /// to [`Key::decrypt_with_aad`].
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - plaintext to encrypt
+    /// * `aad` - associated data to authenticate, but not encrypt
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unspecified`] if encryption fails.
+    pub fn encrypt_with_aad(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let cipher = XChaCha20Poly1305::new(&self.0.into());
         let nonce = XChaCha20Poly1305::generate_nonce(&mut crypto_rng()); // 192-bits; unique per message
 
         let result = cipher
-            .encrypt(&nonce, plaintext)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
             .map_err(|e| Error::Unspecified(format!("{e:?}")))?;
 
         let result = {
@@ -80,6 +120,21 @@ impl Key {
     ///
     /// Returns [`Error::Unspecified`] if decryption fails.
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt_with_aad(ciphertext, &[])
+    }
+
+    /// Decrypt ciphertext produced by [`Key::encrypt_with_aad`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - ciphertext to decrypt
+    /// * `aad` - associated data the ciphertext was encrypted with
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unspecified`] if decryption fails, including when the
+    /// associated data does not match.
+    pub fn decrypt_with_aad(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let nonce = ciphertext
             .get(..NONCE_LEN)
             .ok_or_else(|| Error::Unspecified("ciphertext is too short".to_string()))?;
@@ -91,7 +146,13 @@ impl Key {
         let nonce = Nonce::<XChaCha20Poly1305>::from_slice(nonce);
 
         cipher
-            .decrypt(nonce, ciphertext)
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
             .map_err(|e| Error::Unspecified(e.to_string()))
     }
 }
@@ -138,6 +199,30 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_encrypt_decrypt_with_aad() {
+        let key = Key::generate().unwrap();
+        let plaintext = b"Hello, world!";
+
+        let ciphertext = key.encrypt_with_aad(plaintext, b"row 1").unwrap();
+        assert_eq!(
+            key.decrypt_with_aad(&ciphertext, b"row 1").unwrap(),
+            plaintext
+        );
+        assert!(key.decrypt_with_aad(&ciphertext, b"row 2").is_err());
+        assert!(key.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_derive() {
+        let key = Key::generate().unwrap();
+
+        let webhooks = key.derive("webhooks").unwrap();
+        assert_eq!(webhooks, key.derive("webhooks").unwrap());
+        assert_ne!(webhooks, key.derive("share-links").unwrap());
+        assert_ne!(webhooks, key);
+    }
+
     proptest! {
         #[test]
         fn test_encrypt_decrypt_prop(plaintext in ".*") {
@@ -16,4 +16,16 @@ pub enum Error {
 
     #[error("invalid password hash: {0}")]
     InvalidPasswordHash(String),
+
+    #[error("invalid keyring: {0}")]
+    InvalidKeyring(String),
+
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    #[error("invalid encrypted stream: {0}")]
+    InvalidStream(String),
+
+    #[error("unknown key ID: {0}")]
+    UnknownKeyId(u32),
 }
@@ -0,0 +1,87 @@
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::{Error, Result};
+
+/// Derive key material with HKDF-SHA256, as specified by RFC 5869.
+///
+/// # Arguments
+///
+/// * `ikm` - input keying material, such as a master key
+/// * `salt` - optional non-secret random value
+/// * `info` - context that binds the output to a purpose, so different purposes get unrelated keys
+/// * `okm` - buffer to fill with the derived key material
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidKeyLength`] if `okm` is longer than 8160 bytes.
+pub fn hkdf_sha256(ikm: &[u8], salt: Option<&[u8]>, info: &[u8], okm: &mut [u8]) -> Result<()> {
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, okm)
+        .map_err(|_| Error::InvalidKeyLength(okm.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mac::hex;
+
+    use super::*;
+
+    /// Check a SHA-256 test case from RFC 5869.
+    fn check(ikm: &[u8], salt: &[u8], info: &[u8], expected: &str) {
+        let expected = hex(expected);
+        let mut okm = vec![0; expected.len()];
+        hkdf_sha256(ikm, Some(salt), info, &mut okm).unwrap();
+        assert_eq!(okm, expected);
+    }
+
+    #[test]
+    fn test_rfc5869_case_1() {
+        check(
+            &[0x0b; 22],
+            &hex("000102030405060708090a0b0c"),
+            &hex("f0f1f2f3f4f5f6f7f8f9"),
+            concat!(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+                "34007208d5b887185865",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc5869_case_2() {
+        let ikm = (0x00..=0x4f).collect::<Vec<u8>>();
+        let salt = (0x60..=0xaf).collect::<Vec<u8>>();
+        let info = (0xb0..=0xff).collect::<Vec<u8>>();
+
+        check(
+            &ikm,
+            &salt,
+            &info,
+            concat!(
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c",
+                "59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71",
+                "cc30c58179ec3e87c14c01d5c1f3434f1d87",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc5869_case_3() {
+        check(
+            &[0x0b; 22],
+            &[],
+            &[],
+            concat!(
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d",
+                "9d201395faa4b61a96c8",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_output_too_long() {
+        let mut okm = vec![0; 255 * 32 + 1];
+        assert!(hkdf_sha256(b"key", None, b"", &mut okm).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+
+use crate::{fill_bytes, EncryptionKey, Error, Result};
+
+/// The length of the key ID prefixed to each ciphertext.
+const KEY_ID_LEN: usize = 4;
+
+/// The length of the random secrets generated for new keys.
+const SECRET_LEN: usize = 32;
+
+/// Identifies a key within a [`Keyring`].
+pub type KeyId = u32;
+
+/// A set of encryption keys, each identified by a [`KeyId`].
+///
+/// Data is always encrypted with the primary key, which is the key with the
+/// highest ID, and the ID is prefixed to the ciphertext so it can be decrypted
+/// with the right key after the keyring has been rotated.
+///
+/// # File format
+///
+/// A keyring file holds one key per line, as `<id>:<secret>`. The secret can
+/// be any value of any length, and is hashed into the key. Empty lines and
+/// lines starting with `#` are ignored.
+///
+/// A file holding a single line that is not `<id>:<secret>` is a key file from
+/// before keyrings were introduced, and its line is the secret of key 1.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Keyring {
+    secrets: BTreeMap<KeyId, String>,
+    keys: BTreeMap<KeyId, EncryptionKey>,
+}
+
+impl Keyring {
+    /// Load the keyring from a file that must already hold at least one key.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the keyring file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file could not be read, or
+    /// [`Error::InvalidKeyring`] if it is malformed or holds no keys.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let keyring = Self::parse(&fs::read_to_string(path)?)?;
+        if keyring.keys.is_empty() {
+            return Err(Error::InvalidKeyring("the keyring is empty".to_string()));
+        }
+
+        Ok(keyring)
+    }
+
+    /// Load the keyring from a file, creating it with a newly generated key
+    /// if the file does not exist or is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the keyring file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file could not be read or written, or
+    /// [`Error::InvalidKeyring`] if it is malformed.
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut keyring = Self::parse(&contents)?;
+        if keyring.keys.is_empty() {
+            keyring.rotate()?;
+            keyring.save(path)?;
+        }
+
+        Ok(keyring)
+    }
+
+    /// Parse a keyring from the contents of a keyring file.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - contents of the keyring file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyring`] if a line of a multi-line file is
+    /// malformed or an ID is used more than once.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut keyring = Self {
+            secrets: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        };
+
+        let lines = contents
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+
+        if let [(_, line)] = lines[..] {
+            if parse_line(line).is_none() {
+                keyring.insert(1, line.to_string());
+                return Ok(keyring);
+            }
+        }
+
+        for (number, line) in lines {
+            let (id, secret) = parse_line(line).ok_or_else(|| {
+                Error::InvalidKeyring(format!("line {} is not `<id>:<secret>`", number + 1))
+            })?;
+
+            if keyring.secrets.contains_key(&id) {
+                return Err(Error::InvalidKeyring(format!("key {id} is defined twice")));
+            }
+            keyring.insert(id, secret.to_string());
+        }
+
+        Ok(keyring)
+    }
+
+    /// Write the keyring to a file, replacing it atomically. On unix the file
+    /// is only readable by its owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the keyring file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file could not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(self.contents().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Generate a new key and make it the primary key. Existing keys are kept
+    /// so data encrypted with them can still be decrypted.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new primary key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key could not be generated.
+    pub fn rotate(&mut self) -> Result<KeyId> {
+        let id = match self.primary_id() {
+            Some(id) => id
+                .checked_add(1)
+                .ok_or_else(|| Error::InvalidKeyring("no key IDs are left".to_string()))?,
+            None => 1,
+        };
+
+        let mut secret = [0u8; SECRET_LEN];
+        fill_bytes(&mut secret)?;
+        let secret = secret.iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        });
+
+        self.insert(id, secret);
+
+        Ok(id)
+    }
+
+    /// Get the ID of the key new data is encrypted with.
+    #[must_use]
+    pub fn primary_id(&self) -> Option<KeyId> {
+        self.keys.keys().next_back().copied()
+    }
+
+    /// Get the key with the given ID.
+    pub(crate) fn key(&self, id: KeyId) -> Option<&EncryptionKey> {
+        self.keys.get(&id)
+    }
+
+    /// Get the IDs of all keys in the keyring, in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = KeyId> + '_ {
+        self.keys.keys().copied()
+    }
+
+    /// Encrypt plaintext with the primary key, prefixing the ciphertext with
+    /// the key's ID. The ID is authenticated along with the associated data.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - plaintext to encrypt
+    /// * `aad` - associated data to authenticate, but not encrypt
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyring`] if the keyring is empty, or
+    /// [`Error::Unspecified`] if encryption fails.
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let (id, key) = self
+            .keys
+            .iter()
+            .next_back()
+            .ok_or_else(|| Error::InvalidKeyring("the keyring is empty".to_string()))?;
+
+        let mut result = id.to_be_bytes().to_vec();
+        result.extend(key.encrypt_with_aad(plaintext, &with_key_id(*id, aad))?);
+
+        Ok(result)
+    }
+
+    /// Decrypt ciphertext produced by [`Keyring::encrypt`], using the key
+    /// named by its prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - ciphertext to decrypt
+    /// * `aad` - associated data the ciphertext was encrypted with
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownKeyId`] if the key is not in the keyring, or
+    /// [`Error::Unspecified`] if decryption fails.
+    pub fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let id = key_id(ciphertext)?;
+        let key = self.keys.get(&id).ok_or(Error::UnknownKeyId(id))?;
+
+        key.decrypt_with_aad(&ciphertext[KEY_ID_LEN..], &with_key_id(id, aad))
+    }
+
+    /// Re-encrypt ciphertext with the primary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - ciphertext produced by [`Keyring::encrypt`]
+    /// * `aad` - associated data the ciphertext was encrypted with
+    ///
+    /// # Returns
+    ///
+    /// `None` if the ciphertext is already encrypted with the primary key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ciphertext could not be decrypted or encrypted.
+    pub fn reencrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Option<Vec<u8>>> {
+        if Some(key_id(ciphertext)?) == self.primary_id() {
+            return Ok(None);
+        }
+
+        let plaintext = self.decrypt(ciphertext, aad)?;
+        self.encrypt(&plaintext, aad).map(Some)
+    }
+
+    /// Format the keyring as the contents of a keyring file.
+    fn contents(&self) -> String {
+        self.secrets
+            .iter()
+            .fold(String::new(), |mut contents, (id, secret)| {
+                let _ = writeln!(contents, "{id}:{secret}");
+                contents
+            })
+    }
+
+    fn insert(&mut self, id: KeyId, secret: String) {
+        self.keys
+            .insert(id, EncryptionKey::hash_from(secret.as_bytes()));
+        self.secrets.insert(id, secret);
+    }
+}
+
+/// Split a keyring line into its key ID and secret.
+fn parse_line(line: &str) -> Option<(KeyId, &str)> {
+    line.split_once(':')
+        .and_then(|(id, secret)| Some((id.trim().parse().ok()?, secret.trim())))
+        .filter(|(_, secret)| !secret.is_empty())
+}
+
+/// Get the ID of the key that encrypted the given ciphertext.
+///
+/// # Arguments
+///
+/// * `ciphertext` - ciphertext produced by [`Keyring::encrypt`]
+///
+/// # Errors
+///
+/// Returns [`Error::Unspecified`] if the ciphertext is too short.
+pub fn key_id(ciphertext: &[u8]) -> Result<KeyId> {
+    let id = ciphertext
+        .get(..KEY_ID_LEN)
+        .and_then(|id| id.try_into().ok())
+        .ok_or_else(|| Error::Unspecified("ciphertext is too short".to_string()))?;
+
+    Ok(KeyId::from_be_bytes(id))
+}
+
+/// Prefix associated data with a key ID, so the ID cannot be changed without
+/// failing authentication.
+fn with_key_id(id: KeyId, aad: &[u8]) -> Vec<u8> {
+    let mut result = id.to_be_bytes().to_vec();
+    result.extend_from_slice(aad);
+    result
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut keyring = Keyring::parse("").unwrap();
+        keyring.rotate().unwrap();
+
+        let ciphertext = keyring.encrypt(b"Hello, world!", b"row 1").unwrap();
+        assert_eq!(key_id(&ciphertext).unwrap(), 1);
+        assert_eq!(
+            keyring.decrypt(&ciphertext, b"row 1").unwrap(),
+            b"Hello, world!"
+        );
+        assert!(keyring.decrypt(&ciphertext, b"row 2").is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut keyring = Keyring::parse("1:first secret").unwrap();
+        let old = keyring.encrypt(b"Hello, world!", &[]).unwrap();
+
+        assert_eq!(keyring.rotate().unwrap(), 2);
+        assert_eq!(keyring.decrypt(&old, &[]).unwrap(), b"Hello, world!");
+
+        let new = keyring.reencrypt(&old, &[]).unwrap().unwrap();
+        assert_eq!(key_id(&new).unwrap(), 2);
+        assert_eq!(keyring.decrypt(&new, &[]).unwrap(), b"Hello, world!");
+        assert_eq!(keyring.reencrypt(&new, &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let keyring = Keyring::parse("1:first secret\n2:second secret").unwrap();
+        let ciphertext = keyring.encrypt(b"Hello, world!", &[]).unwrap();
+
+        let keyring = Keyring::parse("1:first secret").unwrap();
+        assert!(matches!(
+            keyring.decrypt(&ciphertext, &[]),
+            Err(Error::UnknownKeyId(2))
+        ));
+    }
+
+    #[test]
+    fn test_key_id_authenticated() {
+        let keyring = Keyring::parse("1:same secret\n2:same secret").unwrap();
+        let mut ciphertext = keyring.encrypt(b"Hello, world!", &[]).unwrap();
+
+        ciphertext[..KEY_ID_LEN].copy_from_slice(&1u32.to_be_bytes());
+        assert!(keyring.decrypt(&ciphertext, &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let keyring = Keyring::parse("# comment\n\n3:c\n1:a\n").unwrap();
+        assert_eq!(keyring.ids().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(keyring.primary_id(), Some(3));
+        assert_eq!(keyring.contents(), "1:a\n3:c\n");
+        assert_eq!(Keyring::parse(&keyring.contents()).unwrap(), keyring);
+
+        assert!(Keyring::parse("secret\n1:a").is_err());
+        assert!(Keyring::parse("1:a\n2:").is_err());
+        assert!(Keyring::parse("1:a\n1:b").is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("keyring-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(matches!(Keyring::load(&path), Err(Error::Io(_))));
+
+        fs::write(&path, "# no keys\n").unwrap();
+        assert!(matches!(
+            Keyring::load(&path),
+            Err(Error::InvalidKeyring(_))
+        ));
+
+        fs::write(&path, "1:a\n").unwrap();
+        assert_eq!(Keyring::load(&path).unwrap().primary_id(), Some(1));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_legacy() {
+        let keyring = Keyring::parse("legacy:secret\n").unwrap();
+        assert_eq!(keyring.ids().collect::<Vec<_>>(), [1]);
+        assert_eq!(keyring.contents(), "1:legacy:secret\n");
+        assert_eq!(Keyring::parse(&keyring.contents()).unwrap(), keyring);
+
+        let ciphertext = keyring.encrypt(b"Hello, world!", &[]).unwrap();
+        let keyring = Keyring::parse("# comment\n1:legacy:secret").unwrap();
+        assert_eq!(keyring.decrypt(&ciphertext, &[]).unwrap(), b"Hello, world!");
+    }
+}
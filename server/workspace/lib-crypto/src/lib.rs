@@ -10,11 +10,27 @@
 pub use encryption::Key as EncryptionKey;
 pub use error::{Error, Result};
 pub use hash::Sha256 as Sha256Hash;
-pub use password::{hash as hash_password, verify as verify_password};
+pub use kdf::hkdf_sha256;
+pub use keyring::{key_id, KeyId, Keyring};
+pub use mac::{Sha256 as HmacSha256, Sha512 as HmacSha512, Tag as HmacTag};
+pub use password::{
+    hash as hash_password, needs_rehash as password_needs_rehash, verify as verify_password,
+};
 pub use rand::fill_bytes;
+pub use signing::{Signature, SigningKey, SigningKeyring, VerifyingKey};
+pub use stream::{
+    decrypt as decrypt_stream, encrypt as encrypt_stream, DecryptingReader,
+    Decryptor as StreamDecryptor, EncryptingWriter, Encryptor as StreamEncryptor,
+    DEFAULT_CHUNK_SIZE as DEFAULT_STREAM_CHUNK_SIZE, MAX_CHUNK_SIZE as MAX_STREAM_CHUNK_SIZE,
+};
 
 mod encryption;
 mod error;
 mod hash;
+mod kdf;
+mod keyring;
+mod mac;
 mod password;
 mod rand;
+mod signing;
+mod stream;
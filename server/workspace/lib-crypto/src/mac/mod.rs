@@ -0,0 +1,58 @@
+use subtle::ConstantTimeEq;
+
+pub use sha256::Mac as Sha256;
+pub use sha512::Mac as Sha512;
+
+mod sha256;
+mod sha512;
+
+/// An HMAC authentication tag. Tags are compared in constant time, so the
+/// comparison does not reveal how much of a forged tag was correct.
+#[derive(Debug, Clone, Copy)]
+pub struct Tag<const N: usize>([u8; N]);
+
+impl<const N: usize> Tag<N> {
+    /// Check whether the given bytes match the tag, in constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - the tag to check, such as one received with a message
+    #[must_use]
+    pub fn verify(&self, tag: &[u8]) -> bool {
+        self.0.as_slice().ct_eq(tag).into()
+    }
+}
+
+impl<const N: usize> PartialEq for Tag<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.verify(&other.0)
+    }
+}
+
+impl<const N: usize> Eq for Tag<N> {}
+
+impl<const N: usize> From<[u8; N]> for Tag<N> {
+    fn from(data: [u8; N]) -> Self {
+        Self(data)
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Tag<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<Tag<N>> for [u8; N] {
+    fn from(tag: Tag<N>) -> Self {
+        tag.0
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
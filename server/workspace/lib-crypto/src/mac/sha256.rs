@@ -0,0 +1,174 @@
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use super::Tag;
+use crate::{Error, Result};
+
+const TAG_LEN: usize = 32;
+
+/// An HMAC-Sha256 computation, fed with message data a piece at a time.
+#[derive(Clone)]
+pub struct Mac(Hmac<Sha256>);
+
+impl Mac {
+    /// Start computing a tag with the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of any length
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyLength`] if the key is rejected.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        Hmac::new_from_slice(key)
+            .map(Self)
+            .map_err(|_| Error::InvalidKeyLength(key.len()))
+    }
+
+    /// Compute the tag of a complete message.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of any length
+    /// * `data` - message to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyLength`] if the key is rejected.
+    pub fn sign(key: &[u8], data: &[u8]) -> Result<Tag<TAG_LEN>> {
+        let mut mac = Self::new(key)?;
+        mac.update(data);
+        Ok(mac.finalize())
+    }
+
+    /// Feed the next piece of the message into the computation.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Finish the computation and get the tag.
+    #[must_use]
+    pub fn finalize(self) -> Tag<TAG_LEN> {
+        let mut tag = [0; TAG_LEN];
+        tag.copy_from_slice(&self.0.finalize().into_bytes());
+        tag.into()
+    }
+
+    /// Finish the computation and check the result against the given tag, in
+    /// constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - the tag to check, such as one received with a message
+    #[must_use]
+    pub fn verify(self, tag: &[u8]) -> bool {
+        self.0.verify_slice(tag).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hex;
+    use super::*;
+
+    /// Check a test case from RFC 4231, comparing only the first `expected.len()` bytes of the tag.
+    fn check(key: &[u8], data: &[u8], expected: &str) {
+        let expected = hex(expected);
+        let tag = Mac::sign(key, data).unwrap();
+        assert_eq!(&tag.as_ref()[..expected.len()], expected.as_slice());
+    }
+
+    #[test]
+    fn test_rfc4231_case_1() {
+        check(
+            &hex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+            b"Hi There",
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_2() {
+        check(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_3() {
+        check(
+            &hex(&"aa".repeat(20)),
+            &hex(&"dd".repeat(50)),
+            "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_4() {
+        check(
+            &hex("0102030405060708090a0b0c0d0e0f10111213141516171819"),
+            &hex(&"cd".repeat(50)),
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_5() {
+        check(
+            &hex("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c"),
+            b"Test With Truncation",
+            "a3b6167473100ee06e0c796c2955552b",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_6() {
+        check(
+            &hex(&"aa".repeat(131)),
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_7() {
+        let data = concat!(
+            "This is a test using a larger than block-size key and a larger than block-size ",
+            "data. The key needs to be hashed before being used by the HMAC algorithm."
+        );
+        check(
+            &hex(&"aa".repeat(131)),
+            data.as_bytes(),
+            "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+        );
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut mac = Mac::new(b"key").unwrap();
+        mac.update(b"Hi ");
+        mac.update(b"There");
+        let tag = mac.finalize();
+
+        assert_eq!(tag, Mac::sign(b"key", b"Hi There").unwrap());
+    }
+
+    #[test]
+    fn test_verify() {
+        let tag = Mac::sign(b"key", b"Hi There").unwrap();
+
+        let mut mac = Mac::new(b"key").unwrap();
+        mac.update(b"Hi There");
+        assert!(mac.clone().verify(tag.as_ref()));
+        assert!(!mac.clone().verify(&tag.as_ref()[1..]));
+        assert!(tag.verify(tag.as_ref()));
+
+        let mut forged: [u8; TAG_LEN] = tag.into();
+        forged[0] ^= 1;
+        assert!(!mac.verify(&forged));
+        assert!(!tag.verify(&forged));
+    }
+}
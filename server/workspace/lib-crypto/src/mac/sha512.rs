@@ -0,0 +1,192 @@
+use hmac::{Hmac, Mac as _};
+use sha2::Sha512;
+
+use super::Tag;
+use crate::{Error, Result};
+
+const TAG_LEN: usize = 64;
+
+/// An HMAC-Sha512 computation, fed with message data a piece at a time.
+#[derive(Clone)]
+pub struct Mac(Hmac<Sha512>);
+
+impl Mac {
+    /// Start computing a tag with the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of any length
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyLength`] if the key is rejected.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        Hmac::new_from_slice(key)
+            .map(Self)
+            .map_err(|_| Error::InvalidKeyLength(key.len()))
+    }
+
+    /// Compute the tag of a complete message.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of any length
+    /// * `data` - message to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyLength`] if the key is rejected.
+    pub fn sign(key: &[u8], data: &[u8]) -> Result<Tag<TAG_LEN>> {
+        let mut mac = Self::new(key)?;
+        mac.update(data);
+        Ok(mac.finalize())
+    }
+
+    /// Feed the next piece of the message into the computation.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Finish the computation and get the tag.
+    #[must_use]
+    pub fn finalize(self) -> Tag<TAG_LEN> {
+        let mut tag = [0; TAG_LEN];
+        tag.copy_from_slice(&self.0.finalize().into_bytes());
+        tag.into()
+    }
+
+    /// Finish the computation and check the result against the given tag, in
+    /// constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - the tag to check, such as one received with a message
+    #[must_use]
+    pub fn verify(self, tag: &[u8]) -> bool {
+        self.0.verify_slice(tag).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hex;
+    use super::*;
+
+    /// Check a test case from RFC 4231, comparing only the first `expected.len()` bytes of the tag.
+    fn check(key: &[u8], data: &[u8], expected: &str) {
+        let expected = hex(expected);
+        let tag = Mac::sign(key, data).unwrap();
+        assert_eq!(&tag.as_ref()[..expected.len()], expected.as_slice());
+    }
+
+    #[test]
+    fn test_rfc4231_case_1() {
+        check(
+            &hex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b"),
+            b"Hi There",
+            concat!(
+                "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde",
+                "daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_2() {
+        check(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            concat!(
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554",
+                "9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_3() {
+        check(
+            &hex(&"aa".repeat(20)),
+            &hex(&"dd".repeat(50)),
+            concat!(
+                "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39",
+                "bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_4() {
+        check(
+            &hex("0102030405060708090a0b0c0d0e0f10111213141516171819"),
+            &hex(&"cd".repeat(50)),
+            concat!(
+                "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3db",
+                "a91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_5() {
+        check(
+            &hex("0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c"),
+            b"Test With Truncation",
+            "415fad6271580a531d4179bc891d87a6",
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_6() {
+        check(
+            &hex(&"aa".repeat(131)),
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+            concat!(
+                "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352",
+                "6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rfc4231_case_7() {
+        let data = concat!(
+            "This is a test using a larger than block-size key and a larger than block-size ",
+            "data. The key needs to be hashed before being used by the HMAC algorithm."
+        );
+        check(
+            &hex(&"aa".repeat(131)),
+            data.as_bytes(),
+            concat!(
+                "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944",
+                "b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut mac = Mac::new(b"key").unwrap();
+        mac.update(b"Hi ");
+        mac.update(b"There");
+        let tag = mac.finalize();
+
+        assert_eq!(tag, Mac::sign(b"key", b"Hi There").unwrap());
+    }
+
+    #[test]
+    fn test_verify() {
+        let tag = Mac::sign(b"key", b"Hi There").unwrap();
+
+        let mut mac = Mac::new(b"key").unwrap();
+        mac.update(b"Hi There");
+        assert!(mac.clone().verify(tag.as_ref()));
+        assert!(!mac.clone().verify(&tag.as_ref()[1..]));
+        assert!(tag.verify(tag.as_ref()));
+
+        let mut forged: [u8; TAG_LEN] = tag.into();
+        forged[0] ^= 1;
+        assert!(!mac.verify(&forged));
+        assert!(!tag.verify(&forged));
+    }
+}
@@ -5,31 +5,39 @@ use argon2::{
     Argon2,
 };
 
-use crate::{Error, Result};
+use crate::{Error, HmacSha256, HmacTag, KeyId, Keyring, Result};
+
+/// The prefix of hashes of peppered passwords, followed by the pepper's version
+/// and the Argon2 hash.
+const PEPPER_PREFIX: &str = "$pepper$k=";
+
+/// The purpose the pepper's HMAC keys are derived for.
+const PEPPER_PURPOSE: &str = "password-pepper";
 
 /// Hashes a password using Argon2id.
 ///
 /// The returned string is a valid password hash that can be stored in the database.
 ///
+/// If a pepper is given, the password is first replaced with its HMAC under the
+/// pepper's primary key, and the key's ID is recorded in the hash as its
+/// version. The pepper is a keyring kept outside the database, so a leaked
+/// database alone is not enough to crack the hashes.
+///
 /// # Arguments
 ///
 /// * `password` - The password to hash.
+/// * `pepper` - The pepper to apply, if any.
 ///
 /// # Errors
 ///
 /// Returns an error if the password could not be hashed.
-pub fn hash(password: &[u8]) -> Result<String> {
-    let salt = {
-        let mut rng = crate::rand::crypto_rng();
-        SaltString::generate(&mut rng)
+pub fn hash(password: &[u8], pepper: Option<&Keyring>) -> Result<String> {
+    let Some((pepper, version)) = pepper.and_then(|p| Some((p, p.primary_id()?))) else {
+        return argon2_hash(password);
     };
 
-    let argon2 = Argon2::default();
-    let hash = argon2
-        .hash_password(password, &salt)
-        .map_err(|e| Error::InvalidPasswordHash(e.to_string()))?
-        .to_string();
-    Ok(hash)
+    let hash = argon2_hash(apply(pepper, version, password)?.as_ref())?;
+    Ok(format!("{PEPPER_PREFIX}{version}{hash}"))
 }
 
 /// Verifies a password against a password hash.
@@ -38,15 +46,88 @@ pub fn hash(password: &[u8]) -> Result<String> {
 ///
 /// * `password` - The password to verify.
 /// * `password_hash` - The password hash to verify against.
+/// * `pepper` - The pepper holding the version the hash was made with, if any.
 ///
 /// # Errors
 ///
-/// Returns an error if the password could not be verified.
-pub fn verify(password: &[u8], password_hash: &str) -> Result<bool> {
+/// Returns an error if the password could not be verified, or
+/// [`Error::UnknownKeyId`] if the hash was made with a pepper version that is
+/// not in the pepper.
+pub fn verify(password: &[u8], password_hash: &str, pepper: Option<&Keyring>) -> Result<bool> {
+    let (version, password_hash) = split(password_hash)?;
     let parsed_hash = ArgonPasswordHash::new(password_hash)
         .map_err(|e| Error::InvalidPasswordHash(e.to_string()))?;
     let argon2 = Argon2::default();
-    Ok(argon2.verify_password(password, &parsed_hash).is_ok())
+
+    let is_valid = match version {
+        None => argon2.verify_password(password, &parsed_hash).is_ok(),
+        Some(version) => {
+            let pepper = pepper.ok_or(Error::UnknownKeyId(version))?;
+            let password = apply(pepper, version, password)?;
+            argon2
+                .verify_password(password.as_ref(), &parsed_hash)
+                .is_ok()
+        }
+    };
+
+    Ok(is_valid)
+}
+
+/// Checks whether a password hash was made without the current version of
+/// the pepper, so it should be replaced once the password has been verified.
+///
+/// # Arguments
+///
+/// * `password_hash` - The password hash to check.
+/// * `pepper` - The current pepper, if any.
+#[must_use]
+pub fn needs_rehash(password_hash: &str, pepper: Option<&Keyring>) -> bool {
+    match split(password_hash) {
+        Ok((version, _)) => version != pepper.and_then(Keyring::primary_id),
+        Err(_) => true,
+    }
+}
+
+fn argon2_hash(password: &[u8]) -> Result<String> {
+    let salt = {
+        let mut rng = crate::rand::crypto_rng();
+        SaltString::generate(&mut rng)
+    };
+
+    let argon2 = Argon2::default();
+    let hash = argon2
+        .hash_password(password, &salt)
+        .map_err(|e| Error::InvalidPasswordHash(e.to_string()))?
+        .to_string();
+    Ok(hash)
+}
+
+/// Replace a password with its HMAC under the given version of the pepper.
+fn apply(pepper: &Keyring, version: KeyId, password: &[u8]) -> Result<HmacTag<32>> {
+    let key = pepper
+        .key(version)
+        .ok_or(Error::UnknownKeyId(version))?
+        .derive(PEPPER_PURPOSE)?;
+
+    HmacSha256::sign(AsRef::<[u8]>::as_ref(&key), password)
+}
+
+/// Split a password hash into its pepper version, if it was peppered, and its
+/// Argon2 hash.
+fn split(password_hash: &str) -> Result<(Option<KeyId>, &str)> {
+    let Some(rest) = password_hash.strip_prefix(PEPPER_PREFIX) else {
+        return Ok((None, password_hash));
+    };
+
+    let (version, password_hash) = rest
+        .find('$')
+        .map(|i| rest.split_at(i))
+        .ok_or_else(|| Error::InvalidPasswordHash("missing pepper version".to_string()))?;
+    let version = version
+        .parse()
+        .map_err(|_| Error::InvalidPasswordHash(format!("invalid pepper version: {version}")))?;
+
+    Ok((Some(version), password_hash))
 }
 
 #[cfg(test)]
@@ -56,23 +137,63 @@ mod tests {
     #[test]
     fn test_password_hash() {
         let password = "hunter42"; // Bad password; don't actually use!
-        let password_hash = hash(password.as_bytes()).unwrap();
-        assert!(verify(password.as_bytes(), &password_hash).unwrap());
+        let password_hash = hash(password.as_bytes(), None).unwrap();
+        assert!(verify(password.as_bytes(), &password_hash, None).unwrap());
     }
 
     #[test]
     fn test_invalid_password() {
         let password = "hunter42"; // Bad password; don't actually use!
-        let password_hash = hash(password.as_bytes()).unwrap();
-        assert!(!verify(b"hunter43", &password_hash).unwrap());
+        let password_hash = hash(password.as_bytes(), None).unwrap();
+        assert!(!verify(b"hunter43", &password_hash, None).unwrap());
     }
 
     #[test]
     fn unique_hashes() {
         let password = "hunter42"; // Bad password; don't actually use!
-        let password_hash = hash(password.as_bytes()).unwrap();
-        let password_hash2 = hash(password.as_bytes()).unwrap();
+        let password_hash = hash(password.as_bytes(), None).unwrap();
+        let password_hash2 = hash(password.as_bytes(), None).unwrap();
 
         assert_ne!(password_hash, password_hash2);
     }
+
+    #[test]
+    fn test_pepper() {
+        let password = "hunter42"; // Bad password; don't actually use!
+        let pepper = Keyring::parse("1:pepper").unwrap();
+        let password_hash = hash(password.as_bytes(), Some(&pepper)).unwrap();
+
+        assert!(password_hash.starts_with("$pepper$k=1$argon2id$"));
+        assert!(verify(password.as_bytes(), &password_hash, Some(&pepper)).unwrap());
+        assert!(!verify(b"hunter43", &password_hash, Some(&pepper)).unwrap());
+        assert!(!needs_rehash(&password_hash, Some(&pepper)));
+
+        let other = Keyring::parse("1:other pepper").unwrap();
+        assert!(!verify(password.as_bytes(), &password_hash, Some(&other)).unwrap());
+        assert!(verify(password.as_bytes(), &password_hash, None).is_err());
+    }
+
+    #[test]
+    fn test_pepper_rotation() {
+        let password = "hunter42"; // Bad password; don't actually use!
+        let mut pepper = Keyring::parse("1:pepper").unwrap();
+        let unpeppered = hash(password.as_bytes(), None).unwrap();
+        let old = hash(password.as_bytes(), Some(&pepper)).unwrap();
+
+        pepper.rotate().unwrap();
+        assert!(verify(password.as_bytes(), &unpeppered, Some(&pepper)).unwrap());
+        assert!(verify(password.as_bytes(), &old, Some(&pepper)).unwrap());
+        assert!(needs_rehash(&unpeppered, Some(&pepper)));
+        assert!(needs_rehash(&old, Some(&pepper)));
+
+        let new = hash(password.as_bytes(), Some(&pepper)).unwrap();
+        assert!(new.starts_with("$pepper$k=2$"));
+        assert!(!needs_rehash(&new, Some(&pepper)));
+
+        let pepper = Keyring::parse("2:removed").unwrap();
+        assert!(matches!(
+            verify(password.as_bytes(), &old, Some(&pepper)),
+            Err(Error::UnknownKeyId(1))
+        ));
+    }
 }
@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+
+use crate::{EncryptionKey, Error, KeyId, Keyring, Result};
+
+/// The length of an Ed25519 public key.
+const PUBLIC_KEY_LEN: usize = 32;
+
+/// The length of an Ed25519 signature.
+const SIGNATURE_LEN: usize = 64;
+
+/// The purpose signing keys are derived from keyring keys for.
+const SIGNING_PURPOSE: &str = "ed25519-signing";
+
+/// An Ed25519 key for signing messages.
+pub struct SigningKey(Ed25519KeyPair);
+
+impl SigningKey {
+    /// Create a signing key from a 32 byte seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed to create the key from
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyLength`] if the seed is not 32 bytes long.
+    pub fn from_seed(seed: &[u8]) -> Result<Self> {
+        Ed25519KeyPair::from_seed_unchecked(seed)
+            .map(Self)
+            .map_err(|_| Error::InvalidKeyLength(seed.len()))
+    }
+
+    /// Derive a signing key from an encryption key, so it can be stored in a
+    /// [`Keyring`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key to derive the signing key from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key could not be derived.
+    pub fn derive_from(key: &EncryptionKey) -> Result<Self> {
+        let seed = key.derive(SIGNING_PURPOSE)?;
+        Self::from_seed(AsRef::<[u8]>::as_ref(&seed))
+    }
+
+    /// Sign a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - message to sign
+    #[must_use]
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(self.0.sign(message).as_ref());
+        Signature(signature)
+    }
+
+    /// Get the key that verifies this key's signatures.
+    #[must_use]
+    pub fn verifying_key(&self) -> VerifyingKey {
+        let mut key = [0u8; PUBLIC_KEY_LEN];
+        key.copy_from_slice(self.0.public_key().as_ref());
+        VerifyingKey(key)
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SigningKey")
+            .field(&self.verifying_key())
+            .finish()
+    }
+}
+
+/// An Ed25519 public key, for verifying signatures made by a [`SigningKey`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VerifyingKey([u8; PUBLIC_KEY_LEN]);
+
+impl VerifyingKey {
+    /// Verify a message's signature.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - message that was signed
+    /// * `signature` - signature to verify
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSignature`] if the signature is not valid.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<()> {
+        UnparsedPublicKey::new(&ED25519, &self.0)
+            .verify(message, &signature.0)
+            .map_err(|_| Error::InvalidSignature)
+    }
+}
+
+impl From<[u8; PUBLIC_KEY_LEN]> for VerifyingKey {
+    fn from(key: [u8; PUBLIC_KEY_LEN]) -> Self {
+        Self(key)
+    }
+}
+
+impl AsRef<[u8]> for VerifyingKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// An Ed25519 signature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Signature([u8; SIGNATURE_LEN]);
+
+impl From<[u8; SIGNATURE_LEN]> for Signature {
+    fn from(signature: [u8; SIGNATURE_LEN]) -> Self {
+        Self(signature)
+    }
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = Error;
+
+    fn try_from(signature: &[u8]) -> Result<Self> {
+        signature
+            .try_into()
+            .map(Self)
+            .map_err(|_| Error::InvalidSignature)
+    }
+}
+
+impl AsRef<[u8]> for Signature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A set of signing keys derived from a [`Keyring`], each identified by the
+/// ID of the key it was derived from.
+///
+/// Messages are signed with the key derived from the keyring's primary key.
+/// Rotating the keyring adds a new signing key, while older keys remain
+/// available to verify the signatures they made.
+pub struct SigningKeyring {
+    keys: BTreeMap<KeyId, SigningKey>,
+}
+
+impl SigningKeyring {
+    /// Derive the signing keys from a keyring.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyring` - keyring to derive the signing keys from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a key could not be derived.
+    pub fn new(keyring: &Keyring) -> Result<Self> {
+        let keys = keyring
+            .ids()
+            .map(|id| {
+                let key = keyring.key(id).ok_or(Error::UnknownKeyId(id))?;
+                Ok((id, SigningKey::derive_from(key)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { keys })
+    }
+
+    /// Load the keyring file and derive the signing keys from it, creating
+    /// the file with a newly generated key if it does not exist or is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the keyring file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring could not be loaded, or a key could
+    /// not be derived.
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(&Keyring::load_or_create(path)?)
+    }
+
+    /// Sign a message with the primary key.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - message to sign
+    ///
+    /// # Returns
+    ///
+    /// The ID of the key that signed the message, and the signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKeyring`] if the keyring is empty.
+    pub fn sign(&self, message: &[u8]) -> Result<(KeyId, Signature)> {
+        let (id, key) = self
+            .keys
+            .iter()
+            .next_back()
+            .ok_or_else(|| Error::InvalidKeyring("the keyring is empty".to_string()))?;
+
+        Ok((*id, key.sign(message)))
+    }
+
+    /// Verify a message's signature.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the key that signed the message
+    /// * `message` - message that was signed
+    /// * `signature` - signature to verify
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownKeyId`] if the key is not in the keyring, or
+    /// [`Error::InvalidSignature`] if the signature is not valid.
+    pub fn verify(&self, id: KeyId, message: &[u8], signature: &Signature) -> Result<()> {
+        self.keys
+            .get(&id)
+            .ok_or(Error::UnknownKeyId(id))?
+            .verifying_key()
+            .verify(message, signature)
+    }
+
+    /// Get the verifying keys of all signing keys, newest first.
+    pub fn verifying_keys(&self) -> impl Iterator<Item = (KeyId, VerifyingKey)> + '_ {
+        self.keys
+            .iter()
+            .rev()
+            .map(|(id, key)| (*id, key.verifying_key()))
+    }
+}
+
+impl fmt::Debug for SigningKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKeyring")
+            .field("ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mac::hex;
+
+    use super::*;
+
+    #[test]
+    fn test_rfc8032_test_1() {
+        let key = SigningKey::from_seed(&hex(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            key.verifying_key().as_ref(),
+            hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+        assert_eq!(
+            key.sign(b"").as_ref(),
+            hex(concat!(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+                "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ))
+        );
+    }
+
+    #[test]
+    fn test_rfc8032_test_2() {
+        let key = SigningKey::from_seed(&hex(
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            key.verifying_key().as_ref(),
+            hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+        );
+        assert_eq!(
+            key.sign(&hex("72")).as_ref(),
+            hex(concat!(
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+                "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ))
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = SigningKey::from_seed(&[7u8; 32]).unwrap();
+        let signature = key.sign(b"Hello, world!");
+        let verifying_key = key.verifying_key();
+
+        assert!(verifying_key.verify(b"Hello, world!", &signature).is_ok());
+        assert!(verifying_key.verify(b"Hello, world?", &signature).is_err());
+
+        let mut tampered: [u8; SIGNATURE_LEN] = signature.0;
+        tampered[0] ^= 1;
+        assert!(verifying_key
+            .verify(b"Hello, world!", &tampered.into())
+            .is_err());
+
+        assert!(SigningKey::from_seed(&[7u8; 31]).is_err());
+        assert!(Signature::try_from(&[0u8; 63][..]).is_err());
+    }
+
+    #[test]
+    fn test_signing_keyring() {
+        let mut keyring = Keyring::parse("1:first secret").unwrap();
+        let signing_keyring = SigningKeyring::new(&keyring).unwrap();
+        let (id, signature) = signing_keyring.sign(b"Hello, world!").unwrap();
+        assert_eq!(id, 1);
+
+        keyring.rotate().unwrap();
+        let signing_keyring = SigningKeyring::new(&keyring).unwrap();
+        assert!(signing_keyring
+            .verify(1, b"Hello, world!", &signature)
+            .is_ok());
+        assert!(signing_keyring
+            .verify(2, b"Hello, world!", &signature)
+            .is_err());
+        assert_eq!(signing_keyring.sign(b"").unwrap().0, 2);
+        assert_eq!(
+            signing_keyring
+                .verifying_keys()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            [2, 1]
+        );
+
+        // The same secret always derives the same key.
+        let again = SigningKeyring::new(&Keyring::parse("1:first secret").unwrap()).unwrap();
+        assert!(again.verify(1, b"Hello, world!", &signature).is_ok());
+    }
+}
@@ -0,0 +1,695 @@
+use std::io::{self, Read, Write};
+
+use aead::generic_array::GenericArray;
+use aead::stream::{DecryptorBE32, EncryptorBE32};
+use aead::Payload;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use futures_util::stream::{self, Stream, StreamExt};
+
+use crate::{fill_bytes, EncryptionKey, Error, Result};
+
+/// Identifies data as an encrypted stream.
+const MAGIC: &[u8; 4] = b"EMSE";
+
+/// The version of the format written by [`Encryptor`].
+const VERSION: u8 = 1;
+
+/// The length of the random nonce prefix. The STREAM construction uses the
+/// remaining 5 bytes of the nonce for the chunk counter and last-chunk flag.
+const NONCE_PREFIX_LEN: usize = 19;
+
+/// The length of the header: magic, version, chunk size and nonce prefix.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + NONCE_PREFIX_LEN;
+
+/// The length of the authentication tag appended to each chunk.
+const TAG_LEN: usize = 16;
+
+/// The number of ciphertext bytes [`DecryptingReader`] reads at a time.
+const READ_LEN: usize = 8 * 1024;
+
+/// The plaintext chunk size used unless another is given.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The largest accepted chunk size, which bounds the memory a decryptor
+/// allocates for an untrusted stream.
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Encrypts a stream of data in chunks, without holding all of it in memory.
+///
+/// # Format
+///
+/// The ciphertext starts with a header holding the magic bytes `EMSE`, the
+/// format version, the chunk size as a big-endian `u32` and a random nonce
+/// prefix. It is followed by the plaintext split into chunks of the chunk
+/// size, each encrypted with XChaCha20-Poly1305 using the STREAM construction.
+/// Only the last chunk may be shorter, and it may be empty.
+///
+/// Each chunk's nonce holds its position and whether it is the last chunk, so
+/// reordered, dropped or truncated chunks fail to decrypt. The header is
+/// authenticated with every chunk.
+pub struct Encryptor {
+    stream: EncryptorBE32<XChaCha20Poly1305>,
+    header: [u8; HEADER_LEN],
+    header_written: bool,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl Encryptor {
+    /// Create an encryptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key to encrypt with
+    /// * `chunk_size` - number of plaintext bytes in each chunk
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStream`] if the chunk size is zero or larger
+    /// than [`MAX_CHUNK_SIZE`], or [`Error::Unspecified`] if the nonce could
+    /// not be generated.
+    pub fn new(key: &EncryptionKey, chunk_size: usize) -> Result<Self> {
+        let encoded_chunk_size = u32::try_from(chunk_size)
+            .ok()
+            .filter(|_| (1..=MAX_CHUNK_SIZE).contains(&chunk_size))
+            .ok_or_else(|| {
+                Error::InvalidStream(format!(
+                    "the chunk size must be between 1 and {MAX_CHUNK_SIZE}"
+                ))
+            })?;
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        fill_bytes(&mut nonce_prefix)?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..MAGIC.len()].copy_from_slice(MAGIC);
+        header[MAGIC.len()] = VERSION;
+        header[MAGIC.len() + 1..HEADER_LEN - NONCE_PREFIX_LEN]
+            .copy_from_slice(&encoded_chunk_size.to_be_bytes());
+        header[HEADER_LEN - NONCE_PREFIX_LEN..].copy_from_slice(&nonce_prefix);
+
+        Ok(Self {
+            stream: EncryptorBE32::from_aead(cipher(key), GenericArray::from_slice(&nonce_prefix)),
+            header,
+            header_written: false,
+            chunk_size,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Encrypt the next part of the plaintext.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - next part of the plaintext, of any length
+    ///
+    /// # Returns
+    ///
+    /// The ciphertext of every chunk completed so far, which may be empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unspecified`] if encryption fails.
+    pub fn update(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut output = self.take_header();
+        self.buffer.extend_from_slice(plaintext);
+
+        // A full chunk is held back until more data arrives, since the last
+        // chunk is encrypted differently.
+        let mut start = 0;
+        while self.buffer.len() - start > self.chunk_size {
+            let msg = &self.buffer[start..start + self.chunk_size];
+            output.extend(
+                self.stream
+                    .encrypt_next(Payload {
+                        msg,
+                        aad: &self.header,
+                    })
+                    .map_err(|e| Error::Unspecified(e.to_string()))?,
+            );
+            start += self.chunk_size;
+        }
+        self.buffer.drain(..start);
+
+        Ok(output)
+    }
+
+    /// Encrypt the rest of the plaintext as the last chunk. A stream that is
+    /// never finished cannot be decrypted.
+    ///
+    /// # Returns
+    ///
+    /// The remaining ciphertext.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unspecified`] if encryption fails.
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let mut output = self.take_header();
+        output.extend(
+            self.stream
+                .encrypt_last(Payload {
+                    msg: &self.buffer,
+                    aad: &self.header,
+                })
+                .map_err(|e| Error::Unspecified(e.to_string()))?,
+        );
+
+        Ok(output)
+    }
+
+    fn take_header(&mut self) -> Vec<u8> {
+        if self.header_written {
+            return Vec::new();
+        }
+
+        self.header_written = true;
+        self.header.to_vec()
+    }
+}
+
+/// Decrypts a stream produced by [`Encryptor`].
+///
+/// Plaintext is only returned once the chunk holding it has been
+/// authenticated, but the stream as a whole is only known to be complete once
+/// [`Decryptor::finish`] succeeds. Once an error is returned, the decryptor
+/// must not be used again.
+pub struct Decryptor {
+    cipher: XChaCha20Poly1305,
+    stream: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    header: [u8; HEADER_LEN],
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl Decryptor {
+    /// Create a decryptor. The chunk size is read from the stream's header.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key the stream was encrypted with
+    #[must_use]
+    pub fn new(key: &EncryptionKey) -> Self {
+        Self {
+            cipher: cipher(key),
+            stream: None,
+            header: [0u8; HEADER_LEN],
+            chunk_size: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Decrypt the next part of the ciphertext.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - next part of the ciphertext, of any length
+    ///
+    /// # Returns
+    ///
+    /// The plaintext of every chunk authenticated so far, which may be empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStream`] if the header is invalid or a chunk
+    /// fails authentication.
+    pub fn update(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.buffer.extend_from_slice(ciphertext);
+
+        let mut start = 0;
+        if self.stream.is_none() {
+            let Some(header) = self.buffer.get(..HEADER_LEN) else { return Ok(Vec::new()) };
+            let (chunk_size, nonce_prefix) = parse_header(header)?;
+
+            self.stream = Some(DecryptorBE32::from_aead(
+                self.cipher.clone(),
+                GenericArray::from_slice(nonce_prefix),
+            ));
+            self.header.copy_from_slice(header);
+            self.chunk_size = chunk_size;
+            start = HEADER_LEN;
+        }
+
+        let mut output = Vec::new();
+        if let Some(stream) = &mut self.stream {
+            // As when encrypting, a full chunk may be the last one.
+            let chunk_len = self.chunk_size + TAG_LEN;
+            while self.buffer.len() - start > chunk_len {
+                let msg = &self.buffer[start..start + chunk_len];
+                output.extend(
+                    stream
+                        .decrypt_next(Payload {
+                            msg,
+                            aad: &self.header,
+                        })
+                        .map_err(|_| {
+                            Error::InvalidStream("a chunk failed authentication".to_string())
+                        })?,
+                );
+                start += chunk_len;
+            }
+        }
+        self.buffer.drain(..start);
+
+        Ok(output)
+    }
+
+    /// Decrypt the last chunk, verifying that the stream is complete.
+    ///
+    /// # Returns
+    ///
+    /// The remaining plaintext.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStream`] if the stream is truncated or the last
+    /// chunk fails authentication.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let Some(stream) = self.stream else {
+            return Err(Error::InvalidStream("the header is truncated".to_string()));
+        };
+
+        stream
+            .decrypt_last(Payload {
+                msg: &self.buffer,
+                aad: &self.header,
+            })
+            .map_err(|_| {
+                Error::InvalidStream(
+                    "the last chunk failed authentication, so the stream may be truncated"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+/// A [`Write`] adapter encrypting everything written to it into the inner
+/// writer. [`EncryptingWriter::finish`] must be called once all data has
+/// been written, or the output cannot be decrypted.
+pub struct EncryptingWriter<W: Write> {
+    inner: W,
+    encryptor: Encryptor,
+}
+
+impl<W: Write> EncryptingWriter<W> {
+    /// Create an encrypting writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key to encrypt with
+    /// * `chunk_size` - number of plaintext bytes in each chunk
+    /// * `inner` - writer to write the ciphertext to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encryptor could not be created.
+    pub fn new(key: &EncryptionKey, chunk_size: usize, inner: W) -> Result<Self> {
+        Ok(Self {
+            inner,
+            encryptor: Encryptor::new(key, chunk_size)?,
+        })
+    }
+
+    /// Write the last chunk and flush the inner writer.
+    ///
+    /// # Returns
+    ///
+    /// The inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the last chunk could not be encrypted or written.
+    pub fn finish(mut self) -> io::Result<W> {
+        let output = self.encryptor.finish().map_err(into_io)?;
+        self.inner.write_all(&output)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let output = self.encryptor.update(buf).map_err(into_io)?;
+        self.inner.write_all(&output)?;
+
+        Ok(buf.len())
+    }
+
+    /// Flush the inner writer. Plaintext that does not yet fill a chunk is
+    /// kept back until more is written or the writer is finished.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A [`Read`] adapter decrypting a stream read from the inner reader.
+///
+/// Reaching the end of the stream, when `read` returns `Ok(0)`, means the
+/// stream was complete and authentic. Data read before an error must be
+/// discarded.
+pub struct DecryptingReader<R: Read> {
+    inner: R,
+    decryptor: Option<Decryptor>,
+    failed: bool,
+    plaintext: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptingReader<R> {
+    /// Create a decrypting reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key the stream was encrypted with
+    /// * `inner` - reader to read the ciphertext from
+    pub fn new(key: &EncryptionKey, inner: R) -> Self {
+        Self {
+            inner,
+            decryptor: Some(Decryptor::new(key)),
+            failed: false,
+            plaintext: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Get the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for DecryptingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.failed {
+                return Err(into_io(Error::InvalidStream(
+                    "the stream failed to decrypt".to_string(),
+                )));
+            }
+            let Some(decryptor) = &mut self.decryptor else { return Ok(0) };
+
+            let mut ciphertext = [0u8; READ_LEN];
+            let read = self.inner.read(&mut ciphertext)?;
+            let plaintext = if read == 0 {
+                self.decryptor
+                    .take()
+                    .map_or(Ok(Vec::new()), Decryptor::finish)
+            } else {
+                decryptor.update(&ciphertext[..read])
+            };
+
+            self.failed = plaintext.is_err();
+            self.plaintext = plaintext.map_err(into_io)?;
+            self.position = 0;
+        }
+
+        let len = buf.len().min(self.plaintext.len() - self.position);
+        buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// Encrypt an async stream of plaintext into a stream of ciphertext.
+///
+/// # Arguments
+///
+/// * `key` - key to encrypt with
+/// * `chunk_size` - number of plaintext bytes in each chunk
+/// * `input` - stream of plaintext, in parts of any length
+///
+/// # Errors
+///
+/// Returns an error if the encryptor could not be created. Errors from the
+/// input end the output stream.
+pub fn encrypt<S, B>(
+    key: &EncryptionKey,
+    chunk_size: usize,
+    input: S,
+) -> Result<impl Stream<Item = io::Result<Vec<u8>>>>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    Ok(transform(input, Encryptor::new(key, chunk_size)?))
+}
+
+/// Decrypt an async stream of ciphertext produced by [`Encryptor`] into a
+/// stream of plaintext.
+///
+/// The output stream ends without an error only if the input was complete and
+/// authentic. Data received before an error must be discarded.
+///
+/// # Arguments
+///
+/// * `key` - key the stream was encrypted with
+/// * `input` - stream of ciphertext, in parts of any length
+pub fn decrypt<S, B>(key: &EncryptionKey, input: S) -> impl Stream<Item = io::Result<Vec<u8>>>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    transform(input, Decryptor::new(key))
+}
+
+/// The operations shared by [`Encryptor`] and [`Decryptor`].
+trait Transform {
+    fn update(&mut self, data: &[u8]) -> Result<Vec<u8>>;
+    fn finish(self) -> Result<Vec<u8>>;
+}
+
+impl Transform for Encryptor {
+    fn update(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        Self::update(self, data)
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        Self::finish(self)
+    }
+}
+
+impl Transform for Decryptor {
+    fn update(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        Self::update(self, data)
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        Self::finish(self)
+    }
+}
+
+/// Apply a transform to every part of an async stream, skipping empty output
+/// and ending the stream after the first error.
+fn transform<S, B, T>(input: S, transform: T) -> impl Stream<Item = io::Result<Vec<u8>>>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+    T: Transform,
+{
+    stream::unfold(Some((input, transform)), |state| async move {
+        let (mut input, mut transform) = state?;
+        loop {
+            let output = match input.next().await {
+                Some(Ok(data)) => transform.update(data.as_ref()),
+                Some(Err(e)) => return Some((Err(e), None)),
+                None => {
+                    return match transform.finish() {
+                        Ok(output) if output.is_empty() => None,
+                        output => Some((output.map_err(into_io), None)),
+                    }
+                }
+            };
+
+            match output {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => return Some((Ok(output), Some((input, transform)))),
+                Err(e) => return Some((Err(into_io(e)), None)),
+            }
+        }
+    })
+}
+
+/// Parse a stream header.
+///
+/// # Returns
+///
+/// The chunk size and nonce prefix.
+fn parse_header(header: &[u8]) -> Result<(usize, &[u8])> {
+    let (magic, rest) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(Error::InvalidStream(
+            "the data is not an encrypted stream".to_string(),
+        ));
+    }
+
+    let (version, rest) = rest.split_at(1);
+    if version[0] != VERSION {
+        return Err(Error::InvalidStream(format!(
+            "unsupported version {}",
+            version[0]
+        )));
+    }
+
+    let (chunk_size, nonce_prefix) = rest.split_at(4);
+    let chunk_size = chunk_size
+        .try_into()
+        .ok()
+        .and_then(|bytes| usize::try_from(u32::from_be_bytes(bytes)).ok())
+        .filter(|chunk_size| (1..=MAX_CHUNK_SIZE).contains(chunk_size))
+        .ok_or_else(|| Error::InvalidStream("invalid chunk size".to_string()))?;
+
+    Ok((chunk_size, nonce_prefix))
+}
+
+fn cipher(key: &EncryptionKey) -> XChaCha20Poly1305 {
+    let key: &[u8; 32] = key.as_ref();
+    XChaCha20Poly1305::new(key.into())
+}
+
+fn into_io(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::TryStreamExt;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const CHUNK_SIZE: usize = 16;
+
+    fn encrypt_all(key: &EncryptionKey, plaintext: &[u8]) -> Vec<u8> {
+        let mut writer = EncryptingWriter::new(key, CHUNK_SIZE, Vec::new()).unwrap();
+        for part in plaintext.chunks(7) {
+            writer.write_all(part).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn decrypt_all(key: &EncryptionKey, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        DecryptingReader::new(key, ciphertext).read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = EncryptionKey::generate().unwrap();
+
+        for len in [
+            0,
+            1,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            CHUNK_SIZE + 1,
+            5 * CHUNK_SIZE,
+        ] {
+            let plaintext = (0..=u8::MAX).cycle().take(len).collect::<Vec<_>>();
+            let ciphertext = encrypt_all(&key, &plaintext);
+
+            let chunks = if len == 0 {
+                1
+            } else {
+                (len - 1) / CHUNK_SIZE + 1
+            };
+            assert_eq!(ciphertext.len(), HEADER_LEN + len + chunks * TAG_LEN);
+            assert_eq!(decrypt_all(&key, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let key = EncryptionKey::generate().unwrap();
+        let ciphertext = encrypt_all(&key, b"Hello, world!");
+
+        let other = EncryptionKey::generate().unwrap();
+        assert!(decrypt_all(&other, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_truncation_detected() {
+        let key = EncryptionKey::generate().unwrap();
+        let ciphertext = encrypt_all(&key, &[0u8; 3 * CHUNK_SIZE]);
+        let chunk_len = CHUNK_SIZE + TAG_LEN;
+
+        // Dropping whole chunks leaves a non-last chunk at the end.
+        assert!(decrypt_all(&key, &ciphertext[..ciphertext.len() - chunk_len]).is_err());
+        assert!(decrypt_all(&key, &ciphertext[..HEADER_LEN + chunk_len]).is_err());
+        assert!(decrypt_all(&key, &ciphertext[..ciphertext.len() - 1]).is_err());
+        assert!(decrypt_all(&key, &ciphertext[..HEADER_LEN]).is_err());
+        assert!(decrypt_all(&key, &ciphertext[..HEADER_LEN - 1]).is_err());
+        assert!(decrypt_all(&key, &[]).is_err());
+    }
+
+    #[test]
+    fn test_reordering_detected() {
+        let key = EncryptionKey::generate().unwrap();
+        let mut ciphertext = encrypt_all(&key, &[0u8; 3 * CHUNK_SIZE]);
+        let chunk_len = CHUNK_SIZE + TAG_LEN;
+
+        let (first, second) = ciphertext[HEADER_LEN..].split_at_mut(chunk_len);
+        first.swap_with_slice(&mut second[..chunk_len]);
+        assert!(decrypt_all(&key, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_header_authenticated() {
+        let key = EncryptionKey::generate().unwrap();
+        let ciphertext = encrypt_all(&key, &[0u8; 3 * CHUNK_SIZE]);
+
+        let mut wrong_version = ciphertext.clone();
+        wrong_version[MAGIC.len()] = VERSION + 1;
+        let err = decrypt_all(&key, &wrong_version).unwrap_err();
+        assert!(err.to_string().contains("unsupported version"));
+
+        let mut wrong_magic = ciphertext.clone();
+        wrong_magic[0] ^= 1;
+        assert!(decrypt_all(&key, &wrong_magic).is_err());
+
+        let mut wrong_chunk_size = ciphertext;
+        wrong_chunk_size[HEADER_LEN - NONCE_PREFIX_LEN - 1] ^= 1;
+        assert!(decrypt_all(&key, &wrong_chunk_size).is_err());
+    }
+
+    #[test]
+    fn test_invalid_chunk_size() {
+        let key = EncryptionKey::generate().unwrap();
+
+        assert!(Encryptor::new(&key, 0).is_err());
+        assert!(Encryptor::new(&key, MAX_CHUNK_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_async_stream() {
+        let key = EncryptionKey::generate().unwrap();
+        let plaintext = (0..100u8).collect::<Vec<_>>();
+
+        let input = stream::iter(plaintext.chunks(7).map(Ok::<_, io::Error>));
+        let ciphertext = block_on(encrypt(&key, CHUNK_SIZE, input).unwrap().try_concat()).unwrap();
+        assert_eq!(decrypt_all(&key, &ciphertext).unwrap(), plaintext);
+
+        let input = stream::iter(ciphertext.chunks(5).map(Ok::<_, io::Error>));
+        let decrypted = block_on(decrypt(&key, input).try_concat()).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let input = stream::iter(
+            ciphertext[..ciphertext.len() - 1]
+                .chunks(5)
+                .map(Ok::<_, io::Error>),
+        );
+        assert!(block_on(decrypt(&key, input).try_concat()).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_encrypt_decrypt_prop(plaintext in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let key = EncryptionKey::hash_from(b"key");
+            let ciphertext = encrypt_all(&key, &plaintext);
+            assert_eq!(decrypt_all(&key, &ciphertext).unwrap(), plaintext);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::seaql_migrations;
+
+use crate::sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use crate::Migrator;
+
+/// The SQL a migration runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationSql {
+    /// The name of the migration.
+    pub name: String,
+
+    /// The statements the migration runs, including the one recording that
+    /// it ran.
+    pub statements: Vec<String>,
+}
+
+impl Migrator {
+    /// Get the SQL that applying pending migrations would run, without
+    /// keeping its changes. The migrations run in a transaction that is
+    /// rolled back, so their SQL is checked against the database too.
+    ///
+    /// This replaces the metric callback of the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database the migrations would be applied to.
+    /// * `steps` - The number of migrations to apply, or `None` for all.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database is `MySQL`, which cannot roll back
+    /// schema changes, or if a migration fails.
+    pub async fn up_sql(
+        db: &mut DatabaseConnection,
+        steps: Option<u32>,
+    ) -> Result<Vec<MigrationSql>, DbErr> {
+        let pending = Self::pending(&*db).await?;
+        let migrations = Self::migrations()
+            .into_iter()
+            .filter(|migration| pending.iter().any(|name| name == migration.name()))
+            .take(steps.map_or(usize::MAX, |steps| steps as usize))
+            .collect();
+
+        dry_run(db, migrations, true).await
+    }
+
+    /// Get the SQL that rolling back applied migrations would run, without
+    /// keeping its changes. The migrations run in a transaction that is
+    /// rolled back, so their SQL is checked against the database too.
+    ///
+    /// This replaces the metric callback of the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database the migrations would be rolled back on.
+    /// * `steps` - The number of migrations to roll back, or `None` for all.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database is `MySQL`, which cannot roll back
+    /// schema changes, or if a migration fails.
+    pub async fn down_sql(
+        db: &mut DatabaseConnection,
+        steps: Option<u32>,
+    ) -> Result<Vec<MigrationSql>, DbErr> {
+        let pending = Self::pending(&*db).await?;
+        let migrations = Self::migrations()
+            .into_iter()
+            .rev()
+            .filter(|migration| !pending.iter().any(|name| name == migration.name()))
+            .take(steps.map_or(usize::MAX, |steps| steps as usize))
+            .collect();
+
+        dry_run(db, migrations, false).await
+    }
+}
+
+/// Run migrations in a transaction that is rolled back, recording their SQL.
+///
+/// # Arguments
+///
+/// * `db` - The database to run the migrations on.
+/// * `migrations` - The migrations to run, in order.
+/// * `up` - Whether to apply the migrations rather than roll them back.
+async fn dry_run(
+    db: &mut DatabaseConnection,
+    migrations: Vec<Box<dyn MigrationTrait>>,
+    up: bool,
+) -> Result<Vec<MigrationSql>, DbErr> {
+    if db.get_database_backend() == DbBackend::MySql {
+        return Err(DbErr::Custom(
+            "MySQL cannot roll back schema changes, so migrations cannot be dry-run on it"
+                .to_string(),
+        ));
+    }
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let callback_recorded = recorded.clone();
+    db.set_metric_callback(move |info| {
+        callback_recorded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(info.statement.to_string());
+    });
+
+    let transaction = db.begin().await?;
+    let manager = SchemaManager::new(&transaction);
+    let mut sql = Vec::new();
+
+    // The table recording applied migrations may not exist yet, and is only
+    // created within the transaction.
+    Migrator::install(&transaction).await?;
+
+    for migration in migrations {
+        recorded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+
+        if up {
+            migration.up(&manager).await?;
+            let applied_at = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+            seaql_migrations::Entity::insert(seaql_migrations::ActiveModel {
+                version: ActiveValue::Set(migration.name().to_string()),
+                applied_at: ActiveValue::Set(applied_at.try_into().unwrap_or(i64::MAX)),
+            })
+            .exec_without_returning(&transaction)
+            .await?;
+        } else {
+            migration.down(&manager).await?;
+            seaql_migrations::Entity::delete_many()
+                .filter(seaql_migrations::Column::Version.eq(migration.name()))
+                .exec(&transaction)
+                .await?;
+        }
+
+        sql.push(MigrationSql {
+            name: migration.name().to_string(),
+            statements: recorded
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .drain(..)
+                .collect(),
+        });
+    }
+
+    transaction.rollback().await?;
+
+    Ok(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sea_orm::{ConnectOptions, Database};
+
+    #[async_std::test]
+    async fn test_dry_run() {
+        let mut options = ConnectOptions::new("sqlite::memory:".to_string());
+        options.max_connections(1);
+        let mut db = Database::connect(options).await.unwrap();
+
+        let migrations = Migrator::up_sql(&mut db, Some(1)).await.unwrap();
+        assert_eq!(migrations.len(), 1);
+        assert_eq!(
+            migrations[0].name,
+            "m20230226_194527_create_user_credentials_table"
+        );
+        assert!(migrations[0].statements[0]
+            .starts_with(r#"CREATE TABLE IF NOT EXISTS "user_credentials""#));
+        assert!(migrations[0]
+            .statements
+            .last()
+            .unwrap()
+            .starts_with(r#"INSERT INTO "seaql_migrations""#));
+
+        // Nothing was run.
+        assert_eq!(
+            Migrator::pending(&db).await.unwrap().len(),
+            Migrator::migrations().len()
+        );
+        assert_eq!(Migrator::down_sql(&mut db, None).await.unwrap(), []);
+        assert!(!SchemaManager::new(&db)
+            .has_table("seaql_migrations")
+            .await
+            .unwrap());
+
+        Migrator::up(&db, None).await.unwrap();
+        let names: Vec<String> = Migrator::down_sql(&mut db, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|migration| migration.name)
+            .collect();
+        let mut expected: Vec<String> = Migrator::migrations()
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect();
+        expected.reverse();
+        assert_eq!(names, expected);
+        assert!(Migrator::pending(&db).await.unwrap().is_empty());
+    }
+}
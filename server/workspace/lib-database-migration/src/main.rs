@@ -0,0 +1,244 @@
+#![deny(
+    clippy::complexity,
+    clippy::correctness,
+    clippy::perf,
+    clippy::style,
+    clippy::suspicious,
+    clippy::pedantic
+)]
+
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
+
+use lib_database_migration::sea_orm::prelude::DateTimeUtc;
+use lib_database_migration::sea_orm::Database;
+use lib_database_migration::{DbErr, MigrationSql, Migrator, MigratorTrait};
+use lib_environment::{DbConnectionString, EnvironmentVariable};
+
+const USAGE: &str = "\
+Usage: migrate <command>
+
+Commands:
+  status                List the migrations and whether they are applied
+  up [N] [--dry-run]    Apply the first N pending migrations, or all of them
+  down [N] [--dry-run]  Roll back the last N applied migrations, or the last one
+  fresh                 Drop every table, then apply all migrations
+
+With --dry-run, the SQL is printed instead of run. Neither status nor
+--dry-run changes the database. The database is the one the server's
+DB_CONNECTION_STRING environment variable points at.";
+
+/// A command given on the command line.
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Status,
+    Up { steps: Option<u32>, dry_run: bool },
+    Down { steps: u32, dry_run: bool },
+    Fresh,
+}
+
+impl Command {
+    /// Parse a command from its arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, without the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the arguments are not a valid command.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((command, args)) = args.split_first() else {
+            return Err("No command given".to_string());
+        };
+
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let mut steps = None;
+        for arg in args.iter().filter(|arg| *arg != "--dry-run") {
+            if steps.is_some() {
+                return Err(format!("Unexpected argument: {arg}"));
+            }
+            let step = arg
+                .parse()
+                .map_err(|_| format!("Not a number of migrations: {arg}"))?;
+            steps = Some(step);
+        }
+
+        match command.as_str() {
+            "status" | "fresh" if dry_run || steps.is_some() => {
+                Err(format!("{command} takes no arguments"))
+            }
+            "status" => Ok(Self::Status),
+            "up" => Ok(Self::Up { steps, dry_run }),
+            "down" => Ok(Self::Down {
+                steps: steps.unwrap_or(1),
+                dry_run,
+            }),
+            "fresh" => Ok(Self::Fresh),
+            _ => Err(format!("Unknown command: {command}")),
+        }
+    }
+
+    /// Run the command against the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection returns one.
+    async fn run(self) -> Result<(), DbErr> {
+        let mut db = Database::connect(DbConnectionString::get()).await?;
+
+        match self {
+            Self::Status => {
+                for state in Migrator::states(&db).await? {
+                    match state.applied_at {
+                        Some(applied_at) => {
+                            let seconds = u64::try_from(applied_at).unwrap_or_default();
+                            let applied_at = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+                            println!("Applied  {}  {}", state.name, DateTimeUtc::from(applied_at));
+                        }
+                        None => println!("Pending  {}", state.name),
+                    }
+                }
+            }
+            Self::Up {
+                steps,
+                dry_run: true,
+            } => print_sql(Migrator::up_sql(&mut db, steps).await?),
+            Self::Up { steps, .. } => {
+                let mut pending = Migrator::pending(&db).await?;
+                if let Some(steps) = steps {
+                    pending.truncate(steps as usize);
+                }
+                Migrator::up(&db, steps).await?;
+                for name in pending {
+                    println!("Applied  {name}");
+                }
+            }
+            Self::Down {
+                steps,
+                dry_run: true,
+            } => print_sql(Migrator::down_sql(&mut db, Some(steps)).await?),
+            Self::Down { steps, .. } => {
+                let applied = Migrator::states(&db).await?;
+                Migrator::down(&db, Some(steps)).await?;
+                for state in applied
+                    .into_iter()
+                    .rev()
+                    .filter(|state| !state.is_pending())
+                    .take(steps as usize)
+                {
+                    println!("Rolled back  {}", state.name);
+                }
+            }
+            Self::Fresh => {
+                Migrator::fresh(&db).await?;
+                for name in Migrator::states(&db)
+                    .await?
+                    .into_iter()
+                    .map(|state| state.name)
+                {
+                    println!("Applied  {name}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Print the SQL migrations would run as a script.
+///
+/// # Arguments
+///
+/// * `migrations` - The migrations to print.
+fn print_sql(migrations: Vec<MigrationSql>) {
+    if migrations.is_empty() {
+        println!("-- Nothing to run");
+    }
+    for migration in migrations {
+        println!("-- {}", migration.name);
+        for statement in migration.statements {
+            println!("{statement};");
+        }
+        println!();
+    }
+}
+
+#[async_std::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match Command::parse(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match command.run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&["status"]), Ok(Command::Status));
+        assert_eq!(parse(&["fresh"]), Ok(Command::Fresh));
+        assert_eq!(
+            parse(&["up"]),
+            Ok(Command::Up {
+                steps: None,
+                dry_run: false
+            })
+        );
+        assert_eq!(
+            parse(&["up", "--dry-run", "2"]),
+            Ok(Command::Up {
+                steps: Some(2),
+                dry_run: true
+            })
+        );
+        assert_eq!(
+            parse(&["down"]),
+            Ok(Command::Down {
+                steps: 1,
+                dry_run: false
+            })
+        );
+        assert_eq!(
+            parse(&["down", "3", "--dry-run"]),
+            Ok(Command::Down {
+                steps: 3,
+                dry_run: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for args in [
+            &[][..],
+            &["sideways"],
+            &["up", "two"],
+            &["up", "-1"],
+            &["down", "1", "2"],
+            &["status", "1"],
+            &["fresh", "--dry-run"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+    }
+}
@@ -1,9 +1,5 @@
 use sea_orm_migration::prelude::*;
 
-use lib_crypto::hash_password;
-
-use crate::sea_orm::prelude::Uuid;
-
 #[derive(DeriveMigrationName)]
 pub struct Migration;
 
@@ -62,24 +58,6 @@ impl MigrationTrait for Migration {
             )
             .await?;
 
-        let insert = Query::insert()
-            .into_table(UserCredentials::Table)
-            .columns([
-                UserCredentials::Id,
-                UserCredentials::Username,
-                UserCredentials::PasswordHash,
-                UserCredentials::IsEnabled,
-            ])
-            .values_panic([
-                Uuid::new_v4().into(),
-                "admin".into(),
-                hash_password(b"admin").unwrap().into(),
-                true.into(),
-            ])
-            .clone();
-
-        manager.exec_stmt(insert).await?;
-
         Ok(())
     }
 
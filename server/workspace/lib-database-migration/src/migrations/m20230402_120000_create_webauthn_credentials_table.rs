@@ -0,0 +1,125 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const IDX_USER_ID: &str = "idx-webauthn_credentials_user_id";
+const FK_USER_ID: &str = "fk-webauthn_credentials_user_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // CREATE TABLE IF NOT EXISTS "webauthn_credentials" (
+        //     "id" UUID NOT NULL PRIMARY KEY,
+        //     "user_id" UUID NOT NULL,
+        //     "credential_id" VARCHAR NOT NULL UNIQUE,
+        //     "public_key" TEXT NOT NULL,
+        //     "sign_count" BIGINT NOT NULL DEFAULT 0,
+        //     "transports" VARCHAR NULL,
+        //     "credential" TEXT NOT NULL,
+        //     CONSTRAINT "fk-webauthn_credentials_user_id" FOREIGN KEY ("user_id")
+        //         REFERENCES "user_credentials" ("id") ON DELETE CASCADE
+        // );
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebauthnCredentials::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::UserId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::CredentialId)
+                            .string()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::PublicKey)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::SignCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(WebauthnCredentials::Transports).string())
+                    .col(
+                        ColumnDef::new(WebauthnCredentials::Credential)
+                            .text()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_USER_ID)
+                            .from(WebauthnCredentials::Table, WebauthnCredentials::UserId)
+                            .to(UserCredentials::Table, UserCredentials::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .clone(),
+            )
+            .await?;
+
+        // CREATE INDEX IF NOT EXISTS "idx-webauthn_credentials_user_id" ON "webauthn_credentials" ("user_id");
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(IDX_USER_ID)
+                    .table(WebauthnCredentials::Table)
+                    .col(WebauthnCredentials::UserId)
+                    .clone(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // DROP INDEX "idx-webauthn_credentials_user_id";
+        manager
+            .drop_index(Index::drop().name(IDX_USER_ID).clone())
+            .await?;
+
+        // DROP TABLE IF EXISTS "webauthn_credentials";
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(WebauthnCredentials::Table)
+                    .clone(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Learn more at <https://docs.rs/sea-query#iden>
+#[derive(Iden)]
+enum WebauthnCredentials {
+    Table,
+    Id,
+    UserId,
+    CredentialId,
+    PublicKey,
+    SignCount,
+    Transports,
+    Credential,
+}
+
+#[derive(Iden)]
+enum UserCredentials {
+    Table,
+    Id,
+}
@@ -0,0 +1,322 @@
+use std::time::SystemTime;
+
+use sea_orm_migration::prelude::*;
+
+use crate::sea_orm::prelude::{DateTimeUtc, DateTimeWithTimeZone};
+use crate::sea_orm::{ConnectionTrait, DbBackend, Statement, TransactionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const IDX_DELETED_AT: &str = "idx-user_credentials_deleted_at";
+
+/// The index created along with the table.
+const IDX_USERNAME: &str = "idx-user_username";
+
+/// The unique index on the usernames of users that have not been deleted.
+const IDX_LIVE_USERNAME: &str = "idx-user_credentials_live_username";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column per statement, and can only add a column that
+        // is not nullable if its default is constant, so existing rows are
+        // given the time of the migration.
+        //
+        // ALTER TABLE "user_credentials" ADD COLUMN "created_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '...';
+        // ALTER TABLE "user_credentials" ADD COLUMN "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '...';
+        // ALTER TABLE "user_credentials" ADD COLUMN "deleted_at" TIMESTAMP WITH TIME ZONE NULL;
+        let now: DateTimeWithTimeZone = DateTimeUtc::from(SystemTime::now()).into();
+        for mut column in [
+            ColumnDef::new(UserCredentials::CreatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(now)
+                .to_owned(),
+            ColumnDef::new(UserCredentials::UpdatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(now)
+                .to_owned(),
+            ColumnDef::new(UserCredentials::DeletedAt)
+                .timestamp_with_time_zone()
+                .null()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserCredentials::Table)
+                        .add_column(&mut column)
+                        .clone(),
+                )
+                .await?;
+        }
+
+        // The times are written again through the driver, which may store them
+        // in a different format than the literal default.
+        //
+        // UPDATE "user_credentials" SET "created_at" = $1, "updated_at" = $1;
+        let update = Query::update()
+            .table(UserCredentials::Table)
+            .values([
+                (UserCredentials::CreatedAt, now.into()),
+                (UserCredentials::UpdatedAt, now.into()),
+            ])
+            .clone();
+
+        manager.exec_stmt(update).await?;
+
+        // CREATE INDEX IF NOT EXISTS "idx-user_credentials_deleted_at" ON "user_credentials" ("deleted_at");
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(IDX_DELETED_AT)
+                    .table(UserCredentials::Table)
+                    .col(UserCredentials::DeletedAt)
+                    .clone(),
+            )
+            .await?;
+
+        // Deleted users keep their username, so it only has to be unique among
+        // the users that have not been deleted.
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Sqlite => rebuild_sqlite(manager, false, now).await?,
+            // ALTER TABLE "user_credentials" DROP CONSTRAINT "user_credentials_username_key";
+            DbBackend::Postgres => {
+                execute_raw(
+                    db,
+                    r#"ALTER TABLE "user_credentials" DROP CONSTRAINT "user_credentials_username_key""#,
+                )
+                .await?;
+            }
+            // MySQL has no partial indexes, so the unique index is on a column
+            // holding the username only while the user is not deleted, as it
+            // allows any number of NULLs.
+            DbBackend::MySql => {
+                execute_raw(
+                    db,
+                    "ALTER TABLE `user_credentials` DROP INDEX `username`, \
+                     ADD COLUMN `live_username` VARCHAR(255) \
+                     AS (IF(`deleted_at` IS NULL, `username`, NULL)) STORED",
+                )
+                .await?;
+                execute_raw(
+                    db,
+                    &format!(
+                        "CREATE UNIQUE INDEX `{IDX_LIVE_USERNAME}` ON `user_credentials` (`live_username`)"
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+
+        // CREATE UNIQUE INDEX "idx-user_credentials_live_username" ON "user_credentials" ("username") WHERE "deleted_at" IS NULL;
+        execute_raw(
+            db,
+            &format!(
+                r#"CREATE UNIQUE INDEX "{IDX_LIVE_USERNAME}" ON "user_credentials" ("username") WHERE "deleted_at" IS NULL"#
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Deleted users would come back to life once their deletion time is
+        // gone, so they are removed for good.
+        //
+        // DELETE FROM "user_credentials" WHERE "deleted_at" IS NOT NULL;
+        let delete = Query::delete()
+            .from_table(UserCredentials::Table)
+            .and_where(Expr::col(UserCredentials::DeletedAt).is_not_null())
+            .clone();
+
+        manager.exec_stmt(delete).await?;
+
+        // Usernames are unique again, now that no deleted user holds one.
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Sqlite => {
+                manager
+                    .drop_index(Index::drop().name(IDX_LIVE_USERNAME).clone())
+                    .await?;
+                rebuild_sqlite(manager, true, DateTimeUtc::from(SystemTime::now()).into()).await?;
+            }
+            // DROP INDEX "idx-user_credentials_live_username";
+            // ALTER TABLE "user_credentials" ADD CONSTRAINT "user_credentials_username_key" UNIQUE ("username");
+            DbBackend::Postgres => {
+                manager
+                    .drop_index(Index::drop().name(IDX_LIVE_USERNAME).clone())
+                    .await?;
+                execute_raw(
+                    db,
+                    r#"ALTER TABLE "user_credentials" ADD CONSTRAINT "user_credentials_username_key" UNIQUE ("username")"#,
+                )
+                .await?;
+            }
+            DbBackend::MySql => {
+                execute_raw(
+                    db,
+                    &format!(
+                        "ALTER TABLE `user_credentials` DROP INDEX `{IDX_LIVE_USERNAME}`, \
+                         DROP COLUMN `live_username`, ADD UNIQUE INDEX `username` (`username`)"
+                    ),
+                )
+                .await?;
+            }
+        }
+
+        // DROP INDEX "idx-user_credentials_deleted_at";
+        manager
+            .drop_index(Index::drop().name(IDX_DELETED_AT).clone())
+            .await?;
+
+        // ALTER TABLE "user_credentials" DROP COLUMN "deleted_at";
+        // ALTER TABLE "user_credentials" DROP COLUMN "updated_at";
+        // ALTER TABLE "user_credentials" DROP COLUMN "created_at";
+        for column in [
+            UserCredentials::DeletedAt,
+            UserCredentials::UpdatedAt,
+            UserCredentials::CreatedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserCredentials::Table)
+                        .drop_column(column)
+                        .clone(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rebuild the `user_credentials` table on `SQLite`, which cannot add or drop
+/// the unique constraint of a column in place, keeping its rows and indexes.
+///
+/// Dropping the old table deletes the passkeys of its users through their
+/// foreign key, so they are copied aside and back.
+///
+/// # Arguments
+///
+/// * `manager` - The schema manager.
+/// * `unique` - Whether the username column is unique.
+/// * `now` - The default time of the timestamp columns.
+async fn rebuild_sqlite(
+    manager: &SchemaManager<'_>,
+    unique: bool,
+    now: DateTimeWithTimeZone,
+) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let backend = manager.get_database_backend();
+    let transaction = db.begin().await?;
+
+    let mut username = ColumnDef::new(UserCredentials::Username);
+    username.string().not_null();
+    if unique {
+        username.unique_key();
+    }
+    let create = Table::create()
+        .table(UserCredentials::New)
+        .col(
+            ColumnDef::new(UserCredentials::Id)
+                .uuid()
+                .not_null()
+                .primary_key(),
+        )
+        .col(&mut username)
+        .col(
+            ColumnDef::new(UserCredentials::PasswordHash)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(UserCredentials::IsEnabled)
+                .boolean()
+                .not_null()
+                .default(true),
+        )
+        .col(
+            ColumnDef::new(UserCredentials::CreatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(now),
+        )
+        .col(
+            ColumnDef::new(UserCredentials::UpdatedAt)
+                .timestamp_with_time_zone()
+                .not_null()
+                .default(now),
+        )
+        .col(
+            ColumnDef::new(UserCredentials::DeletedAt)
+                .timestamp_with_time_zone()
+                .null(),
+        )
+        .clone();
+    transaction.execute(backend.build(&create)).await?;
+
+    for sql in [
+        r#"CREATE TABLE "webauthn_credentials_copy" AS SELECT * FROM "webauthn_credentials""#,
+        r#"INSERT INTO "user_credentials_new" SELECT "id", "username", "password_hash", "is_enabled", "created_at", "updated_at", "deleted_at" FROM "user_credentials""#,
+        r#"DROP TABLE "user_credentials""#,
+        r#"ALTER TABLE "user_credentials_new" RENAME TO "user_credentials""#,
+        r#"INSERT INTO "webauthn_credentials" SELECT * FROM "webauthn_credentials_copy""#,
+        r#"DROP TABLE "webauthn_credentials_copy""#,
+    ] {
+        execute_raw(&transaction, sql).await?;
+    }
+
+    for (name, column) in [
+        (IDX_USERNAME, UserCredentials::Username),
+        (IDX_DELETED_AT, UserCredentials::DeletedAt),
+    ] {
+        let index = Index::create()
+            .name(name)
+            .table(UserCredentials::Table)
+            .col(column)
+            .clone();
+        transaction.execute(backend.build(&index)).await?;
+    }
+
+    transaction.commit().await
+}
+
+/// Run SQL the query builder cannot express. Unlike
+/// `ConnectionTrait::execute_unprepared`, this goes through the metric
+/// callback of the connection, so dry runs print it.
+///
+/// # Arguments
+///
+/// * `db` - The connection to run the SQL on.
+/// * `sql` - The SQL to run.
+async fn execute_raw<C: ConnectionTrait>(db: &C, sql: &str) -> Result<(), DbErr> {
+    db.execute(Statement::from_string(
+        db.get_database_backend(),
+        sql.to_string(),
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Learn more at <https://docs.rs/sea-query#iden>
+#[derive(Iden)]
+enum UserCredentials {
+    Table,
+    #[iden = "user_credentials_new"]
+    New,
+    Id,
+    Username,
+    PasswordHash,
+    IsEnabled,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
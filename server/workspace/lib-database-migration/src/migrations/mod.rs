@@ -1 +1,3 @@
 pub mod m20230226_194527_create_user_credentials_table;
+pub mod m20230402_120000_create_webauthn_credentials_table;
+pub mod m20230501_120000_add_user_credentials_timestamps;
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::{seaql_migrations, IntoSchemaManagerConnection};
+
+use crate::sea_orm::{ConnectionTrait, EntityName};
+use crate::Migrator;
+
+/// The state of a migration in a database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationState {
+    /// The name of the migration.
+    pub name: String,
+
+    /// When the migration was applied, in seconds since the Unix epoch, or
+    /// `None` if it is pending.
+    pub applied_at: Option<i64>,
+}
+
+impl MigrationState {
+    /// Whether the migration is yet to be applied.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.applied_at.is_none()
+    }
+}
+
+impl Migrator {
+    /// Get the state of every migration, oldest first.
+    ///
+    /// Unlike the migrator's own status, this does not create the table
+    /// recording applied migrations, so a database that has none is left as
+    /// it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database has applied a migration that does not
+    /// exist, or if the database connection returns one.
+    pub async fn states<'c, C>(db: &'c C) -> Result<Vec<MigrationState>, DbErr>
+    where
+        C: ConnectionTrait,
+        &'c C: IntoSchemaManagerConnection<'c>,
+    {
+        let table = seaql_migrations::Entity.table_name();
+        let mut applied: HashMap<String, i64> = if SchemaManager::new(db).has_table(table).await? {
+            Self::get_migration_models(db)
+                .await?
+                .into_iter()
+                .map(|model| (model.version, model.applied_at))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let states = Self::migrations()
+            .iter()
+            .map(|migration| MigrationState {
+                name: migration.name().to_string(),
+                applied_at: applied.remove(migration.name()),
+            })
+            .collect();
+
+        if !applied.is_empty() {
+            let mut names: Vec<String> = applied.into_keys().collect();
+            names.sort();
+            return Err(DbErr::Custom(format!(
+                "Applied migrations are missing: {}",
+                names.join(", ")
+            )));
+        }
+
+        Ok(states)
+    }
+
+    /// Get the names of the migrations yet to be applied, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database has applied a migration that does not
+    /// exist, or if the database connection returns one.
+    pub async fn pending<'c, C>(db: &'c C) -> Result<Vec<String>, DbErr>
+    where
+        C: ConnectionTrait,
+        &'c C: IntoSchemaManagerConnection<'c>,
+    {
+        Ok(Self::states(db)
+            .await?
+            .into_iter()
+            .filter(MigrationState::is_pending)
+            .map(|state| state.name)
+            .collect())
+    }
+}
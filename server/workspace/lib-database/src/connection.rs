@@ -5,6 +5,7 @@ use sea_orm::ConnectOptions;
 
 use lib_database_migration::{Migrator, MigratorTrait};
 
+use crate::transaction::{Executor, Transaction, TransactionFuture};
 use crate::{Error, Result};
 
 /// A database connection controller.
@@ -42,6 +43,67 @@ impl Connection {
         Ok(())
     }
 
+    /// Get the names of the database migrations yet to be applied, oldest
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the migration state could not be read.
+    pub async fn pending_migrations(&self) -> Result<Vec<String>> {
+        Migrator::pending(&self.connection)
+            .await
+            .map_err(|e| Error::Migration(e.to_string()))
+    }
+
+    /// Begin a transaction, which is rolled back unless it is committed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction could not be begun.
+    pub async fn begin(&self) -> Result<Transaction> {
+        Transaction::begin_on(&self.connection).await
+    }
+
+    /// Run a callback in a transaction, committing it if the callback succeeds
+    /// and rolling it back if it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback to run, which returns a boxed future.
+    ///
+    /// # Errors
+    ///
+    /// Returns the callback's error, or an error if the transaction could not
+    /// be begun or committed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn example(connection: lib_database::Connection) -> lib_database::Result<()> {
+    /// use lib_database::{UserCredentialsController, UserCredentialsFilter};
+    ///
+    /// let controller = UserCredentialsController::new(connection.clone());
+    /// connection
+    ///     .transaction(|transaction| {
+    ///         let controller = controller.on(transaction);
+    ///         Box::pin(async move {
+    ///             let filter = UserCredentialsFilter::default().enabled(false);
+    ///             controller.delete_many(filter).await
+    ///         })
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn transaction<F, T, E>(&self, callback: F) -> std::result::Result<T, E>
+    where
+        F: for<'c> FnOnce(&'c Transaction) -> TransactionFuture<'c, T, E> + Send,
+        T: Send,
+        E: From<Error> + Send,
+    {
+        Transaction::run_on(&self.connection, callback).await
+    }
+
     /// Close the connection controller.
     ///
     /// # Errors
@@ -62,6 +124,14 @@ impl AsRef<sea_orm::DatabaseConnection> for Connection {
     }
 }
 
+impl Executor for Connection {
+    type Connection = sea_orm::DatabaseConnection;
+
+    fn connection(&self) -> &Self::Connection {
+        &self.connection
+    }
+}
+
 /// A database connection configuration object.
 pub struct Options {
     options: ConnectOptions,
@@ -1 +1,2 @@
 pub mod user_credentials;
+pub mod webauthn_credentials;
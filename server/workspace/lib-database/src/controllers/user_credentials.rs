@@ -1,18 +1,35 @@
+use std::ops::RangeBounds;
+
 use sea_orm::prelude::*;
 use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::Condition;
 
 use crate::entities::prelude::UserCredentials;
-use crate::entities::user_credentials::{ActiveModel, Column, Model};
-use crate::Result;
+use crate::entities::user_credentials::{ActiveModel, Column};
+use crate::predicate::Predicate;
+use crate::repository::{self, Repository, Table};
+
+pub type Controller<C = crate::Connection> = Repository<UserCredentials, C>;
+
+impl Table for UserCredentials {
+    type ActiveModel = ActiveModel;
+    type Filter = Filter;
+    type Write = Write;
+
+    fn updated_at() -> Option<Column> {
+        Some(Column::UpdatedAt)
+    }
 
-pub struct Controller {
-    connection: crate::Connection,
+    fn deleted_at() -> Option<Column> {
+        Some(Column::DeletedAt)
+    }
 }
 
 #[derive(Default)]
 pub struct Filter {
-    pub id: Option<Uuid>,
-    pub username: Option<String>,
+    pub id: Option<Predicate<Uuid>>,
+    pub username: Option<Predicate<String>>,
+    pub password_hash: Option<String>,
     pub is_enabled: Option<bool>,
 }
 
@@ -24,7 +41,18 @@ impl Filter {
     /// - `id`: The ID of the user to filter by.
     #[must_use]
     pub fn id(mut self, id: Uuid) -> Self {
-        self.id = Some(id);
+        self.id = Some(Predicate::Eq(id));
+        self
+    }
+
+    /// Set the IDs of the users to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: The IDs of the users to filter by.
+    #[must_use]
+    pub fn ids(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.id = Some(Predicate::is_in(ids));
         self
     }
 
@@ -35,7 +63,53 @@ impl Filter {
     /// - `username`: The username of the user to filter by.
     #[must_use]
     pub fn username(mut self, username: String) -> Self {
-        self.username = Some(username);
+        self.username = Some(Predicate::Eq(username));
+        self
+    }
+
+    /// Set the usernames of the users to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `usernames`: The usernames of the users to filter by.
+    #[must_use]
+    pub fn usernames(mut self, usernames: impl IntoIterator<Item = String>) -> Self {
+        self.username = Some(Predicate::is_in(usernames));
+        self
+    }
+
+    /// Set the prefix of the usernames of the users to filter by. Whether case
+    /// is ignored depends on the database, as described on
+    /// [`Prefix`](crate::Prefix).
+    ///
+    /// # Parameters
+    ///
+    /// - `prefix`: The text usernames start with.
+    #[must_use]
+    pub fn username_prefix(mut self, prefix: String) -> Self {
+        self.username = Some(Predicate::prefix(prefix));
+        self
+    }
+
+    /// Set the range of the usernames of the users to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `range`: The range usernames lie within.
+    #[must_use]
+    pub fn username_range(mut self, range: impl RangeBounds<String>) -> Self {
+        self.username = Some(Predicate::range(range));
+        self
+    }
+
+    /// Set the password hash of the user to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `password_hash`: The password hash of the user to filter by.
+    #[must_use]
+    pub fn password_hash(mut self, password_hash: String) -> Self {
+        self.password_hash = Some(password_hash);
         self
     }
 
@@ -78,213 +152,30 @@ impl Write {
     }
 }
 
-impl Controller {
-    #[must_use]
-    pub fn new(connection: crate::Connection) -> Self {
-        Self { connection }
-    }
-
-    /// Create a new user credential.
-    ///
-    /// # Parameters
-    ///
-    /// - `write`: The data to write.
-    ///
-    /// # Returns
-    ///
-    /// The ID of the newly created user credential.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn create(&self, write: Write) -> Result<Uuid> {
-        let id = Uuid::new_v4();
-        let user_credentials = {
-            let mut tmp = get_write(write);
-            tmp.id = Set(id);
-            tmp
-        };
-
-        user_credentials.insert(self.connection.as_ref()).await?;
-
-        Ok(id)
-    }
-
-    /// Read a user credential.
-    ///
-    /// # Parameters
-    ///
-    /// - `filter`: The filter to apply.
-    ///
-    /// # Returns
-    ///
-    /// The user credential.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn read(&self, filter: Filter) -> Result<Option<Model>> {
-        let model = get_filter(UserCredentials::find(), filter)
-            .one(self.connection.as_ref())
-            .await?;
-
-        Ok(model)
-    }
-
-    /// Read many user credentials.
-    ///
-    /// # Parameters
-    ///
-    /// - `filter`: The filter to apply.
-    ///
-    /// # Returns
-    ///
-    /// The user credentials.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn read_many(&self, filter: Filter) -> Result<Vec<Model>> {
-        let models = get_filter(UserCredentials::find(), filter)
-            .all(self.connection.as_ref())
-            .await?;
-
-        Ok(models)
-    }
-
-    /// Update a user credential.
-    ///
-    /// # Parameters
-    ///
-    /// - `id`: The ID of the user credential to update.
-    /// - `write`: The data to write.
-    ///
-    /// # Returns
-    ///
-    /// The updated user credential.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn update(&self, id: Uuid, write: Write) -> Result<Model> {
-        let write = {
-            let mut tmp = get_write(write);
-            tmp.id = Set(id);
-            tmp
-        };
-        let model = UserCredentials::update(write)
-            .filter(Column::Id.eq(id))
-            .exec(self.connection.as_ref())
-            .await?;
-
-        Ok(model)
-    }
-
-    /// Update many user credentials.
-    ///
-    /// # Parameters
-    ///
-    /// - `filter`: The filter to apply.
-    /// - `write`: The data to write.
-    ///
-    /// # Returns
-    ///
-    /// The number of rows updated.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn update_many(&self, filter: Filter, write: Write) -> Result<u64> {
-        let write = get_write(write);
-
-        let result = get_filter(UserCredentials::update_many(), filter)
-            .set(write)
-            .exec(self.connection.as_ref())
-            .await?;
-
-        Ok(result.rows_affected)
-    }
-
-    /// Delete a user credential.
-    ///
-    /// # Parameters
-    ///
-    /// - `id`: The ID of the user credential to delete.
-    ///
-    /// # Returns
-    ///
-    /// The number of rows deleted.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn delete(&self, id: Uuid) -> Result<u64> {
-        let result = UserCredentials::delete_by_id(id)
-            .exec(self.connection.as_ref())
-            .await?;
-
-        Ok(result.rows_affected)
-    }
-
-    /// Delete many user credentials.
-    ///
-    /// # Parameters
-    ///
-    /// - `filter`: The filter to use when deleting.
-    ///
-    /// # Returns
-    ///
-    /// The number of rows deleted.
-    ///
-    /// # Errors
-    ///
-    /// If the database connection returns an error.
-    pub async fn delete_many(&self, filter: Filter) -> Result<u64> {
-        let result = get_filter(UserCredentials::delete_many(), filter)
-            .exec(self.connection.as_ref())
-            .await?;
-
-        Ok(result.rows_affected)
-    }
-}
-
-/// Get a query filter from a filter.
-///
-/// # Parameters
-///
-/// - `query`: The query to filter.
-/// - `filter`: The filter to use.
-///
-/// # Returns
-///
-/// The filtered query.
-fn get_filter<T: QueryFilter>(mut query: T, filter: Filter) -> T {
-    let Filter {
-        id,
-        username,
-        is_enabled,
-    } = filter;
-
-    if let Some(id) = id {
-        query = query.filter(Column::Id.eq(id));
-    }
-
-    if let Some(username) = username {
-        query = query.filter(Column::Username.eq(username));
-    }
-
-    if let Some(is_enabled) = is_enabled {
-        query = query.filter(Column::IsEnabled.eq(is_enabled));
+impl repository::Filter<UserCredentials> for Filter {
+    fn condition(self) -> Condition {
+        let Filter {
+            id,
+            username,
+            password_hash,
+            is_enabled,
+        } = self;
+
+        Condition::all()
+            .add_option(id.map(|id| id.condition(Column::Id)))
+            .add_option(username.map(|username| username.condition(Column::Username)))
+            .add_option(password_hash.map(|password_hash| Column::PasswordHash.eq(password_hash)))
+            .add_option(is_enabled.map(|is_enabled| Column::IsEnabled.eq(is_enabled)))
     }
-
-    query
 }
 
-fn get_write(write: Write) -> ActiveModel {
-    ActiveModel {
-        username: write.username.map_or(NotSet, Set),
-        password_hash: write.password_hash.map_or(NotSet, Set),
-        is_enabled: write.is_enabled.map_or(NotSet, Set),
-        ..Default::default()
+impl repository::Write<UserCredentials> for Write {
+    fn active_model(self) -> ActiveModel {
+        ActiveModel {
+            username: self.username.map_or(NotSet, Set),
+            password_hash: self.password_hash.map_or(NotSet, Set),
+            is_enabled: self.is_enabled.map_or(NotSet, Set),
+            ..Default::default()
+        }
     }
 }
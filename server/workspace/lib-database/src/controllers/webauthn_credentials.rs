@@ -0,0 +1,170 @@
+use sea_orm::prelude::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::Condition;
+
+use crate::entities::prelude::WebauthnCredentials;
+use crate::entities::webauthn_credentials::{ActiveModel, Column};
+use crate::predicate::Predicate;
+use crate::repository::{self, Repository, Table};
+
+pub type Controller<C = crate::Connection> = Repository<WebauthnCredentials, C>;
+
+impl Table for WebauthnCredentials {
+    type ActiveModel = ActiveModel;
+    type Filter = Filter;
+    type Write = Write;
+}
+
+#[derive(Default)]
+pub struct Filter {
+    pub id: Option<Predicate<Uuid>>,
+    pub user_id: Option<Predicate<Uuid>>,
+    pub credential_id: Option<Predicate<String>>,
+}
+
+impl Filter {
+    /// Set the ID of the credential to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The ID of the credential to filter by.
+    #[must_use]
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(Predicate::Eq(id));
+        self
+    }
+
+    /// Set the IDs of the credentials to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `ids`: The IDs of the credentials to filter by.
+    #[must_use]
+    pub fn ids(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.id = Some(Predicate::is_in(ids));
+        self
+    }
+
+    /// Set the ID of the user who owns the credential to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `user_id`: The ID of the user to filter by.
+    #[must_use]
+    pub fn user_id(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(Predicate::Eq(user_id));
+        self
+    }
+
+    /// Set the IDs of the users who own the credentials to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `user_ids`: The IDs of the users to filter by.
+    #[must_use]
+    pub fn user_ids(mut self, user_ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.user_id = Some(Predicate::is_in(user_ids));
+        self
+    }
+
+    /// Set the authenticator-assigned credential ID to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `credential_id`: The credential ID to filter by.
+    #[must_use]
+    pub fn credential_id(mut self, credential_id: String) -> Self {
+        self.credential_id = Some(Predicate::Eq(credential_id));
+        self
+    }
+
+    /// Set the authenticator-assigned credential IDs to filter by.
+    ///
+    /// # Parameters
+    ///
+    /// - `credential_ids`: The credential IDs to filter by.
+    #[must_use]
+    pub fn credential_ids(mut self, credential_ids: impl IntoIterator<Item = String>) -> Self {
+        self.credential_id = Some(Predicate::is_in(credential_ids));
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Write {
+    pub user_id: Option<Uuid>,
+    pub credential_id: Option<String>,
+    pub public_key: Option<String>,
+    pub sign_count: Option<i64>,
+    pub transports: Option<Option<String>>,
+    pub credential: Option<String>,
+}
+
+impl Write {
+    #[must_use]
+    pub fn user_id(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    #[must_use]
+    pub fn credential_id(mut self, credential_id: String) -> Self {
+        self.credential_id = Some(credential_id);
+        self
+    }
+
+    #[must_use]
+    pub fn public_key(mut self, public_key: String) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+
+    #[must_use]
+    pub fn sign_count(mut self, sign_count: i64) -> Self {
+        self.sign_count = Some(sign_count);
+        self
+    }
+
+    #[must_use]
+    pub fn transports(mut self, transports: Option<String>) -> Self {
+        self.transports = Some(transports);
+        self
+    }
+
+    #[must_use]
+    pub fn credential(mut self, credential: String) -> Self {
+        self.credential = Some(credential);
+        self
+    }
+}
+
+impl repository::Filter<WebauthnCredentials> for Filter {
+    fn condition(self) -> Condition {
+        let Filter {
+            id,
+            user_id,
+            credential_id,
+        } = self;
+
+        Condition::all()
+            .add_option(id.map(|id| id.condition(Column::Id)))
+            .add_option(user_id.map(|user_id| user_id.condition(Column::UserId)))
+            .add_option(
+                credential_id.map(|credential_id| credential_id.condition(Column::CredentialId)),
+            )
+    }
+}
+
+impl repository::Write<WebauthnCredentials> for Write {
+    fn active_model(self) -> ActiveModel {
+        ActiveModel {
+            user_id: self.user_id.map_or(NotSet, Set),
+            credential_id: self.credential_id.map_or(NotSet, Set),
+            public_key: self.public_key.map_or(NotSet, Set),
+            sign_count: self.sign_count.map_or(NotSet, Set),
+            transports: self.transports.map_or(NotSet, Set),
+            credential: self.credential.map_or(NotSet, Set),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use lib_crypto::Keyring;
+use sea_orm::sea_query::{
+    Alias, ArrayType, BlobSize, ColumnType, Expr, Nullable, Query, ValueType, ValueTypeErr,
+};
+use sea_orm::{ColIdx, ConnectionTrait, DbErr, QueryResult, TryGetError, TryGetable, Value};
+use uuid::Uuid;
+
+use crate::{Connection, Error, Result};
+
+/// The column [`Encrypted`] values read the ID of their row from.
+const ID_COLUMN: &str = "id";
+
+/// The keyring [`Encrypted`] values are encrypted and decrypted with.
+static KEYRING: RwLock<Option<Keyring>> = RwLock::new(None);
+
+/// Set the keyring [`Encrypted`] values are encrypted and decrypted with. This
+/// must be called before any entity with an [`Encrypted`] field is written or
+/// read, and replaces any keyring set before.
+///
+/// # Arguments
+///
+/// * `keyring` - The keyring to use.
+pub fn set_keyring(keyring: Keyring) {
+    let mut guard = KEYRING
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    *guard = Some(keyring);
+}
+
+/// Run a function with the keyring set by [`set_keyring`].
+///
+/// # Errors
+///
+/// Returns [`Error::Encryption`] if no keyring has been set, or the error the
+/// function returns.
+fn with_keyring<T>(f: impl FnOnce(&Keyring) -> lib_crypto::Result<T>) -> Result<T> {
+    let guard = KEYRING
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let keyring = guard
+        .as_ref()
+        .ok_or_else(|| Error::Encryption("no keyring has been set".to_string()))?;
+
+    f(keyring).map_err(|e| Error::Encryption(e.to_string()))
+}
+
+/// A column value encrypted with the keyring set by [`set_keyring`], for use
+/// as a field of an entity. The column must be binary, such as
+/// `ColumnDef::binary()`, and the entity's primary key must be a UUID column
+/// named `id`.
+///
+/// The value is encrypted when it is created, and decrypted when it is read
+/// from a query result, so entities hold the plaintext. The ID of the row is
+/// authenticated along with the value, so a value copied into another row will
+/// fail to decrypt.
+///
+/// Values can only be converted from a [`Value`] by reading them from a query
+/// result, because the [`Value`] does not hold the row's ID, so setting the
+/// field from JSON or through `ActiveModelTrait::set` fails.
+#[derive(Clone)]
+pub struct Encrypted {
+    row_id: Uuid,
+    plaintext: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl Encrypted {
+    /// Encrypt a value for storage in the given row.
+    ///
+    /// # Arguments
+    ///
+    /// * `row_id` - The ID of the row the value will be stored in.
+    /// * `plaintext` - The value to encrypt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring has been set, or the value could not be
+    /// encrypted.
+    pub fn new(row_id: Uuid, plaintext: impl Into<Vec<u8>>) -> Result<Self> {
+        let plaintext = plaintext.into();
+        let ciphertext = with_keyring(|keyring| keyring.encrypt(&plaintext, row_id.as_bytes()))?;
+
+        Ok(Self {
+            row_id,
+            plaintext,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt a value read from the given row.
+    ///
+    /// # Arguments
+    ///
+    /// * `row_id` - The ID of the row the value was read from.
+    /// * `ciphertext` - The value as stored in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no keyring has been set, or the value could not be
+    /// decrypted or was encrypted for a different row.
+    fn decrypt(row_id: Uuid, ciphertext: Vec<u8>) -> Result<Self> {
+        let plaintext = with_keyring(|keyring| keyring.decrypt(&ciphertext, row_id.as_bytes()))?;
+
+        Ok(Self {
+            row_id,
+            plaintext,
+            ciphertext,
+        })
+    }
+
+    /// Get the ID of the row the value belongs to.
+    #[must_use]
+    pub fn row_id(&self) -> Uuid {
+        self.row_id
+    }
+
+    /// Get the decrypted value.
+    #[must_use]
+    pub fn plaintext(&self) -> &[u8] {
+        &self.plaintext
+    }
+
+    /// Take the decrypted value.
+    #[must_use]
+    pub fn into_plaintext(self) -> Vec<u8> {
+        self.plaintext
+    }
+
+    /// Read the value and the ID of its row from a query result.
+    fn try_get_with_id<I: ColIdx>(
+        res: &QueryResult,
+        index: I,
+        id: &str,
+    ) -> std::result::Result<Self, TryGetError> {
+        let ciphertext = Vec::<u8>::try_get_by(res, index)?;
+        let row_id = Uuid::try_get_by(res, id)?;
+
+        Self::decrypt(row_id, ciphertext)
+            .map_err(|e| TryGetError::DbErr(DbErr::Type(format!("{index:?}: {e}"))))
+    }
+}
+
+impl PartialEq for Encrypted {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_id == other.row_id && self.plaintext == other.plaintext
+    }
+}
+
+impl Eq for Encrypted {}
+
+impl fmt::Debug for Encrypted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encrypted")
+            .field("row_id", &self.row_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<Encrypted> for Value {
+    fn from(value: Encrypted) -> Self {
+        value.ciphertext.into()
+    }
+}
+
+impl TryGetable for Encrypted {
+    fn try_get(res: &QueryResult, pre: &str, col: &str) -> std::result::Result<Self, TryGetError> {
+        Self::try_get_with_id(
+            res,
+            format!("{pre}{col}").as_str(),
+            &format!("{pre}{ID_COLUMN}"),
+        )
+    }
+
+    fn try_get_by<I: ColIdx>(
+        res: &QueryResult,
+        index: I,
+    ) -> std::result::Result<Self, TryGetError> {
+        Self::try_get_with_id(res, index, ID_COLUMN)
+    }
+}
+
+impl ValueType for Encrypted {
+    fn try_from(_: Value) -> std::result::Result<Self, ValueTypeErr> {
+        Err(ValueTypeErr)
+    }
+
+    fn type_name() -> String {
+        stringify!(Encrypted).to_owned()
+    }
+
+    fn array_type() -> ArrayType {
+        ArrayType::Bytes
+    }
+
+    fn column_type() -> ColumnType {
+        ColumnType::Binary(BlobSize::Blob(None))
+    }
+}
+
+impl Nullable for Encrypted {
+    fn null() -> Value {
+        Value::Bytes(None)
+    }
+}
+
+/// A column holding [`Encrypted`] values.
+pub struct EncryptedColumn {
+    /// The table the column belongs to.
+    pub table: &'static str,
+
+    /// The table's primary key column, which must be a UUID.
+    pub id: &'static str,
+
+    /// The column holding the ciphertext.
+    pub column: &'static str,
+}
+
+/// Every column holding [`Encrypted`] values, which are re-encrypted when the
+/// encryption key is rotated. A column must be added here along with the
+/// entity field that holds it.
+pub const ENCRYPTED_COLUMNS: &[EncryptedColumn] = &[];
+
+/// Re-encrypt every value in the given columns that is not already encrypted
+/// with the keyring's primary key.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection.
+/// * `keyring` - The keyring holding both the new primary key and the keys the data is encrypted with.
+/// * `columns` - The columns holding [`Encrypted`] values.
+///
+/// # Returns
+///
+/// The number of values re-encrypted.
+///
+/// # Errors
+///
+/// Returns an error if a value could not be read, decrypted or written. Values
+/// re-encrypted before the error are kept, so the rotation can be resumed by
+/// calling this again.
+pub async fn reencrypt(
+    connection: &Connection,
+    keyring: &Keyring,
+    columns: &[EncryptedColumn],
+) -> Result<u64> {
+    let db = connection.as_ref();
+    let backend = db.get_database_backend();
+    let mut count = 0;
+
+    for EncryptedColumn { table, id, column } in columns {
+        let select = Query::select()
+            .columns([Alias::new(id), Alias::new(column)])
+            .from(Alias::new(table))
+            .to_owned();
+
+        for row in db.query_all(backend.build(&select)).await? {
+            let row_id = row.try_get::<Uuid>("", id)?;
+            let Some(ciphertext) = row.try_get::<Option<Vec<u8>>>("", column)? else { continue };
+            let ciphertext = keyring
+                .reencrypt(&ciphertext, row_id.as_bytes())
+                .map_err(|e| Error::Encryption(e.to_string()))?;
+            let Some(ciphertext) = ciphertext else { continue };
+
+            let update = Query::update()
+                .table(Alias::new(table))
+                .value(Alias::new(column), ciphertext)
+                .and_where(Expr::col(Alias::new(id)).eq(row_id))
+                .to_owned();
+            db.execute(backend.build(&update)).await?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// A plaintext column being converted into an [`EncryptedColumn`].
+///
+/// To convert a column, add a migration creating a nullable binary column
+/// next to it, then call [`encrypt_plaintext`] once the migration has run, and
+/// drop the plaintext column in a later migration.
+pub struct PlaintextColumn {
+    /// The table the column belongs to.
+    pub table: &'static str,
+
+    /// The table's primary key column, which must be a UUID.
+    pub id: &'static str,
+
+    /// The text column holding the plaintext.
+    pub plaintext: &'static str,
+
+    /// The binary column to hold the ciphertext.
+    pub encrypted: &'static str,
+}
+
+/// Encrypt every value of a plaintext column into its encrypted column, and
+/// clear the plaintext.
+///
+/// # Arguments
+///
+/// * `connection` - The database connection.
+/// * `keyring` - The keyring to encrypt the values with.
+/// * `column` - The column to convert.
+///
+/// # Returns
+///
+/// The number of values encrypted.
+///
+/// # Errors
+///
+/// Returns an error if a value could not be read, encrypted or written. Values
+/// encrypted before the error are kept, and are skipped when this is called again.
+pub async fn encrypt_plaintext(
+    connection: &Connection,
+    keyring: &Keyring,
+    column: &PlaintextColumn,
+) -> Result<u64> {
+    let PlaintextColumn {
+        table,
+        id,
+        plaintext,
+        encrypted,
+    } = *column;
+    let db = connection.as_ref();
+    let backend = db.get_database_backend();
+    let mut count = 0;
+
+    let select = Query::select()
+        .columns([Alias::new(id), Alias::new(plaintext)])
+        .from(Alias::new(table))
+        .and_where(Expr::col(Alias::new(plaintext)).is_not_null())
+        .to_owned();
+
+    for row in db.query_all(backend.build(&select)).await? {
+        let row_id = row.try_get::<Uuid>("", id)?;
+        let value = row.try_get::<String>("", plaintext)?;
+        let ciphertext = keyring
+            .encrypt(value.as_bytes(), row_id.as_bytes())
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+
+        let update = Query::update()
+            .table(Alias::new(table))
+            .value(Alias::new(encrypted), ciphertext)
+            .value(Alias::new(plaintext), Option::<String>::None)
+            .and_where(Expr::col(Alias::new(id)).eq(row_id))
+            .to_owned();
+        db.execute(backend.build(&update)).await?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_crypto::Keyring;
+    use sea_orm::sea_query::{Alias, Query};
+    use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait, Set, Statement};
+    use uuid::Uuid;
+
+    use super::{
+        encrypt_plaintext, reencrypt, set_keyring, Encrypted, EncryptedColumn, PlaintextColumn,
+    };
+    use crate::{Connection, Options};
+
+    mod secret {
+        use sea_orm::entity::prelude::*;
+
+        use crate::Encrypted;
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+        #[sea_orm(table_name = "secrets")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: Uuid,
+            pub plaintext: Option<String>,
+            pub secret: Option<Encrypted>,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    const SECRETS: EncryptedColumn = EncryptedColumn {
+        table: "secrets",
+        id: "id",
+        column: "secret",
+    };
+
+    async fn connection() -> Connection {
+        let connection = Connection::connect(Options::new("sqlite::memory:".to_string()))
+            .await
+            .unwrap();
+        let db = connection.as_ref();
+        db.execute(Statement::from_string(
+            db.get_database_backend(),
+            "CREATE TABLE secrets (id BLOB PRIMARY KEY, plaintext TEXT, secret BLOB)".to_string(),
+        ))
+        .await
+        .unwrap();
+        connection
+    }
+
+    async fn ciphertexts(connection: &Connection) -> Vec<(Uuid, Vec<u8>)> {
+        let db = connection.as_ref();
+        let select = Query::select()
+            .columns([Alias::new("id"), Alias::new("secret")])
+            .from(Alias::new("secrets"))
+            .to_owned();
+
+        db.query_all(db.get_database_backend().build(&select))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| {
+                let id = row.try_get("", "id").unwrap();
+                let ciphertext = row.try_get("", "secret").unwrap();
+                (id, ciphertext)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_entity_round_trip() {
+        let connection = connection().await;
+        let keyring = Keyring::parse("1:entity secret").unwrap();
+        set_keyring(keyring.clone());
+        let db = connection.as_ref();
+
+        let mut ids = Vec::new();
+        for plaintext in ["first", "second"] {
+            let id = Uuid::new_v4();
+            secret::ActiveModel {
+                id: Set(id),
+                plaintext: Set(None),
+                secret: Set(Some(Encrypted::new(id, plaintext).unwrap())),
+            }
+            .insert(db)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let first = secret::Entity::find_by_id(ids[0])
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap();
+        let secret = first.secret.unwrap();
+        assert_eq!(secret.row_id(), ids[0]);
+        assert_eq!(secret.plaintext(), b"first");
+
+        let stored = ciphertexts(&connection).await;
+        let (_, ciphertext) = stored.iter().find(|(id, _)| *id == ids[0]).unwrap();
+        assert_ne!(ciphertext.as_slice(), b"first");
+        assert_eq!(
+            keyring.decrypt(ciphertext, ids[0].as_bytes()).unwrap(),
+            b"first"
+        );
+
+        let swap = Query::update()
+            .table(Alias::new("secrets"))
+            .value(Alias::new("secret"), ciphertext.clone())
+            .and_where(sea_orm::sea_query::Expr::col(Alias::new("id")).eq(ids[1]))
+            .to_owned();
+        db.execute(db.get_database_backend().build(&swap))
+            .await
+            .unwrap();
+        assert!(secret::Entity::find_by_id(ids[1]).one(db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reencrypt() {
+        let connection = connection().await;
+        let mut keyring = Keyring::parse("1:first secret").unwrap();
+
+        let db = connection.as_ref();
+        for plaintext in ["first", "second"] {
+            let insert = Query::insert()
+                .into_table(Alias::new("secrets"))
+                .columns([Alias::new("id"), Alias::new("plaintext")])
+                .values_panic([Uuid::new_v4().into(), plaintext.into()])
+                .to_owned();
+            db.execute(db.get_database_backend().build(&insert))
+                .await
+                .unwrap();
+        }
+
+        let plaintext = PlaintextColumn {
+            table: SECRETS.table,
+            id: SECRETS.id,
+            plaintext: "plaintext",
+            encrypted: SECRETS.column,
+        };
+        assert_eq!(
+            encrypt_plaintext(&connection, &keyring, &plaintext)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            encrypt_plaintext(&connection, &keyring, &plaintext)
+                .await
+                .unwrap(),
+            0
+        );
+
+        keyring.rotate().unwrap();
+        assert_eq!(
+            reencrypt(&connection, &keyring, &[SECRETS]).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            reencrypt(&connection, &keyring, &[SECRETS]).await.unwrap(),
+            0
+        );
+
+        let mut plaintexts = Vec::new();
+        for (id, ciphertext) in ciphertexts(&connection).await {
+            assert_eq!(lib_crypto::key_id(&ciphertext).unwrap(), 2);
+            plaintexts.push(keyring.decrypt(&ciphertext, id.as_bytes()).unwrap());
+        }
+        plaintexts.sort();
+        assert_eq!(plaintexts, [b"first".to_vec(), b"second".to_vec()]);
+    }
+}
@@ -3,3 +3,4 @@
 pub mod prelude;
 
 pub mod user_credentials;
+pub mod webauthn_credentials;
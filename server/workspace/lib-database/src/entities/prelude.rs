@@ -1,3 +1,4 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.0
 
 pub use super::user_credentials::Entity as UserCredentials;
+pub use super::webauthn_credentials::Entity as WebauthnCredentials;
@@ -1,3 +1,5 @@
+use sea_orm::{DbErr, RuntimeErr, SqlxError, SqlxMySqlError};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug, Eq, PartialEq)]
@@ -11,6 +13,66 @@ pub enum Error {
     #[error("Database migration error: {0}")]
     Migration(String),
 
+    #[error("Database encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Database record not found")]
+    NotFound,
+
+    #[error("Database record conflicts with an existing one: {0}")]
+    Conflict(String),
+
+    #[error("Invalid pagination cursor")]
+    InvalidCursor,
+
     #[error("Database error: {0}")]
-    Database(#[from] sea_orm::error::DbErr),
+    Database(DbErr),
+}
+
+/// The codes databases report a row breaking a unique or primary key
+/// constraint with: the `PostgreSQL` `SQLSTATE`, and the `SQLite` extended
+/// result codes.
+const CONFLICT_CODES: [&str; 3] = ["23505", "2067", "1555"];
+
+/// The `MySQL` error number for a duplicate unique or primary key. Its
+/// `SQLSTATE`, `23000`, is shared by every integrity constraint, including
+/// `NOT NULL` and foreign keys.
+const MYSQL_DUPLICATE_ENTRY: u16 = 1062;
+
+/// Get the message of a database error that is a row breaking a unique or
+/// primary key constraint.
+///
+/// # Arguments
+///
+/// * `error` - The error the driver reported.
+///
+/// # Returns
+///
+/// The database's message, or `None` if the error is not such a conflict.
+fn conflict(error: &SqlxError) -> Option<String> {
+    let SqlxError::Database(error) = error else {
+        return None;
+    };
+
+    let conflict = match error.try_downcast_ref::<SqlxMySqlError>() {
+        Some(error) => error.number() == MYSQL_DUPLICATE_ENTRY,
+        None => matches!(error.code(), Some(code) if CONFLICT_CODES.contains(&code.as_ref())),
+    };
+
+    conflict.then(|| error.message().to_string())
+}
+
+impl From<DbErr> for Error {
+    fn from(error: DbErr) -> Self {
+        match error {
+            DbErr::RecordNotFound(_) | DbErr::RecordNotUpdated => Self::NotFound,
+            DbErr::ConnectionAcquire | DbErr::Conn(_) => Self::Connection(error.to_string()),
+            DbErr::Exec(RuntimeErr::SqlxError(ref e))
+            | DbErr::Query(RuntimeErr::SqlxError(ref e)) => match conflict(e) {
+                Some(message) => Self::Conflict(message),
+                None => Self::Database(error),
+            },
+            error => Self::Database(error),
+        }
+    }
 }
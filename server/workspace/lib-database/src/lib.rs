@@ -13,11 +13,31 @@ pub use self::{
         Controller as UserCredentialsController, Filter as UserCredentialsFilter,
         Write as UserCredentialsWrite,
     },
-    entities::user_credentials::Model as UserCredentials,
+    controllers::webauthn_credentials::{
+        Controller as WebauthnCredentialsController, Filter as WebauthnCredentialsFilter,
+        Write as WebauthnCredentialsWrite,
+    },
+    encryption::{
+        encrypt_plaintext, reencrypt, set_keyring, Encrypted, EncryptedColumn, PlaintextColumn,
+        ENCRYPTED_COLUMNS,
+    },
+    entities::{
+        user_credentials::{Column as UserCredentialsColumn, Model as UserCredentials},
+        webauthn_credentials::{Column as WebauthnCredentialsColumn, Model as WebauthnCredentials},
+    },
     error::{Error, Result},
+    pagination::{Cursor, Order, Page, PageRequest},
+    predicate::{Predicate, Prefix},
+    repository::{Deleted, Filter, Id, Repository, Table, Write},
+    transaction::{Executor, Transaction, TransactionFuture},
 };
 
 mod connection;
 mod controllers;
+mod encryption;
 mod entities;
 mod error;
+mod pagination;
+mod predicate;
+mod repository;
+mod transaction;
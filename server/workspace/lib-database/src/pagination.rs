@@ -0,0 +1,424 @@
+use std::fmt;
+
+use lib_base64::{decode, encode};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{ColumnTrait, Condition, IdenStatic, ModelTrait, Value};
+
+use crate::{Error, Result};
+
+/// The direction rows are sorted in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// From the lowest value to the highest.
+    #[default]
+    Asc,
+
+    /// From the highest value to the lowest.
+    Desc,
+}
+
+impl From<Order> for sea_orm::Order {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Asc => Self::Asc,
+            Order::Desc => Self::Desc,
+        }
+    }
+}
+
+/// An opaque position in a sorted list of rows, from which the next page is
+/// read.
+///
+/// A cursor holds the sort keys of the last row of a page, so the next page
+/// starts right after it even if rows are added or removed in between. It is
+/// only valid for requests sorted the same way as the one it came from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cursor(String);
+
+impl Cursor {
+    /// Get the cursor as a URL-safe string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Create a cursor pointing just after a row.
+    ///
+    /// # Parameters
+    ///
+    /// - `model`: The row.
+    /// - `sort`: The columns the rows are sorted by.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Query`] if a sort column is null or of a type that
+    /// cannot be held in a cursor.
+    pub(crate) fn after<M: ModelTrait>(
+        model: &M,
+        sort: &[(<M::Entity as sea_orm::EntityTrait>::Column, Order)],
+    ) -> Result<Self> {
+        let keys = sort
+            .iter()
+            .map(|(column, _)| {
+                let value = encode_value(model.get(*column)).ok_or_else(|| {
+                    Error::Query(format!("Cannot paginate by column {}", column.as_str()))
+                })?;
+                Ok((column.as_str().to_string(), value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let json = serde_json::to_vec(&keys).map_err(|e| Error::Query(e.to_string()))?;
+        let cursor = encode(json).map_err(|e| Error::Query(e.to_string()))?;
+        Ok(Self(cursor))
+    }
+
+    /// Get the condition rows after the cursor meet.
+    ///
+    /// # Parameters
+    ///
+    /// - `sort`: The columns the rows are sorted by.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCursor`] if the cursor could not be decoded, or
+    /// was made for rows sorted by other columns.
+    pub(crate) fn condition<C: ColumnTrait>(&self, sort: &[(C, Order)]) -> Result<Condition> {
+        let json = decode(&self.0).map_err(|_| Error::InvalidCursor)?;
+        let keys: Vec<(String, String)> =
+            serde_json::from_slice(&json).map_err(|_| Error::InvalidCursor)?;
+
+        if keys.len() != sort.len() {
+            return Err(Error::InvalidCursor);
+        }
+
+        let mut values = Vec::with_capacity(keys.len());
+        for ((name, value), (column, _)) in keys.iter().zip(sort) {
+            if name != column.as_str() {
+                return Err(Error::InvalidCursor);
+            }
+            values.push(decode_value(value).ok_or(Error::InvalidCursor)?);
+        }
+
+        // (a, b) after (x, y) is a > x OR (a = x AND b > y), with < in place
+        // of > for descending columns.
+        let mut condition = Condition::any();
+        for (i, ((column, order), value)) in sort.iter().zip(&values).enumerate() {
+            let mut after = Condition::all();
+            for ((column, _), value) in sort.iter().zip(&values).take(i) {
+                after = after.add(column.eq(value.clone()));
+            }
+            after = after.add(match order {
+                Order::Asc => column.gt(value.clone()),
+                Order::Desc => column.lt(value.clone()),
+            });
+            condition = condition.add(after);
+        }
+
+        Ok(condition)
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Cursor {
+    fn from(cursor: String) -> Self {
+        Self(cursor)
+    }
+}
+
+/// Where a page starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Position {
+    /// After skipping a number of rows.
+    Offset(u64),
+
+    /// Right after the row a cursor points to.
+    After(Cursor),
+}
+
+/// Which page of rows to read, and how to sort them.
+///
+/// Rows are always sorted by their primary key last, so that every row has a
+/// distinct position and pages neither skip nor repeat rows.
+///
+/// # Examples
+///
+/// ```
+/// use lib_database::{Order, PageRequest, UserCredentialsColumn};
+///
+/// let request = PageRequest::new(50)
+///     .sort_by(UserCredentialsColumn::IsEnabled, Order::Desc)
+///     .sort_by(UserCredentialsColumn::Username, Order::Asc)
+///     .with_total();
+/// assert_eq!(request.limit(), 50);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageRequest<C> {
+    limit: u64,
+    position: Position,
+    sort: Vec<(C, Order)>,
+    total: bool,
+}
+
+impl<C: ColumnTrait> PageRequest<C> {
+    /// Create a request for the first page.
+    ///
+    /// # Parameters
+    ///
+    /// - `limit`: The most rows in the page, which is raised to at least one.
+    #[must_use]
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit: limit.max(1),
+            position: Position::Offset(0),
+            sort: Vec::new(),
+            total: false,
+        }
+    }
+
+    /// Start the page after skipping a number of rows.
+    ///
+    /// Offsets are simple, but each page is slower to read than the last, and
+    /// rows added or removed in between shift the pages. Prefer
+    /// [`PageRequest::after`] to walk through many rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `offset`: The number of rows to skip.
+    #[must_use]
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.position = Position::Offset(offset);
+        self
+    }
+
+    /// Start the page right after the row a cursor points to.
+    ///
+    /// # Parameters
+    ///
+    /// - `cursor`: The cursor of the previous page.
+    #[must_use]
+    pub fn after(mut self, cursor: Cursor) -> Self {
+        self.position = Position::After(cursor);
+        self
+    }
+
+    /// Sort the rows by a column, after any columns they are already sorted
+    /// by.
+    ///
+    /// Cursors hold the values of the sort columns, which must not be null.
+    ///
+    /// # Parameters
+    ///
+    /// - `column`: The column to sort by.
+    /// - `order`: The direction to sort in.
+    #[must_use]
+    pub fn sort_by(mut self, column: C, order: Order) -> Self {
+        self.sort.push((column, order));
+        self
+    }
+
+    /// Count every row matching the filter, as well as reading the page.
+    #[must_use]
+    pub fn with_total(mut self) -> Self {
+        self.total = true;
+        self
+    }
+
+    /// Get the most rows in the page.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Get the number of rows to skip, if the page starts at an offset.
+    pub(crate) fn offset_rows(&self) -> Option<u64> {
+        match self.position {
+            Position::Offset(offset) => Some(offset),
+            Position::After(_) => None,
+        }
+    }
+
+    /// Get the cursor the page starts after, if any.
+    pub(crate) fn cursor(&self) -> Option<&Cursor> {
+        match &self.position {
+            Position::Offset(_) => None,
+            Position::After(cursor) => Some(cursor),
+        }
+    }
+
+    /// Get whether every matching row should be counted.
+    pub(crate) fn counts_total(&self) -> bool {
+        self.total
+    }
+
+    /// Get the columns to sort by, followed by the primary key columns that
+    /// are not already among them.
+    ///
+    /// # Parameters
+    ///
+    /// - `keys`: The primary key columns.
+    pub(crate) fn sort_keys(&self, keys: impl IntoIterator<Item = C>) -> Vec<(C, Order)> {
+        let mut sort = self.sort.clone();
+        for key in keys {
+            if !sort
+                .iter()
+                .any(|(column, _)| column.as_str() == key.as_str())
+            {
+                sort.push((key, Order::Asc));
+            }
+        }
+        sort
+    }
+}
+
+/// A page of rows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<M> {
+    /// The rows in the page.
+    pub items: Vec<M>,
+
+    /// The cursor to read the next page after, if there are more rows.
+    pub next: Option<Cursor>,
+
+    /// The number of rows matching the filter across every page, if it was
+    /// requested.
+    pub total: Option<u64>,
+}
+
+/// Encode a column value as tagged text.
+///
+/// # Parameters
+///
+/// - `value`: The value.
+///
+/// # Returns
+///
+/// The encoded value, or `None` if it is null or of an unsupported type.
+fn encode_value(value: Value) -> Option<String> {
+    let encoded = match value {
+        Value::Bool(Some(value)) => format!("b:{value}"),
+        Value::TinyInt(Some(value)) => format!("i8:{value}"),
+        Value::SmallInt(Some(value)) => format!("i16:{value}"),
+        Value::Int(Some(value)) => format!("i32:{value}"),
+        Value::BigInt(Some(value)) => format!("i64:{value}"),
+        Value::String(Some(value)) => format!("s:{value}"),
+        Value::Uuid(Some(value)) => format!("u:{value}"),
+        Value::ChronoDateTimeWithTimeZone(Some(value)) => format!("t:{}", value.to_rfc3339()),
+        _ => return None,
+    };
+    Some(encoded)
+}
+
+/// Decode a column value encoded by [`encode_value`].
+///
+/// # Parameters
+///
+/// - `encoded`: The encoded value.
+fn decode_value(encoded: &str) -> Option<Value> {
+    let (tag, value) = encoded.split_once(':')?;
+    let value = match tag {
+        "b" => value.parse::<bool>().ok()?.into(),
+        "i8" => value.parse::<i8>().ok()?.into(),
+        "i16" => value.parse::<i16>().ok()?.into(),
+        "i32" => value.parse::<i32>().ok()?.into(),
+        "i64" => value.parse::<i64>().ok()?.into(),
+        "s" => value.to_string().into(),
+        "u" => value.parse::<Uuid>().ok()?.into(),
+        "t" => DateTimeWithTimeZone::parse_from_rfc3339(value).ok()?.into(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::user_credentials::{Column, Model};
+    use crate::repository::now;
+
+    fn model(username: &str, is_enabled: bool) -> Model {
+        Model {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            password_hash: String::new(),
+            is_enabled,
+            created_at: now(),
+            updated_at: now(),
+            deleted_at: None,
+        }
+    }
+
+    fn names(sort: &[(Column, Order)]) -> Vec<(&str, Order)> {
+        sort.iter()
+            .map(|(column, order)| (column.as_str(), *order))
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let request = PageRequest::new(10).sort_by(Column::Username, Order::Desc);
+        assert_eq!(
+            names(&request.sort_keys([Column::Id])),
+            [("username", Order::Desc), ("id", Order::Asc)]
+        );
+
+        let request = PageRequest::new(10).sort_by(Column::Id, Order::Desc);
+        assert_eq!(
+            names(&request.sort_keys([Column::Id])),
+            [("id", Order::Desc)]
+        );
+    }
+
+    #[test]
+    fn test_cursor() {
+        let sort = [
+            (Column::IsEnabled, Order::Desc),
+            (Column::Username, Order::Asc),
+            (Column::Id, Order::Asc),
+        ];
+        let cursor = Cursor::after(&model("a:b", true), &sort).unwrap();
+        assert!(cursor.condition(&sort).is_ok());
+
+        // A cursor is only valid for the sort it was made for.
+        assert!(matches!(
+            cursor.condition(&sort[1..]),
+            Err(Error::InvalidCursor)
+        ));
+        assert!(matches!(
+            cursor.condition(&[
+                (Column::Username, Order::Asc),
+                (Column::IsEnabled, Order::Desc),
+                (Column::Id, Order::Asc),
+            ]),
+            Err(Error::InvalidCursor)
+        ));
+        assert!(matches!(
+            Cursor::from("not a cursor".to_string()).condition(&sort),
+            Err(Error::InvalidCursor)
+        ));
+    }
+
+    #[test]
+    fn test_values() {
+        let values: [Value; 5] = [
+            true.into(),
+            42_i64.into(),
+            "a:b".to_string().into(),
+            Uuid::new_v4().into(),
+            DateTimeWithTimeZone::parse_from_rfc3339("2023-04-01T12:00:00+02:00")
+                .unwrap()
+                .into(),
+        ];
+        for value in values {
+            let encoded = encode_value(value.clone()).unwrap();
+            assert_eq!(decode_value(&encoded), Some(value));
+        }
+
+        assert_eq!(encode_value(Value::String(None)), None);
+        assert_eq!(decode_value("x:1"), None);
+    }
+}
@@ -0,0 +1,174 @@
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use sea_orm::sea_query::{Expr, LikeExpr};
+use sea_orm::{ColumnTrait, Condition, Value};
+
+/// The character `LIKE` patterns escape their wildcards with.
+const ESCAPE: char = '\\';
+
+/// A condition on the value of a single column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Predicate<T> {
+    /// The value equals the given one.
+    Eq(T),
+
+    /// The value is one of the given ones.
+    In(Vec<T>),
+
+    /// The value lies within the given bounds.
+    Range(Bound<T>, Bound<T>),
+
+    /// The value is text starting with the given prefix. Built with
+    /// [`Predicate::prefix`], so only text columns can be matched by prefix.
+    Prefix(Prefix<T>),
+}
+
+/// The text a column's value starts with.
+///
+/// The prefix is matched with `LIKE`, which ignores the case of ASCII letters
+/// on `SQLite`, and on `MySQL` with its default collations, but not on
+/// `PostgreSQL`. Callers that need the same results everywhere should store
+/// text in one case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prefix<T> {
+    text: String,
+    value: PhantomData<T>,
+}
+
+impl<T> Predicate<T> {
+    /// Create a predicate matching values that are one of the given ones.
+    ///
+    /// # Parameters
+    ///
+    /// - `values`: The values to match.
+    pub fn is_in(values: impl IntoIterator<Item = T>) -> Self {
+        Self::In(values.into_iter().collect())
+    }
+}
+
+impl Predicate<String> {
+    /// Create a predicate matching text that starts with the given prefix.
+    ///
+    /// Whether case is ignored depends on the database, as described on
+    /// [`Prefix`].
+    ///
+    /// # Parameters
+    ///
+    /// - `prefix`: The text values start with.
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self::Prefix(Prefix {
+            text: prefix.into(),
+            value: PhantomData,
+        })
+    }
+}
+
+impl<T: Clone> Predicate<T> {
+    /// Create a predicate matching values within a range, such as `a..b` or
+    /// `a..`.
+    ///
+    /// # Parameters
+    ///
+    /// - `range`: The range to match.
+    pub fn range(range: impl RangeBounds<T>) -> Self {
+        Self::Range(range.start_bound().cloned(), range.end_bound().cloned())
+    }
+}
+
+impl<T: Into<Value>> Predicate<T> {
+    /// Get the condition a column's value must meet.
+    ///
+    /// # Parameters
+    ///
+    /// - `column`: The column the predicate applies to.
+    pub fn condition<C: ColumnTrait>(self, column: C) -> Condition {
+        match self {
+            Self::Eq(value) => Condition::all().add(column.eq(value)),
+            Self::In(values) => Condition::all().add(column.is_in(values)),
+            Self::Range(start, end) => {
+                let start = match start {
+                    Bound::Included(value) => Some(column.gte(value)),
+                    Bound::Excluded(value) => Some(column.gt(value)),
+                    Bound::Unbounded => None,
+                };
+                let end = match end {
+                    Bound::Included(value) => Some(column.lte(value)),
+                    Bound::Excluded(value) => Some(column.lt(value)),
+                    Bound::Unbounded => None,
+                };
+                Condition::all().add_option(start).add_option(end)
+            }
+            Self::Prefix(prefix) => {
+                let pattern = format!("{}%", escape(&prefix.text));
+                let like = LikeExpr::new(pattern).escape(ESCAPE);
+                Condition::all().add(Expr::col((column.entity_name(), column)).like(like))
+            }
+        }
+    }
+}
+
+/// Escape the wildcards in text matched by a `LIKE` pattern.
+///
+/// # Parameters
+///
+/// - `text`: The text to match literally.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | ESCAPE) {
+            escaped.push(ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    use super::*;
+    use crate::entities::user_credentials::{Column, Entity};
+
+    fn sql(condition: Condition) -> String {
+        Entity::find()
+            .filter(condition)
+            .build(DbBackend::Sqlite)
+            .to_string()
+            .split(" WHERE ")
+            .nth(1)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn test_range() {
+        let predicate = Predicate::range("b".to_string().."d".to_string());
+        assert_eq!(
+            sql(predicate.condition(Column::Username)),
+            r#""user_credentials"."username" >= 'b' AND "user_credentials"."username" < 'd'"#
+        );
+
+        let predicate = Predicate::<String>::range(..);
+        assert_eq!(sql(predicate.condition(Column::Username)), "TRUE");
+    }
+
+    #[test]
+    fn test_in() {
+        let predicate = Predicate::is_in(["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            sql(predicate.condition(Column::Username)),
+            r#""user_credentials"."username" IN ('a', 'b')"#
+        );
+    }
+
+    #[test]
+    fn test_prefix() {
+        let predicate = Predicate::prefix(r"50%_of\");
+        assert_eq!(
+            sql(predicate.condition(Column::Username)),
+            r#""user_credentials"."username" LIKE '50\%\_of\\%' ESCAPE '\'"#
+        );
+    }
+}
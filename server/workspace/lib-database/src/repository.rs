@@ -0,0 +1,790 @@
+use std::{marker::PhantomData, time::SystemTime};
+
+use sea_orm::prelude::{DateTimeUtc, DateTimeWithTimeZone};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, Condition, EntityTrait, IntoActiveModel,
+    Iterable, PaginatorTrait, PrimaryKeyToColumn, PrimaryKeyTrait, QueryFilter, QueryOrder,
+    QuerySelect, Value,
+};
+
+use crate::pagination::{Cursor, Page, PageRequest};
+use crate::transaction::Executor;
+use crate::Result;
+
+/// The value of an entity's primary key.
+pub type Id<E> = <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType;
+
+/// Narrows the rows of a table a [`Repository`] operation applies to.
+pub trait Filter<E: EntityTrait>: Send {
+    /// Get the condition rows must meet, which every row meets if the filter
+    /// is empty.
+    fn condition(self) -> Condition;
+}
+
+/// The columns a [`Repository`] operation writes, leaving every other column
+/// as it is.
+pub trait Write<E: Table>: Send {
+    /// Get an active model with only the written columns set.
+    fn active_model(self) -> E::ActiveModel;
+}
+
+/// An entity that can be read and written through a [`Repository`].
+///
+/// Default values, such as a generated primary key, are set in the entity's
+/// [`ActiveModelBehavior::before_save`] hook.
+pub trait Table: EntityTrait {
+    /// The active model rows of the table are written with.
+    type ActiveModel: ActiveModelTrait<Entity = Self> + ActiveModelBehavior + Send;
+
+    /// The filter rows of the table are selected with.
+    type Filter: Filter<Self>;
+
+    /// The partial write rows of the table are created and updated with.
+    type Write: Write<Self>;
+
+    /// Get the column rows hold the time they were last updated in, if any,
+    /// which is set when many rows are updated at once. When a single row is
+    /// written, the entity's hooks set it instead.
+    #[must_use]
+    fn updated_at() -> Option<Self::Column> {
+        None
+    }
+
+    /// Get the column soft-deleted rows hold the time they were deleted in, if
+    /// rows are soft-deleted rather than removed.
+    #[must_use]
+    fn deleted_at() -> Option<Self::Column> {
+        None
+    }
+}
+
+/// Which soft-deleted rows a [`Repository`] operation applies to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Deleted {
+    /// Only rows that have not been deleted.
+    #[default]
+    Excluded,
+
+    /// Every row, whether deleted or not.
+    Included,
+
+    /// Only rows that have been deleted.
+    Only,
+}
+
+/// Get the current time, as timestamp columns hold it.
+pub(crate) fn now() -> DateTimeWithTimeZone {
+    DateTimeUtc::from(SystemTime::now()).into()
+}
+
+/// Creates, reads, updates and deletes the rows of a table, on a
+/// [`Connection`] or within a [`Transaction`].
+///
+/// Deleting a row of a table with a [`Table::deleted_at`] column only marks it
+/// deleted, and operations skip such rows unless told otherwise with
+/// [`Repository::with_deleted`]. Marked rows are removed for good with
+/// [`Repository::purge`].
+///
+/// [`Connection`]: crate::Connection
+/// [`Transaction`]: crate::Transaction
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example(connection: lib_database::Connection, password_hash: String) -> lib_database::Result<()> {
+/// use lib_database::{UserCredentialsController, UserCredentialsFilter, UserCredentialsWrite};
+///
+/// let controller = UserCredentialsController::new(connection);
+/// let user = controller
+///     .create(
+///         UserCredentialsWrite::default()
+///             .username("alice".to_string())
+///             .password_hash(password_hash),
+///     )
+///     .await?;
+/// let users = controller
+///     .read_many(UserCredentialsFilter::default().enabled(true))
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Repository<E, C = crate::Connection> {
+    executor: C,
+    deleted: Deleted,
+    entity: PhantomData<fn() -> E>,
+}
+
+impl<E, C: Clone> Clone for Repository<E, C> {
+    fn clone(&self) -> Self {
+        Self::new(self.executor.clone()).with_deleted(self.deleted)
+    }
+}
+
+impl<E, C> Repository<E, C> {
+    #[must_use]
+    pub fn new(executor: C) -> Self {
+        Self {
+            executor,
+            deleted: Deleted::default(),
+            entity: PhantomData,
+        }
+    }
+
+    /// Get a repository for the same table that runs on another connection or
+    /// transaction.
+    ///
+    /// # Parameters
+    ///
+    /// - `executor`: The connection or transaction to run on.
+    #[must_use]
+    pub fn on<X: Executor>(&self, executor: X) -> Repository<E, X> {
+        Repository::new(executor).with_deleted(self.deleted)
+    }
+
+    /// Select which soft-deleted rows operations apply to, which are none by
+    /// default.
+    ///
+    /// # Parameters
+    ///
+    /// - `deleted`: The soft-deleted rows to apply to.
+    #[must_use]
+    pub fn with_deleted(mut self, deleted: Deleted) -> Self {
+        self.deleted = deleted;
+        self
+    }
+}
+
+impl<E, C> Repository<E, C>
+where
+    E: Table,
+    C: Executor,
+    E::Model: IntoActiveModel<E::ActiveModel> + Sync,
+    Id<E>: Clone + Into<Value>,
+{
+    /// Create a new row.
+    ///
+    /// # Parameters
+    ///
+    /// - `write`: The data to write.
+    ///
+    /// # Returns
+    ///
+    /// The newly created row.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Conflict`] if the row has the same unique key as an
+    /// existing one, or an error if the database connection returns one.
+    ///
+    /// [`Error::Conflict`]: crate::Error::Conflict
+    pub async fn create(&self, write: E::Write) -> Result<E::Model> {
+        let model = write
+            .active_model()
+            .insert(self.executor.connection())
+            .await?;
+
+        Ok(model)
+    }
+
+    /// Read a row.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to apply.
+    ///
+    /// # Returns
+    ///
+    /// The first row matching the filter, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the database connection returns an error.
+    pub async fn read(&self, filter: E::Filter) -> Result<Option<E::Model>> {
+        let model = E::find()
+            .filter(self.scope(filter.condition()))
+            .one(self.executor.connection())
+            .await?;
+
+        Ok(model)
+    }
+
+    /// Read many rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to apply.
+    ///
+    /// # Returns
+    ///
+    /// The rows matching the filter.
+    ///
+    /// # Errors
+    ///
+    /// If the database connection returns an error.
+    pub async fn read_many(&self, filter: E::Filter) -> Result<Vec<E::Model>> {
+        let models = E::find()
+            .filter(self.scope(filter.condition()))
+            .all(self.executor.connection())
+            .await?;
+
+        Ok(models)
+    }
+
+    /// Read a page of rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to apply.
+    /// - `request`: Which page to read, and how to sort the rows.
+    ///
+    /// # Returns
+    ///
+    /// The rows in the page, the cursor to read the next page after, and the
+    /// number of rows matching the filter if it was requested.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCursor`] if the cursor was not made for rows
+    /// sorted this way, or an error if the database connection returns one.
+    ///
+    /// [`Error::InvalidCursor`]: crate::Error::InvalidCursor
+    pub async fn read_page(
+        &self,
+        filter: E::Filter,
+        request: PageRequest<E::Column>,
+    ) -> Result<Page<E::Model>> {
+        let condition = self.scope(filter.condition());
+        let sort = request.sort_keys(E::PrimaryKey::iter().map(PrimaryKeyToColumn::into_column));
+
+        let mut query = E::find().filter(condition.clone());
+        if let Some(cursor) = request.cursor() {
+            query = query.filter(cursor.condition(&sort)?);
+        }
+        if let Some(offset) = request.offset_rows() {
+            query = query.offset(offset);
+        }
+        for (column, order) in &sort {
+            query = query.order_by(*column, (*order).into());
+        }
+
+        // Reading one more row than the page holds tells whether there is a
+        // next page.
+        let limit = request.limit();
+        let mut items = query
+            .limit(limit.saturating_add(1))
+            .all(self.executor.connection())
+            .await?;
+
+        let len = usize::try_from(limit).unwrap_or(usize::MAX);
+        let next = if items.len() > len {
+            items.truncate(len);
+            items
+                .last()
+                .map(|model| Cursor::after(model, &sort))
+                .transpose()?
+        } else {
+            None
+        };
+
+        let total = if request.counts_total() {
+            let total = E::find()
+                .filter(condition)
+                .count(self.executor.connection())
+                .await?;
+            Some(total)
+        } else {
+            None
+        };
+
+        Ok(Page { items, next, total })
+    }
+
+    /// Update a row.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The primary key of the row to update.
+    /// - `write`: The data to write.
+    ///
+    /// # Returns
+    ///
+    /// The updated row.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if there is no such row, [`Error::Conflict`]
+    /// if the row would have the same unique key as another one, or an error if
+    /// the database connection returns one.
+    ///
+    /// [`Error::NotFound`]: crate::Error::NotFound
+    /// [`Error::Conflict`]: crate::Error::Conflict
+    pub async fn update(&self, id: Id<E>, write: E::Write) -> Result<E::Model> {
+        let connection = self.executor.connection();
+
+        let mut model = write.active_model();
+        for key in E::PrimaryKey::iter() {
+            model.set(key.into_column(), id.clone().into());
+        }
+
+        // Running the hooks by hand, as `ActiveModelTrait::update` would,
+        // lets the update skip soft-deleted rows.
+        let model = ActiveModelBehavior::before_save(model, connection, false).await?;
+        let model = E::update(model)
+            .filter(self.scope(Condition::all()))
+            .exec(connection)
+            .await?;
+        let model = E::ActiveModel::after_save(model, connection, false).await?;
+
+        Ok(model)
+    }
+
+    /// Update many rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to apply.
+    /// - `write`: The data to write.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Conflict`] if a row would have the same unique key as
+    /// another one, or an error if the database connection returns one.
+    ///
+    /// [`Error::Conflict`]: crate::Error::Conflict
+    pub async fn update_many(&self, filter: E::Filter, write: E::Write) -> Result<u64> {
+        let mut query = E::update_many().set(write.active_model());
+        if let Some(updated_at) = E::updated_at() {
+            query = query.col_expr(updated_at, Expr::value(now()));
+        }
+
+        let result = query
+            .filter(self.scope(filter.condition()))
+            .exec(self.executor.connection())
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Delete a row, or mark it deleted if the table soft-deletes rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The primary key of the row to delete.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows deleted.
+    ///
+    /// # Errors
+    ///
+    /// If the database connection returns an error.
+    pub async fn delete(&self, id: Id<E>) -> Result<u64> {
+        self.delete_where(key_condition::<E>(&id)).await
+    }
+
+    /// Delete many rows, or mark them deleted if the table soft-deletes rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to use when deleting.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows deleted.
+    ///
+    /// # Errors
+    ///
+    /// If the database connection returns an error.
+    pub async fn delete_many(&self, filter: E::Filter) -> Result<u64> {
+        self.delete_where(filter.condition()).await
+    }
+
+    /// Restore a soft-deleted row.
+    ///
+    /// # Parameters
+    ///
+    /// - `id`: The primary key of the row to restore.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows restored, which is zero if the row was not deleted
+    /// or the table does not soft-delete rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Conflict`] if the row has the same unique key as a row
+    /// created since it was deleted, or an error if the database connection
+    /// returns one.
+    ///
+    /// [`Error::Conflict`]: crate::Error::Conflict
+    pub async fn restore(&self, id: Id<E>) -> Result<u64> {
+        let Some(deleted_at) = E::deleted_at() else { return Ok(0) };
+
+        let mut query = E::update_many().col_expr(
+            deleted_at,
+            Expr::value(Value::ChronoDateTimeWithTimeZone(None)),
+        );
+        if let Some(updated_at) = E::updated_at() {
+            query = query.col_expr(updated_at, Expr::value(now()));
+        }
+
+        let result = query
+            .filter(key_condition::<E>(&id))
+            .filter(deleted_at.is_not_null())
+            .exec(self.executor.connection())
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Remove soft-deleted rows for good. Rows of tables that do not
+    /// soft-delete rows are removed whether or not they were deleted.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter`: The filter to use when removing.
+    ///
+    /// # Returns
+    ///
+    /// The number of rows removed.
+    ///
+    /// # Errors
+    ///
+    /// If the database connection returns an error.
+    pub async fn purge(&self, filter: E::Filter) -> Result<u64> {
+        let mut query = E::delete_many().filter(filter.condition());
+        if let Some(deleted_at) = E::deleted_at() {
+            query = query.filter(deleted_at.is_not_null());
+        }
+
+        let result = query.exec(self.executor.connection()).await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Delete the rows meeting a condition, or mark them deleted if the table
+    /// soft-deletes rows.
+    ///
+    /// # Parameters
+    ///
+    /// - `condition`: The condition rows must meet.
+    async fn delete_where(&self, condition: Condition) -> Result<u64> {
+        let connection = self.executor.connection();
+
+        let Some(deleted_at) = E::deleted_at() else {
+            let result = E::delete_many().filter(condition).exec(connection).await?;
+            return Ok(result.rows_affected);
+        };
+
+        let now = now();
+        let mut query = E::update_many().col_expr(deleted_at, Expr::value(now));
+        if let Some(updated_at) = E::updated_at() {
+            query = query.col_expr(updated_at, Expr::value(now));
+        }
+
+        let result = query
+            .filter(condition)
+            .filter(deleted_at.is_null())
+            .exec(connection)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Narrow a condition to the soft-deleted rows operations apply to.
+    ///
+    /// # Parameters
+    ///
+    /// - `condition`: The condition to narrow.
+    fn scope(&self, condition: Condition) -> Condition {
+        let Some(deleted_at) = E::deleted_at() else { return condition };
+
+        match self.deleted {
+            Deleted::Excluded => condition.add(deleted_at.is_null()),
+            Deleted::Included => condition,
+            Deleted::Only => condition.add(deleted_at.is_not_null()),
+        }
+    }
+}
+
+/// Get the condition that selects a row by its primary key.
+///
+/// # Parameters
+///
+/// - `id`: The primary key.
+fn key_condition<E>(id: &Id<E>) -> Condition
+where
+    E: EntityTrait,
+    Id<E>: Clone + Into<Value>,
+{
+    E::PrimaryKey::iter().fold(Condition::all(), |condition, key| {
+        condition.add(key.into_column().eq(id.clone()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Deleted, Error, Options, Order, Page, PageRequest, UserCredentials, UserCredentialsColumn,
+        UserCredentialsController, UserCredentialsFilter, UserCredentialsWrite,
+    };
+
+    async fn controller() -> UserCredentialsController {
+        let connection = crate::Connection::connect(Options::new("sqlite::memory:".to_string()))
+            .await
+            .unwrap();
+        connection.migrate().await.unwrap();
+        UserCredentialsController::new(connection)
+    }
+
+    fn write(username: &str) -> UserCredentialsWrite {
+        UserCredentialsWrite::default()
+            .username(username.to_string())
+            .password_hash("hash".to_string())
+            .enabled(true)
+    }
+
+    async fn usernames(
+        controller: &UserCredentialsController,
+        filter: UserCredentialsFilter,
+    ) -> Vec<String> {
+        let mut usernames: Vec<String> = controller
+            .read_many(filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.username)
+            .collect();
+        usernames.sort();
+        usernames
+    }
+
+    #[tokio::test]
+    async fn test_create_and_read() {
+        let controller = controller().await;
+
+        let user = controller.create(write("alice")).await.unwrap();
+        assert_eq!(user.username, "alice");
+        assert!(user.is_enabled);
+
+        let filter = UserCredentialsFilter::default().id(user.id);
+        assert_eq!(controller.read(filter).await.unwrap(), Some(user));
+
+        let filter = UserCredentialsFilter::default().username("bob".to_string());
+        assert_eq!(controller.read(filter).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_partial_update() {
+        let controller = controller().await;
+        let user = controller.create(write("alice")).await.unwrap();
+
+        let update = UserCredentialsWrite::default().enabled(false);
+        let updated = controller.update(user.id, update).await.unwrap();
+        assert_eq!(updated.username, "alice");
+        assert_eq!(updated.password_hash, "hash");
+        assert!(!updated.is_enabled);
+
+        let filter = UserCredentialsFilter::default().enabled(false);
+        let update = UserCredentialsWrite::default().enabled(true);
+        assert_eq!(controller.update_many(filter, update).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let controller = controller().await;
+        let user = controller.create(write("alice")).await.unwrap();
+
+        assert!(matches!(
+            controller.create(write("alice")).await,
+            Err(Error::Conflict(_))
+        ));
+
+        let update = UserCredentialsWrite::default().enabled(false);
+        assert_eq!(
+            controller.update(uuid::Uuid::new_v4(), update).await,
+            Err(Error::NotFound)
+        );
+
+        assert_eq!(controller.delete(user.id).await.unwrap(), 1);
+        assert_eq!(controller.delete(user.id).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_page() {
+        let controller = controller().await;
+        for username in ["u1", "u2", "u3", "u4", "u5", "v1"] {
+            controller.create(write(username)).await.unwrap();
+        }
+
+        let filter = || UserCredentialsFilter::default().username_prefix("u".to_string());
+        let request = || PageRequest::new(2).sort_by(UserCredentialsColumn::Username, Order::Desc);
+        let usernames = |page: &Page<UserCredentials>| -> Vec<String> {
+            page.items
+                .iter()
+                .map(|user| user.username.clone())
+                .collect()
+        };
+
+        let page = controller
+            .read_page(filter(), request().with_total())
+            .await
+            .unwrap();
+        assert_eq!(usernames(&page), ["u5", "u4"]);
+        assert_eq!(page.total, Some(5));
+
+        let page = controller
+            .read_page(filter(), request().after(page.next.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(usernames(&page), ["u3", "u2"]);
+        assert_eq!(page.total, None);
+
+        let page = controller
+            .read_page(filter(), request().after(page.next.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(usernames(&page), ["u1"]);
+        assert_eq!(page.next, None);
+
+        let page = controller
+            .read_page(filter(), request().offset(3))
+            .await
+            .unwrap();
+        assert_eq!(usernames(&page), ["u2", "u1"]);
+        assert_eq!(page.next, None);
+
+        // A cursor is only valid for the sort it was made for.
+        let page = controller.read_page(filter(), request()).await.unwrap();
+        let request = PageRequest::new(2).after(page.next.unwrap());
+        assert_eq!(
+            controller.read_page(filter(), request).await,
+            Err(Error::InvalidCursor)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filters() {
+        let controller = controller().await;
+        let mut ids = Vec::new();
+        for username in ["x_1", "xy1", "y", "z"] {
+            ids.push(controller.create(write(username)).await.unwrap().id);
+        }
+
+        let filter = UserCredentialsFilter::default().username_prefix("x_".to_string());
+        assert_eq!(usernames(&controller, filter).await, ["x_1"]);
+
+        let filter =
+            UserCredentialsFilter::default().username_range("xy".to_string().."z".to_string());
+        assert_eq!(usernames(&controller, filter).await, ["xy1", "y"]);
+
+        let filter = UserCredentialsFilter::default().ids(ids[2..].iter().copied());
+        assert_eq!(usernames(&controller, filter).await, ["y", "z"]);
+
+        let filter = UserCredentialsFilter::default()
+            .usernames(["y".to_string(), "z".to_string(), "zz".to_string()])
+            .enabled(true);
+        assert_eq!(usernames(&controller, filter).await, ["y", "z"]);
+    }
+
+    #[tokio::test]
+    async fn test_timestamps() {
+        let controller = controller().await;
+
+        let user = controller.create(write("u")).await.unwrap();
+        assert_eq!(user.created_at, user.updated_at);
+        assert_eq!(user.deleted_at, None);
+
+        let updated = controller
+            .update(user.id, UserCredentialsWrite::default().enabled(false))
+            .await
+            .unwrap();
+        assert_eq!(updated.created_at, user.created_at);
+        assert!(updated.updated_at > user.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() {
+        let controller = controller().await;
+        let mut ids = Vec::new();
+        for username in ["u", "v", "w"] {
+            ids.push(controller.create(write(username)).await.unwrap().id);
+        }
+        let filter =
+            || UserCredentialsFilter::default().usernames(["u", "v", "w"].map(String::from));
+
+        assert_eq!(controller.delete(ids[0]).await, Ok(1));
+        assert_eq!(controller.delete(ids[0]).await, Ok(0));
+        assert_eq!(usernames(&controller, filter()).await, ["v", "w"]);
+        assert_eq!(
+            controller
+                .read(UserCredentialsFilter::default().id(ids[0]))
+                .await,
+            Ok(None)
+        );
+
+        // Deleted rows cannot be updated until they are restored.
+        let disable = || UserCredentialsWrite::default().enabled(false);
+        assert_eq!(
+            controller.update(ids[0], disable()).await,
+            Err(Error::NotFound)
+        );
+        assert_eq!(controller.update_many(filter(), disable()).await, Ok(2));
+
+        let deleted = controller.clone().with_deleted(Deleted::Only);
+        assert_eq!(usernames(&deleted, filter()).await, ["u"]);
+        let user = deleted.read(filter()).await.unwrap().unwrap();
+        assert!(user.deleted_at.is_some());
+        assert!(user.is_enabled);
+
+        let all = controller.clone().with_deleted(Deleted::Included);
+        assert_eq!(usernames(&all, filter()).await, ["u", "v", "w"]);
+        let page = all
+            .read_page(filter(), PageRequest::new(2).with_total())
+            .await
+            .unwrap();
+        assert_eq!(page.total, Some(3));
+
+        assert_eq!(controller.restore(ids[0]).await, Ok(1));
+        assert_eq!(controller.restore(ids[0]).await, Ok(0));
+        assert_eq!(usernames(&controller, filter()).await, ["u", "v", "w"]);
+
+        // Only deleted rows are purged.
+        assert_eq!(controller.delete_many(filter()).await, Ok(3));
+        assert_eq!(controller.restore(ids[1]).await, Ok(1));
+        assert_eq!(controller.purge(filter()).await, Ok(2));
+        assert_eq!(usernames(&all, filter()).await, ["v"]);
+        assert_eq!(controller.restore(ids[0]).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_deleted_username() {
+        let controller = controller().await;
+        let first = controller.create(write("u")).await.unwrap().id;
+        assert!(matches!(
+            controller.create(write("u")).await,
+            Err(Error::Conflict(_))
+        ));
+
+        // A deleted row's username may be given to another row, which keeps the
+        // deleted row from being restored.
+        assert_eq!(controller.delete(first).await, Ok(1));
+        let second = controller.create(write("u")).await.unwrap().id;
+        assert!(matches!(
+            controller.restore(first).await,
+            Err(Error::Conflict(_))
+        ));
+
+        assert_eq!(controller.delete(second).await, Ok(1));
+        assert_eq!(controller.restore(first).await, Ok(1));
+        assert!(matches!(
+            controller.restore(second).await,
+            Err(Error::Conflict(_))
+        ));
+    }
+}
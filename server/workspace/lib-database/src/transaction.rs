@@ -0,0 +1,299 @@
+use std::{future::Future, pin::Pin};
+
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
+
+use crate::{Error, Result};
+
+/// The future a transaction callback returns, which borrows the transaction.
+pub type TransactionFuture<'c, T, E> =
+    Pin<Box<dyn Future<Output = std::result::Result<T, E>> + Send + 'c>>;
+
+/// Something queries can be run on: a [`Connection`] or a [`Transaction`].
+///
+/// [`Connection`]: crate::Connection
+pub trait Executor: Send + Sync {
+    /// The `SeaORM` connection queries are run on.
+    type Connection: ConnectionTrait + Send + Sync;
+
+    /// Get the `SeaORM` connection queries are run on.
+    fn connection(&self) -> &Self::Connection;
+}
+
+impl<T: Executor> Executor for &T {
+    type Connection = T::Connection;
+
+    fn connection(&self) -> &Self::Connection {
+        (**self).connection()
+    }
+}
+
+/// A database transaction.
+///
+/// A transaction that is dropped without being committed is rolled back.
+/// Transactions begun within a transaction are savepoints, which are rolled
+/// back on their own without affecting the enclosing transaction.
+pub struct Transaction {
+    transaction: DatabaseTransaction,
+}
+
+impl Transaction {
+    /// Begin a transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection or transaction to begin it on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction could not be begun.
+    pub(crate) async fn begin_on<C: TransactionTrait>(connection: &C) -> Result<Self> {
+        let transaction = connection.begin().await?;
+        Ok(Self { transaction })
+    }
+
+    /// Run a callback in a transaction, committing it if the callback succeeds
+    /// and rolling it back if it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection or transaction to begin it on.
+    /// * `callback` - The callback to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the callback's error, or an error if the transaction could not
+    /// be begun or committed.
+    pub(crate) async fn run_on<C, F, T, E>(connection: &C, callback: F) -> std::result::Result<T, E>
+    where
+        C: TransactionTrait,
+        F: for<'c> FnOnce(&'c Transaction) -> TransactionFuture<'c, T, E> + Send,
+        T: Send,
+        E: From<Error> + Send,
+    {
+        let transaction = Self::begin_on(connection).await?;
+
+        match callback(&transaction).await {
+            Ok(value) => {
+                transaction.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = transaction.rollback().await {
+                    log::warn!("Failed to roll back transaction: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Begin a savepoint within the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the savepoint could not be created.
+    pub async fn begin(&self) -> Result<Self> {
+        Self::begin_on(&self.transaction).await
+    }
+
+    /// Run a callback in a savepoint within the transaction, releasing it if
+    /// the callback succeeds and rolling back to it if it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - The callback to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the callback's error, or an error if the savepoint could not be
+    /// created or released.
+    pub async fn transaction<F, T, E>(&self, callback: F) -> std::result::Result<T, E>
+    where
+        F: for<'c> FnOnce(&'c Transaction) -> TransactionFuture<'c, T, E> + Send,
+        T: Send,
+        E: From<Error> + Send,
+    {
+        Self::run_on(&self.transaction, callback).await
+    }
+
+    /// Commit the transaction, or release the savepoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction could not be committed.
+    pub async fn commit(self) -> Result<()> {
+        self.transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Roll back the transaction, or roll back to the savepoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction could not be rolled back.
+    pub async fn rollback(self) -> Result<()> {
+        self.transaction.rollback().await?;
+        Ok(())
+    }
+}
+
+impl Executor for Transaction {
+    type Connection = DatabaseTransaction;
+
+    fn connection(&self) -> &Self::Connection {
+        &self.transaction
+    }
+}
+
+impl AsRef<DatabaseTransaction> for Transaction {
+    fn as_ref(&self) -> &DatabaseTransaction {
+        &self.transaction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Connection, Options, UserCredentialsController, UserCredentialsWrite};
+
+    async fn connection() -> Connection {
+        let connection = Connection::connect(Options::new("sqlite::memory:".to_string()))
+            .await
+            .unwrap();
+        connection.migrate().await.unwrap();
+        connection
+    }
+
+    fn write(username: &str) -> UserCredentialsWrite {
+        UserCredentialsWrite::default()
+            .username(username.to_string())
+            .password_hash("hash".to_string())
+    }
+
+    async fn usernames(controller: &UserCredentialsController) -> Vec<String> {
+        let mut usernames: Vec<String> = controller
+            .read_many(crate::UserCredentialsFilter::default())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.username)
+            .collect();
+        usernames.sort();
+        usernames
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_rollback() {
+        let connection = connection().await;
+        let controller = UserCredentialsController::new(connection.clone());
+
+        let transaction = connection.begin().await.unwrap();
+        controller
+            .on(&transaction)
+            .create(write("alice"))
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let transaction = connection.begin().await.unwrap();
+        controller
+            .on(&transaction)
+            .create(write("bob"))
+            .await
+            .unwrap();
+        transaction.rollback().await.unwrap();
+
+        // A transaction that is dropped is rolled back.
+        let transaction = connection.begin().await.unwrap();
+        controller
+            .on(&transaction)
+            .create(write("carol"))
+            .await
+            .unwrap();
+        drop(transaction);
+
+        assert_eq!(usernames(&controller).await, ["alice"]);
+    }
+
+    #[tokio::test]
+    async fn test_callback() {
+        let connection = connection().await;
+        let controller = UserCredentialsController::new(connection.clone());
+
+        let result: Result<()> = connection
+            .transaction(|transaction| {
+                let controller = controller.on(transaction);
+                Box::pin(async move {
+                    controller.create(write("alice")).await?;
+                    controller.create(write("alice")).await?;
+                    Ok(())
+                })
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert!(usernames(&controller).await.is_empty());
+
+        let id = connection
+            .transaction::<_, _, Error>(|transaction| {
+                let controller = controller.on(transaction);
+                Box::pin(async move { Ok(controller.create(write("alice")).await?.id) })
+            })
+            .await
+            .unwrap();
+        assert_eq!(usernames(&controller).await, ["alice"]);
+
+        let filter = crate::UserCredentialsFilter::default().id(id);
+        assert!(controller.read(filter).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_savepoints() {
+        let connection = connection().await;
+        let controller = UserCredentialsController::new(connection.clone());
+
+        let transaction = connection.begin().await.unwrap();
+        controller
+            .on(&transaction)
+            .create(write("alice"))
+            .await
+            .unwrap();
+
+        let savepoint = transaction.begin().await.unwrap();
+        controller
+            .on(&savepoint)
+            .create(write("bob"))
+            .await
+            .unwrap();
+        savepoint.rollback().await.unwrap();
+
+        let result: Result<()> = transaction
+            .transaction(|savepoint| {
+                let controller = controller.on(savepoint);
+                Box::pin(async move {
+                    controller.create(write("carol")).await?;
+                    Err(Error::Query("failed".to_string()))
+                })
+            })
+            .await;
+        assert!(result.is_err());
+
+        transaction
+            .transaction::<_, _, Error>(|savepoint| {
+                let controller = controller.on(savepoint);
+                Box::pin(async move {
+                    controller.create(write("dave")).await?;
+                    Ok(())
+                })
+            })
+            .await
+            .unwrap();
+
+        let users = controller
+            .on(&transaction)
+            .read_many(crate::UserCredentialsFilter::default())
+            .await;
+        assert_eq!(users.unwrap().len(), 2);
+        transaction.commit().await.unwrap();
+
+        assert_eq!(usernames(&controller).await, ["alice", "dave"]);
+    }
+}
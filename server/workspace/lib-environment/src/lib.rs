@@ -8,6 +8,7 @@
 )]
 
 use std::env::VarError;
+use std::net::IpAddr;
 
 /// Environment variable interface.
 pub trait EnvironmentVariable<T> {
@@ -33,6 +34,100 @@ pub trait EnvironmentVariable<T> {
     fn get() -> T;
 }
 
+/// The password of the first admin. Sets the password when the admin does not
+/// exist yet or still has the password it was once seeded with, and is
+/// ignored otherwise.
+pub struct AdminPassword;
+impl EnvironmentVariable<Option<String>> for AdminPassword {
+    const NAME: &'static str = "ADMIN_PASSWORD";
+
+    fn default() -> Option<String> {
+        None
+    }
+
+    fn get() -> Option<String> {
+        match Self::get_raw() {
+            Ok(value) if !value.is_empty() => Some(value),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// The file path to the password of the first admin, such as a mounted secret,
+/// used like `ADMIN_PASSWORD` when that is not set.
+pub struct AdminPasswordPath;
+impl EnvironmentVariable<Option<String>> for AdminPasswordPath {
+    const NAME: &'static str = "ADMIN_PASSWORD_PATH";
+
+    fn default() -> Option<String> {
+        None
+    }
+
+    fn get() -> Option<String> {
+        match Self::get_raw() {
+            Ok(value) if !value.is_empty() => Some(value),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// The username of the first admin.
+pub struct AdminUsername;
+impl EnvironmentVariable<String> for AdminUsername {
+    const NAME: &'static str = "ADMIN_USERNAME";
+
+    fn default() -> String {
+        "admin".to_string()
+    }
+
+    fn get() -> String {
+        match Self::get_raw() {
+            Ok(value) if !value.is_empty() => value,
+            _ => Self::default(),
+        }
+    }
+}
+
+/// The minimum time between extensions of a sliding authentication token, in seconds.
+pub struct AuthTokenExtendInterval;
+impl EnvironmentVariable<u64> for AuthTokenExtendInterval {
+    const NAME: &'static str = "AUTH_TOKEN_EXTEND_INTERVAL";
+
+    fn default() -> u64 {
+        // 1 minute
+        60
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The maximum lifetime of an authentication token, in seconds.
+///
+/// When this is non-zero, authentication tokens expire after `AUTH_TOKEN_TTL`
+/// seconds of inactivity rather than `AUTH_TOKEN_TTL` seconds after login, but
+/// never live longer than this.
+pub struct AuthTokenMaxLifetime;
+impl EnvironmentVariable<u64> for AuthTokenMaxLifetime {
+    const NAME: &'static str = "AUTH_TOKEN_MAX_LIFETIME";
+
+    fn default() -> u64 {
+        // Disabled
+        0
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
 /// The size of the authentication token.
 pub struct AuthTokenSize;
 impl EnvironmentVariable<usize> for AuthTokenSize {
@@ -85,9 +180,49 @@ impl EnvironmentVariable<String> for DbConnectionString {
     }
 }
 
-/// The file path to the encryption key. This can hold any value of any length.
-/// If the file does not exist, it will be created. If the file is empty, a new
-/// key will be generated.
+/// Whether to apply pending database migrations at startup. When disabled,
+/// the server refuses to start while any migration is pending, leaving them
+/// to be applied with the `migrate` binary.
+///
+/// Accepts `true`, `1`, `yes` and `on`, or `false`, `0`, `no` and `off`, in
+/// any case. Any other value disables migrations, so a typo cannot apply them.
+pub struct DbMigrateOnStartup;
+impl EnvironmentVariable<bool> for DbMigrateOnStartup {
+    const NAME: &'static str = "DB_MIGRATE_ON_STARTUP";
+
+    fn default() -> bool {
+        true
+    }
+
+    fn get() -> bool {
+        match Self::get_raw() {
+            Ok(value) => parse_bool(&value).unwrap_or(false),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Parse a flag from the common ways of writing it.
+///
+/// # Arguments
+///
+/// * `value` - The value of the environment variable.
+///
+/// # Returns
+///
+/// The flag, or `None` if the value is not a recognised spelling.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// The file path to the encryption keyring. Each line holds a key as
+/// `<id>:<secret>`, where the secret can be any value of any length, and a file
+/// holding a single line without an ID is read as key 1. If the file does not
+/// exist or is empty, it will be created with a new key.
 pub struct EncryptionKeyPath;
 
 impl EnvironmentVariable<String> for EncryptionKeyPath {
@@ -105,7 +240,86 @@ impl EnvironmentVariable<String> for EncryptionKeyPath {
     }
 }
 
-/// The connection string to use to connect to the redis cache.
+/// The file path to the password pepper, a keyring in the same format as the
+/// encryption keyring. Passwords are not peppered if this is empty. The server
+/// refuses to start if the file does not exist or holds no pepper, because
+/// peppered hashes could not be verified without it; create it with the
+/// `rotate-password-pepper` command.
+pub struct PasswordPepperPath;
+
+impl EnvironmentVariable<Option<String>> for PasswordPepperPath {
+    const NAME: &'static str = "PASSWORD_PEPPER_PATH";
+
+    fn default() -> Option<String> {
+        None
+    }
+
+    fn get() -> Option<String> {
+        match Self::get_raw() {
+            Ok(value) if !value.is_empty() => Some(value),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Parse a rate limit of the form `requests/seconds`, or `off` for none.
+///
+/// # Errors
+///
+/// Returns an error if the value is neither.
+fn parse_rate_limit(value: &str) -> Result<Option<(u64, u64)>, ()> {
+    let value = value.trim();
+    if value == "off" {
+        return Ok(None);
+    }
+
+    let (requests, seconds) = value.split_once('/').ok_or(())?;
+    let requests = requests.parse().map_err(|_| ())?;
+    let seconds = seconds.parse().map_err(|_| ())?;
+    Ok(Some((requests, seconds)))
+}
+
+/// The number of requests each client may make to the `/auth` routes within a
+/// number of seconds, as `requests/seconds`, or `off` for no limit.
+pub struct RateLimitAuth;
+impl EnvironmentVariable<Option<(u64, u64)>> for RateLimitAuth {
+    const NAME: &'static str = "RATE_LIMIT_AUTH";
+
+    fn default() -> Option<(u64, u64)> {
+        // 120 requests per minute
+        Some((120, 60))
+    }
+
+    fn get() -> Option<(u64, u64)> {
+        match Self::get_raw() {
+            Ok(value) => parse_rate_limit(&value).unwrap_or_else(|()| Self::default()),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The number of requests each service may make to the `/oauth` routes within
+/// a number of seconds, as `requests/seconds`, or `off` for no limit.
+pub struct RateLimitOauth;
+impl EnvironmentVariable<Option<(u64, u64)>> for RateLimitOauth {
+    const NAME: &'static str = "RATE_LIMIT_OAUTH";
+
+    fn default() -> Option<(u64, u64)> {
+        // 1200 requests per minute
+        Some((1200, 60))
+    }
+
+    fn get() -> Option<(u64, u64)> {
+        match Self::get_raw() {
+            Ok(value) => parse_rate_limit(&value).unwrap_or_else(|()| Self::default()),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The connection string to use to connect to the redis cache, or `memory://`
+/// to use an in-process cache instead. The `redis+cluster://` and
+/// `redis+sentinel://` schemes list several comma-separated nodes.
 pub struct RedisCacheConnectionString;
 impl EnvironmentVariable<String> for RedisCacheConnectionString {
     const NAME: &'static str = "REDIS_CACHE_CONNECTION_STRING";
@@ -122,6 +336,133 @@ impl EnvironmentVariable<String> for RedisCacheConnectionString {
     }
 }
 
+/// How long to wait for a cache operation to complete, in milliseconds.
+pub struct RedisCommandTimeout;
+impl EnvironmentVariable<u64> for RedisCommandTimeout {
+    const NAME: &'static str = "REDIS_COMMAND_TIMEOUT";
+
+    fn default() -> u64 {
+        // 5 seconds
+        5_000
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// How long to wait for a new connection to the cache, in milliseconds.
+pub struct RedisConnectTimeout;
+impl EnvironmentVariable<u64> for RedisConnectTimeout {
+    const NAME: &'static str = "REDIS_CONNECT_TIMEOUT";
+
+    fn default() -> u64 {
+        // 5 seconds
+        5_000
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// How many cache operations in a row must fail to reach Redis before further
+/// operations fail fast, or zero to never fail fast.
+pub struct RedisFailureThreshold;
+impl EnvironmentVariable<u32> for RedisFailureThreshold {
+    const NAME: &'static str = "REDIS_FAILURE_THRESHOLD";
+
+    fn default() -> u32 {
+        5
+    }
+
+    fn get() -> u32 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The most connections kept to each Redis node.
+pub struct RedisPoolSize;
+impl EnvironmentVariable<usize> for RedisPoolSize {
+    const NAME: &'static str = "REDIS_POOL_SIZE";
+
+    fn default() -> usize {
+        16
+    }
+
+    fn get() -> usize {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The longest delay between attempts to reach the cache again after losing
+/// it, in milliseconds.
+pub struct RedisRetryMaxDelay;
+impl EnvironmentVariable<u64> for RedisRetryMaxDelay {
+    const NAME: &'static str = "REDIS_RETRY_MAX_DELAY";
+
+    fn default() -> u64 {
+        // 5 seconds
+        5_000
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The delay before first trying to reach the cache again after losing it, in
+/// milliseconds. The delay doubles after each failed attempt.
+pub struct RedisRetryMinDelay;
+impl EnvironmentVariable<u64> for RedisRetryMinDelay {
+    const NAME: &'static str = "REDIS_RETRY_MIN_DELAY";
+
+    fn default() -> u64 {
+        100
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// How long to wait for a free connection to the cache when every pooled
+/// connection is in use, in milliseconds.
+pub struct RedisWaitTimeout;
+impl EnvironmentVariable<u64> for RedisWaitTimeout {
+    const NAME: &'static str = "REDIS_WAIT_TIMEOUT";
+
+    fn default() -> u64 {
+        // 5 seconds
+        5_000
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
 /// The size of the refresh token.
 pub struct RefreshTokenSize;
 impl EnvironmentVariable<usize> for RefreshTokenSize {
@@ -156,3 +497,211 @@ impl EnvironmentVariable<u64> for RefreshTokenTtl {
         }
     }
 }
+
+/// The credentials of the services that may introspect and revoke tokens.
+///
+/// Entries are separated by whitespace, and each entry takes the form
+/// `client_id:secret_hash`, where `secret_hash` is an Argon2 password hash of
+/// the client secret. Any other entry is an error, rather than a service that
+/// silently cannot authenticate.
+pub struct ServiceCredentials;
+impl EnvironmentVariable<Result<Vec<(String, String)>, String>> for ServiceCredentials {
+    const NAME: &'static str = "SERVICE_CREDENTIALS";
+
+    fn default() -> Result<Vec<(String, String)>, String> {
+        Ok(Vec::new())
+    }
+
+    fn get() -> Result<Vec<(String, String)>, String> {
+        match Self::get_raw() {
+            Ok(value) => value
+                .split_whitespace()
+                .enumerate()
+                .map(|(index, entry)| match entry.split_once(':') {
+                    Some((client_id, secret_hash))
+                        if !client_id.is_empty() && !secret_hash.is_empty() =>
+                    {
+                        Ok((client_id.to_string(), secret_hash.to_string()))
+                    }
+                    _ => Err(format!(
+                        "entry {} of {} is not of the form client_id:secret_hash",
+                        index + 1,
+                        Self::NAME
+                    )),
+                })
+                .collect(),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The file path to the keyring the keys signing exports and webhooks are
+/// derived from, in the same format as the encryption keyring. If the file does
+/// not exist or is empty, it will be created with a new key.
+pub struct SigningKeyPath;
+
+impl EnvironmentVariable<String> for SigningKeyPath {
+    const NAME: &'static str = "SIGNING_KEY_PATH";
+
+    fn default() -> String {
+        "signing.key".to_string()
+    }
+
+    fn get() -> String {
+        match Self::get_raw() {
+            Ok(value) => value,
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The reverse proxies trusted to report the address a request came from in its
+/// `X-Forwarded-For` header, as networks of an address and prefix length.
+///
+/// Entries are separated by whitespace, and each is an IP address or a network
+/// in CIDR notation, such as `10.0.0.0/8`. Any other entry is an error. Without
+/// any, the address of the connection is used.
+pub struct TrustedProxies;
+impl EnvironmentVariable<Result<Vec<(IpAddr, u8)>, String>> for TrustedProxies {
+    const NAME: &'static str = "TRUSTED_PROXIES";
+
+    fn default() -> Result<Vec<(IpAddr, u8)>, String> {
+        Ok(Vec::new())
+    }
+
+    fn get() -> Result<Vec<(IpAddr, u8)>, String> {
+        match Self::get_raw() {
+            Ok(value) => value
+                .split_whitespace()
+                .enumerate()
+                .map(|(index, entry)| {
+                    parse_network(entry).ok_or_else(|| {
+                        format!(
+                            "entry {} of {} is not an IP address or network",
+                            index + 1,
+                            Self::NAME
+                        )
+                    })
+                })
+                .collect(),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Parse an IP address, or a network of the form `address/prefix_length`.
+fn parse_network(value: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix_length) = match value.split_once('/') {
+        Some((address, prefix_length)) => (address, Some(prefix_length)),
+        None => (value, None),
+    };
+
+    let address: IpAddr = address.parse().ok()?;
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix_length = match prefix_length {
+        Some(prefix_length) => prefix_length.parse().ok().filter(|&n| n <= max)?,
+        None => max,
+    };
+
+    Some((address, prefix_length))
+}
+
+/// The time to live of a pending passkey registration or login ceremony.
+pub struct WebauthnChallengeTtl;
+impl EnvironmentVariable<u64> for WebauthnChallengeTtl {
+    const NAME: &'static str = "WEBAUTHN_CHALLENGE_TTL";
+
+    fn default() -> u64 {
+        // 5 minutes
+        300
+    }
+
+    fn get() -> u64 {
+        match Self::get_raw() {
+            Ok(value) => value.parse().ok().unwrap_or_else(Self::default),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The `WebAuthn` relying party ID, which must be the domain that the client is served from.
+pub struct WebauthnRpId;
+impl EnvironmentVariable<String> for WebauthnRpId {
+    const NAME: &'static str = "WEBAUTHN_RP_ID";
+
+    fn default() -> String {
+        "localhost".to_string()
+    }
+
+    fn get() -> String {
+        match Self::get_raw() {
+            Ok(value) => value,
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The name of the `WebAuthn` relying party, as shown to the user by their authenticator.
+pub struct WebauthnRpName;
+impl EnvironmentVariable<String> for WebauthnRpName {
+    const NAME: &'static str = "WEBAUTHN_RP_NAME";
+
+    fn default() -> String {
+        "Engineering Metrics".to_string()
+    }
+
+    fn get() -> String {
+        match Self::get_raw() {
+            Ok(value) => value,
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// The origin that the client is served from, as reported by browsers during `WebAuthn` ceremonies.
+pub struct WebauthnRpOrigin;
+impl EnvironmentVariable<String> for WebauthnRpOrigin {
+    const NAME: &'static str = "WEBAUTHN_RP_ORIGIN";
+
+    fn default() -> String {
+        "http://localhost:8080".to_string()
+    }
+
+    fn get() -> String {
+        match Self::get_raw() {
+            Ok(value) => value,
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bool() {
+        for value in ["true", "1", "yes", "on", "TRUE", " On "] {
+            assert_eq!(parse_bool(value), Some(true), "{value}");
+        }
+        for value in ["false", "0", "no", "off", "False", "NO"] {
+            assert_eq!(parse_bool(value), Some(false), "{value}");
+        }
+        for value in ["", "2", "enabled", "nope"] {
+            assert_eq!(parse_bool(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let v4 = IpAddr::from([10, 0, 0, 1]);
+        let v6 = IpAddr::from([0xfd00, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(parse_network("10.0.0.1"), Some((v4, 32)));
+        assert_eq!(parse_network("10.0.0.1/8"), Some((v4, 8)));
+        assert_eq!(parse_network("fd00::1"), Some((v6, 128)));
+        assert_eq!(parse_network("fd00::1/64"), Some((v6, 64)));
+        for value in ["", "proxy", "10.0.0.1/33", "fd00::1/129", "10.0.0.1/", "/8"] {
+            assert_eq!(parse_network(value), None, "{value}");
+        }
+    }
+}